@@ -0,0 +1,6 @@
+CREATE TABLE if NOT EXISTS source_links (
+    id INTEGER PRIMARY KEY,
+    source_id INTEGER NOT NULL REFERENCES sources (id) ON DELETE CASCADE,
+    target_id INTEGER NOT NULL REFERENCES sources (id) ON DELETE CASCADE,
+    relation TEXT NOT NULL
+);
@@ -0,0 +1,7 @@
+CREATE TABLE if NOT EXISTS quotes (
+    id INTEGER PRIMARY KEY,
+    source_id INTEGER NOT NULL REFERENCES sources (id) ON DELETE CASCADE,
+    text TEXT NOT NULL,
+    page TEXT NOT NULL,
+    note TEXT NOT NULL
+);
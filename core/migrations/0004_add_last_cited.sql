@@ -0,0 +1 @@
+ALTER TABLE sources ADD COLUMN last_cited DATETIME;
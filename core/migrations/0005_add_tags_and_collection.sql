@@ -0,0 +1,2 @@
+ALTER TABLE sources ADD COLUMN tags TEXT NOT NULL DEFAULT '';
+ALTER TABLE sources ADD COLUMN collection TEXT NOT NULL DEFAULT '';
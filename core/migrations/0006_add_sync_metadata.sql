@@ -0,0 +1,36 @@
+-- stable id and change time of every source for LAN sync
+ALTER TABLE sources ADD COLUMN uuid TEXT NOT NULL DEFAULT '';
+ALTER TABLE sources ADD COLUMN updated_at DATETIME;
+
+UPDATE sources SET uuid = lower(hex(randomblob(16))), updated_at = strftime('%Y-%m-%d %H:%M:%f', 'now');
+
+-- new sources get an id unless one is given (e.g. by sync)
+CREATE TRIGGER sources_insert_sync AFTER INSERT ON sources
+WHEN NEW.uuid = ''
+BEGIN
+    UPDATE sources SET uuid = lower(hex(randomblob(16))), updated_at = strftime('%Y-%m-%d %H:%M:%f', 'now') WHERE id = NEW.id;
+END;
+
+-- every change updates the change time unless it is set explicitly (e.g. by sync)
+CREATE TRIGGER sources_update_sync AFTER UPDATE ON sources
+WHEN NEW.updated_at IS OLD.updated_at
+BEGIN
+    UPDATE sources SET updated_at = strftime('%Y-%m-%d %H:%M:%f', 'now') WHERE id = NEW.id;
+END;
+
+-- deleted sources are kept as tombstones so the deletion can be synced
+CREATE TABLE deleted_sources (
+    uuid TEXT PRIMARY KEY NOT NULL,
+    deleted_at DATETIME NOT NULL
+);
+
+CREATE TRIGGER sources_delete_sync AFTER DELETE ON sources
+BEGIN
+    INSERT OR REPLACE INTO deleted_sources (uuid, deleted_at) VALUES (OLD.uuid, strftime('%Y-%m-%d %H:%M:%f', 'now'));
+END;
+
+-- last successful sync per peer device, in UTC by the clock of this device
+CREATE TABLE sync_peers (
+    device_id TEXT PRIMARY KEY NOT NULL,
+    last_sync DATETIME NOT NULL
+);
@@ -0,0 +1 @@
+ALTER TABLE sources ADD COLUMN doi TEXT NOT NULL DEFAULT '';
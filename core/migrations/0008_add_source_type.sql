@@ -0,0 +1 @@
+ALTER TABLE sources ADD COLUMN source_type TEXT NOT NULL DEFAULT 'webpage';
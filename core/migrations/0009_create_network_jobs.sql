@@ -0,0 +1,9 @@
+-- network actions that failed or were postponed by offline mode, retried in the background
+CREATE TABLE if NOT EXISTS network_jobs (
+    id INTEGER PRIMARY KEY,
+    source_id INTEGER NOT NULL REFERENCES sources (id) ON DELETE CASCADE,
+    kind TEXT NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT NOT NULL DEFAULT '',
+    created_at DATETIME NOT NULL
+);
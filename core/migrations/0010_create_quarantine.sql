@@ -0,0 +1,9 @@
+-- rows moved out of the library by the integrity check, kept so nothing is lost
+CREATE TABLE if NOT EXISTS quarantine (
+    id INTEGER PRIMARY KEY,
+    table_name TEXT NOT NULL,
+    row_id INTEGER NOT NULL,
+    content TEXT NOT NULL, -- the row as JSON
+    reason TEXT NOT NULL,
+    quarantined_at DATETIME NOT NULL
+);
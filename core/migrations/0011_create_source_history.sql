@@ -0,0 +1,78 @@
+-- every change of a field of a source with the old and the new value, for the history of the
+-- edit modal, the values are stored as text
+CREATE TABLE source_history (
+    id INTEGER PRIMARY KEY,
+    source_id INTEGER NOT NULL REFERENCES sources (id) ON DELETE CASCADE,
+    field TEXT NOT NULL,
+    old_value TEXT,
+    new_value TEXT,
+    changed_at DATETIME NOT NULL -- UTC
+);
+
+CREATE INDEX source_history_source_id ON source_history (source_id);
+
+CREATE TRIGGER sources_history_title AFTER UPDATE OF title ON sources
+WHEN OLD.title IS NOT NEW.title
+BEGIN
+    INSERT INTO source_history (source_id, field, old_value, new_value, changed_at) VALUES (NEW.id, 'title', CAST(OLD.title AS TEXT), CAST(NEW.title AS TEXT), strftime('%Y-%m-%d %H:%M:%f', 'now'));
+END;
+
+CREATE TRIGGER sources_history_url AFTER UPDATE OF url ON sources
+WHEN OLD.url IS NOT NEW.url
+BEGIN
+    INSERT INTO source_history (source_id, field, old_value, new_value, changed_at) VALUES (NEW.id, 'url', CAST(OLD.url AS TEXT), CAST(NEW.url AS TEXT), strftime('%Y-%m-%d %H:%M:%f', 'now'));
+END;
+
+CREATE TRIGGER sources_history_author AFTER UPDATE OF author ON sources
+WHEN OLD.author IS NOT NEW.author
+BEGIN
+    INSERT INTO source_history (source_id, field, old_value, new_value, changed_at) VALUES (NEW.id, 'author', CAST(OLD.author AS TEXT), CAST(NEW.author AS TEXT), strftime('%Y-%m-%d %H:%M:%f', 'now'));
+END;
+
+CREATE TRIGGER sources_history_published_date AFTER UPDATE OF published_date ON sources
+WHEN OLD.published_date IS NOT NEW.published_date
+BEGIN
+    INSERT INTO source_history (source_id, field, old_value, new_value, changed_at) VALUES (NEW.id, 'published_date', CAST(OLD.published_date AS TEXT), CAST(NEW.published_date AS TEXT), strftime('%Y-%m-%d %H:%M:%f', 'now'));
+END;
+
+CREATE TRIGGER sources_history_viewed_date AFTER UPDATE OF viewed_date ON sources
+WHEN OLD.viewed_date IS NOT NEW.viewed_date
+BEGIN
+    INSERT INTO source_history (source_id, field, old_value, new_value, changed_at) VALUES (NEW.id, 'viewed_date', CAST(OLD.viewed_date AS TEXT), CAST(NEW.viewed_date AS TEXT), strftime('%Y-%m-%d %H:%M:%f', 'now'));
+END;
+
+CREATE TRIGGER sources_history_published_date_unknown AFTER UPDATE OF published_date_unknown ON sources
+WHEN OLD.published_date_unknown IS NOT NEW.published_date_unknown
+BEGIN
+    INSERT INTO source_history (source_id, field, old_value, new_value, changed_at) VALUES (NEW.id, 'published_date_unknown', CAST(OLD.published_date_unknown AS TEXT), CAST(NEW.published_date_unknown AS TEXT), strftime('%Y-%m-%d %H:%M:%f', 'now'));
+END;
+
+CREATE TRIGGER sources_history_comment AFTER UPDATE OF comment ON sources
+WHEN OLD.comment IS NOT NEW.comment
+BEGIN
+    INSERT INTO source_history (source_id, field, old_value, new_value, changed_at) VALUES (NEW.id, 'comment', CAST(OLD.comment AS TEXT), CAST(NEW.comment AS TEXT), strftime('%Y-%m-%d %H:%M:%f', 'now'));
+END;
+
+CREATE TRIGGER sources_history_tags AFTER UPDATE OF tags ON sources
+WHEN OLD.tags IS NOT NEW.tags
+BEGIN
+    INSERT INTO source_history (source_id, field, old_value, new_value, changed_at) VALUES (NEW.id, 'tags', CAST(OLD.tags AS TEXT), CAST(NEW.tags AS TEXT), strftime('%Y-%m-%d %H:%M:%f', 'now'));
+END;
+
+CREATE TRIGGER sources_history_collection AFTER UPDATE OF collection ON sources
+WHEN OLD.collection IS NOT NEW.collection
+BEGIN
+    INSERT INTO source_history (source_id, field, old_value, new_value, changed_at) VALUES (NEW.id, 'collection', CAST(OLD.collection AS TEXT), CAST(NEW.collection AS TEXT), strftime('%Y-%m-%d %H:%M:%f', 'now'));
+END;
+
+CREATE TRIGGER sources_history_doi AFTER UPDATE OF doi ON sources
+WHEN OLD.doi IS NOT NEW.doi
+BEGIN
+    INSERT INTO source_history (source_id, field, old_value, new_value, changed_at) VALUES (NEW.id, 'doi', CAST(OLD.doi AS TEXT), CAST(NEW.doi AS TEXT), strftime('%Y-%m-%d %H:%M:%f', 'now'));
+END;
+
+CREATE TRIGGER sources_history_source_type AFTER UPDATE OF source_type ON sources
+WHEN OLD.source_type IS NOT NEW.source_type
+BEGIN
+    INSERT INTO source_history (source_id, field, old_value, new_value, changed_at) VALUES (NEW.id, 'source_type', CAST(OLD.source_type AS TEXT), CAST(NEW.source_type AS TEXT), strftime('%Y-%m-%d %H:%M:%f', 'now'));
+END;
@@ -0,0 +1,2 @@
+-- pinned sources are listed above all others
+ALTER TABLE sources ADD COLUMN pinned BOOLEAN NOT NULL DEFAULT 0;
@@ -0,0 +1,11 @@
+-- documents the sources are cited in, e.g. "Thesis chapter 3"
+CREATE TABLE if NOT EXISTS documents (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE
+);
+
+CREATE TABLE if NOT EXISTS document_sources (
+    document_id INTEGER NOT NULL REFERENCES documents (id) ON DELETE CASCADE,
+    source_id INTEGER NOT NULL REFERENCES sources (id) ON DELETE CASCADE,
+    PRIMARY KEY (document_id, source_id)
+);
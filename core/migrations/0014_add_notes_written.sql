@@ -0,0 +1,8 @@
+-- set when the notes of a source are written, for the annotation progress
+ALTER TABLE sources ADD COLUMN notes_written BOOLEAN NOT NULL DEFAULT 0;
+
+CREATE TRIGGER sources_history_notes_written AFTER UPDATE OF notes_written ON sources
+WHEN OLD.notes_written IS NOT NEW.notes_written
+BEGIN
+    INSERT INTO source_history (source_id, field, old_value, new_value, changed_at) VALUES (NEW.id, 'notes_written', CAST(OLD.notes_written AS TEXT), CAST(NEW.notes_written AS TEXT), strftime('%Y-%m-%d %H:%M:%f', 'now'));
+END;
@@ -0,0 +1,8 @@
+-- final address of the URL after redirects and <link rel="canonical">, empty if it's the URL itself
+ALTER TABLE sources ADD COLUMN canonical_url TEXT NOT NULL DEFAULT '';
+
+CREATE TRIGGER sources_history_canonical_url AFTER UPDATE OF canonical_url ON sources
+WHEN OLD.canonical_url IS NOT NEW.canonical_url
+BEGIN
+    INSERT INTO source_history (source_id, field, old_value, new_value, changed_at) VALUES (NEW.id, 'canonical_url', CAST(OLD.canonical_url AS TEXT), CAST(NEW.canonical_url AS TEXT), strftime('%Y-%m-%d %H:%M:%f', 'now'));
+END;
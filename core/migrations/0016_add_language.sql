@@ -0,0 +1,8 @@
+-- language of the source, e.g. "de", the built-in formats use its terms, empty for the format's own
+ALTER TABLE sources ADD COLUMN language TEXT NOT NULL DEFAULT '';
+
+CREATE TRIGGER sources_history_language AFTER UPDATE OF language ON sources
+WHEN OLD.language IS NOT NEW.language
+BEGIN
+    INSERT INTO source_history (source_id, field, old_value, new_value, changed_at) VALUES (NEW.id, 'language', CAST(OLD.language AS TEXT), CAST(NEW.language AS TEXT), strftime('%Y-%m-%d %H:%M:%f', 'now'));
+END;
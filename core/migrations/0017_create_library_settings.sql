@@ -0,0 +1,5 @@
+-- settings that belong to the library instead of the device, e.g. its collation
+CREATE TABLE library_settings (
+    key TEXT PRIMARY KEY NOT NULL,
+    value TEXT NOT NULL
+);
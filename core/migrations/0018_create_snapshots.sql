@@ -0,0 +1,9 @@
+-- text of the page of a source when it was cited, compared with the page when it's re-checked
+CREATE TABLE if NOT EXISTS snapshots (
+    source_id INTEGER PRIMARY KEY NOT NULL REFERENCES sources (id) ON DELETE CASCADE,
+    url TEXT NOT NULL,
+    text TEXT NOT NULL,
+    fetched_at DATETIME NOT NULL,
+    similarity REAL,     -- of the page at the last re-check, NULL before the first
+    checked_at DATETIME
+);
@@ -0,0 +1,127 @@
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use chrono::{DateTime, Local};
+use sqlx::SqlitePool;
+use tokio::task::JoinHandle;
+use tracing::*;
+
+use crate::config::Config;
+use crate::crash::record_operation;
+use crate::database::get_all_sources;
+use crate::formats::{self, Format};
+use crate::hooks::{self, HookEvent};
+
+const SECONDS_PER_HOUR: u64 = 60 * 60;
+
+// shown on the settings page
+#[derive(Debug, Clone, Default)]
+pub struct AutoExportStatus {
+    pub last_export: Option<DateTime<Local>>,
+    pub error: Option<String>,
+}
+
+// library file that is written again every few hours, stopped when dropped
+pub struct AutoExport {
+    task: JoinHandle<()>,
+    pub status: Arc<RwLock<AutoExportStatus>>,
+}
+
+impl Drop for AutoExport {
+    fn drop(&mut self) {
+        debug!("Stopping automatic export");
+        self.task.abort();
+    }
+}
+
+// file and format of the automatic export, None if it's turned off
+pub fn target(config: &Config) -> Result<Option<(PathBuf, &'static dyn Format)>, String> {
+    if !config.auto_export {
+        return Ok(None);
+    }
+    if config.auto_export_path.is_empty() {
+        return Err("Choose a file for the automatic export".to_string());
+    }
+
+    match formats::by_id(&config.auto_export_format) {
+        Some(format) if format.can_export() => {
+            Ok(Some((PathBuf::from(&config.auto_export_path), format)))
+        }
+        _ => Err(format!(
+            "Unknown export format \"{}\"",
+            config.auto_export_format
+        )),
+    }
+}
+
+// needs to be called from within the tokio runtime, exports right away and then every `hours`
+pub fn start(
+    path: PathBuf,
+    format: &'static dyn Format,
+    hours: u64,
+    pool: Arc<SqlitePool>,
+) -> AutoExport {
+    debug!(
+        "Starting automatic export to {} every {} hours",
+        path.display(),
+        hours
+    );
+
+    let status = Arc::new(RwLock::new(AutoExportStatus::default()));
+
+    let task_status = status.clone();
+    let task = tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(hours * SECONDS_PER_HOUR));
+        loop {
+            interval.tick().await;
+
+            let result = export(&path, format, &pool).await;
+            let mut status = task_status.write().unwrap();
+            match result {
+                Ok(()) => {
+                    status.last_export = Some(Local::now());
+                    status.error = None;
+                }
+                Err(e) => {
+                    error!("Error exporting library automatically: {}", e);
+                    status.error = Some(e.to_string());
+                }
+            }
+        }
+    });
+
+    AutoExport { task, status }
+}
+
+// writes all sources to the file, readers of the file never see a half written export
+pub async fn export(
+    path: &Path,
+    format: &dyn Format,
+    pool: &SqlitePool,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    debug!("Exporting library to {}", path.display());
+    record_operation(format!("Automatic export as {}", format.id()));
+
+    let sources = get_all_sources(pool).await?;
+    let content = format.export(&sources).map_err(|e| e.to_string())?;
+
+    let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(".tmp");
+    let temp = path.with_file_name(temp_name);
+
+    fs::write(&temp, content)?;
+    fs::rename(&temp, path)?;
+
+    if let Ok(config) = Config::try_load() {
+        hooks::run(
+            HookEvent::ExportFinished,
+            hooks::export_payload(path, format, &sources),
+            &config,
+        );
+    }
+
+    Ok(())
+}
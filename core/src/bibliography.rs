@@ -0,0 +1,235 @@
+use std::fmt::{Display, Formatter};
+
+use crate::collation;
+use crate::config::{Config, FormatStandard};
+use crate::locale::{Terms, ENGLISH, GERMAN};
+use crate::source::{Source, SourceType};
+
+// first authors with more words are taken as organizations and sorted by their full name
+const MAX_NAME_WORDS: usize = 3;
+
+// how an author is written in the entry after one with the same author
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RepeatedAuthors {
+    Repeat, // APA and most author-date styles
+    Ders,   // German humanities, "Ders." or "Dies." for several authors
+    Dash,   // Chicago and MLA, a 3-em dash
+}
+
+impl RepeatedAuthors {
+    pub const ALL: [RepeatedAuthors; 3] = [
+        RepeatedAuthors::Repeat,
+        RepeatedAuthors::Ders,
+        RepeatedAuthors::Dash,
+    ];
+
+    // the built-in format is German, custom formats and plugins write the names themselves
+    pub fn for_standard(standard: &FormatStandard) -> Self {
+        match standard {
+            FormatStandard::Default => RepeatedAuthors::Ders,
+            FormatStandard::Custom | FormatStandard::Plugin => RepeatedAuthors::Repeat,
+        }
+    }
+
+    fn replacement(&self, author: &str) -> Option<&'static str> {
+        match self {
+            RepeatedAuthors::Repeat => None,
+            RepeatedAuthors::Ders if is_several(author) => Some("Dies."),
+            RepeatedAuthors::Ders => Some("Ders."),
+            RepeatedAuthors::Dash => Some("———"),
+        }
+    }
+}
+
+impl Display for RepeatedAuthors {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RepeatedAuthors::Repeat => write!(f, "Repeat the name"),
+            RepeatedAuthors::Ders => write!(f, "Ders. / Dies."),
+            RepeatedAuthors::Dash => write!(f, "——— (3-em dash)"),
+        }
+    }
+}
+
+// sections with a heading each, e.g. for primary and secondary literature
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum SectionBy {
+    #[default]
+    None,
+    Type,
+    Tag,
+}
+
+impl SectionBy {
+    pub const ALL: [SectionBy; 3] = [SectionBy::None, SectionBy::Type, SectionBy::Tag];
+}
+
+impl Display for SectionBy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SectionBy::None => write!(f, "No sections"),
+            SectionBy::Type => write!(f, "Type"),
+            SectionBy::Tag => write!(f, "Tag"),
+        }
+    }
+}
+
+// the headings are German like the built-in format
+pub fn headings(standard: &FormatStandard) -> &'static Terms {
+    match standard {
+        FormatStandard::Default => &GERMAN,
+        FormatStandard::Custom | FormatStandard::Plugin => &ENGLISH,
+    }
+}
+
+// sources per heading, empty sections are left out; by tag a source is in the section of the
+// first of the tags it has, or of its own first tag if no tags are given, sources without one of
+// them are in the last section
+pub fn sections(
+    sources: &[Source],
+    by: SectionBy,
+    tags: &[&str],
+    terms: &Terms,
+    collation: &str,
+) -> Vec<(String, Vec<Source>)> {
+    let mut sections: Vec<(String, Vec<Source>)> = match by {
+        SectionBy::None => return vec![(String::new(), sources.to_vec())],
+        SectionBy::Type => [
+            terms.literature,
+            terms.internet_sources,
+            terms.audiovisual_sources,
+            terms.images,
+        ]
+        .iter()
+        .map(|heading| (heading.to_string(), vec![]))
+        .collect(),
+        SectionBy::Tag => tags.iter().map(|tag| (tag.to_string(), vec![])).collect(),
+    };
+    let mut other = vec![];
+
+    for source in sources {
+        let index =
+            match by {
+                SectionBy::None => None,
+                SectionBy::Type => match source.source_type {
+                    SourceType::Book | SourceType::JournalArticle => Some(0),
+                    SourceType::Webpage => Some(1),
+                    SourceType::Video => Some(2),
+                    SourceType::Image => Some(3),
+                    SourceType::Other => None,
+                },
+                SectionBy::Tag if tags.is_empty() => source.tag_list().first().map(|tag| {
+                    match sections.iter().position(|(heading, _)| heading == tag) {
+                        Some(index) => index,
+                        None => {
+                            sections.push((tag.to_string(), vec![]));
+                            sections.len() - 1
+                        }
+                    }
+                }),
+                SectionBy::Tag => tags.iter().position(|tag| {
+                    source
+                        .tag_list()
+                        .iter()
+                        .any(|own| own.to_lowercase() == tag.to_lowercase())
+                }),
+            };
+
+        match index {
+            Some(index) => sections[index].1.push(source.clone()),
+            None => other.push(source.clone()),
+        }
+    }
+
+    if by == SectionBy::Tag && tags.is_empty() {
+        sections.sort_by(|(a, _), (b, _)| collation::compare(collation, a, b));
+    }
+    sections.push((terms.other_sources.to_string(), other));
+    sections.retain(|(_, sources)| !sources.is_empty());
+    sections
+}
+
+// the sections one after another, each with its heading on its own line
+pub fn sectioned(
+    sections: &[(String, Vec<Source>)],
+    standard: &FormatStandard,
+    config: &Config,
+    repeated: RepeatedAuthors,
+    collation: &str,
+) -> String {
+    let mut out = String::new();
+
+    for (heading, sources) in sections {
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        if !heading.is_empty() {
+            out.push_str(heading);
+            out.push('\n');
+        }
+        out.push_str(&alphabetical(
+            sources, standard, config, repeated, collation,
+        ));
+    }
+
+    out
+}
+
+// one formatted source per line, sorted by the surname of the first author or the title of
+// sources without an author
+pub fn alphabetical(
+    sources: &[Source],
+    standard: &FormatStandard,
+    config: &Config,
+    repeated: RepeatedAuthors,
+    collation: &str,
+) -> String {
+    let mut keyed: Vec<(String, &Source)> = sources
+        .iter()
+        .map(|source| (sort_key(source), source))
+        .collect();
+    keyed.sort_by(|(a_key, a), (b_key, b)| {
+        collation::compare(collation, a_key, b_key)
+            .then_with(|| collation::compare(collation, a.author.trim(), b.author.trim()))
+            .then_with(|| collation::compare(collation, &a.title, &b.title))
+    });
+
+    let mut out = String::new();
+    let mut previous_author = "";
+    for (_, source) in keyed {
+        let author = source.author.trim();
+        let mut entry = source.format(standard, config);
+
+        if !author.is_empty() && author == previous_author {
+            if let Some(replacement) = repeated.replacement(author) {
+                entry = entry.replacen(author, replacement, 1);
+            }
+        }
+        previous_author = author;
+
+        out.push_str(&entry);
+        out.push('\n');
+    }
+
+    out
+}
+
+// "Steve Klabnik, Carol Nichols" -> "Klabnik Steve", authors are stored as "Given Surname"
+fn sort_key(source: &Source) -> String {
+    let first = source
+        .author
+        .split([',', ';', '&'])
+        .next()
+        .unwrap_or_default();
+    let words: Vec<&str> = first.split_whitespace().collect();
+
+    match words.split_last() {
+        None => source.title.trim().to_string(),
+        Some(_) if words.len() > MAX_NAME_WORDS => words.join(" "),
+        Some((surname, given)) => format!("{} {}", surname, given.join(" ")),
+    }
+}
+
+fn is_several(author: &str) -> bool {
+    author.contains([',', ';', '&']) || author.contains(" and ") || author.contains(" und ")
+}
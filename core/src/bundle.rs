@@ -0,0 +1,92 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use chrono::{Local, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use tracing::*;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::quote::Quote;
+use crate::source::Source;
+use crate::source_link::SourceLink;
+
+// increase when the manifest layout changes
+pub const BUNDLE_VERSION: i64 = 1;
+const MANIFEST_NAME: &str = "manifest.json";
+
+// contents of a shared bundle, stored as manifest.json in the zip file
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Manifest {
+    pub bundle_version: i64,
+    pub app_version: String,
+    pub created: NaiveDateTime,
+    pub sources: Vec<Source>,
+    pub quotes: Vec<Quote>,
+    pub links: Vec<SourceLink>, // only links between bundled sources
+}
+
+impl Manifest {
+    // bundles the given sources with their quotes and the links between them
+    pub fn new(sources: Vec<Source>, quotes: &[Quote], links: &[SourceLink]) -> Self {
+        let contains = |id: i64| sources.iter().any(|source| source.id == id);
+
+        let quotes = quotes
+            .iter()
+            .filter(|quote| contains(quote.source_id))
+            .cloned()
+            .collect();
+        let links = links
+            .iter()
+            .filter(|link| contains(link.source_id) && contains(link.target_id))
+            .cloned()
+            .collect();
+
+        Self {
+            bundle_version: BUNDLE_VERSION,
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            created: Local::now().naive_local(),
+            sources,
+            quotes,
+            links,
+        }
+    }
+}
+
+pub fn write_bundle(manifest: &Manifest, path: &Path) -> Result<(), Box<dyn Error>> {
+    debug!(
+        "Writing bundle with {} sources to {}",
+        manifest.sources.len(),
+        path.display()
+    );
+
+    let mut zip = ZipWriter::new(File::create(path)?);
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    zip.start_file(MANIFEST_NAME, options)?;
+    zip.write_all(serde_json::to_string_pretty(manifest)?.as_bytes())?;
+    zip.finish()?;
+
+    Ok(())
+}
+
+pub fn read_bundle(path: &Path) -> Result<Manifest, Box<dyn Error>> {
+    debug!("Reading bundle {}", path.display());
+
+    let mut zip = ZipArchive::new(File::open(path)?)?;
+    let mut content = String::new();
+    zip.by_name(MANIFEST_NAME)?.read_to_string(&mut content)?;
+
+    let manifest: Manifest = serde_json::from_str(&content)?;
+    if manifest.bundle_version > BUNDLE_VERSION {
+        return Err(format!(
+            "Bundle version {} is not supported, please update SaveIt",
+            manifest.bundle_version
+        )
+        .into());
+    }
+
+    Ok(manifest)
+}
@@ -0,0 +1,93 @@
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use icu_collator::{Collator, CollatorOptions};
+use icu_locid::Locale;
+use tracing::*;
+
+use crate::config::SortBy;
+use crate::source::Source;
+
+// key of the collation in the library settings
+pub const COLLATION_SETTING: &str = "collation";
+// Unicode rules without a language, "Ärzte" next to "Arzte"
+pub const DEFAULT_COLLATION: &str = "und";
+
+// collations a library can be sorted by, as (locale, name), empty compares code points
+pub const COLLATIONS: [(&str, &str); 8] = [
+    ("und", "Unicode"),
+    ("de", "German (Ä like A)"),
+    ("de-u-co-phonebk", "German phone book (Ä like Ae)"),
+    ("en", "English"),
+    ("fr", "French"),
+    ("es", "Spanish"),
+    ("sv", "Swedish (Ä after Z)"),
+    ("", "Code points (Ä after Z)"),
+];
+
+thread_local! {
+    // creating a collator loads its tables, the list is sorted every frame
+    static COLLATORS: RefCell<HashMap<String, Option<Collator>>> = RefCell::new(HashMap::new());
+}
+
+// "German (Ä like A)" for "de", the locale itself for collations not in the list
+pub fn name(collation: &str) -> &str {
+    COLLATIONS
+        .iter()
+        .find(|(locale, _)| *locale == collation)
+        .map_or(collation, |(_, name)| name)
+}
+
+// compares with the rules of the collation, unknown locales compare code points
+pub fn compare(collation: &str, a: &str, b: &str) -> Ordering {
+    if collation.is_empty() {
+        return a.cmp(b);
+    }
+
+    COLLATORS.with(|collators| {
+        let mut collators = collators.borrow_mut();
+        let collator = collators
+            .entry(collation.to_string())
+            .or_insert_with(|| collator(collation));
+        match collator {
+            Some(collator) => collator.compare(a, b),
+            None => a.cmp(b),
+        }
+    })
+}
+
+fn collator(collation: &str) -> Option<Collator> {
+    let locale: Locale = match collation.parse() {
+        Ok(locale) => locale,
+        Err(e) => {
+            warn!("Invalid collation {}: {}", collation, e);
+            return None;
+        }
+    };
+
+    match Collator::try_new(&(&locale).into(), CollatorOptions::new()) {
+        Ok(collator) => Some(collator),
+        Err(e) => {
+            warn!("Error creating collator for {}: {}", collation, e);
+            None
+        }
+    }
+}
+
+// sources without an author go last when sorting by author, Index keeps the order of the
+// library
+pub fn sort_sources(sources: &mut [Source], sort_by: SortBy, collation: &str) {
+    match sort_by {
+        SortBy::Index => {}
+        SortBy::Title => sources.sort_by(|a, b| compare(collation, &a.title, &b.title)),
+        SortBy::Author => sources.sort_by(|a, b| {
+            let (a_author, b_author) = (a.author.trim(), b.author.trim());
+            a_author
+                .is_empty()
+                .cmp(&b_author.is_empty())
+                .then_with(|| compare(collation, a_author, b_author))
+                .then_with(|| compare(collation, &a.title, &b.title))
+        }),
+    }
+}
@@ -0,0 +1,495 @@
+use confy::ConfyError;
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::fs::create_dir_all;
+use std::path::Path;
+use std::sync::Mutex;
+use toml::Table;
+use tracing::*;
+
+use crate::crash::record_operation;
+use crate::search::{Filter, SearchMode};
+use crate::source::Source;
+use crate::tasks::spawn_tracked;
+
+pub const CONFIG_NAME: &str = "save-it";
+
+// increase when config keys are renamed or restructured and add a step to `migrate`
+pub const CONFIG_VERSION: i64 = 1;
+
+// number of exported files offered for exporting again
+pub const RECENT_EXPORTS: usize = 5;
+
+// contents of the config file as the app last wrote it, the watcher sees those writes as well
+static SAVED: Mutex<String> = Mutex::new(String::new());
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)] // missing fields (e.g. from older versions) fall back to the default config
+pub struct Config {
+    pub config_version: i64,
+    pub language: String,
+    pub format_standard: FormatStandard,
+    pub custom_format: String,
+    pub custom_in_text_format: String,
+    pub plugin_format: String, // id of the plugin used by FormatStandard::Plugin
+    pub collection_formats: BTreeMap<String, FormatStandard>, // instead of format_standard
+    pub update_viewed_on_copy: bool,
+    pub last_page: AppPage,
+    pub search_query: String,
+    pub search_mode: SearchMode,
+    pub filters: Vec<Filter>, // active filter chips of the list page
+    pub group_by: GroupBy,
+    pub sort_by: SortBy,
+    pub shared_library: String, // path of a read-only library shown alongside the own one, empty if none
+    pub other_libraries: Vec<String>, // paths of read-only libraries searched by "Everywhere"
+    pub search_everywhere: bool,
+    pub lan_sync: bool,
+    pub sync_key: String, // shared secret of the devices that sync with each other
+    pub sync_device_id: String, // generated on the first sync
+    pub local_server: bool,
+    pub local_server_port: u16,
+    pub assistant_api: bool,     // JSON-RPC endpoint of the local server
+    pub metadata_only: bool,     // only download the head of pages when looking them up
+    pub resolve_urls: bool,      // store where the URL leads when a source is saved
+    pub http_timeout: u64,       // seconds
+    pub http_proxy: String,      // empty for a direct connection
+    pub http_user_agent: String, // empty for the default
+    pub offline_mode: bool,
+    pub auto_export: bool,
+    pub auto_export_path: String,
+    pub auto_export_format: String, // id of the format
+    pub auto_export_interval: u64,  // hours, 0 to only export on exit
+    pub auto_export_on_exit: bool,
+    pub inbox: bool,
+    pub inbox_dir: String,         // files dropped here are imported
+    pub hook_source_saved: String, // shell commands run with the event as JSON on stdin, empty for none
+    pub hook_citation_copied: String,
+    pub hook_export_finished: String,
+    pub dark_theme: bool,
+    pub ui_scale: f32,      // on top of the scale of the monitor, 1.0 is 100 %
+    pub import_dir: String, // last directory a file was opened from, empty for the home directory
+    pub export_dir: String, // last directory a file was saved to, empty for the home directory
+    pub recent_exports: Vec<RecentExport>, // newest first
+}
+
+// exported file that can be written again from the export menu
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RecentExport {
+    pub path: String,
+    pub format: String, // id of the format
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        trace!("Creating new config");
+
+        Self {
+            config_version: CONFIG_VERSION,
+            language: "en".to_string(),
+            format_standard: FormatStandard::Default,
+            custom_format: "CUSTOM FORMAT".to_string(),
+            custom_in_text_format: "({AUTHOR} {P_DATE(%Y)}{PAGES(, S. )})".to_string(),
+            plugin_format: String::new(),
+            collection_formats: BTreeMap::new(),
+            update_viewed_on_copy: false,
+            last_page: AppPage::Start,
+            search_query: String::new(),
+            search_mode: SearchMode::Text,
+            filters: vec![],
+            group_by: GroupBy::None,
+            sort_by: SortBy::Index,
+            shared_library: String::new(),
+            other_libraries: vec![],
+            search_everywhere: false,
+            lan_sync: false,
+            sync_key: String::new(),
+            sync_device_id: String::new(),
+            local_server: false,
+            local_server_port: 8787,
+            assistant_api: false,
+            metadata_only: true,
+            resolve_urls: true,
+            http_timeout: 10,
+            http_proxy: String::new(),
+            http_user_agent: String::new(),
+            offline_mode: false,
+            auto_export: false,
+            auto_export_path: String::new(),
+            auto_export_format: "bibtex".to_string(),
+            auto_export_interval: 1,
+            auto_export_on_exit: true,
+            inbox: false,
+            inbox_dir: String::new(),
+            hook_source_saved: String::new(),
+            hook_citation_copied: String::new(),
+            hook_export_finished: String::new(),
+            dark_theme: true,
+            ui_scale: 1.0,
+            import_dir: String::new(),
+            export_dir: String::new(),
+            recent_exports: vec![],
+        }
+    }
+}
+
+impl Config {
+    pub fn get_config() -> Self {
+        debug!("Getting config");
+
+        let path =
+            confy::get_configuration_file_path(CONFIG_NAME, None).expect("Error loading config");
+        let res = Self::load_migrated(&path);
+
+        res.unwrap_or_else(|e| {
+            if let ConfyError::BadTomlData(_) = e {
+                // keep the broken file around so customizations can be recovered by hand
+                let backup = path.with_extension("toml.bak");
+                warn!("Invalid config file, resetting it. Backup: {:?}", &backup);
+                if let Err(e) = fs::copy(&path, &backup) {
+                    error!("Error backing up config: {}", e);
+                }
+
+                let default = Config::default();
+
+                confy::store(CONFIG_NAME, None, default).expect("Error resetting config");
+                Self::get_config()
+            } else {
+                panic!("Error loading config: {}", &e)
+            }
+        })
+    }
+
+    // loads the config without resetting it on errors, e.g. while the file is still being written
+    pub fn try_load() -> Result<Self, ConfyError> {
+        debug!("Trying to load config");
+
+        let path = confy::get_configuration_file_path(CONFIG_NAME, None)?;
+        Self::load_migrated(&path)
+    }
+
+    // reads a config file and upgrades it if it was written by an older version
+    fn load_migrated(path: &Path) -> Result<Self, ConfyError> {
+        if !path.exists() {
+            // creates the default config
+            return confy::load_path(path);
+        }
+
+        let content = fs::read_to_string(path).map_err(ConfyError::ReadConfigurationFileError)?;
+        let mut table: Table = toml::from_str(&content).map_err(ConfyError::BadTomlData)?;
+
+        let migrated = migrate(&mut table);
+        let config: Config = table.try_into().map_err(ConfyError::BadTomlData)?;
+
+        if migrated {
+            confy::store_path(path, config.clone())?;
+        }
+
+        Ok(config)
+    }
+
+    // writes the config to a user chosen file to share it, without the sync key and the id of
+    // this device and only with the commands run on events if asked to
+    pub fn export(&self, path: &Path, include_hooks: bool) -> Result<(), ConfyError> {
+        debug!("Exporting config to {:?}", path);
+
+        let mut config = self.clone();
+        config.sync_key.clear();
+        config.sync_device_id.clear();
+        if !include_hooks {
+            config.hook_source_saved.clear();
+            config.hook_citation_copied.clear();
+            config.hook_export_finished.clear();
+        }
+
+        confy::store_path(path, config)
+    }
+
+    // reads a config exported with Config::export, the device id of older exports is dropped so
+    // two devices don't share it
+    pub fn import(path: &Path) -> Result<Self, ConfyError> {
+        debug!("Importing config from {:?}", path);
+
+        let content = fs::read_to_string(path).map_err(ConfyError::ReadConfigurationFileError)?;
+        let mut table: Table = toml::from_str(&content).map_err(ConfyError::BadTomlData)?;
+
+        migrate(&mut table);
+        let mut config: Config = table.try_into().map_err(ConfyError::BadTomlData)?;
+        config.sync_device_id.clear();
+        Ok(config)
+    }
+
+    // saves on the current thread, used when the app is closing
+    pub fn save_blocking(&self) {
+        debug!("Saving config (blocking)");
+
+        self.remember_save();
+        confy::store(CONFIG_NAME, None, self.clone()).expect("Error saving config");
+    }
+
+    // the format of the collection of the source if it has its own, otherwise the given one
+    pub fn standard_for(&self, source: &Source, standard: &FormatStandard) -> FormatStandard {
+        self.collection_formats
+            .get(source.collection.trim())
+            .copied()
+            .unwrap_or(*standard)
+    }
+
+    // moves the export to the top of the recent exports
+    pub fn add_recent_export(&mut self, export: RecentExport) {
+        self.recent_exports
+            .retain(|recent| recent.path != export.path);
+        self.recent_exports.insert(0, export);
+        self.recent_exports.truncate(RECENT_EXPORTS);
+    }
+
+    pub fn save(&self) {
+        debug!("Saving config");
+        record_operation("Save config");
+        self.remember_save();
+        let config = self.clone();
+
+        spawn_tracked(async move {
+            confy::store(CONFIG_NAME, None, config).expect("Error saving config");
+        });
+    }
+
+    // written the same way as confy writes it
+    fn remember_save(&self) {
+        match toml::to_string_pretty(self) {
+            Ok(content) => *SAVED.lock().unwrap() = content,
+            Err(e) => error!("Error serializing config: {}", e),
+        }
+    }
+}
+
+// true if the config file has what the app saved last, i.e. it wasn't changed outside the app
+pub fn is_own_save() -> bool {
+    let path = match confy::get_configuration_file_path(CONFIG_NAME, None) {
+        Ok(path) => path,
+        Err(_) => return false,
+    };
+
+    match fs::read_to_string(path) {
+        Ok(content) => content == *SAVED.lock().unwrap(),
+        Err(_) => false,
+    }
+}
+
+// upgrades a raw config from older versions step by step, returns true if anything changed
+fn migrate(table: &mut Table) -> bool {
+    let version = table
+        .get("config_version")
+        .and_then(|version| version.as_integer())
+        .unwrap_or(0);
+
+    if version >= CONFIG_VERSION {
+        return false;
+    }
+
+    info!(
+        "Migrating config from version {} to {}",
+        version, CONFIG_VERSION
+    );
+
+    // 0 -> 1: configs without a version, no keys changed
+    //
+    // future renames go here, e.g.
+    // if version < 2 {
+    //     if let Some(value) = table.remove("old_key") {
+    //         table.insert("new_key".to_string(), value);
+    //     }
+    // }
+
+    table.insert(
+        "config_version".to_string(),
+        toml::Value::Integer(CONFIG_VERSION),
+    );
+
+    true
+}
+
+// watches the config file and calls on_change when it's modified, e.g. by hand or a sync tool
+pub fn watch_config(
+    on_change: impl Fn() + Send + 'static,
+) -> Result<RecommendedWatcher, Box<dyn std::error::Error>> {
+    let config_path = confy::get_configuration_file_path(CONFIG_NAME, None)?;
+    let config_dir = config_path
+        .parent()
+        .ok_or("Config file has no parent directory")?
+        .to_owned();
+    let file_name = config_path.file_name().map(|name| name.to_owned());
+
+    debug!("Watching config file {:?}", &config_path);
+
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| match res {
+        Ok(event) => {
+            // editors often replace the file instead of writing to it, so the directory is watched
+            let is_config = event
+                .paths
+                .iter()
+                .any(|path| path.file_name() == file_name.as_deref());
+
+            if is_config && (event.kind.is_modify() || event.kind.is_create()) {
+                debug!("Config file changed");
+                on_change();
+            }
+        }
+        Err(e) => error!("Error watching config file: {}", e),
+    })?;
+
+    create_dir_all(&config_dir)?;
+    watcher.watch(&config_dir, RecursiveMode::NonRecursive)?;
+
+    Ok(watcher)
+}
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Copy)]
+pub enum FormatStandard {
+    Default,
+    Custom,
+    Plugin, // see plugins.rs
+}
+
+// page the window opens on, remembered between sessions
+#[derive(PartialOrd, PartialEq, Serialize, Deserialize, Debug, Clone, Copy)]
+pub enum AppPage {
+    Start,
+    List,
+    Timeline,
+    Graph,
+    Settings,
+    Help,
+}
+
+// grouping of the list page
+#[derive(PartialEq, Serialize, Deserialize, Debug, Clone, Copy)]
+pub enum GroupBy {
+    None,
+    Domain,
+    Author,
+    Year,
+    Tag,
+    Collection,
+}
+
+impl GroupBy {
+    pub const ALL: [GroupBy; 6] = [
+        GroupBy::None,
+        GroupBy::Domain,
+        GroupBy::Author,
+        GroupBy::Year,
+        GroupBy::Tag,
+        GroupBy::Collection,
+    ];
+}
+
+impl Display for GroupBy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GroupBy::None => write!(f, "None"),
+            GroupBy::Domain => write!(f, "Domain"),
+            GroupBy::Author => write!(f, "Author"),
+            GroupBy::Year => write!(f, "Year"),
+            GroupBy::Tag => write!(f, "Tag"),
+            GroupBy::Collection => write!(f, "Collection"),
+        }
+    }
+}
+
+// order of the list page, titles and authors are compared with the collation of the library
+#[derive(PartialEq, Serialize, Deserialize, Debug, Clone, Copy)]
+pub enum SortBy {
+    Index,
+    Title,
+    Author,
+}
+
+impl SortBy {
+    pub const ALL: [SortBy; 3] = [SortBy::Index, SortBy::Title, SortBy::Author];
+}
+
+impl Display for SortBy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SortBy::Index => write!(f, "Index"),
+            SortBy::Title => write!(f, "Title"),
+            SortBy::Author => write!(f, "Author"),
+        }
+    }
+}
+
+impl Display for AppPage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AppPage::Start => {
+                write!(f, "Start")
+            }
+            AppPage::List => {
+                write!(f, "List")
+            }
+            AppPage::Timeline => {
+                write!(f, "Timeline")
+            }
+            AppPage::Graph => {
+                write!(f, "Graph")
+            }
+            AppPage::Settings => {
+                write!(f, "Settings")
+            }
+            AppPage::Help => {
+                write!(f, "Help")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exports_without_sync_key_and_device_id() {
+        let path =
+            std::env::temp_dir().join(format!("saveit-settings-{}.toml", uuid::Uuid::new_v4()));
+        let config = Config {
+            custom_format: "{TITLE}".to_string(),
+            sync_key: "secret".to_string(),
+            sync_device_id: "device".to_string(),
+            hook_source_saved: "notify-send saved".to_string(),
+            ..Config::default()
+        };
+
+        config.export(&path, false).unwrap();
+        let imported = Config::import(&path).unwrap();
+        assert_eq!(imported.custom_format, "{TITLE}");
+        assert!(imported.sync_key.is_empty());
+        assert!(imported.sync_device_id.is_empty());
+        assert!(imported.hook_source_saved.is_empty());
+
+        config.export(&path, true).unwrap();
+        let imported = Config::import(&path).unwrap();
+        assert_eq!(imported.hook_source_saved, "notify-send saved");
+        assert!(imported.sync_key.is_empty());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn import_drops_device_id() {
+        let path =
+            std::env::temp_dir().join(format!("saveit-settings-{}.toml", uuid::Uuid::new_v4()));
+        // written by an older version that exported everything
+        let config = Config {
+            sync_device_id: "device".to_string(),
+            ..Config::default()
+        };
+        confy::store_path(&path, config).unwrap();
+
+        assert!(Config::import(&path).unwrap().sync_device_id.is_empty());
+        fs::remove_file(&path).unwrap();
+    }
+}
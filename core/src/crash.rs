@@ -0,0 +1,99 @@
+use std::backtrace::Backtrace;
+use std::collections::VecDeque;
+use std::fs;
+use std::panic;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use chrono::Local;
+use directories::ProjectDirs;
+use once_cell::sync::Lazy;
+use tracing::*;
+
+const CRASH_REPORT_NAME: &str = "crash-report.txt";
+const MAX_OPERATIONS: usize = 20;
+
+// most recent operations, included in crash reports
+static LAST_OPERATIONS: Lazy<Mutex<VecDeque<String>>> =
+    Lazy::new(|| Mutex::new(VecDeque::with_capacity(MAX_OPERATIONS)));
+
+pub struct CrashReport {
+    pub path: PathBuf,
+    pub content: String,
+}
+
+fn crash_report_path() -> PathBuf {
+    ProjectDirs::from("com", "tgz39", "saveit")
+        .unwrap()
+        .data_dir()
+        .join(CRASH_REPORT_NAME)
+}
+
+// remember an operation so it shows up in the crash report
+pub fn record_operation(operation: impl Into<String>) {
+    let mut operations = match LAST_OPERATIONS.lock() {
+        Ok(operations) => operations,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+
+    if operations.len() == MAX_OPERATIONS {
+        operations.pop_front();
+    }
+    operations.push_back(format!(
+        "{} {}",
+        Local::now().format("%H:%M:%S"),
+        operation.into()
+    ));
+}
+
+// writes a crash report to disk whenever any thread panics
+pub fn install_panic_hook() {
+    debug!("Installing panic hook");
+
+    let default_hook = panic::take_hook();
+
+    panic::set_hook(Box::new(move |info| {
+        let operations = match LAST_OPERATIONS.lock() {
+            Ok(operations) => operations.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        };
+
+        let mut report = format!(
+            "SaveIt v{} crashed at {}\n\n{}\n\nLast operations:\n",
+            env!("CARGO_PKG_VERSION"),
+            Local::now().format("%Y-%m-%d %H:%M:%S"),
+            info
+        );
+        for operation in operations {
+            report.push_str(&format!("  {}\n", operation));
+        }
+        report.push_str(&format!("\nBacktrace:\n{}\n", Backtrace::force_capture()));
+
+        let path = crash_report_path();
+        if let Some(parent) = path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        if let Err(e) = fs::write(&path, report) {
+            error!("Error writing crash report: {}", e);
+        }
+
+        default_hook(info);
+    }));
+}
+
+// crash report left behind by a previous run
+pub fn load_crash_report() -> Option<CrashReport> {
+    let path = crash_report_path();
+    let content = fs::read_to_string(&path).ok()?;
+
+    debug!("Found crash report {:?}", &path);
+    Some(CrashReport { path, content })
+}
+
+pub fn delete_crash_report() {
+    debug!("Deleting crash report");
+
+    if let Err(e) = fs::remove_file(crash_report_path()) {
+        error!("Error deleting crash report: {}", e);
+    }
+}
@@ -0,0 +1,1184 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::{create_dir_all, File};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use chrono::{Local, NaiveDateTime};
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use sqlx::migrate::{MigrateDatabase, Migrator};
+use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::{FromRow, Sqlite, SqliteExecutor, SqlitePool};
+use tracing::*;
+
+use crate::bundle::Manifest;
+use crate::document::{CitedIn, Document};
+use crate::formats::Format;
+use crate::history::Change;
+use crate::import::{DuplicateIndex, ImportIds, ImportProgress, ProgressReader};
+use crate::queue::{JobKind, NetworkJob};
+use crate::quote::Quote;
+use crate::snapshot::Snapshot;
+use crate::source::Source;
+use crate::source_link::SourceLink;
+
+const IMPORT_CHUNK_SIZE: usize = 1000; // sources per transaction of a streaming import
+
+#[macro_export]
+macro_rules! db_version {
+    () => {
+        format!("sources-{}.db", &env!("CARGO_PKG_VERSION")[0..3])
+    };
+}
+
+// migrations of the library, run by the app before anything else touches it
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+// size and contents of the library, shown on the settings page
+#[derive(Debug, Clone, Default)]
+pub struct LibraryStats {
+    pub file_size: u64, // bytes, with the write-ahead log
+    pub sources: i64,
+    pub links: i64,
+    pub quotes: i64,
+    pub changes: i64,                   // rows of the history
+    pub notes: Vec<(String, i64, i64)>, // collection, sources with notes written, sources
+}
+
+pub fn database_file() -> PathBuf {
+    ProjectDirs::from("com", "tgz39", "saveit")
+        .unwrap()
+        .data_dir()
+        .join(db_version!())
+}
+
+// SQLite writes changes to a -wal file next to the library before moving them into it
+fn database_size() -> u64 {
+    let path = database_file();
+    let size = |path: &Path| std::fs::metadata(path).map(|metadata| metadata.len());
+
+    let mut wal = path.clone().into_os_string();
+    wal.push("-wal");
+    size(&path).unwrap_or(0) + size(Path::new(&wal)).unwrap_or(0)
+}
+
+pub async fn establish_connection() -> Result<SqlitePool, sqlx::Error> {
+    let db_loc = database_url();
+
+    // create DB file if it doesn't exist
+    if !Sqlite::database_exists(&db_loc).await.unwrap_or(false) {
+        debug!("Database doesn't exists. Creating database {}", &db_loc);
+
+        match Sqlite::create_database(&db_loc).await {
+            Ok(_) => {
+                debug!("Successfully created database")
+            }
+            Err(e) => {
+                error!("Error creating database: {}", e)
+            }
+        }
+    }
+
+    // connect to DB
+    debug!("Establishing connection to database {}...", &db_loc);
+    SqlitePool::connect(&db_loc).await
+}
+
+// connects on first use and creates the file then, so the window can open before the database
+// is ready, the migrations are left to the caller
+pub fn lazy_connection() -> Result<SqlitePool, sqlx::Error> {
+    let db_loc = database_url();
+    debug!("Connecting to database {} on first use", &db_loc);
+
+    let options = SqliteConnectOptions::from_str(&db_loc)?.create_if_missing(true);
+    Ok(SqlitePool::connect_lazy_with(options))
+}
+
+// creates the folder of the database if it doesn't exist
+fn database_url() -> String {
+    let db_path = ProjectDirs::from("com", "tgz39", "saveit")
+        .unwrap()
+        .data_dir()
+        .to_owned();
+
+    // create DB path if it doesn't exist
+    if !&db_path.exists() {
+        debug!("Creating database directories...");
+        create_dir_all(&db_path).expect("Error creating database directories");
+    }
+
+    // DB path + DB name
+    format!(
+        "sqlite://{}/{}",
+        &db_path.to_str().unwrap().to_owned(),
+        db_version!()
+    )
+}
+
+// returns the id of the new source
+pub async fn insert_source<'e>(
+    source: &Source,
+    executor: impl SqliteExecutor<'e>,
+) -> Result<i64, sqlx::Error> {
+    insert_source_as(None, source, executor).await
+}
+
+// with the given id or, if None, the next free one
+async fn insert_source_as<'e>(
+    id: Option<i64>,
+    source: &Source,
+    executor: impl SqliteExecutor<'e>,
+) -> Result<i64, sqlx::Error> {
+    debug!("Inserting source into database: {:#?}", &source);
+
+    sqlx::query("INSERT INTO sources (id, title, url, author, published_date, viewed_date, published_date_unknown, comment, last_cited, tags, collection, doi, source_type, pinned, notes_written, canonical_url, language) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17)")
+        .bind(id)
+        .bind(&source.title)
+        .bind(&source.url)
+        .bind(&source.author)
+        .bind(source.published_date)
+        .bind(source.viewed_date)
+        .bind(source.published_date_unknown)
+        .bind(&source.comment)
+        .bind(source.last_cited)
+        .bind(&source.tags)
+        .bind(&source.collection)
+        .bind(&source.doi)
+        .bind(source.source_type)
+        .bind(source.pinned)
+        .bind(source.notes_written)
+        .bind(&source.canonical_url)
+        .bind(&source.language)
+        .execute(executor)
+        .await
+        .map(|result| result.last_insert_rowid())
+}
+
+pub async fn get_all_sources(pool: &SqlitePool) -> Result<Vec<Source>, sqlx::Error> {
+    debug!("Fetching all sources");
+
+    sqlx::query_as::<_, Source>("SELECT * FROM sources")
+        .fetch_all(pool)
+        .await
+}
+
+// loads all sources of another library file without writing to it, e.g. a shared DB on a network
+// drive, the sources are labelled with the name of the library
+pub async fn get_shared_sources(path: &Path, library: &str) -> Result<Vec<Source>, sqlx::Error> {
+    debug!("Fetching shared sources from {}", path.display());
+
+    let options = SqliteConnectOptions::new().filename(path).read_only(true);
+    let pool = SqlitePool::connect_with(options).await?;
+
+    let sources = sqlx::query_as::<_, Source>("SELECT * FROM sources")
+        .fetch_all(&pool)
+        .await;
+    pool.close().await;
+
+    sources.map(|sources| {
+        sources
+            .into_iter()
+            .map(|source| Source {
+                shared: true,
+                library: library.to_string(),
+                ..source
+            })
+            .collect()
+    })
+}
+
+pub async fn get_source(id: i64, pool: &SqlitePool) -> Result<Option<Source>, sqlx::Error> {
+    sqlx::query_as::<_, Source>("SELECT * FROM sources WHERE id = $1")
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+}
+
+pub async fn delete_source(id: i64, pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    debug!("Deleting source: {}", id);
+
+    sqlx::query("DELETE FROM sources WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await
+        .map(|_| ())
+}
+
+// inserts a deleted source again with its uuid, quotes and links, e.g. to undo the deletion
+pub async fn restore_source(
+    source: &Source,
+    quotes: &[Quote],
+    links: &[SourceLink],
+    cited_in: &[CitedIn],
+    pool: &SqlitePool,
+) -> Result<i64, sqlx::Error> {
+    debug!("Restoring source {}", source.uuid);
+
+    let mut transaction = pool.begin().await?;
+
+    // a new change time so synced devices restore it as well
+    let id = sqlx::query("INSERT INTO sources (title, url, author, published_date, viewed_date, published_date_unknown, comment, last_cited, tags, collection, doi, source_type, uuid, notes_written, canonical_url, language, updated_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, strftime('%Y-%m-%d %H:%M:%f', 'now'))")
+        .bind(&source.title)
+        .bind(&source.url)
+        .bind(&source.author)
+        .bind(source.published_date)
+        .bind(source.viewed_date)
+        .bind(source.published_date_unknown)
+        .bind(&source.comment)
+        .bind(source.last_cited)
+        .bind(&source.tags)
+        .bind(&source.collection)
+        .bind(&source.doi)
+        .bind(source.source_type)
+        .bind(&source.uuid)
+        .bind(source.notes_written)
+        .bind(&source.canonical_url)
+        .bind(&source.language)
+        .execute(&mut *transaction)
+        .await?
+        .last_insert_rowid();
+
+    sqlx::query("DELETE FROM deleted_sources WHERE uuid = $1")
+        .bind(&source.uuid)
+        .execute(&mut *transaction)
+        .await?;
+
+    for quote in quotes {
+        let quote = Quote {
+            source_id: id,
+            ..quote.clone()
+        };
+        insert_quote(&quote, &mut *transaction).await?;
+    }
+
+    // links to sources that were deleted in the meantime are dropped
+    let new_id = |old: i64| if old == source.id { id } else { old };
+    for link in links {
+        sqlx::query("INSERT INTO source_links (source_id, target_id, relation) SELECT $1, $2, $3 WHERE EXISTS (SELECT 1 FROM sources WHERE id = $1) AND EXISTS (SELECT 1 FROM sources WHERE id = $2)")
+            .bind(new_id(link.source_id))
+            .bind(new_id(link.target_id))
+            .bind(link.relation)
+            .execute(&mut *transaction)
+            .await?;
+    }
+
+    // as are documents
+    for cited_in in cited_in {
+        sqlx::query("INSERT INTO document_sources (document_id, source_id) SELECT $1, $2 WHERE EXISTS (SELECT 1 FROM documents WHERE id = $1)")
+            .bind(cited_in.document_id)
+            .bind(id)
+            .execute(&mut *transaction)
+            .await?;
+    }
+
+    transaction.commit().await?;
+    Ok(id)
+}
+
+pub async fn update_source(id: i64, source: &Source, pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    debug!("Updating source: {} to {:#?}", id, &source);
+
+    sqlx::query("UPDATE sources SET title = $1, url = $2, author = $3, published_date = $4, viewed_date = $5, published_date_unknown = $6, comment = $7, tags = $8, collection = $9, doi = $10, source_type = $11, notes_written = $12, canonical_url = $13, language = $14 WHERE id = $15")
+        .bind(&source.title)
+        .bind(&source.url)
+        .bind(&source.author)
+        .bind(source.published_date)
+        .bind(source.viewed_date)
+        .bind(source.published_date_unknown)
+        .bind(&source.comment)
+        .bind(&source.tags)
+        .bind(&source.collection)
+        .bind(&source.doi)
+        .bind(source.source_type)
+        .bind(source.notes_written)
+        .bind(&source.canonical_url)
+        .bind(&source.language)
+        .bind(id)
+        .execute(pool)
+        .await
+        .map(|_| ())
+}
+
+pub async fn set_pinned(id: i64, pinned: bool, pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    debug!("Setting pinned of source {} to {}", id, pinned);
+
+    sqlx::query("UPDATE sources SET pinned = $1 WHERE id = $2")
+        .bind(pinned)
+        .bind(id)
+        .execute(pool)
+        .await
+        .map(|_| ())
+}
+
+pub async fn get_library_stats(pool: &SqlitePool) -> Result<LibraryStats, sqlx::Error> {
+    debug!("Fetching library stats");
+
+    let count = |table: &str| format!("SELECT COUNT(*) FROM {}", table);
+    Ok(LibraryStats {
+        file_size: database_size(),
+        sources: sqlx::query_scalar(&count("sources"))
+            .fetch_one(pool)
+            .await?,
+        links: sqlx::query_scalar(&count("source_links"))
+            .fetch_one(pool)
+            .await?,
+        quotes: sqlx::query_scalar(&count("quotes")).fetch_one(pool).await?,
+        changes: sqlx::query_scalar(&count("source_history"))
+            .fetch_one(pool)
+            .await?,
+        notes: sqlx::query_as("SELECT collection, SUM(notes_written), COUNT(*) FROM sources GROUP BY collection ORDER BY collection")
+            .fetch_all(pool)
+            .await?,
+    })
+}
+
+// rewrites the library without the space of deleted rows, returns the bytes reclaimed
+pub async fn compact_database(pool: &SqlitePool) -> Result<u64, sqlx::Error> {
+    debug!("Compacting database");
+
+    let before = database_size();
+    sqlx::query("VACUUM").execute(pool).await?;
+    // VACUUM goes through the write-ahead log, which keeps its size until truncated
+    sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)")
+        .execute(pool)
+        .await?;
+    let after = database_size();
+
+    debug!("Compacted database from {} to {} bytes", before, after);
+    Ok(before.saturating_sub(after))
+}
+
+// changes of a source, newest first
+pub async fn get_source_history(
+    source_id: i64,
+    pool: &SqlitePool,
+) -> Result<Vec<Change>, sqlx::Error> {
+    debug!("Fetching history of source {}", source_id);
+
+    sqlx::query_as::<_, Change>(
+        "SELECT * FROM source_history WHERE source_id = $1 ORDER BY changed_at DESC, id DESC",
+    )
+    .bind(source_id)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn insert_link<'e>(
+    link: &SourceLink,
+    executor: impl SqliteExecutor<'e>,
+) -> Result<(), sqlx::Error> {
+    debug!("Inserting source link into database: {:#?}", &link);
+
+    sqlx::query("INSERT INTO source_links (source_id, target_id, relation) VALUES ($1, $2, $3)")
+        .bind(link.source_id)
+        .bind(link.target_id)
+        .bind(link.relation)
+        .execute(executor)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn get_all_links(pool: &SqlitePool) -> Result<Vec<SourceLink>, sqlx::Error> {
+    debug!("Fetching all source links");
+
+    sqlx::query_as::<_, SourceLink>("SELECT * FROM source_links")
+        .fetch_all(pool)
+        .await
+}
+
+pub async fn delete_link(id: i64, pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    debug!("Deleting source link: {}", id);
+
+    sqlx::query("DELETE FROM source_links WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await
+        .map(|_| ())
+}
+
+pub async fn insert_quote<'e>(
+    quote: &Quote,
+    executor: impl SqliteExecutor<'e>,
+) -> Result<(), sqlx::Error> {
+    debug!("Inserting quote into database: {:#?}", &quote);
+
+    sqlx::query("INSERT INTO quotes (source_id, text, page, note) VALUES ($1, $2, $3, $4)")
+        .bind(quote.source_id)
+        .bind(&quote.text)
+        .bind(&quote.page)
+        .bind(&quote.note)
+        .execute(executor)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn get_all_quotes(pool: &SqlitePool) -> Result<Vec<Quote>, sqlx::Error> {
+    debug!("Fetching all quotes");
+
+    sqlx::query_as::<_, Quote>("SELECT * FROM quotes")
+        .fetch_all(pool)
+        .await
+}
+
+pub async fn update_quote(id: i64, quote: &Quote, pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    debug!("Updating quote: {} to {:#?}", id, &quote);
+
+    sqlx::query("UPDATE quotes SET text = $1, page = $2, note = $3 WHERE id = $4")
+        .bind(&quote.text)
+        .bind(&quote.page)
+        .bind(&quote.note)
+        .bind(id)
+        .execute(pool)
+        .await
+        .map(|_| ())
+}
+
+pub async fn delete_quote(id: i64, pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    debug!("Deleting quote: {}", id);
+
+    sqlx::query("DELETE FROM quotes WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await
+        .map(|_| ())
+}
+
+pub async fn get_all_documents(pool: &SqlitePool) -> Result<Vec<Document>, sqlx::Error> {
+    debug!("Fetching all documents");
+
+    sqlx::query_as::<_, Document>("SELECT * FROM documents ORDER BY name")
+        .fetch_all(pool)
+        .await
+}
+
+pub async fn get_all_cited_in(pool: &SqlitePool) -> Result<Vec<CitedIn>, sqlx::Error> {
+    debug!("Fetching all document citations");
+
+    sqlx::query_as::<_, CitedIn>("SELECT * FROM document_sources")
+        .fetch_all(pool)
+        .await
+}
+
+pub async fn insert_document(name: &str, pool: &SqlitePool) -> Result<i64, sqlx::Error> {
+    debug!("Inserting document {:?}", name);
+
+    sqlx::query("INSERT INTO documents (name) VALUES ($1)")
+        .bind(name)
+        .execute(pool)
+        .await
+        .map(|result| result.last_insert_rowid())
+}
+
+pub async fn rename_document(id: i64, name: &str, pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    debug!("Renaming document {} to {:?}", id, name);
+
+    sqlx::query("UPDATE documents SET name = $1 WHERE id = $2")
+        .bind(name)
+        .bind(id)
+        .execute(pool)
+        .await
+        .map(|_| ())
+}
+
+// the sources stay, only the citations are deleted with the document
+pub async fn delete_document(id: i64, pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    debug!("Deleting document {}", id);
+
+    sqlx::query("DELETE FROM documents WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await
+        .map(|_| ())
+}
+
+pub async fn set_cited_in(
+    cited_in: &CitedIn,
+    cited: bool,
+    pool: &SqlitePool,
+) -> Result<(), sqlx::Error> {
+    debug!("Setting {:?} to {}", cited_in, cited);
+
+    let query = match cited {
+        true => "INSERT OR IGNORE INTO document_sources (document_id, source_id) VALUES ($1, $2)",
+        false => "DELETE FROM document_sources WHERE document_id = $1 AND source_id = $2",
+    };
+    sqlx::query(query)
+        .bind(cited_in.document_id)
+        .bind(cited_in.source_id)
+        .execute(pool)
+        .await
+        .map(|_| ())
+}
+
+// sets the viewed date and last cited timestamp of copied sources in one transaction
+pub async fn mark_cited(ids: &[i64], pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    debug!("Marking sources as cited: {:?}", ids);
+
+    let viewed_date = Local::now().date_naive();
+    let last_cited = Local::now().naive_local();
+
+    let mut transaction = pool.begin().await?;
+    for id in ids {
+        sqlx::query("UPDATE sources SET viewed_date = $1, last_cited = $2 WHERE id = $3")
+            .bind(viewed_date)
+            .bind(last_cited)
+            .bind(id)
+            .execute(&mut *transaction)
+            .await?;
+    }
+    transaction.commit().await
+}
+
+// updates all given sources in one transaction, counting finished sources in progress
+pub async fn bulk_update(
+    sources: &[Source],
+    progress: &AtomicUsize,
+    pool: &SqlitePool,
+) -> Result<(), sqlx::Error> {
+    debug!("Bulk updating {} sources", sources.len());
+
+    let mut transaction = pool.begin().await?;
+    for source in sources {
+        // the canonical URL of a replaced link is out of date
+        sqlx::query("UPDATE sources SET title = $1, url = $2, canonical_url = CASE WHEN url IS $2 THEN canonical_url ELSE '' END, author = $3, viewed_date = $4, comment = $5, tags = $6, collection = $7 WHERE id = $8")
+            .bind(&source.title)
+            .bind(&source.url)
+            .bind(&source.author)
+            .bind(source.viewed_date)
+            .bind(&source.comment)
+            .bind(&source.tags)
+            .bind(&source.collection)
+            .bind(source.id)
+            .execute(&mut *transaction)
+            .await?;
+
+        progress.fetch_add(1, Ordering::Relaxed);
+    }
+    transaction.commit().await
+}
+
+// inserts all sources of a bundle as new sources in one transaction, keeping their quotes and links
+pub async fn import_bundle(manifest: &Manifest, pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    debug!("Importing bundle with {} sources", manifest.sources.len());
+
+    let mut transaction = pool.begin().await?;
+
+    // bundled id -> new id
+    let mut ids = HashMap::new();
+    for source in &manifest.sources {
+        let id = insert_source(source, &mut *transaction).await?;
+        ids.insert(source.id, id);
+    }
+
+    for quote in &manifest.quotes {
+        if let Some(source_id) = ids.get(&quote.source_id) {
+            let quote = Quote {
+                source_id: *source_id,
+                ..quote.clone()
+            };
+            insert_quote(&quote, &mut *transaction).await?;
+        }
+    }
+
+    for link in &manifest.links {
+        if let (Some(source_id), Some(target_id)) =
+            (ids.get(&link.source_id), ids.get(&link.target_id))
+        {
+            let link = SourceLink {
+                source_id: *source_id,
+                target_id: *target_id,
+                ..link.clone()
+            };
+            insert_link(&link, &mut *transaction).await?;
+        }
+    }
+
+    transaction.commit().await
+}
+
+// source deleted on this device, kept so the deletion can be synced
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct Tombstone {
+    pub uuid: String,
+    pub deleted_at: NaiveDateTime,
+}
+
+// adds all sources in one transaction, either all or none are imported
+pub async fn import_sources(
+    sources: &[Source],
+    ids: ImportIds,
+    pool: &SqlitePool,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    debug!("Importing {} sources ({:?})", sources.len(), ids);
+
+    let mut transaction = pool.begin().await?;
+    for source in sources {
+        if ids == ImportIds::New || source.id <= 0 {
+            insert_source(source, &mut *transaction).await?;
+            continue;
+        }
+
+        let used = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM sources WHERE id = $1")
+            .bind(source.id)
+            .fetch_one(&mut *transaction)
+            .await?
+            > 0;
+        match (used, ids) {
+            (false, _) => {
+                insert_source_as(Some(source.id), source, &mut *transaction).await?;
+            }
+            (true, ImportIds::Remap) => {
+                let id = insert_source(source, &mut *transaction).await?;
+                info!(
+                    "Imported source {} as {}, the id is already used",
+                    source.id, id
+                );
+            }
+            (true, _) => {
+                return Err(format!(
+                    "Source {} \"{}\" can't keep its ID, the ID is already used",
+                    source.id, source.title
+                )
+                .into())
+            }
+        }
+    }
+
+    transaction.commit().await?;
+    Ok(())
+}
+
+// reads the file on a blocking thread and adds its sources in chunks, so files larger than the
+// memory can be imported, sources imported before an error are kept
+pub async fn stream_import(
+    path: &Path,
+    format: &'static dyn Format,
+    skip_duplicates: bool,
+    ids: ImportIds,
+    progress: Arc<ImportProgress>,
+    pool: &SqlitePool,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    debug!(
+        "Streaming import of {} as {}",
+        path.display(),
+        format.name()
+    );
+
+    let mut known = DuplicateIndex::new(&get_all_sources(pool).await?);
+    let file = File::open(path)?;
+    progress
+        .total_bytes
+        .store(file.metadata()?.len(), Ordering::Relaxed);
+
+    let (sender, mut receiver) = tokio::sync::mpsc::channel(IMPORT_CHUNK_SIZE);
+    let reader_progress = progress.clone();
+    let reader = tokio::task::spawn_blocking(move || {
+        let mut reader = ProgressReader {
+            inner: file,
+            progress: reader_progress.clone(),
+        };
+        format
+            .import_stream(&mut reader, &mut |entry| match entry {
+                Ok(source) => sender
+                    .blocking_send(source)
+                    .map_err(|_| "Import stopped".into()),
+                Err(e) => {
+                    warn!("Skipping entry of the import file: {}", e);
+                    reader_progress.skipped.write().unwrap().push(e);
+                    Ok(())
+                }
+            })
+            .map_err(|e| e.to_string())
+    });
+
+    let mut chunk = Vec::with_capacity(IMPORT_CHUNK_SIZE);
+    while let Some(source) = receiver.recv().await {
+        if skip_duplicates && known.contains(&source) {
+            progress.duplicates.fetch_add(1, Ordering::Relaxed);
+            continue;
+        }
+        known.insert(&source);
+        chunk.push(source);
+
+        if chunk.len() >= IMPORT_CHUNK_SIZE {
+            import_sources(&chunk, ids, pool).await?;
+            progress.imported.fetch_add(chunk.len(), Ordering::Relaxed);
+            chunk.clear();
+        }
+    }
+    import_sources(&chunk, ids, pool).await?;
+    progress.imported.fetch_add(chunk.len(), Ordering::Relaxed);
+
+    reader.await??;
+    Ok(())
+}
+
+// sources and deletions changed after since (UTC), everything if since is None
+pub async fn get_changes_since(
+    since: Option<NaiveDateTime>,
+    pool: &SqlitePool,
+) -> Result<(Vec<Source>, Vec<Tombstone>), sqlx::Error> {
+    debug!("Fetching changes since {:?}", since);
+
+    let changed = |time: Option<NaiveDateTime>| match (since, time) {
+        (None, _) => true,
+        (Some(since), Some(time)) => time > since,
+        (Some(_), None) => false,
+    };
+
+    let sources = get_all_sources(pool)
+        .await?
+        .into_iter()
+        .filter(|source| changed(source.updated_at))
+        .collect();
+    let tombstones = sqlx::query_as::<_, Tombstone>("SELECT * FROM deleted_sources")
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .filter(|tombstone| changed(Some(tombstone.deleted_at)))
+        .collect();
+
+    Ok((sources, tombstones))
+}
+
+// merges changes of another device in one transaction, the newer change wins; returns the number of applied changes
+pub async fn apply_changes(
+    sources: &[Source],
+    tombstones: &[Tombstone],
+    pool: &SqlitePool,
+) -> Result<usize, sqlx::Error> {
+    debug!(
+        "Applying {} changed and {} deleted sources",
+        sources.len(),
+        tombstones.len()
+    );
+
+    let mut transaction = pool.begin().await?;
+    let mut applied = 0;
+
+    for source in sources {
+        let local = sqlx::query_as::<_, Source>("SELECT * FROM sources WHERE uuid = $1")
+            .bind(&source.uuid)
+            .fetch_optional(&mut *transaction)
+            .await?;
+        let deleted =
+            sqlx::query_as::<_, Tombstone>("SELECT * FROM deleted_sources WHERE uuid = $1")
+                .bind(&source.uuid)
+                .fetch_optional(&mut *transaction)
+                .await?;
+
+        if deleted.is_some_and(|deleted| Some(deleted.deleted_at) >= source.updated_at) {
+            continue;
+        }
+
+        match local {
+            Some(local) if local.updated_at >= source.updated_at => continue,
+            Some(local) => {
+                // updated_at is set explicitly so the update trigger keeps it
+                sqlx::query("UPDATE sources SET title = $1, url = $2, author = $3, published_date = $4, viewed_date = $5, published_date_unknown = $6, comment = $7, last_cited = $8, tags = $9, collection = $10, doi = $11, source_type = $12, notes_written = $13, canonical_url = $14, language = $15, updated_at = $16 WHERE id = $17")
+                    .bind(&source.title)
+                    .bind(&source.url)
+                    .bind(&source.author)
+                    .bind(source.published_date)
+                    .bind(source.viewed_date)
+                    .bind(source.published_date_unknown)
+                    .bind(&source.comment)
+                    .bind(source.last_cited)
+                    .bind(&source.tags)
+                    .bind(&source.collection)
+                    .bind(&source.doi)
+                    .bind(source.source_type)
+                    .bind(source.notes_written)
+                    .bind(&source.canonical_url)
+                    .bind(&source.language)
+                    .bind(source.updated_at)
+                    .bind(local.id)
+                    .execute(&mut *transaction)
+                    .await?;
+            }
+            None => {
+                sqlx::query("INSERT INTO sources (title, url, author, published_date, viewed_date, published_date_unknown, comment, last_cited, tags, collection, doi, source_type, uuid, notes_written, canonical_url, language, updated_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17)")
+                    .bind(&source.title)
+                    .bind(&source.url)
+                    .bind(&source.author)
+                    .bind(source.published_date)
+                    .bind(source.viewed_date)
+                    .bind(source.published_date_unknown)
+                    .bind(&source.comment)
+                    .bind(source.last_cited)
+                    .bind(&source.tags)
+                    .bind(&source.collection)
+                    .bind(&source.doi)
+                    .bind(source.source_type)
+                    .bind(&source.uuid)
+                    .bind(source.notes_written)
+                    .bind(&source.canonical_url)
+                    .bind(&source.language)
+                    .bind(source.updated_at)
+                    .execute(&mut *transaction)
+                    .await?;
+            }
+        }
+        applied += 1;
+    }
+
+    for tombstone in tombstones {
+        let result = sqlx::query(
+            "DELETE FROM sources WHERE uuid = $1 AND (updated_at IS NULL OR updated_at <= $2)",
+        )
+        .bind(&tombstone.uuid)
+        .bind(tombstone.deleted_at)
+        .execute(&mut *transaction)
+        .await?;
+        if result.rows_affected() > 0 {
+            applied += 1;
+        }
+
+        // keep the original deletion time instead of the one written by the delete trigger
+        sqlx::query("INSERT OR REPLACE INTO deleted_sources (uuid, deleted_at) VALUES ($1, $2)")
+            .bind(&tombstone.uuid)
+            .bind(tombstone.deleted_at)
+            .execute(&mut *transaction)
+            .await?;
+    }
+
+    transaction.commit().await?;
+    Ok(applied)
+}
+
+pub async fn get_last_sync(
+    device_id: &str,
+    pool: &SqlitePool,
+) -> Result<Option<NaiveDateTime>, sqlx::Error> {
+    sqlx::query_scalar("SELECT last_sync FROM sync_peers WHERE device_id = $1")
+        .bind(device_id)
+        .fetch_optional(pool)
+        .await
+}
+
+pub async fn set_last_sync(
+    device_id: &str,
+    last_sync: NaiveDateTime,
+    pool: &SqlitePool,
+) -> Result<(), sqlx::Error> {
+    debug!("Setting last sync with {} to {}", device_id, last_sync);
+
+    sqlx::query("INSERT OR REPLACE INTO sync_peers (device_id, last_sync) VALUES ($1, $2)")
+        .bind(device_id)
+        .bind(last_sync)
+        .execute(pool)
+        .await
+        .map(|_| ())
+}
+
+pub async fn get_library_setting(
+    key: &str,
+    pool: &SqlitePool,
+) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query_scalar("SELECT value FROM library_settings WHERE key = $1")
+        .bind(key)
+        .fetch_optional(pool)
+        .await
+}
+
+pub async fn set_library_setting(
+    key: &str,
+    value: &str,
+    pool: &SqlitePool,
+) -> Result<(), sqlx::Error> {
+    debug!("Setting library setting {} to {}", key, value);
+
+    sqlx::query("INSERT OR REPLACE INTO library_settings (key, value) VALUES ($1, $2)")
+        .bind(key)
+        .bind(value)
+        .execute(pool)
+        .await
+        .map(|_| ())
+}
+
+// replaces the snapshot the source had
+pub async fn save_snapshot(snapshot: &Snapshot, pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    debug!(
+        "Saving snapshot of source {} ({} characters)",
+        snapshot.source_id,
+        snapshot.text.len()
+    );
+
+    sqlx::query("INSERT OR REPLACE INTO snapshots (source_id, url, text, fetched_at, similarity, checked_at) VALUES ($1, $2, $3, $4, NULL, NULL)")
+        .bind(snapshot.source_id)
+        .bind(&snapshot.url)
+        .bind(&snapshot.text)
+        .bind(snapshot.fetched_at)
+        .execute(pool)
+        .await
+        .map(|_| ())
+}
+
+pub async fn get_snapshot(
+    source_id: i64,
+    pool: &SqlitePool,
+) -> Result<Option<Snapshot>, sqlx::Error> {
+    debug!("Fetching snapshot of source {}", source_id);
+
+    sqlx::query_as::<_, Snapshot>("SELECT * FROM snapshots WHERE source_id = $1")
+        .bind(source_id)
+        .fetch_optional(pool)
+        .await
+}
+
+// without their texts, the list only shows if a page changed
+pub async fn get_all_snapshots(pool: &SqlitePool) -> Result<Vec<Snapshot>, sqlx::Error> {
+    debug!("Fetching all snapshots");
+
+    sqlx::query_as::<_, Snapshot>(
+        "SELECT source_id, url, '' AS text, fetched_at, similarity, checked_at FROM snapshots",
+    )
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn set_snapshot_check(
+    source_id: i64,
+    similarity: f64,
+    checked_at: NaiveDateTime,
+    pool: &SqlitePool,
+) -> Result<(), sqlx::Error> {
+    debug!(
+        "Setting similarity of snapshot of source {} to {}",
+        source_id, similarity
+    );
+
+    sqlx::query("UPDATE snapshots SET similarity = $1, checked_at = $2 WHERE source_id = $3")
+        .bind(similarity)
+        .bind(checked_at)
+        .bind(source_id)
+        .execute(pool)
+        .await
+        .map(|_| ())
+}
+
+pub async fn delete_snapshot(source_id: i64, pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    debug!("Deleting snapshot of source {}", source_id);
+
+    sqlx::query("DELETE FROM snapshots WHERE source_id = $1")
+        .bind(source_id)
+        .execute(pool)
+        .await
+        .map(|_| ())
+}
+
+pub async fn insert_job(
+    source_id: i64,
+    kind: JobKind,
+    last_error: &str,
+    pool: &SqlitePool,
+) -> Result<(), sqlx::Error> {
+    debug!("Queueing network job {:?} for source {}", kind, source_id);
+
+    sqlx::query("INSERT INTO network_jobs (source_id, kind, attempts, last_error, created_at) VALUES ($1, $2, 1, $3, $4)")
+        .bind(source_id)
+        .bind(kind)
+        .bind(last_error)
+        .bind(Local::now().naive_local())
+        .execute(pool)
+        .await
+        .map(|_| ())
+}
+
+pub async fn get_all_jobs(pool: &SqlitePool) -> Result<Vec<NetworkJob>, sqlx::Error> {
+    sqlx::query_as::<_, NetworkJob>("SELECT * FROM network_jobs ORDER BY id")
+        .fetch_all(pool)
+        .await
+}
+
+pub async fn set_job_error(id: i64, error: &str, pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE network_jobs SET attempts = attempts + 1, last_error = $1 WHERE id = $2")
+        .bind(error)
+        .bind(id)
+        .execute(pool)
+        .await
+        .map(|_| ())
+}
+
+pub async fn delete_job(id: i64, pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    debug!("Deleting network job {}", id);
+
+    sqlx::query("DELETE FROM network_jobs WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await
+        .map(|_| ())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use chrono::TimeDelta;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    use super::*;
+
+    // empty library in memory, a single connection so every query sees the same database
+    pub(crate) async fn pool() -> SqlitePool {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        MIGRATOR.run(&pool).await.unwrap();
+        pool
+    }
+
+    async fn saved(title: &str, pool: &SqlitePool) -> Source {
+        let source = Source {
+            title: title.to_string(),
+            ..Source::default()
+        };
+        let id = insert_source(&source, pool).await.unwrap();
+        get_source(id, pool).await.unwrap().unwrap()
+    }
+
+    fn changed(source: &Source, title: &str, by: TimeDelta) -> Source {
+        Source {
+            title: title.to_string(),
+            updated_at: Some(source.updated_at.unwrap() + by),
+            ..source.clone()
+        }
+    }
+
+    fn tombstone(source: &Source, by: TimeDelta) -> Tombstone {
+        Tombstone {
+            uuid: source.uuid.clone(),
+            deleted_at: source.updated_at.unwrap() + by,
+        }
+    }
+
+    #[tokio::test]
+    async fn newer_change_wins() {
+        let pool = pool().await;
+        let local = saved("Local", &pool).await;
+        let remote = changed(&local, "Remote", TimeDelta::minutes(1));
+
+        assert_eq!(
+            apply_changes(std::slice::from_ref(&remote), &[], &pool)
+                .await
+                .unwrap(),
+            1
+        );
+        let merged = get_source(local.id, &pool).await.unwrap().unwrap();
+        assert_eq!(merged.title, "Remote");
+        assert_eq!(merged.updated_at, remote.updated_at);
+    }
+
+    #[tokio::test]
+    async fn older_change_is_ignored() {
+        let pool = pool().await;
+        let local = saved("Local", &pool).await;
+        let remote = changed(&local, "Remote", TimeDelta::minutes(-1));
+
+        assert_eq!(apply_changes(&[remote], &[], &pool).await.unwrap(), 0);
+        let merged = get_source(local.id, &pool).await.unwrap().unwrap();
+        assert_eq!(merged.title, "Local");
+        assert_eq!(merged.updated_at, local.updated_at);
+    }
+
+    #[tokio::test]
+    async fn tombstone_before_edit_keeps_source() {
+        let pool = pool().await;
+        let local = saved("Local", &pool).await;
+
+        let deleted = tombstone(&local, TimeDelta::minutes(-1));
+        assert_eq!(apply_changes(&[], &[deleted], &pool).await.unwrap(), 0);
+        assert!(get_source(local.id, &pool).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn tombstone_after_edit_deletes_source() {
+        let pool = pool().await;
+        let local = saved("Local", &pool).await;
+
+        let deleted = tombstone(&local, TimeDelta::minutes(1));
+        assert_eq!(
+            apply_changes(&[], std::slice::from_ref(&deleted), &pool)
+                .await
+                .unwrap(),
+            1
+        );
+        assert!(get_source(local.id, &pool).await.unwrap().is_none());
+
+        // the deletion keeps its time, so an edit made before it can't bring the source back
+        let (_, tombstones) = get_changes_since(None, &pool).await.unwrap();
+        assert_eq!(tombstones.len(), 1);
+        assert_eq!(tombstones[0].deleted_at, deleted.deleted_at);
+        let earlier = changed(&local, "Earlier", TimeDelta::seconds(30));
+        assert_eq!(apply_changes(&[earlier], &[], &pool).await.unwrap(), 0);
+        assert!(get_all_sources(&pool).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn edit_after_deletion_restores_source() {
+        let pool = pool().await;
+        let local = saved("Local", &pool).await;
+        let deleted = tombstone(&local, TimeDelta::minutes(1));
+        apply_changes(&[], &[deleted], &pool).await.unwrap();
+
+        let later = changed(&local, "Later", TimeDelta::minutes(2));
+        assert_eq!(apply_changes(&[later], &[], &pool).await.unwrap(), 1);
+        let sources = get_all_sources(&pool).await.unwrap();
+        assert_eq!(sources.len(), 1);
+        assert_eq!(sources[0].title, "Later");
+        assert_eq!(sources[0].uuid, local.uuid);
+    }
+
+    #[tokio::test]
+    async fn unknown_uuid_is_inserted() {
+        let pool = pool().await;
+        let remote = Source {
+            title: "Remote".to_string(),
+            uuid: "0123456789abcdef0123456789abcdef".to_string(),
+            updated_at: NaiveDateTime::from_str("2024-03-01T10:00:00").ok(),
+            ..Source::default()
+        };
+
+        assert_eq!(
+            apply_changes(std::slice::from_ref(&remote), &[], &pool)
+                .await
+                .unwrap(),
+            1
+        );
+        let sources = get_all_sources(&pool).await.unwrap();
+        assert_eq!(sources.len(), 1);
+        assert_eq!(sources[0].title, "Remote");
+        assert_eq!(sources[0].uuid, remote.uuid);
+        assert_eq!(sources[0].updated_at, remote.updated_at);
+    }
+
+    #[tokio::test]
+    async fn changes_since_last_sync() {
+        let pool = pool().await;
+        let old = saved("Old", &pool).await;
+        let since = old.updated_at.unwrap() + TimeDelta::minutes(1);
+        let new = changed(&saved("New", &pool).await, "New", TimeDelta::minutes(2));
+        apply_changes(&[new], &[], &pool).await.unwrap();
+        let gone = saved("Gone", &pool).await;
+        apply_changes(&[], &[tombstone(&gone, TimeDelta::minutes(3))], &pool)
+            .await
+            .unwrap();
+
+        let (sources, tombstones) = get_changes_since(None, &pool).await.unwrap();
+        assert_eq!(sources.len(), 2);
+        assert_eq!(tombstones.len(), 1);
+
+        let (sources, tombstones) = get_changes_since(Some(since), &pool).await.unwrap();
+        assert_eq!(
+            sources.iter().map(|s| s.title.as_str()).collect::<Vec<_>>(),
+            ["New"]
+        );
+        assert_eq!(tombstones.len(), 1);
+        assert_eq!(tombstones[0].uuid, gone.uuid);
+    }
+}
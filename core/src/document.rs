@@ -0,0 +1,16 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+// text the sources are cited in, e.g. "Thesis chapter 3"
+#[derive(Debug, FromRow, Clone, Serialize, Deserialize)]
+pub struct Document {
+    pub id: i64,
+    pub name: String,
+}
+
+// source cited in a document
+#[derive(Debug, FromRow, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CitedIn {
+    pub document_id: i64,
+    pub source_id: i64,
+}
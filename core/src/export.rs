@@ -0,0 +1,182 @@
+use chrono::{Datelike, NaiveDate};
+use serde_json::{json, Value};
+
+use crate::locale::{self, ENGLISH};
+use crate::source::{Source, SourceType};
+
+// BibTeX entries of the sources, one entry per source
+pub fn to_bibtex(sources: &[Source]) -> String {
+    let mut out = String::new();
+
+    for source in sources {
+        let entry_type = match source.source_type {
+            SourceType::JournalArticle => "article",
+            SourceType::Book => "book",
+            _ => "misc",
+        };
+        out.push_str(&format!("@{}{{saveit{},\n", entry_type, source.id));
+        out.push_str(&format!(
+            "  title = {{{}}},\n",
+            escape_bibtex(&source.title)
+        ));
+        if !source.author.is_empty() {
+            out.push_str(&format!(
+                "  author = {{{}}},\n",
+                escape_bibtex(&source.author)
+            ));
+        }
+        if !source.published_date_unknown {
+            out.push_str(&format!("  year = {{{}}},\n", source.published_date.year()));
+            out.push_str(&format!(
+                "  month = {{{}}},\n",
+                source.published_date.month()
+            ));
+        }
+        if !source.doi.is_empty() {
+            out.push_str(&format!("  doi = {{{}}},\n", escape_bibtex(&source.doi)));
+        }
+        if !source.url.is_empty() {
+            out.push_str(&format!(
+                "  url = {{{}}},\n",
+                escape_bibtex(source.cited_url())
+            ));
+        }
+        out.push_str(&format!(
+            "  urldate = {{{}}},\n",
+            source.viewed_date.format("%Y-%m-%d")
+        ));
+        if !source.tags.is_empty() {
+            out.push_str(&format!(
+                "  keywords = {{{}}},\n",
+                escape_bibtex(&source.tags)
+            ));
+        }
+        // biblatex names languages, e.g. "ngerman"
+        if let Some(langid) = locale::langid(&source.language) {
+            out.push_str(&format!("  langid = {{{}}},\n", langid));
+        }
+        if !source.comment.is_empty() {
+            out.push_str(&format!(
+                "  note = {{{}}},\n",
+                escape_bibtex(&source.comment)
+            ));
+        }
+        out.push_str("}\n\n");
+    }
+
+    out
+}
+
+// escapes characters with a special meaning in BibTeX values
+fn escape_bibtex(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '{' | '}' | '%' | '&' | '$' | '#' | '_' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '\\' => out.push_str("\\textbackslash{}"),
+            '\n' => out.push(' '),
+            c => out.push(c),
+        }
+    }
+
+    out
+}
+
+// CSL-JSON items of the sources, as used by Pandoc, Zotero and citeproc
+pub fn to_csl_json(sources: &[Source]) -> Value {
+    let items: Vec<Value> = sources
+        .iter()
+        .map(|source| {
+            let mut item = json!({
+                "id": format!("saveit{}", source.id),
+                "type": csl_type(source.source_type),
+                "title": source.title,
+                "accessed": csl_date(&source.viewed_date),
+            });
+
+            if !source.url.is_empty() {
+                item["URL"] = json!(source.cited_url());
+            }
+            if !source.doi.is_empty() {
+                item["DOI"] = json!(source.doi);
+            }
+            if !source.author.is_empty() {
+                item["author"] = json!([{ "literal": source.author }]);
+            }
+            if !source.published_date_unknown {
+                item["issued"] = csl_date(&source.published_date);
+            }
+            if !source.comment.is_empty() {
+                item["note"] = json!(source.comment);
+            }
+            if !source.language.is_empty() {
+                item["language"] = json!(source.language);
+            }
+            if !source.tags.is_empty() {
+                item["keyword"] = json!(source.tag_list().join(", "));
+            }
+
+            item
+        })
+        .collect();
+
+    Value::Array(items)
+}
+
+fn csl_type(source_type: SourceType) -> &'static str {
+    match source_type {
+        SourceType::Webpage => "webpage",
+        SourceType::Video => "motion_picture",
+        SourceType::JournalArticle => "article-journal",
+        SourceType::Book => "book",
+        SourceType::Image => "graphic",
+        SourceType::Other => "document",
+    }
+}
+
+fn csl_date(date: &NaiveDate) -> Value {
+    json!({ "date-parts": [[date.year(), date.month(), date.day()]] })
+}
+
+// APA style reference of a web source, e.g.
+// "Klabnik, S. (2018, August 12). The Rust Programming Language. Retrieved March 1, 2024, from https://…",
+// in German for German sources
+pub fn to_apa(source: &Source) -> String {
+    let terms = locale::terms(&source.language).unwrap_or(&ENGLISH);
+    let date = match source.published_date_unknown {
+        true => terms.no_date.to_string(),
+        false => format!(
+            "{}, {}",
+            source.published_date.year(),
+            terms.day_month(&source.published_date)
+        ),
+    };
+
+    let title = match source.source_type {
+        SourceType::Video => format!("{} [Video]", source.title),
+        SourceType::Image => format!("{} [Image]", source.title),
+        _ => source.title.clone(),
+    };
+
+    // without an author the title moves to the author position
+    let mut out = match source.author.is_empty() {
+        true => format!("{}. ({}).", title, date),
+        false => format!("{} ({}). {}.", source.author, date, title),
+    };
+
+    if !source.url.is_empty() {
+        out.push_str(&format!(
+            " {} {}, {} {}",
+            terms.retrieved,
+            terms.long_date(&source.viewed_date),
+            terms.from,
+            source.cited_url()
+        ));
+    }
+
+    out
+}
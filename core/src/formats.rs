@@ -0,0 +1,1251 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::fs::{self, File};
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime};
+use serde::de::{self, DeserializeSeed, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
+use tracing::*;
+
+use crate::export::{to_apa, to_bibtex, to_csl_json};
+use crate::import::{
+    is_citation_list, parse_bibtex, parse_bookmarks_html, parse_citations, parse_endnote_xml,
+    parse_firefox_json, parse_instapaper_csv, parse_pocket_csv, parse_reading_list_html, parse_ris,
+    ImportItem, ImportReport,
+};
+use crate::source::{Source, SourceType};
+
+const DETECT_PREFIX: u64 = 64 * 1024;
+
+// file format sources can be imported from and/or exported to, add new formats to FORMATS
+pub trait Format: Sync {
+    // used on the command line, e.g. "bibtex"
+    fn id(&self) -> &'static str;
+
+    // shown in file dialogs, e.g. "BibTeX"
+    fn name(&self) -> &'static str;
+
+    // first one is used for exported files
+    fn extensions(&self) -> &'static [&'static str];
+
+    fn can_import(&self) -> bool {
+        false
+    }
+
+    fn can_export(&self) -> bool {
+        false
+    }
+
+    // whether the content is a file of this format, used to pick the importer of a file
+    fn detect(&self, _content: &str) -> bool {
+        false
+    }
+
+    // sources with the folders they were stored in, for formats that have folders
+    fn import_items(&self, _content: &str) -> Result<Vec<ImportItem>, Box<dyn Error>> {
+        Err(format!("{} files can't be imported", self.name()).into())
+    }
+
+    // items with the entries that couldn't be read, formats that can skip single entries
+    // override it
+    fn import_report(&self, content: &str) -> Result<ImportReport, Box<dyn Error>> {
+        Ok(ImportReport {
+            items: self.import_items(content)?,
+            errors: vec![],
+        })
+    }
+
+    // sources with their folders as tags
+    fn import(&self, content: &str) -> Result<Vec<Source>, Box<dyn Error>> {
+        let items = self.import_items(content)?;
+
+        let sources = items
+            .into_iter()
+            .map(|mut item| {
+                item.source.add_tags(&item.folders.join(","));
+                item.source
+            })
+            .collect();
+        Ok(sources)
+    }
+
+    fn export(&self, _sources: &[Source]) -> Result<String, Box<dyn Error>> {
+        Err(format!("{} files can't be exported", self.name()).into())
+    }
+
+    // whether large files can be imported with import_stream instead of reading them at once
+    fn can_stream(&self) -> bool {
+        false
+    }
+
+    // passes every source to the sink while reading, entries that can't be converted to a source
+    // are passed as the reason, stops at the first error of the sink
+    fn import_stream(
+        &self,
+        _reader: &mut dyn Read,
+        _sink: &mut SourceSink,
+    ) -> Result<(), Box<dyn Error>> {
+        Err(format!("{} files can't be streamed", self.name()).into())
+    }
+}
+
+pub type SourceSink<'a> = dyn FnMut(Result<Source, String>) -> Result<(), Box<dyn Error>> + 'a;
+
+// detection tries the formats in this order
+pub static FORMATS: &[&dyn Format] = &[
+    &SaveItJson,
+    &SaveItJsonLines,
+    &CslJson,
+    &BibTex,
+    &Ris,
+    &Apa,
+    &FirefoxJson,
+    &PocketHtml,
+    &InstapaperHtml,
+    &PocketCsv,
+    &InstapaperCsv,
+    &EndNoteXml,
+    &BookmarksHtml,
+    &Citations, // guessed from the text, so after every format with a marker
+];
+
+pub fn importers() -> impl Iterator<Item = &'static dyn Format> {
+    FORMATS.iter().copied().filter(|format| format.can_import())
+}
+
+pub fn exporters() -> impl Iterator<Item = &'static dyn Format> {
+    FORMATS.iter().copied().filter(|format| format.can_export())
+}
+
+pub fn by_id(id: &str) -> Option<&'static dyn Format> {
+    FORMATS.iter().copied().find(|format| format.id() == id)
+}
+
+// first bytes of a file, enough to detect the format of files too large to read at once
+pub fn detect_prefix(path: &Path) -> Result<Option<&'static dyn Format>, Box<dyn Error>> {
+    let mut prefix = vec![];
+    File::open(path)?
+        .take(DETECT_PREFIX)
+        .read_to_end(&mut prefix)?;
+    let prefix = String::from_utf8_lossy(&prefix);
+
+    Ok(detect(prefix.trim_start_matches('\u{feff}')))
+}
+
+// extensions of all importable files, for the "supported files" filter of file dialogs
+pub fn import_extensions() -> Vec<&'static str> {
+    let mut extensions: Vec<&str> = importers()
+        .flat_map(|format| format.extensions().iter().copied())
+        .collect();
+    extensions.sort();
+    extensions.dedup();
+    extensions
+}
+
+pub fn detect(content: &str) -> Option<&'static dyn Format> {
+    importers().find(|format| format.detect(content))
+}
+
+// reads the file with the detected format
+pub fn read_file(path: &Path) -> Result<(&'static dyn Format, ImportReport), Box<dyn Error>> {
+    debug!("Reading import file {}", path.display());
+
+    let content = fs::read_to_string(path)?;
+    let content = content.trim_start_matches('\u{feff}');
+    let format = detect(content).ok_or("Unknown file format")?;
+    let report = format.import_report(content)?;
+
+    debug!(
+        "Read {} items as {}, {} entries skipped",
+        report.items.len(),
+        format.name(),
+        report.errors.len()
+    );
+    Ok((format, report))
+}
+
+// increase when older versions of SaveIt can't read the export anymore, new fields with a
+// default keep the version
+pub const EXPORT_VERSION: i64 = 3;
+
+// SaveIt's own export, {"version": 3, "sources": […]} since version 2 and a bare list of
+// sources before, dates are ISO dates like 2024-01-31 since version 3 and day numbers counting
+// January 1st of the year 1 as day 1 before
+struct SaveItJson;
+
+#[derive(Serialize)]
+struct Envelope {
+    version: i64,
+    app_version: String,
+    exported: NaiveDateTime,
+    sources: Vec<Entry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    id: i64,
+    title: String,
+    url: String,
+    author: String,
+    published_date: EntryDate,
+    viewed_date: EntryDate,
+    published_date_unknown: bool,
+    comment: String,
+    #[serde(default)]
+    last_cited: Option<NaiveDateTime>,
+    #[serde(default)]
+    tags: String,
+    #[serde(default)]
+    collection: String,
+    #[serde(default)]
+    doi: String,
+    #[serde(default)]
+    source_type: SourceType,
+    #[serde(default)]
+    uuid: String,
+    #[serde(default)]
+    updated_at: Option<NaiveDateTime>, // UTC
+    #[serde(default)]
+    pinned: bool,
+    #[serde(default)]
+    notes_written: bool,
+    #[serde(default)]
+    canonical_url: String,
+    #[serde(default)]
+    language: String,
+}
+
+impl From<Source> for Entry {
+    fn from(value: Source) -> Self {
+        Self {
+            id: value.id,
+            title: value.title,
+            url: value.url,
+            author: value.author,
+            published_date: EntryDate::from(value.published_date),
+            viewed_date: EntryDate::from(value.viewed_date),
+            published_date_unknown: value.published_date_unknown,
+            comment: value.comment,
+            last_cited: value.last_cited,
+            tags: value.tags,
+            collection: value.collection,
+            doi: value.doi,
+            source_type: value.source_type,
+            uuid: value.uuid,
+            updated_at: value.updated_at,
+            pinned: value.pinned,
+            notes_written: value.notes_written,
+            canonical_url: value.canonical_url,
+            language: value.language,
+        }
+    }
+}
+
+// ISO 8601 date like 2024-01-31 or the day number of exports before version 3, checked when the
+// entry is converted so a single invalid date doesn't stop the import
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum EntryDate {
+    Days(i64),
+    Iso(String),
+}
+
+impl EntryDate {
+    fn to_date(&self) -> Option<NaiveDate> {
+        match self {
+            EntryDate::Days(days) => i32::try_from(*days)
+                .ok()
+                .and_then(NaiveDate::from_num_days_from_ce_opt),
+            EntryDate::Iso(text) => {
+                let text = text.trim();
+                NaiveDate::parse_from_str(text, "%Y-%m-%d")
+                    .ok()
+                    .or_else(|| Some(DateTime::parse_from_rfc3339(text).ok()?.date_naive()))
+                    .or_else(|| Some(text.parse::<NaiveDateTime>().ok()?.date()))
+            }
+        }
+    }
+}
+
+impl From<NaiveDate> for EntryDate {
+    fn from(value: NaiveDate) -> Self {
+        EntryDate::Iso(value.format("%Y-%m-%d").to_string())
+    }
+}
+
+impl Display for EntryDate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EntryDate::Days(days) => write!(f, "{}", days),
+            EntryDate::Iso(text) => write!(f, "\"{}\"", text),
+        }
+    }
+}
+
+impl TryFrom<Entry> for Source {
+    type Error = String;
+
+    fn try_from(value: Entry) -> Result<Self, Self::Error> {
+        let date = |date: &EntryDate, name: &str| {
+            date.to_date()
+                .ok_or_else(|| format!("Invalid date {} {} in \"{}\"", name, date, value.title))
+        };
+
+        Ok(Source {
+            id: value.id,
+            published_date: date(&value.published_date, "published")?,
+            viewed_date: date(&value.viewed_date, "viewed")?,
+            title: value.title,
+            url: value.url,
+            author: value.author,
+            published_date_unknown: value.published_date_unknown,
+            comment: value.comment,
+            last_cited: value.last_cited,
+            tags: value.tags,
+            collection: value.collection,
+            doi: value.doi,
+            source_type: value.source_type,
+            uuid: value.uuid,
+            updated_at: value.updated_at,
+            pinned: value.pinned,
+            notes_written: value.notes_written,
+            canonical_url: value.canonical_url,
+            language: value.language,
+            shared: false,
+            library: String::new(),
+        })
+    }
+}
+
+impl Format for SaveItJson {
+    fn id(&self) -> &'static str {
+        "saveit-json"
+    }
+
+    fn name(&self) -> &'static str {
+        "SaveIt (JSON)"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["json"]
+    }
+
+    fn can_import(&self) -> bool {
+        true
+    }
+
+    fn can_export(&self) -> bool {
+        true
+    }
+
+    fn detect(&self, content: &str) -> bool {
+        let content = content.trim_start();
+        let start = content.get(..256).unwrap_or(content);
+
+        // a list of objects, not a reference list starting with "[1] …"
+        let list = content
+            .strip_prefix('[')
+            .is_some_and(|list| list.trim_start().starts_with(['{', ']']));
+
+        (list && !content.contains("\"date-parts\""))
+            || (content.starts_with('{')
+                && start.contains("\"version\"")
+                && content.contains("\"sources\""))
+    }
+
+    fn import_items(&self, content: &str) -> Result<Vec<ImportItem>, Box<dyn Error>> {
+        Ok(self.import_report(content)?.items)
+    }
+
+    fn import_report(&self, content: &str) -> Result<ImportReport, Box<dyn Error>> {
+        let mut report = ImportReport::default();
+        self.import_stream(&mut content.as_bytes(), &mut |entry| {
+            report.add(entry);
+            Ok(())
+        })?;
+
+        Ok(report)
+    }
+
+    fn export(&self, sources: &[Source]) -> Result<String, Box<dyn Error>> {
+        let envelope = Envelope {
+            version: EXPORT_VERSION,
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            exported: Local::now().naive_local(),
+            sources: sources.iter().cloned().map(Entry::from).collect(),
+        };
+        Ok(serde_json::to_string_pretty(&envelope)?)
+    }
+
+    fn can_stream(&self) -> bool {
+        true
+    }
+
+    // one entry of the list at a time instead of the whole list
+    fn import_stream(
+        &self,
+        reader: &mut dyn Read,
+        sink: &mut SourceSink,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut deserializer = serde_json::Deserializer::from_reader(BufReader::new(reader));
+        deserializer.deserialize_any(ExportVisitor { sink })?;
+        deserializer.end()?;
+
+        Ok(())
+    }
+}
+
+// the envelope or, before version 2, the bare list of sources
+struct ExportVisitor<'a, 'b> {
+    sink: &'a mut SourceSink<'b>,
+}
+
+impl<'de> Visitor<'de> for ExportVisitor<'_, '_> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a SaveIt export or a list of sources")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<(), A::Error> {
+        EntryVisitor { sink: self.sink }.visit_seq(seq)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        let mut version = None;
+
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "version" => {
+                    let number: i64 = map.next_value()?;
+                    if number > EXPORT_VERSION {
+                        return Err(de::Error::custom(format!(
+                            "Export version {} is not supported, please update SaveIt",
+                            number
+                        )));
+                    }
+                    version = Some(number);
+                }
+                "sources" => map.next_value_seed(EntrySeed {
+                    sink: &mut *self.sink,
+                })?,
+                // e.g. "app_version" and fields of newer compatible versions
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+
+        match version {
+            Some(_) => Ok(()),
+            None => Err(de::Error::missing_field("version")),
+        }
+    }
+}
+
+struct EntrySeed<'a, 'b> {
+    sink: &'a mut SourceSink<'b>,
+}
+
+impl<'de> DeserializeSeed<'de> for EntrySeed<'_, '_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(EntryVisitor { sink: self.sink })
+    }
+}
+
+// one entry of the list at a time
+struct EntryVisitor<'a, 'b> {
+    sink: &'a mut SourceSink<'b>,
+}
+
+impl<'de> Visitor<'de> for EntryVisitor<'_, '_> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a list of sources")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(entry) = seq.next_element::<Entry>()? {
+            (self.sink)(Source::try_from(entry)).map_err(de::Error::custom)?;
+        }
+
+        Ok(())
+    }
+}
+
+// one SaveIt JSON entry per line, can be read and written without holding all sources
+struct SaveItJsonLines;
+
+impl Format for SaveItJsonLines {
+    fn id(&self) -> &'static str {
+        "saveit-jsonl"
+    }
+
+    fn name(&self) -> &'static str {
+        "SaveIt (JSON lines)"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["jsonl", "ndjson"]
+    }
+
+    fn can_import(&self) -> bool {
+        true
+    }
+
+    fn can_export(&self) -> bool {
+        true
+    }
+
+    fn detect(&self, content: &str) -> bool {
+        let first_line = content.trim_start().lines().next().unwrap_or_default();
+        first_line.starts_with('{') && first_line.contains("\"viewed_date\"")
+    }
+
+    fn import_items(&self, content: &str) -> Result<Vec<ImportItem>, Box<dyn Error>> {
+        Ok(self.import_report(content)?.items)
+    }
+
+    fn import_report(&self, content: &str) -> Result<ImportReport, Box<dyn Error>> {
+        let mut report = ImportReport::default();
+        self.import_stream(&mut content.as_bytes(), &mut |entry| {
+            report.add(entry);
+            Ok(())
+        })?;
+
+        Ok(report)
+    }
+
+    fn export(&self, sources: &[Source]) -> Result<String, Box<dyn Error>> {
+        let mut out = String::new();
+        for source in sources {
+            out.push_str(&serde_json::to_string(&Entry::from(source.clone()))?);
+            out.push('\n');
+        }
+
+        Ok(out)
+    }
+
+    fn can_stream(&self) -> bool {
+        true
+    }
+
+    fn import_stream(
+        &self,
+        reader: &mut dyn Read,
+        sink: &mut SourceSink,
+    ) -> Result<(), Box<dyn Error>> {
+        let entries =
+            serde_json::Deserializer::from_reader(BufReader::new(reader)).into_iter::<Entry>();
+        for entry in entries {
+            sink(Source::try_from(entry?))?;
+        }
+
+        Ok(())
+    }
+}
+
+struct CslJson;
+
+impl Format for CslJson {
+    fn id(&self) -> &'static str {
+        "csl-json"
+    }
+
+    fn name(&self) -> &'static str {
+        "CSL-JSON"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["json"]
+    }
+
+    fn can_export(&self) -> bool {
+        true
+    }
+
+    fn export(&self, sources: &[Source]) -> Result<String, Box<dyn Error>> {
+        Ok(serde_json::to_string_pretty(&to_csl_json(sources))?)
+    }
+}
+
+struct BibTex;
+
+impl Format for BibTex {
+    fn id(&self) -> &'static str {
+        "bibtex"
+    }
+
+    fn name(&self) -> &'static str {
+        "BibTeX"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["bib"]
+    }
+
+    fn can_import(&self) -> bool {
+        true
+    }
+
+    fn can_export(&self) -> bool {
+        true
+    }
+
+    // text before the first entry is a comment, usually lines starting with %
+    fn detect(&self, content: &str) -> bool {
+        content
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty() && !line.starts_with('%'))
+            .is_some_and(|line| line.starts_with('@'))
+    }
+
+    fn import_items(&self, content: &str) -> Result<Vec<ImportItem>, Box<dyn Error>> {
+        Ok(self.import_report(content)?.items)
+    }
+
+    fn import_report(&self, content: &str) -> Result<ImportReport, Box<dyn Error>> {
+        Ok(parse_bibtex(content))
+    }
+
+    fn export(&self, sources: &[Source]) -> Result<String, Box<dyn Error>> {
+        Ok(to_bibtex(sources))
+    }
+}
+
+// tagged records, e.g. from Zotero, Mendeley or publisher websites
+struct Ris;
+
+impl Format for Ris {
+    fn id(&self) -> &'static str {
+        "ris"
+    }
+
+    fn name(&self) -> &'static str {
+        "RIS"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["ris"]
+    }
+
+    fn can_import(&self) -> bool {
+        true
+    }
+
+    fn detect(&self, content: &str) -> bool {
+        content.trim_start().starts_with("TY  -")
+    }
+
+    fn import_items(&self, content: &str) -> Result<Vec<ImportItem>, Box<dyn Error>> {
+        Ok(self.import_report(content)?.items)
+    }
+
+    fn import_report(&self, content: &str) -> Result<ImportReport, Box<dyn Error>> {
+        Ok(parse_ris(content))
+    }
+}
+
+// one reference per line
+struct Apa;
+
+impl Format for Apa {
+    fn id(&self) -> &'static str {
+        "apa"
+    }
+
+    fn name(&self) -> &'static str {
+        "APA references (text)"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["txt"]
+    }
+
+    fn can_export(&self) -> bool {
+        true
+    }
+
+    fn export(&self, sources: &[Source]) -> Result<String, Box<dyn Error>> {
+        let mut references: Vec<String> = sources.iter().map(to_apa).collect();
+        references.sort();
+
+        Ok(references.join("\n") + "\n")
+    }
+}
+
+// Netscape bookmark file exported by every browser
+struct BookmarksHtml;
+
+impl Format for BookmarksHtml {
+    fn id(&self) -> &'static str {
+        "bookmarks-html"
+    }
+
+    fn name(&self) -> &'static str {
+        "Bookmarks (HTML)"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["html", "htm"]
+    }
+
+    fn can_import(&self) -> bool {
+        true
+    }
+
+    fn detect(&self, content: &str) -> bool {
+        // some exporters leave out the doctype
+        content
+            .get(..40)
+            .unwrap_or(content)
+            .to_ascii_uppercase()
+            .contains("NETSCAPE-BOOKMARK-FILE")
+            || content.contains("<DT><A")
+    }
+
+    fn import_items(&self, content: &str) -> Result<Vec<ImportItem>, Box<dyn Error>> {
+        Ok(parse_bookmarks_html(content))
+    }
+}
+
+// reference list copied from a document, e.g. "Müller, H. (2019): Titel. URL: … [Stand: …]"
+struct Citations;
+
+impl Format for Citations {
+    fn id(&self) -> &'static str {
+        "citations"
+    }
+
+    fn name(&self) -> &'static str {
+        "Formatted references"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["txt"]
+    }
+
+    fn can_import(&self) -> bool {
+        true
+    }
+
+    fn detect(&self, content: &str) -> bool {
+        is_citation_list(content)
+    }
+
+    fn import_items(&self, content: &str) -> Result<Vec<ImportItem>, Box<dyn Error>> {
+        Ok(self.import_report(content)?.items)
+    }
+
+    fn import_report(&self, content: &str) -> Result<ImportReport, Box<dyn Error>> {
+        Ok(parse_citations(content))
+    }
+}
+
+// Firefox bookmark backup
+struct FirefoxJson;
+
+impl Format for FirefoxJson {
+    fn id(&self) -> &'static str {
+        "firefox-json"
+    }
+
+    fn name(&self) -> &'static str {
+        "Firefox bookmarks (JSON)"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["json"]
+    }
+
+    fn can_import(&self) -> bool {
+        true
+    }
+
+    fn detect(&self, content: &str) -> bool {
+        content.trim_start().starts_with('{') && content.contains("text/x-moz-place")
+    }
+
+    fn import_items(&self, content: &str) -> Result<Vec<ImportItem>, Box<dyn Error>> {
+        parse_firefox_json(content)
+    }
+}
+
+struct PocketHtml;
+
+impl Format for PocketHtml {
+    fn id(&self) -> &'static str {
+        "pocket-html"
+    }
+
+    fn name(&self) -> &'static str {
+        "Pocket (HTML)"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["html", "htm"]
+    }
+
+    fn can_import(&self) -> bool {
+        true
+    }
+
+    fn detect(&self, content: &str) -> bool {
+        content.contains("<title>Pocket Export</title>")
+    }
+
+    fn import_items(&self, content: &str) -> Result<Vec<ImportItem>, Box<dyn Error>> {
+        Ok(parse_reading_list_html(content))
+    }
+}
+
+struct PocketCsv;
+
+impl Format for PocketCsv {
+    fn id(&self) -> &'static str {
+        "pocket-csv"
+    }
+
+    fn name(&self) -> &'static str {
+        "Pocket (CSV)"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["csv"]
+    }
+
+    fn can_import(&self) -> bool {
+        true
+    }
+
+    fn detect(&self, content: &str) -> bool {
+        csv_header(content).starts_with("title,url,time_added")
+    }
+
+    fn import_items(&self, content: &str) -> Result<Vec<ImportItem>, Box<dyn Error>> {
+        parse_pocket_csv(content)
+    }
+}
+
+struct InstapaperHtml;
+
+impl Format for InstapaperHtml {
+    fn id(&self) -> &'static str {
+        "instapaper-html"
+    }
+
+    fn name(&self) -> &'static str {
+        "Instapaper (HTML)"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["html", "htm"]
+    }
+
+    fn can_import(&self) -> bool {
+        true
+    }
+
+    fn detect(&self, content: &str) -> bool {
+        content.contains("<title>Instapaper: Export</title>")
+    }
+
+    fn import_items(&self, content: &str) -> Result<Vec<ImportItem>, Box<dyn Error>> {
+        Ok(parse_reading_list_html(content))
+    }
+}
+
+struct InstapaperCsv;
+
+impl Format for InstapaperCsv {
+    fn id(&self) -> &'static str {
+        "instapaper-csv"
+    }
+
+    fn name(&self) -> &'static str {
+        "Instapaper (CSV)"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["csv"]
+    }
+
+    fn can_import(&self) -> bool {
+        true
+    }
+
+    fn detect(&self, content: &str) -> bool {
+        csv_header(content).starts_with("url,title,selection,folder")
+    }
+
+    fn import_items(&self, content: &str) -> Result<Vec<ImportItem>, Box<dyn Error>> {
+        parse_instapaper_csv(content)
+    }
+}
+
+struct EndNoteXml;
+
+impl Format for EndNoteXml {
+    fn id(&self) -> &'static str {
+        "endnote-xml"
+    }
+
+    fn name(&self) -> &'static str {
+        "EndNote (XML)"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["xml"]
+    }
+
+    fn can_import(&self) -> bool {
+        true
+    }
+
+    fn detect(&self, content: &str) -> bool {
+        let content = content.trim_start();
+        (content.starts_with("<?xml") || content.starts_with("<xml>"))
+            && content.contains("<records>")
+    }
+
+    fn import_items(&self, content: &str) -> Result<Vec<ImportItem>, Box<dyn Error>> {
+        parse_endnote_xml(content)
+    }
+}
+
+fn csv_header(content: &str) -> String {
+    content
+        .trim_start()
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    fn sample() -> Source {
+        Source {
+            id: 7,
+            title: "The Rust Programming Language".to_string(),
+            url: "https://doc.rust-lang.org/book/".to_string(),
+            author: "Steve Klabnik, Carol Nichols".to_string(),
+            published_date: NaiveDate::from_ymd_opt(2018, 8, 12).unwrap(),
+            viewed_date: NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(),
+            published_date_unknown: false,
+            comment: "Chapter 4 \"Ownership\"\nline two".to_string(),
+            last_cited: NaiveDate::from_ymd_opt(2024, 3, 2)
+                .unwrap()
+                .and_hms_opt(10, 30, 0),
+            tags: "rust, books".to_string(),
+            collection: "Thesis".to_string(),
+            doi: "10.1000/182".to_string(),
+            source_type: SourceType::Book,
+            uuid: "0123456789abcdef0123456789abcdef".to_string(),
+            updated_at: NaiveDate::from_ymd_opt(2024, 3, 3)
+                .unwrap()
+                .and_hms_milli_opt(8, 0, 0, 250),
+            pinned: true,
+            notes_written: true,
+            canonical_url: "https://doc.rust-lang.org/stable/book/".to_string(),
+            language: "en".to_string(),
+            shared: false,
+            library: String::new(),
+        }
+    }
+
+    fn assert_same(a: &Source, b: &Source) {
+        assert_eq!(a.id, b.id);
+        assert_eq!(a.title, b.title);
+        assert_eq!(a.url, b.url);
+        assert_eq!(a.author, b.author);
+        assert_eq!(a.published_date, b.published_date);
+        assert_eq!(a.viewed_date, b.viewed_date);
+        assert_eq!(a.published_date_unknown, b.published_date_unknown);
+        assert_eq!(a.comment, b.comment);
+        assert_eq!(a.last_cited, b.last_cited);
+        assert_eq!(a.tags, b.tags);
+        assert_eq!(a.collection, b.collection);
+        assert_eq!(a.doi, b.doi);
+        assert_eq!(a.source_type, b.source_type);
+        assert_eq!(a.uuid, b.uuid);
+        assert_eq!(a.updated_at, b.updated_at);
+        assert_eq!(a.pinned, b.pinned);
+        assert_eq!(a.notes_written, b.notes_written);
+        assert_eq!(a.canonical_url, b.canonical_url);
+        assert_eq!(a.language, b.language);
+    }
+
+    fn import(format: &dyn Format, content: &str) -> Result<Vec<Source>, Box<dyn Error>> {
+        Ok(format
+            .import_items(content)?
+            .into_iter()
+            .map(|item| item.source)
+            .collect())
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let sources = vec![sample(), Source::default()];
+        let content = SaveItJson.export(&sources).unwrap();
+        let imported = import(&SaveItJson, &content).unwrap();
+
+        assert_eq!(imported.len(), 2);
+        assert_same(&imported[0], &sources[0]);
+        assert_same(&imported[1], &sources[1]);
+    }
+
+    #[test]
+    fn json_lines_round_trip() {
+        let sources = vec![sample(), Source::default()];
+        let content = SaveItJsonLines.export(&sources).unwrap();
+        let imported = import(&SaveItJsonLines, &content).unwrap();
+
+        assert_eq!(imported.len(), 2);
+        assert_same(&imported[0], &sources[0]);
+        assert_same(&imported[1], &sources[1]);
+    }
+
+    #[test]
+    fn export_has_version() {
+        let content = SaveItJson.export(&[sample()]).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&content).unwrap();
+
+        assert_eq!(value["version"], EXPORT_VERSION);
+        assert_eq!(value["sources"].as_array().unwrap().len(), 1);
+        assert_eq!(value["sources"][0]["published_date"], "2018-08-12");
+        assert_eq!(value["sources"][0]["viewed_date"], "2024-03-01");
+        assert!(SaveItJson.detect(&content));
+    }
+
+    #[test]
+    fn reads_version_1() {
+        // bare list of a version 1 export, without the fields added later
+        let content = r#"[{"id":3,"title":"Old","url":"https://example.com","author":"","published_date":738000,"viewed_date":738100,"published_date_unknown":true,"comment":""}]"#;
+        assert!(SaveItJson.detect(content));
+
+        let imported = import(&SaveItJson, content).unwrap();
+        assert_eq!(imported.len(), 1);
+        assert_eq!(imported[0].title, "Old");
+        assert_eq!(
+            imported[0].viewed_date,
+            NaiveDate::from_num_days_from_ce_opt(738100).unwrap()
+        );
+        assert_eq!(imported[0].source_type, SourceType::Webpage);
+        assert!(imported[0].uuid.is_empty());
+        assert!(imported[0].updated_at.is_none());
+    }
+
+    #[test]
+    fn reads_iso_dates() {
+        let content = r#"{"version":2,"sources":[{"id":1,"title":"Edited","url":"","author":"","published_date":"2023-05-17","viewed_date":"2024-01-31T12:00:00Z","published_date_unknown":false,"comment":""}]}"#;
+
+        let imported = import(&SaveItJson, content).unwrap();
+        assert_eq!(
+            imported[0].published_date,
+            NaiveDate::from_ymd_opt(2023, 5, 17).unwrap()
+        );
+        assert_eq!(
+            imported[0].viewed_date,
+            NaiveDate::from_ymd_opt(2024, 1, 31).unwrap()
+        );
+    }
+
+    #[test]
+    fn skips_invalid_dates() {
+        let content = r#"{"id":1,"title":"Broken","url":"","author":"","published_date":99999999999,"viewed_date":738000,"published_date_unknown":false,"comment":""}
+{"id":2,"title":"Impossible","url":"","author":"","published_date":738000,"viewed_date":"2024-02-30","published_date_unknown":false,"comment":""}
+{"id":3,"title":"Good","url":"","author":"","published_date":738000,"viewed_date":738000,"published_date_unknown":false,"comment":""}
+"#;
+
+        let report = SaveItJsonLines.import_report(content).unwrap();
+        assert_eq!(report.items.len(), 1);
+        assert_eq!(report.items[0].source.title, "Good");
+        assert_eq!(report.errors.len(), 2);
+        assert!(report.errors[0].contains("Broken"), "{}", report.errors[0]);
+        assert!(
+            report.errors[1].contains("2024-02-30"),
+            "{}",
+            report.errors[1]
+        );
+    }
+
+    #[test]
+    fn ignores_unknown_fields() {
+        let content = r#"{"version":2,"future":{"a":[1,2]},"sources":[{"id":1,"title":"New","url":"","author":"","published_date":738000,"viewed_date":738000,"published_date_unknown":false,"comment":"","rating":5}]}"#;
+
+        let imported = import(&SaveItJson, content).unwrap();
+        assert_eq!(imported.len(), 1);
+        assert_eq!(imported[0].title, "New");
+    }
+
+    #[test]
+    fn rejects_newer_version() {
+        let content = format!(r#"{{"version":{},"sources":[]}}"#, EXPORT_VERSION + 1);
+
+        let error = import(&SaveItJson, &content).unwrap_err().to_string();
+        assert!(error.contains("please update SaveIt"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_missing_version() {
+        assert!(import(&SaveItJson, r#"{"sources":[]}"#).is_err());
+    }
+
+    #[test]
+    fn envelope_is_not_csl() {
+        let content = SaveItJson.export(&[sample()]).unwrap();
+        assert_eq!(
+            detect(&content).map(|format| format.id()),
+            Some("saveit-json")
+        );
+    }
+
+    #[test]
+    fn bibtex_round_trip() {
+        let content = BibTex.export(&[sample()]).unwrap();
+        assert_eq!(detect(&content).map(|format| format.id()), Some("bibtex"));
+
+        let imported = import(&BibTex, &content).unwrap();
+        assert_eq!(imported.len(), 1);
+        assert_eq!(imported[0].title, "The Rust Programming Language");
+        assert_eq!(imported[0].author, "Steve Klabnik, Carol Nichols");
+        // bibliographies cite the canonical URL
+        assert_eq!(imported[0].url, "https://doc.rust-lang.org/stable/book/");
+        assert_eq!(imported[0].doi, "10.1000/182");
+        assert_eq!(imported[0].source_type, SourceType::Book);
+        assert_eq!(imported[0].tags, "rust, books");
+        assert_eq!(imported[0].language, "en");
+        assert_eq!(
+            imported[0].published_date,
+            NaiveDate::from_ymd_opt(2018, 8, 1).unwrap()
+        );
+    }
+
+    #[test]
+    fn apa_follows_language() {
+        let mut source = sample();
+        source.published_date_unknown = true;
+        source.viewed_date = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+
+        let english = Apa.export(&[source.clone()]).unwrap();
+        assert!(english.contains("(n.d.)"));
+        assert!(english.contains("Retrieved March 1, 2024, from"));
+
+        source.language = "de".to_string();
+        let german = Apa.export(&[source]).unwrap();
+        assert!(german.contains("(o. J.)"));
+        assert!(german.contains("Abgerufen am 1. März 2024, von"));
+    }
+
+    #[test]
+    fn reads_bibtex() {
+        let content = r#"% exported by hand
+@string{rust = "Rust"}
+@article{mueller2019,
+  author = "M{\"u}ller, Hans and Doe, Jane",
+  title = "The " # {Rust} # " Book \& more",
+  year = 2019, month = may,
+  url = {https://example.com/paper}
+}
+@misc{broken, note = {no title}}
+"#;
+
+        let report = BibTex.import_report(content).unwrap();
+        assert_eq!(report.items.len(), 1);
+        assert_eq!(report.errors.len(), 1);
+
+        let source = &report.items[0].source;
+        assert_eq!(source.author, "Hans Müller, Jane Doe");
+        assert_eq!(source.title, "The Rust Book & more");
+        assert_eq!(source.source_type, SourceType::JournalArticle);
+        assert_eq!(
+            source.published_date,
+            NaiveDate::from_ymd_opt(2019, 5, 1).unwrap()
+        );
+    }
+
+    #[test]
+    fn reads_ris() {
+        let content = "TY  - JOUR
+TI  - Ownership in Rust
+AU  - Klabnik, Steve
+AU  - Nichols, Carol
+PY  - 2018/08/12/
+UR  - https://example.com/ownership
+DO  - 10.1000/182
+KW  - rust
+KW  - memory
+LA  - German
+ER  - 
+
+TY  - ELEC
+UR  - https://example.com/
+Y2  - 2024/03/01
+ER  - 
+";
+        assert_eq!(detect(content).map(|format| format.id()), Some("ris"));
+
+        let imported = import(&Ris, content).unwrap();
+        assert_eq!(imported.len(), 2);
+        assert_eq!(imported[0].title, "Ownership in Rust");
+        assert_eq!(imported[0].author, "Steve Klabnik, Carol Nichols");
+        assert_eq!(imported[0].doi, "10.1000/182");
+        assert_eq!(imported[0].tags, "rust, memory");
+        assert_eq!(imported[0].language, "de");
+        assert_eq!(imported[0].source_type, SourceType::JournalArticle);
+        assert_eq!(
+            imported[0].published_date,
+            NaiveDate::from_ymd_opt(2018, 8, 12).unwrap()
+        );
+        assert_eq!(imported[1].title, "https://example.com/");
+        assert!(imported[1].published_date_unknown);
+        assert_eq!(
+            imported[1].viewed_date,
+            NaiveDate::from_ymd_opt(2024, 3, 1).unwrap()
+        );
+    }
+
+    #[test]
+    fn reads_references() {
+        let content = "[1] Müller, H. (2019): Rust im Alltag. URL: https://example.com/rust [Stand: 01. 03. 2024]
+[2] Doe, J., & Roe, K. (2020, May 5). Ownership explained. Retrieved March 10, 2024, from https://doi.org/10.1000/182
+[3] Unbekannt (2021): Ohne Autor URL: https://example.com/ [Stand: 02. 03. 2024]
+";
+        assert_eq!(detect(content).map(|format| format.id()), Some("citations"));
+
+        let imported = import(&Citations, content).unwrap();
+        assert_eq!(imported.len(), 3);
+        assert_eq!(imported[0].title, "Rust im Alltag");
+        assert_eq!(imported[0].author, "H. Müller");
+        assert_eq!(imported[0].url, "https://example.com/rust");
+        assert_eq!(
+            imported[0].published_date,
+            NaiveDate::from_ymd_opt(2019, 1, 1).unwrap()
+        );
+        assert_eq!(
+            imported[0].viewed_date,
+            NaiveDate::from_ymd_opt(2024, 3, 1).unwrap()
+        );
+        assert_eq!(imported[1].title, "Ownership explained");
+        assert_eq!(imported[1].author, "J. Doe, K. Roe");
+        assert_eq!(imported[1].doi, "10.1000/182");
+        assert_eq!(imported[1].source_type, SourceType::JournalArticle);
+        assert_eq!(
+            imported[1].published_date,
+            NaiveDate::from_ymd_opt(2020, 5, 5).unwrap()
+        );
+        assert_eq!(imported[2].author, "");
+        assert_eq!(imported[2].title, "Ohne Autor");
+    }
+}
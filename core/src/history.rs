@@ -0,0 +1,100 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use sqlx::FromRow;
+
+use crate::source::{Source, SourceType};
+
+// change of a field of a source, written by the triggers of the source_history table
+#[derive(Debug, FromRow, Clone)]
+pub struct Change {
+    pub id: i64,
+    pub source_id: i64,
+    pub field: String, // column of the sources table, e.g. "url"
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+    pub changed_at: NaiveDateTime, // UTC
+}
+
+impl Change {
+    // name of the field as in the edit modal
+    pub fn field_name(&self) -> &str {
+        match self.field.as_str() {
+            "title" => "Title",
+            "url" => "URL",
+            "author" => "Author",
+            "published_date" => "Date published",
+            "viewed_date" => "Date viewed",
+            "published_date_unknown" => "Date published unknown",
+            "comment" => "Comment",
+            "tags" => "Tags",
+            "collection" => "Collection",
+            "doi" => "DOI",
+            "source_type" => "Type",
+            "notes_written" => "Notes written",
+            "canonical_url" => "Canonical URL",
+            "language" => "Language",
+            field => field,
+        }
+    }
+
+    pub fn old_text(&self) -> String {
+        display_value(&self.field, self.old_value.as_deref())
+    }
+
+    pub fn new_text(&self) -> String {
+        display_value(&self.field, self.new_value.as_deref())
+    }
+
+    // sets the field back to the old value, false if the value can't be read
+    pub fn revert(&self, source: &mut Source) -> bool {
+        let value = self.old_value.clone().unwrap_or_default();
+
+        match self.field.as_str() {
+            "title" => source.title = value,
+            "url" => source.url = value,
+            "author" => source.author = value,
+            "comment" => source.comment = value,
+            "tags" => source.tags = value,
+            "collection" => source.collection = value,
+            "doi" => source.doi = value,
+            "canonical_url" => source.canonical_url = value,
+            "language" => source.language = value,
+            "published_date" => match NaiveDate::parse_from_str(&value, "%Y-%m-%d") {
+                Ok(date) => source.published_date = date,
+                Err(_) => return false,
+            },
+            "viewed_date" => match NaiveDate::parse_from_str(&value, "%Y-%m-%d") {
+                Ok(date) => source.viewed_date = date,
+                Err(_) => return false,
+            },
+            "published_date_unknown" => source.published_date_unknown = value == "1",
+            "notes_written" => source.notes_written = value == "1",
+            "source_type" => match source_type(&value) {
+                Some(source_type) => source.source_type = source_type,
+                None => return false,
+            },
+            _ => return false,
+        }
+
+        true
+    }
+}
+
+// stored like the sqlx::Type of SourceType
+fn source_type(value: &str) -> Option<SourceType> {
+    serde_json::from_value(serde_json::Value::String(value.to_string())).ok()
+}
+
+fn display_value(field: &str, value: Option<&str>) -> String {
+    let value = value.unwrap_or_default();
+
+    match field {
+        "published_date_unknown" | "notes_written" => match value {
+            "1" => "Yes".to_string(),
+            _ => "No".to_string(),
+        },
+        "source_type" => source_type(value)
+            .map(|source_type| source_type.to_string())
+            .unwrap_or_else(|| value.to_string()),
+        _ => value.to_string(),
+    }
+}
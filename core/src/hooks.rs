@@ -0,0 +1,132 @@
+use std::error::Error;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::thread::JoinHandle;
+
+use serde_json::{json, Value};
+use tracing::*;
+
+use crate::config::Config;
+use crate::crash::record_operation;
+use crate::formats::Format;
+use crate::source::Source;
+
+#[cfg(windows)]
+const CREATE_NO_WINDOW: u32 = 0x08000000; // the GUI would flash a console window otherwise
+
+// events that run the command set for them in the settings
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HookEvent {
+    SourceSaved,
+    CitationCopied,
+    ExportFinished,
+}
+
+impl HookEvent {
+    // passed to the command as SAVEIT_EVENT and as "event" of the JSON
+    pub fn id(&self) -> &'static str {
+        match self {
+            HookEvent::SourceSaved => "source_saved",
+            HookEvent::CitationCopied => "citation_copied",
+            HookEvent::ExportFinished => "export_finished",
+        }
+    }
+
+    fn command<'a>(&self, config: &'a Config) -> &'a str {
+        match self {
+            HookEvent::SourceSaved => &config.hook_source_saved,
+            HookEvent::CitationCopied => &config.hook_citation_copied,
+            HookEvent::ExportFinished => &config.hook_export_finished,
+        }
+        .trim()
+    }
+}
+
+// runs the command of the event in the background with the payload as JSON on stdin, does
+// nothing if no command is set
+pub fn run(event: HookEvent, payload: Value, config: &Config) -> Option<JoinHandle<()>> {
+    let command = event.command(config).to_string();
+    if command.is_empty() {
+        return None;
+    }
+
+    debug!("Running {} hook: {}", event.id(), command);
+    record_operation(format!("Run {} hook", event.id()));
+
+    let mut input = json!({ "event": event.id() });
+    if let (Some(input), Value::Object(payload)) = (input.as_object_mut(), payload) {
+        input.extend(payload);
+    }
+
+    Some(std::thread::spawn(move || {
+        if let Err(e) = execute(&command, event, &input.to_string()) {
+            error!("Error running {} hook: {}", event.id(), e);
+        }
+    }))
+}
+
+// "export_finished" input
+pub fn export_payload(path: &Path, format: &dyn Format, sources: &[Source]) -> Value {
+    json!({
+        "path": path.to_string_lossy(),
+        "format": format.id(),
+        "sources": sources,
+    })
+}
+
+// runs the command and waits for it, for the command line where the process ends right after
+pub fn run_blocking(event: HookEvent, payload: Value, config: &Config) {
+    if let Some(handle) = run(event, payload, config) {
+        if handle.join().is_err() {
+            warn!("{} hook thread panicked", event.id());
+        }
+    }
+}
+
+fn execute(command: &str, event: HookEvent, input: &str) -> Result<(), Box<dyn Error>> {
+    let mut child = shell(command)
+        .env("SAVEIT_EVENT", event.id())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // commands don't have to read their input, a closed pipe isn't an error
+    if let Some(mut stdin) = child.stdin.take() {
+        if let Err(e) = stdin.write_all(input.as_bytes()) {
+            trace!("Hook didn't read its input: {}", e);
+        }
+    }
+
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(format!(
+            "{} ({})",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+
+    Ok(())
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    use std::os::windows::process::CommandExt;
+
+    let mut shell = Command::new("cmd");
+    shell
+        .arg("/C")
+        .arg(command)
+        .creation_flags(CREATE_NO_WINDOW);
+    shell
+}
+
+#[cfg(not(windows))]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
@@ -0,0 +1,101 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::sync::RwLock;
+use std::time::Duration;
+
+use once_cell::sync::Lazy;
+use tracing::*;
+
+use crate::config::Config;
+
+// client shared by every feature that goes online, rebuilt when the settings change
+static CLIENT: Lazy<RwLock<Result<reqwest::Client, HttpError>>> =
+    Lazy::new(|| RwLock::new(build(&HttpSettings::default())));
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpSettings {
+    pub timeout: Duration,
+    pub proxy: String, // e.g. "http://proxy:8080", empty for a direct connection
+    pub user_agent: String, // empty for "SaveIt/<version>"
+    pub offline: bool, // no network access at all
+}
+
+impl Default for HttpSettings {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(10),
+            proxy: String::new(),
+            user_agent: String::new(),
+            offline: false,
+        }
+    }
+}
+
+impl HttpSettings {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            timeout: Duration::from_secs(config.http_timeout.max(1)),
+            proxy: config.http_proxy.trim().to_string(),
+            user_agent: config.http_user_agent.trim().to_string(),
+            offline: config.offline_mode,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HttpError {
+    Offline,
+    InvalidSettings(String), // e.g. an invalid proxy URL
+}
+
+impl Display for HttpError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HttpError::Offline => write!(f, "Offline mode is enabled"),
+            HttpError::InvalidSettings(e) => write!(f, "Invalid network settings: {}", e),
+        }
+    }
+}
+
+impl Error for HttpError {}
+
+// applies the network settings to all following requests
+pub fn configure(settings: &HttpSettings) {
+    debug!("Configuring HTTP client: {:?}", settings);
+
+    *CLIENT.write().unwrap() = build(settings);
+}
+
+// the shared client, fails in offline mode
+pub fn client() -> Result<reqwest::Client, HttpError> {
+    CLIENT.read().unwrap().clone()
+}
+
+fn build(settings: &HttpSettings) -> Result<reqwest::Client, HttpError> {
+    if settings.offline {
+        return Err(HttpError::Offline);
+    }
+
+    let user_agent = match settings.user_agent.is_empty() {
+        true => format!(
+            "SaveIt/{} (https://github.com/TgZ39/SaveIt)",
+            env!("CARGO_PKG_VERSION")
+        ),
+        false => settings.user_agent.clone(),
+    };
+
+    // long downloads like updates set their own timeout per request
+    let mut builder = reqwest::Client::builder()
+        .user_agent(user_agent)
+        .connect_timeout(settings.timeout)
+        .timeout(settings.timeout);
+    if !settings.proxy.is_empty() {
+        let proxy = reqwest::Proxy::all(&settings.proxy)
+            .map_err(|e| HttpError::InvalidSettings(e.to_string()))?;
+        builder = builder.proxy(proxy);
+    }
+
+    builder
+        .build()
+        .map_err(|e| HttpError::InvalidSettings(e.to_string()))
+}
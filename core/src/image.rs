@@ -0,0 +1,80 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use chrono::NaiveDate;
+use exif::{Exif, In, Tag, Value};
+use reqwest::Url;
+use tracing::*;
+
+use crate::crash::record_operation;
+use crate::metadata::Metadata;
+use crate::source::SourceType;
+
+// files that can become image sources, the ones with EXIF data also get their capture date
+pub const IMAGE_EXTENSIONS: [&str; 9] = [
+    "jpg", "jpeg", "png", "tif", "tiff", "webp", "heic", "heif", "avif",
+];
+
+pub fn is_image(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| {
+        IMAGE_EXTENSIONS
+            .iter()
+            .any(|known| extension.eq_ignore_ascii_case(known))
+    })
+}
+
+// image source for a photo, figure or screenshot: the file as URL, the file name or EXIF
+// description as title, the EXIF artist as author and the capture date as date published
+pub fn read_metadata(path: &Path) -> Metadata {
+    debug!("Reading image metadata of {}", path.display());
+    record_operation("Read image metadata");
+
+    // screenshots usually don't have EXIF data, that isn't an error
+    let exif = match read_exif(path) {
+        Ok(exif) => Some(exif),
+        Err(e) => {
+            debug!("No EXIF data in {}: {}", path.display(), e);
+            None
+        }
+    };
+    let field = |tag: Tag| exif.as_ref().and_then(|exif| ascii_field(exif, tag));
+
+    let title = field(Tag::ImageDescription).or_else(|| {
+        path.file_stem()
+            .map(|stem| stem.to_string_lossy().replace(['_', '-'], " "))
+    });
+    let published_date = [Tag::DateTimeOriginal, Tag::DateTimeDigitized, Tag::DateTime]
+        .into_iter()
+        .find_map(|tag| field(tag).and_then(|date| exif_date(&date)));
+
+    Metadata {
+        title,
+        author: field(Tag::Artist),
+        published_date,
+        url: Url::from_file_path(path).ok().map(String::from),
+        doi: None,
+        source_type: Some(SourceType::Image),
+    }
+}
+
+fn read_exif(path: &Path) -> Result<Exif, Box<dyn std::error::Error>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    Ok(exif::Reader::new().read_from_container(&mut reader)?)
+}
+
+fn ascii_field(exif: &Exif, tag: Tag) -> Option<String> {
+    let field = exif.get_field(tag, In::PRIMARY)?;
+    let Value::Ascii(values) = &field.value else {
+        return None;
+    };
+    let value = String::from_utf8_lossy(values.first()?);
+    let value = value.trim_matches(char::from(0)).trim();
+
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+// "2019:05:03 14:03:23" -> 2019-05-03
+fn exif_date(date: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(date.get(..10)?, "%Y:%m:%d").ok()
+}
@@ -0,0 +1,1644 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::io::Read;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+
+use chrono::{DateTime, Datelike, Local, NaiveDate};
+use once_cell::sync::Lazy;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use regex::Regex;
+use serde_json::Value;
+
+use crate::locale;
+use crate::metadata::{decode_entities, parse_attributes};
+use crate::paste::{find_date, find_url};
+use crate::search::normalize;
+use crate::source::{Source, SourceType};
+use crate::url_analysis::extract_doi;
+
+// source read from an import file with the folders it was stored in, outermost first
+#[derive(Debug, Clone)]
+pub struct ImportItem {
+    pub source: Source,
+    pub folders: Vec<String>,
+}
+
+// what was read from an import file, entries that couldn't be read are skipped with the reason
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub items: Vec<ImportItem>,
+    pub errors: Vec<String>,
+}
+
+impl ImportReport {
+    pub fn add(&mut self, entry: Result<Source, String>) {
+        match entry {
+            Ok(source) => self.items.push(ImportItem {
+                source,
+                folders: vec![],
+            }),
+            Err(e) => self.errors.push(e),
+        }
+    }
+}
+
+// <DL> lists of <DT><H3>folder</H3> and <DT><A HREF="…" ADD_DATE="…">title</A> entries,
+// optionally followed by a <DD> description
+pub fn parse_bookmarks_html(html: &str) -> Vec<ImportItem> {
+    let mut items = vec![];
+    let mut folders: Vec<Option<String>> = vec![]; // one per open <DL>, None for the browser's root folders
+    let mut next_folder: Option<String> = None; // <H3> waiting for its <DL>
+    let mut last_was_link = false;
+
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        let Some(end) = rest[start..].find('>').map(|end| start + end) else {
+            break;
+        };
+        let tag = &rest[start + 1..end];
+        let after = &rest[end + 1..];
+        let name_end = tag.find(char::is_whitespace).unwrap_or(tag.len());
+        let (name, attributes) = (tag[..name_end].to_ascii_lowercase(), &tag[name_end..]);
+        let text = decode_entities(after[..after.find('<').unwrap_or(after.len())].trim());
+
+        match name.as_str() {
+            "h3" => {
+                let attributes = parse_attributes(attributes);
+                let root = attributes.iter().any(|(key, _)| {
+                    key == "personal_toolbar_folder" || key == "unfiled_bookmarks_folder"
+                });
+                next_folder = (!root && !text.is_empty()).then_some(text);
+                last_was_link = false;
+            }
+            "dl" => folders.push(next_folder.take()),
+            "/dl" => {
+                folders.pop();
+                last_was_link = false;
+            }
+            "a" => {
+                let attributes = parse_attributes(attributes);
+                let attribute = |name: &str| {
+                    attributes
+                        .iter()
+                        .find(|(key, _)| key == name)
+                        .map(|(_, value)| value.as_str())
+                };
+
+                last_was_link = false;
+                if let Some(url) = attribute("href").filter(|url| is_web_url(url)) {
+                    let mut source = Source {
+                        title: match text.is_empty() {
+                            true => url.to_string(),
+                            false => text,
+                        },
+                        url: url.to_string(),
+                        published_date_unknown: true,
+                        tags: attribute("tags").unwrap_or_default().replace(',', ", "),
+                        ..Default::default()
+                    };
+                    if let Some(date) = attribute("add_date")
+                        .and_then(|seconds| seconds.parse().ok())
+                        .and_then(date_from_seconds)
+                    {
+                        source.viewed_date = date;
+                    }
+
+                    items.push(ImportItem {
+                        source,
+                        folders: folders.iter().flatten().cloned().collect(),
+                    });
+                    last_was_link = true;
+                }
+            }
+            "dd" if last_was_link => {
+                if let Some(item) = items.last_mut() {
+                    item.source.comment = text;
+                }
+                last_was_link = false;
+            }
+            _ => {}
+        }
+
+        rest = after;
+    }
+
+    items
+}
+
+// tree of "text/x-moz-place-container" folders with "text/x-moz-place" bookmarks
+pub fn parse_firefox_json(json: &str) -> Result<Vec<ImportItem>, Box<dyn Error>> {
+    let root: Value = serde_json::from_str(json)?;
+
+    let mut items = vec![];
+    collect_firefox_items(&root, &mut vec![], &mut items);
+
+    Ok(items)
+}
+
+fn collect_firefox_items(node: &Value, folders: &mut Vec<String>, items: &mut Vec<ImportItem>) {
+    let title = node["title"].as_str().unwrap_or_default();
+
+    match node["type"].as_str() {
+        Some("text/x-moz-place-container") => {
+            // "menu", "toolbar" etc. aren't folders of the user
+            let folder = node.get("root").is_none() && !title.is_empty();
+            if folder {
+                folders.push(title.to_string());
+            }
+            for child in node["children"].as_array().into_iter().flatten() {
+                collect_firefox_items(child, folders, items);
+            }
+            if folder {
+                folders.pop();
+            }
+        }
+        Some("text/x-moz-place") => {
+            let Some(url) = node["uri"].as_str().filter(|url| is_web_url(url)) else {
+                return;
+            };
+
+            let mut source = Source {
+                title: match title.is_empty() {
+                    true => url.to_string(),
+                    false => title.to_string(),
+                },
+                url: url.to_string(),
+                published_date_unknown: true,
+                tags: node["tags"].as_str().unwrap_or_default().replace(',', ", "),
+                ..Default::default()
+            };
+            // microseconds
+            if let Some(date) = node["dateAdded"]
+                .as_i64()
+                .and_then(|micros| date_from_seconds(micros / 1_000_000))
+            {
+                source.viewed_date = date;
+            }
+
+            items.push(ImportItem {
+                source,
+                folders: folders.clone(),
+            });
+        }
+        _ => {}
+    }
+}
+
+// <h1>Unread</h1><ul><li><a href="…" time_added="…" tags="…">title</a></li></ul>, the
+// heading ("Unread", "Read Archive") becomes the folder
+pub fn parse_reading_list_html(html: &str) -> Vec<ImportItem> {
+    let mut items = vec![];
+    let mut folder: Option<String> = None;
+
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        let Some(end) = rest[start..].find('>').map(|end| start + end) else {
+            break;
+        };
+        let tag = &rest[start + 1..end];
+        let after = &rest[end + 1..];
+        let name_end = tag.find(char::is_whitespace).unwrap_or(tag.len());
+        let (name, attributes) = (tag[..name_end].to_ascii_lowercase(), &tag[name_end..]);
+        let text = decode_entities(after[..after.find('<').unwrap_or(after.len())].trim());
+
+        match name.as_str() {
+            "h1" => folder = (!text.is_empty()).then_some(text),
+            "a" => {
+                let attributes = parse_attributes(attributes);
+                let attribute = |name: &str| {
+                    attributes
+                        .iter()
+                        .find(|(key, _)| key == name)
+                        .map(|(_, value)| value.as_str())
+                };
+
+                if let Some(url) = attribute("href").filter(|url| is_web_url(url)) {
+                    items.push(reading_list_item(
+                        url,
+                        &text,
+                        attribute("time_added").unwrap_or_default(),
+                        &attribute("tags").unwrap_or_default().replace(',', ", "),
+                        folder.as_deref(),
+                    ));
+                }
+            }
+            _ => {}
+        }
+
+        rest = after;
+    }
+
+    items
+}
+
+// title,url,time_added,tags,status with tags separated by "|"
+pub fn parse_pocket_csv(csv: &str) -> Result<Vec<ImportItem>, Box<dyn Error>> {
+    let mut items = vec![];
+
+    for record in csv_records(csv)? {
+        let field = |name: &str| record.get(name).map(String::as_str).unwrap_or_default();
+        if !is_web_url(field("url")) {
+            continue;
+        }
+
+        items.push(reading_list_item(
+            field("url"),
+            field("title"),
+            field("time_added"),
+            &field("tags").replace('|', ", "),
+            Some(field("status")).filter(|status| !status.is_empty()),
+        ));
+    }
+
+    Ok(items)
+}
+
+// URL,Title,Selection,Folder,Timestamp and in newer exports Tags as a JSON list
+pub fn parse_instapaper_csv(csv: &str) -> Result<Vec<ImportItem>, Box<dyn Error>> {
+    let mut items = vec![];
+
+    for record in csv_records(csv)? {
+        let field = |name: &str| record.get(name).map(String::as_str).unwrap_or_default();
+        if !is_web_url(field("url")) {
+            continue;
+        }
+
+        let tags = serde_json::from_str::<Vec<String>>(field("tags"))
+            .unwrap_or_default()
+            .join(", ");
+        let mut item = reading_list_item(
+            field("url"),
+            field("title"),
+            field("timestamp"),
+            &tags,
+            Some(field("folder")).filter(|folder| !folder.is_empty()),
+        );
+        item.source.comment = field("selection").to_string();
+
+        items.push(item);
+    }
+
+    Ok(items)
+}
+
+// rows of a CSV file by lowercase column name
+fn csv_records(csv: &str) -> Result<Vec<HashMap<String, String>>, Box<dyn Error>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(csv.trim_start_matches('\u{feff}').as_bytes());
+    let headers: Vec<String> = reader
+        .headers()?
+        .iter()
+        .map(|header| header.trim().to_lowercase())
+        .collect();
+
+    let mut records = vec![];
+    for record in reader.records() {
+        let record = record?;
+        records.push(
+            headers
+                .iter()
+                .cloned()
+                .zip(record.iter().map(|value| value.trim().to_string()))
+                .collect(),
+        );
+    }
+
+    Ok(records)
+}
+
+fn reading_list_item(
+    url: &str,
+    title: &str,
+    time_added: &str, // unix time in seconds
+    tags: &str,
+    folder: Option<&str>,
+) -> ImportItem {
+    let mut source = Source {
+        title: match title.is_empty() {
+            true => url.to_string(),
+            false => title.to_string(),
+        },
+        url: url.to_string(),
+        published_date_unknown: true,
+        tags: tags.to_string(),
+        ..Default::default()
+    };
+    if let Some(date) = time_added.parse().ok().and_then(date_from_seconds) {
+        source.viewed_date = date;
+    }
+
+    ImportItem {
+        source,
+        folders: folder.map(str::to_string).into_iter().collect(),
+    }
+}
+
+// <record> elements of an EndNote library exported as XML, text is usually wrapped in
+// <style> elements
+pub fn parse_endnote_xml(xml: &str) -> Result<Vec<ImportItem>, Box<dyn Error>> {
+    // not trimmed, the text of a field is split into <style> runs like "Klabnik," and " Steve",
+    // the whitespace is collapsed in into_item
+    let mut reader = Reader::from_str(xml);
+
+    let mut items = vec![];
+    let mut path: Vec<String> = vec![];
+    let mut record = EndNoteRecord::default();
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(tag) => {
+                let name = String::from_utf8_lossy(tag.local_name().as_ref()).to_string();
+                if name == "record" {
+                    record = EndNoteRecord::default();
+                }
+                if name == "ref-type" {
+                    record.ref_type = endnote_ref_type(&tag)?;
+                }
+                // a new author or keyword starts a new list entry
+                if name == "author" {
+                    record.authors.push(String::new());
+                }
+                if name == "keyword" {
+                    record.keywords.push(String::new());
+                }
+                if name != "style" {
+                    path.push(name);
+                }
+            }
+            Event::Empty(tag) if tag.local_name().as_ref() == b"ref-type" => {
+                record.ref_type = endnote_ref_type(&tag)?;
+            }
+            Event::End(tag) => {
+                if tag.local_name().as_ref() == b"style" {
+                    continue;
+                }
+                if path.pop().as_deref() == Some("record") {
+                    if let Some(item) = std::mem::take(&mut record).into_item() {
+                        items.push(item);
+                    }
+                }
+            }
+            Event::Text(text) => {
+                let text = text.unescape()?;
+                record.add_text(&path, &text);
+            }
+            Event::CData(text) => {
+                let text = String::from_utf8_lossy(&text).to_string();
+                record.add_text(&path, &text);
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(items)
+}
+
+fn endnote_ref_type(tag: &BytesStart) -> Result<Option<SourceType>, Box<dyn Error>> {
+    let name = match tag.try_get_attribute("name")? {
+        Some(attribute) => attribute.unescape_value()?.to_string(),
+        None => return Ok(None),
+    };
+
+    let source_type = match name.as_str() {
+        "Journal Article" | "Electronic Article" | "Magazine Article" => SourceType::JournalArticle,
+        "Book" | "Book Section" | "Edited Book" | "Electronic Book" => SourceType::Book,
+        "Web Page" | "Blog" => SourceType::Webpage,
+        "Film or Broadcast" | "Online Multimedia" => SourceType::Video,
+        "Artwork" | "Figure" => SourceType::Image,
+        _ => SourceType::Other,
+    };
+
+    Ok(Some(source_type))
+}
+
+// fields of one EndNote record while it is read
+#[derive(Default)]
+struct EndNoteRecord {
+    ref_type: Option<SourceType>,
+    title: String,
+    authors: Vec<String>,
+    year: String,
+    date: String, // e.g. "March 3" or "03/03/2020"
+    url: String,
+    doi: String,
+    notes: String,
+    keywords: Vec<String>,
+}
+
+impl EndNoteRecord {
+    fn add_text(&mut self, path: &[String], text: &str) {
+        let append = |field: &mut String| field.push_str(text);
+
+        match path.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+            [.., "record", "titles", "title"] => append(&mut self.title),
+            [.., "contributors", "authors", "author"] => {
+                if let Some(author) = self.authors.last_mut() {
+                    author.push_str(text);
+                }
+            }
+            [.., "dates", "year"] => append(&mut self.year),
+            [.., "pub-dates", "date"] => append(&mut self.date),
+            // only the first URL
+            [.., "urls", "related-urls", "url"] | [.., "urls", "web-urls", "url"]
+                if self.url.is_empty() =>
+            {
+                self.url = text.trim().to_string()
+            }
+            [.., "record", "electronic-resource-num"] => append(&mut self.doi),
+            [.., "record", "notes"] => append(&mut self.notes),
+            [.., "keywords", "keyword"] => {
+                if let Some(keyword) = self.keywords.last_mut() {
+                    keyword.push_str(text);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn into_item(self) -> Option<ImportItem> {
+        // titles are wrapped over several lines
+        let title = collapse_whitespace(&self.title);
+        if title.is_empty() && self.url.is_empty() {
+            return None;
+        }
+
+        // "Klabnik, Steve" -> "Steve Klabnik"
+        let authors: Vec<String> = self
+            .authors
+            .iter()
+            .map(|author| collapse_whitespace(author))
+            .map(|author| match author.split_once(',') {
+                Some((last, first)) if !first.trim().is_empty() => {
+                    format!("{} {}", first.trim(), last.trim())
+                }
+                _ => author,
+            })
+            .filter(|author| !author.is_empty())
+            .collect();
+
+        let published_date = self.year.trim().parse::<i32>().ok().and_then(|year| {
+            let date = collapse_whitespace(&self.date);
+            let date = date.as_str();
+            NaiveDate::parse_from_str(&format!("{} {}", date, year), "%B %d %Y")
+                .ok()
+                .or_else(|| NaiveDate::parse_from_str(date, "%m/%d/%Y").ok())
+                .filter(|date| date.year() == year)
+                .or_else(|| NaiveDate::from_ymd_opt(year, 1, 1))
+        });
+
+        let keywords: Vec<String> = self
+            .keywords
+            .iter()
+            .map(|keyword| collapse_whitespace(keyword))
+            .filter(|keyword| !keyword.is_empty())
+            .collect();
+
+        let mut source = Source {
+            title: match title.is_empty() {
+                true => self.url.clone(),
+                false => title,
+            },
+            url: self.url,
+            author: authors.join(", "),
+            published_date_unknown: published_date.is_none(),
+            comment: self.notes.trim().to_string(),
+            tags: keywords.join(", "),
+            doi: extract_doi(&self.doi).unwrap_or_default(),
+            source_type: self.ref_type.unwrap_or_default(),
+            ..Default::default()
+        };
+        if let Some(date) = published_date {
+            source.published_date = date;
+        }
+
+        Some(ImportItem {
+            source,
+            folders: vec![],
+        })
+    }
+}
+
+// "The Rust\n    Programming " -> "The Rust Programming"
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// @type{key, field = {value}, field = "value", year = 2019} entries, @string, @preamble and
+// @comment are skipped, entries without a title or URL are reported
+pub fn parse_bibtex(bibtex: &str) -> ImportReport {
+    let mut report = ImportReport::default();
+    let mut rest = bibtex;
+
+    while let Some(at) = rest.find('@') {
+        rest = &rest[at + 1..];
+        let Some(open) = rest.find(['{', '(']) else {
+            break;
+        };
+        let entry_type = rest[..open].trim().to_ascii_lowercase();
+        let close = match rest.as_bytes()[open] {
+            b'(' => ')',
+            _ => '}',
+        };
+        let end = matching_close(rest, open, close).unwrap_or(rest.len());
+        let body = &rest[open + 1..end];
+        rest = &rest[(end + 1).min(rest.len())..];
+
+        if entry_type.is_empty() || matches!(entry_type.as_str(), "string" | "preamble" | "comment")
+        {
+            continue;
+        }
+
+        let (key, fields) = match body.split_once(',') {
+            Some((key, fields)) => (key.trim(), parse_bibtex_fields(fields)),
+            None => (body.trim(), vec![]),
+        };
+        report.add(bibtex_source(&entry_type, key, &fields));
+    }
+
+    report
+}
+
+// index of the bracket closing the one at `open`, nested braces and quoted values are skipped
+fn matching_close(text: &str, open: usize, close: char) -> Option<usize> {
+    let mut depth = 0;
+    let mut escaped = false;
+
+    for (index, c) in text[open + 1..].char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '{' => depth += 1,
+            '}' if depth > 0 => depth -= 1,
+            c if c == close && depth == 0 => return Some(open + 1 + index),
+            _ => {}
+        }
+    }
+
+    None
+}
+
+// name = value pairs with lowercase names, values can be joined with #
+fn parse_bibtex_fields(text: &str) -> Vec<(String, String)> {
+    let mut fields = vec![];
+    let mut rest = text;
+
+    while let Some(equals) = rest.find('=') {
+        let name = rest[..equals]
+            .trim()
+            .trim_start_matches(',')
+            .trim()
+            .to_ascii_lowercase();
+        rest = rest[equals + 1..].trim_start();
+
+        let mut value = String::new();
+        loop {
+            let (part, after) = match rest.chars().next() {
+                Some('{') => {
+                    let end = matching_close(rest, 0, '}').unwrap_or(rest.len());
+                    (&rest[1..end], &rest[(end + 1).min(rest.len())..])
+                }
+                Some('"') => {
+                    let mut depth = 0;
+                    let end = rest[1..]
+                        .char_indices()
+                        .find(|(_, c)| {
+                            match c {
+                                '{' => depth += 1,
+                                '}' => depth -= 1,
+                                _ => {}
+                            }
+                            *c == '"' && depth == 0
+                        })
+                        .map_or(rest.len(), |(index, _)| index + 1);
+                    (&rest[1..end], &rest[(end + 1).min(rest.len())..])
+                }
+                _ => {
+                    let end = rest.find([',', '#']).unwrap_or(rest.len());
+                    (rest[..end].trim(), &rest[end..])
+                }
+            };
+            value.push_str(part);
+
+            rest = after.trim_start();
+            match rest.strip_prefix('#') {
+                Some(after) => rest = after.trim_start(),
+                None => break,
+            }
+        }
+
+        fields.push((name, unescape_latex(&value)));
+        rest = rest.trim_start_matches(|c: char| c == ',' || c.is_whitespace());
+    }
+
+    fields
+}
+
+// removes the braces, commands and the escapes written by to_bibtex, keeps common accents
+fn unescape_latex(text: &str) -> String {
+    let mut text = text.replace("\\textbackslash{}", "\u{0}");
+    for (escaped, plain) in [
+        ("\\\"a", "ä"),
+        ("\\\"o", "ö"),
+        ("\\\"u", "ü"),
+        ("\\\"A", "Ä"),
+        ("\\\"O", "Ö"),
+        ("\\\"U", "Ü"),
+        ("\\ss{}", "ß"),
+        ("\\'e", "é"),
+        ("\\`e", "è"),
+        ("\\'a", "á"),
+    ] {
+        text = text.replace(escaped, plain);
+    }
+
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            // commands like \emph are dropped, escaped characters like \& are kept
+            '\\' => match chars.peek() {
+                Some(next) if next.is_ascii_alphabetic() => {
+                    while chars.next_if(char::is_ascii_alphabetic).is_some() {}
+                }
+                _ => out.extend(chars.next()),
+            },
+            '{' | '}' => {}
+            '\u{0}' => out.push('\\'),
+            c => out.push(c),
+        }
+    }
+
+    out.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn bibtex_source(
+    entry_type: &str,
+    key: &str,
+    fields: &[(String, String)],
+) -> Result<Source, String> {
+    let field = |name: &str| {
+        fields
+            .iter()
+            .find(|(key, value)| key == name && !value.is_empty())
+            .map(|(_, value)| value.as_str())
+    };
+
+    let url = field("url").unwrap_or_default().to_string();
+    let title = field("title").unwrap_or(url.as_str()).to_string();
+    if title.is_empty() {
+        return Err(format!("{} has no title or URL", key));
+    }
+
+    let author = bibtex_authors(
+        field("author")
+            .or_else(|| field("editor"))
+            .unwrap_or_default(),
+    );
+
+    // biblatex "date = {2019-05-03}" or "year" with an optional "month" (number or "may")
+    let published_date = field("date").and_then(parse_iso_prefix).or_else(|| {
+        let year = field("year")?.trim().parse::<i32>().ok()?;
+        let month = field("month").map_or(1, |month| {
+            let month = month.trim().to_ascii_lowercase();
+            month.parse::<u32>().ok().unwrap_or_else(|| {
+                [
+                    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov",
+                    "dec",
+                ]
+                .iter()
+                .position(|name| month.starts_with(name))
+                .map_or(1, |index| index as u32 + 1)
+            })
+        });
+        NaiveDate::from_ymd_opt(year, month, 1).or_else(|| NaiveDate::from_ymd_opt(year, 1, 1))
+    });
+
+    let source_type = match entry_type {
+        "article" => SourceType::JournalArticle,
+        "book" | "inbook" | "incollection" | "mvbook" => SourceType::Book,
+        "online" | "electronic" | "www" => SourceType::Webpage,
+        "video" | "movie" => SourceType::Video,
+        "artwork" | "image" => SourceType::Image,
+        "misc" if !url.is_empty() => SourceType::Webpage,
+        _ => SourceType::Other,
+    };
+
+    let mut source = Source {
+        title,
+        url,
+        author,
+        published_date_unknown: published_date.is_none(),
+        comment: field("note")
+            .or_else(|| field("annote"))
+            .unwrap_or_default()
+            .to_string(),
+        tags: field("keywords").unwrap_or_default().to_string(),
+        doi: field("doi").and_then(extract_doi).unwrap_or_default(),
+        source_type,
+        language: field("langid")
+            .or_else(|| field("language"))
+            .map(locale::language_code)
+            .unwrap_or_default(),
+        ..Default::default()
+    };
+    if let Some(date) = published_date {
+        source.published_date = date;
+    }
+    if let Some(date) = field("urldate").and_then(parse_iso_prefix) {
+        source.viewed_date = date;
+    }
+
+    Ok(source)
+}
+
+// "Klabnik, Steve and Nichols, Carol" -> "Steve Klabnik, Carol Nichols", lists separated by
+// commas like "Steve Klabnik, Carol Nichols" from SaveIt's own export are kept
+fn bibtex_authors(text: &str) -> String {
+    let names: Vec<&str> = text.split(" and ").collect();
+    if let [name] = names[..] {
+        let saveit_list = name
+            .split_once(',')
+            .is_some_and(|(_, first)| first.trim().contains(' '));
+        if saveit_list {
+            return name.trim().to_string();
+        }
+    }
+
+    names
+        .iter()
+        .map(|name| match name.split_once(',') {
+            Some((last, first)) if !first.trim().is_empty() => {
+                format!("{} {}", first.trim(), last.trim())
+            }
+            _ => name.trim().to_string(),
+        })
+        .filter(|name| !name.is_empty())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// "2019-05-03", also with a time after it
+fn parse_iso_prefix(text: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(text.trim().get(..10)?, "%Y-%m-%d").ok()
+}
+
+// "TY  - JOUR" … "ER  -" records of tagged lines, used by Zotero, Mendeley, EndNote and most
+// publishers
+pub fn parse_ris(ris: &str) -> ImportReport {
+    let mut report = ImportReport::default();
+    let mut record: Vec<(String, String)> = vec![];
+    let mut in_record = false;
+
+    for line in ris.lines() {
+        let line = line.trim_end();
+        // "AU  - value", some exporters only write one space before the dash
+        let Some((tag, value)) = line
+            .split_once(" -")
+            .filter(|(tag, _)| tag.trim().len() == 2)
+        else {
+            // continuation of a long value
+            if let Some((_, value)) = record.last_mut().filter(|_| !line.trim().is_empty()) {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+            continue;
+        };
+        let (tag, value) = (tag.trim().to_ascii_uppercase(), value.trim().to_string());
+
+        match tag.as_str() {
+            "TY" => {
+                record = vec![(tag, value)];
+                in_record = true;
+            }
+            "ER" if in_record => {
+                report.add(ris_source(&record));
+                record.clear();
+                in_record = false;
+            }
+            _ if in_record => record.push((tag, value)),
+            _ => {}
+        }
+    }
+
+    if in_record {
+        report
+            .errors
+            .push("Last record has no end (ER)".to_string());
+    }
+
+    report
+}
+
+fn ris_source(record: &[(String, String)]) -> Result<Source, String> {
+    let values = |tags: &[&str]| -> Vec<&str> {
+        record
+            .iter()
+            .filter(|(tag, value)| tags.contains(&tag.as_str()) && !value.is_empty())
+            .map(|(_, value)| value.as_str())
+            .collect()
+    };
+    let field = |tags: &[&str]| values(tags).first().map(|value| value.to_string());
+
+    let url = field(&["UR", "L2"]).unwrap_or_default();
+    let title = field(&["TI", "T1", "CT", "BT"]).unwrap_or_else(|| url.clone());
+    if title.is_empty() {
+        return Err(format!(
+            "{} record has no title or URL",
+            field(&["TY"]).unwrap_or_default()
+        ));
+    }
+
+    // "Klabnik, Steve" -> "Steve Klabnik"
+    let author = values(&["AU", "A1", "A2"])
+        .iter()
+        .map(|author| match author.split_once(',') {
+            Some((last, first)) if !first.trim().is_empty() => {
+                format!("{} {}", first.trim(), last.trim())
+            }
+            _ => author.trim().to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    // "2019/05/03/" or "2019"
+    let ris_date = |value: String| {
+        let parts: Vec<u32> = value
+            .split(['/', '-'])
+            .map_while(|part| part.trim().parse().ok())
+            .collect();
+        match parts[..] {
+            [year, month, day, ..] => NaiveDate::from_ymd_opt(year as i32, month, day),
+            [year, month] => NaiveDate::from_ymd_opt(year as i32, month, 1),
+            [year] => NaiveDate::from_ymd_opt(year as i32, 1, 1),
+            _ => None,
+        }
+    };
+    let published_date = field(&["DA", "PY", "Y1"]).and_then(ris_date);
+
+    let source_type = match field(&["TY"]).unwrap_or_default().as_str() {
+        "JOUR" | "EJOUR" | "MGZN" | "JFULL" => SourceType::JournalArticle,
+        "BOOK" | "CHAP" | "EBOOK" | "ECHAP" | "EDBOOK" => SourceType::Book,
+        "ELEC" | "WEB" | "BLOG" | "ICOMM" => SourceType::Webpage,
+        "VIDEO" | "MPCT" => SourceType::Video,
+        "ART" | "FIGURE" => SourceType::Image,
+        _ => SourceType::Other,
+    };
+
+    let mut source = Source {
+        title,
+        url,
+        author,
+        published_date_unknown: published_date.is_none(),
+        comment: values(&["N1", "AB"]).join("\n"),
+        tags: values(&["KW"]).join(", "),
+        doi: field(&["DO"])
+            .and_then(|doi| extract_doi(&doi))
+            .unwrap_or_default(),
+        source_type,
+        language: field(&["LA"])
+            .map(|language| locale::language_code(&language))
+            .unwrap_or_default(),
+        ..Default::default()
+    };
+    if let Some(date) = published_date {
+        source.published_date = date;
+    }
+    if let Some(date) = field(&["Y2"]).and_then(ris_date) {
+        source.viewed_date = date;
+    }
+
+    Ok(source)
+}
+
+// "[3]", "(3)", "3." or "3)" in front of a reference
+static REFERENCE_NUMBER: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(?:\[\d+\]|\(\d+\)|\d{1,3}[.)])\s+").unwrap());
+// "[Stand: 01. 03. 2024]", "Zugriff am 1.3.2024", "Retrieved March 1, 2024, from"
+static VIEWED: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)[\[(]?\s*\b(?:stand|zugriff(?:\s+am)?|(?:zuletzt\s+)?abgerufen(?:\s+am)?|(?:last\s+)?accessed(?:\s+on)?|retrieved)\s*:?\s*([^\])]*?\d{4})\s*[\])]?(?:,?\s*from\b)?").unwrap()
+});
+// what comes before the link, e.g. "URL:" or "Available at"
+static URL_LABEL: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)(?:url|online|verfügbar unter|available (?:at|from)|from|unter)\s*:?\s*$")
+        .unwrap()
+});
+static DOI_LABEL: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)\bdoi:?\s*10\.\S+").unwrap());
+// ", S. 12" or ", pp. 12-15"
+static PAGES: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r",\s*(?:S\.|pp?\.)\s*\d+(?:\s*[-–]\s*\d+)?").unwrap());
+// "Müller, H. (2019): Titel", "Doe, J. (2019, March 5). Title" or "Title. (n.d.)."
+static AUTHOR_DATE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)^(.*?)\s*\(([^()]*?\d{4}[a-z]?[^()]*|n\.\s?d\.|o\.\s?j\.)\)\s*[.:,]?\s*(.*)$")
+        .unwrap()
+});
+// "Müller, H. 2019. Titel."
+static AUTHOR_YEAR: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(.+?),?\s+(\d{4})[a-z]?[.:]\s+(.+)$").unwrap());
+static YEAR: Lazy<Regex> = Lazy::new(|| Regex::new(r"\d{4}").unwrap());
+// "H." or "H.-P."
+static INITIALS: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(?:\p{Lu}\.\s?-?)+$").unwrap());
+
+// reference lists copied from a document, one reference per paragraph or, without empty lines,
+// per line; the fields are guessed, so they should be checked in the import preview
+pub fn parse_citations(text: &str) -> ImportReport {
+    let mut report = ImportReport::default();
+    for reference in citation_entries(text) {
+        report.add(parse_citation(&reference));
+    }
+    report
+}
+
+// true if most entries look like formatted references, e.g. with a year in parentheses or the
+// date viewed
+pub fn is_citation_list(text: &str) -> bool {
+    let entries: Vec<String> = citation_entries(text).into_iter().take(20).collect();
+    let references = entries
+        .iter()
+        .filter(|entry| {
+            (AUTHOR_DATE.is_match(entry) || VIEWED.is_match(entry)) && parse_citation(entry).is_ok()
+        })
+        .count();
+
+    !entries.is_empty() && references * 5 >= entries.len() * 4
+}
+
+fn citation_entries(text: &str) -> Vec<String> {
+    let lines: Vec<&str> = text.lines().map(str::trim).collect();
+    // wrapped references are separated by empty lines
+    let paragraphs = lines.iter().any(|line| line.is_empty())
+        && lines
+            .split(|line| line.is_empty())
+            .any(|paragraph| paragraph.len() > 1);
+
+    match paragraphs {
+        true => lines
+            .split(|line| line.is_empty())
+            .filter(|paragraph| !paragraph.is_empty())
+            .map(|paragraph| paragraph.join(" "))
+            .collect(),
+        false => lines
+            .into_iter()
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect(),
+    }
+}
+
+// fields of a formatted reference like "Müller, H. (2019): Titel. URL: … [Stand: …]", the
+// SaveIt and APA formats and similar styles are recognized
+pub fn parse_citation(reference: &str) -> Result<Source, String> {
+    let mut text = REFERENCE_NUMBER.replace(reference.trim(), "").to_string();
+    let mut source = Source::default();
+
+    let viewed = VIEWED
+        .captures(&text)
+        .map(|captures| (captures[0].to_string(), find_date(&captures[1])));
+    if let Some((matched, date)) = viewed {
+        if let Some(date) = date {
+            source.viewed_date = date;
+        }
+        text = text.replace(&matched, " ");
+    }
+
+    if let Some(url) = find_url(&text) {
+        let index = text.find(&url).unwrap_or_default();
+        let before = URL_LABEL.replace(text[..index].trim_end(), "").to_string();
+        text = format!("{} {}", before, &text[index + url.len()..]);
+        source.url = url;
+    }
+    if let Some(doi) = extract_doi(&source.url).or_else(|| extract_doi(&text)) {
+        text = DOI_LABEL.replace(&text, "").to_string();
+        source.doi = doi;
+    }
+    text = PAGES.replace(&text, "").to_string();
+    let text = text.trim();
+
+    let (author, date, rest) = match AUTHOR_DATE.captures(text) {
+        Some(captures) => (
+            captures[1].to_string(),
+            Some(captures[2].to_string()),
+            captures[3].to_string(),
+        ),
+        None => match AUTHOR_YEAR.captures(text) {
+            Some(captures) => (
+                captures[1].to_string(),
+                Some(captures[2].to_string()),
+                captures[3].to_string(),
+            ),
+            None => (String::new(), None, text.to_string()),
+        },
+    };
+
+    // "Title. (n.d.). Retrieved …" has the title where the author would be
+    let (author, rest) = match rest.trim_matches(['.', ',', ':', ' ']).is_empty() {
+        true => (String::new(), author),
+        false => (author, rest),
+    };
+
+    let published_date = date.as_deref().and_then(|date| {
+        // APA puts the year first, "2019, March 5"
+        let apa = date
+            .split_once(',')
+            .filter(|(year, _)| year.trim().len() == 4)
+            .and_then(|(year, day)| find_date(&format!("{} {}", day, year)));
+        apa.or_else(|| find_date(date)).or_else(|| {
+            let year = YEAR.find(date)?.as_str().parse().ok()?;
+            NaiveDate::from_ymd_opt(year, 1, 1)
+        })
+    });
+    match published_date {
+        Some(date) => source.published_date = date,
+        None => source.published_date_unknown = true,
+    }
+
+    // up to the end of the first sentence, the rest is the journal, publisher or pages
+    let rest = rest.trim_start_matches(['.', ',', ':', ' ']);
+    let title = match rest.find(". ") {
+        Some(index) => &rest[..index],
+        None => rest,
+    };
+    let mut title = title.trim().trim_end_matches(['.', ',', ':']).trim();
+    // APA marks videos and images after the title
+    let mut marked_type = None;
+    for (marker, source_type) in [
+        ("[Video]", SourceType::Video),
+        ("[Image]", SourceType::Image),
+    ] {
+        if let Some(stripped) = title.strip_suffix(marker) {
+            title = stripped.trim();
+            marked_type = Some(source_type);
+        }
+    }
+    source.title = match title.is_empty() {
+        true => source.url.clone(),
+        false => title.to_string(),
+    };
+    if source.title.is_empty() {
+        return Err(format!("No title found in \"{}\"", reference.trim()));
+    }
+
+    source.author = citation_authors(&author);
+    source.source_type =
+        marked_type.unwrap_or(match (source.doi.is_empty(), source.url.is_empty()) {
+            (false, _) => SourceType::JournalArticle,
+            (true, false) => SourceType::Webpage,
+            (true, true) => SourceType::Other,
+        });
+
+    Ok(source)
+}
+
+// "Müller, H., & Schmidt, K." -> "H. Müller, K. Schmidt", unknown authors are left empty
+fn citation_authors(text: &str) -> String {
+    let text = text.trim().trim_end_matches([',', ':']).trim();
+    if ["unbekannt", "anonymous", "o. v.", "o.v.", "n.n."].contains(&text.to_lowercase().as_str()) {
+        return String::new();
+    }
+
+    let mut authors = vec![];
+    for group in text
+        .split([';', '&', '/'])
+        .flat_map(|group| group.split(" und "))
+    {
+        let group = group.split(" and ").collect::<Vec<_>>().join(",");
+        let parts: Vec<&str> = group
+            .split(',')
+            .map(str::trim)
+            .filter(|part| !part.is_empty())
+            .collect();
+
+        // "Last, F., Last, F." or a single "Last, First"
+        let pairs = parts.len().is_multiple_of(2)
+            && parts
+                .chunks(2)
+                .all(|pair| INITIALS.is_match(pair[1]) || parts.len() == 2);
+        match pairs {
+            true => authors.extend(
+                parts
+                    .chunks(2)
+                    .map(|pair| format!("{} {}", pair[1], pair[0])),
+            ),
+            false => authors.extend(parts.into_iter().map(str::to_string)),
+        }
+    }
+
+    authors.join(", ")
+}
+
+// skips "javascript:", "place:" and similar bookmarks
+fn is_web_url(url: &str) -> bool {
+    let url = url.to_ascii_lowercase();
+    url.starts_with("http://") || url.starts_with("https://")
+}
+
+fn date_from_seconds(seconds: i64) -> Option<NaiveDate> {
+    DateTime::from_timestamp(seconds, 0).map(|date| date.with_timezone(&Local).date_naive())
+}
+
+// URL compared for duplicates, ignoring the scheme, "www.", a trailing slash and the fragment
+pub fn normalize_url(url: &str) -> String {
+    let url = url.trim();
+    let url = url.split_once('#').map_or(url, |(url, _)| url);
+    let url = url
+        .split_once("://")
+        .map_or(url, |(_, rest)| rest)
+        .trim_end_matches('/');
+
+    let (host, path) = url.split_once('/').unwrap_or((url, ""));
+    let host = host.to_ascii_lowercase();
+    let host = host.strip_prefix("www.").unwrap_or(&host);
+
+    match path.is_empty() {
+        true => host.to_string(),
+        false => format!("{}/{}", host, path),
+    }
+}
+
+// URLs and DOIs of the library, a source with the same URL or DOI is a duplicate, sources without
+// either are compared by title and author
+#[derive(Default)]
+pub struct DuplicateIndex {
+    urls: HashSet<String>,
+    dois: HashSet<String>,
+    titles: HashSet<String>,
+}
+
+impl DuplicateIndex {
+    pub fn new(library: &[Source]) -> Self {
+        let mut index = Self::default();
+        for source in library {
+            index.insert(source);
+        }
+        index
+    }
+
+    pub fn insert(&mut self, source: &Source) {
+        if !source.url.is_empty() {
+            self.urls.insert(normalize_url(&source.url));
+        }
+        if !source.doi.is_empty() {
+            self.dois.insert(normalize(&source.doi));
+        }
+        if let Some(title) = title_key(source) {
+            self.titles.insert(title);
+        }
+    }
+
+    pub fn contains(&self, source: &Source) -> bool {
+        (!source.url.is_empty() && self.urls.contains(&normalize_url(&source.url)))
+            || (!source.doi.is_empty() && self.dois.contains(&normalize(&source.doi)))
+            || title_key(source).is_some_and(|title| self.titles.contains(&title))
+    }
+}
+
+// e.g. books typed in by hand, "Müller: Geschichte" and "Muller: geschichte" are the same
+fn title_key(source: &Source) -> Option<String> {
+    let title = normalize(source.title.trim());
+    match source.url.is_empty() && source.doi.is_empty() && !title.is_empty() {
+        true => Some(format!("{}\n{}", normalize(source.author.trim()), title)),
+        false => None,
+    }
+}
+
+// ids of imported sources, keeping them keeps citation numbers of a manuscript valid after
+// moving the library to another computer
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ImportIds {
+    #[default]
+    New,
+    Keep,  // stops the import if an id is already used
+    Remap, // sources whose id is already used get a new one
+}
+
+impl ImportIds {
+    pub const ALL: [ImportIds; 3] = [ImportIds::New, ImportIds::Keep, ImportIds::Remap];
+}
+
+impl Display for ImportIds {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImportIds::New => write!(f, "New IDs"),
+            ImportIds::Keep => write!(f, "Keep IDs"),
+            ImportIds::Remap => write!(f, "Keep IDs, new ones for used IDs"),
+        }
+    }
+}
+
+// progress of a streaming import, shared with the UI
+#[derive(Default)]
+pub struct ImportProgress {
+    pub total_bytes: AtomicU64,
+    pub read_bytes: AtomicU64,
+    pub imported: AtomicUsize,
+    pub duplicates: AtomicUsize,
+    pub skipped: RwLock<Vec<String>>, // entries that couldn't be read
+    pub done: AtomicBool,
+    pub error: RwLock<Option<String>>,
+}
+
+impl ImportProgress {
+    pub fn fraction(&self) -> f32 {
+        let total = self.total_bytes.load(Ordering::Relaxed).max(1);
+        self.read_bytes.load(Ordering::Relaxed) as f32 / total as f32
+    }
+}
+
+// counts the bytes read for the progress bar
+pub struct ProgressReader<R> {
+    pub inner: R,
+    pub progress: Arc<ImportProgress>,
+}
+
+impl<R: Read> Read for ProgressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.progress
+            .read_bytes
+            .fetch_add(read as u64, Ordering::Relaxed);
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    // 2024-03-01 12:00 UTC, the same day in every time zone
+    const ADDED: i64 = 1_709_294_400;
+
+    fn folders(item: &ImportItem) -> Vec<&str> {
+        item.folders.iter().map(String::as_str).collect()
+    }
+
+    #[test]
+    fn reads_bookmarks_html() {
+        let html = r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<META HTTP-EQUIV="Content-Type" CONTENT="text/html; charset=UTF-8">
+<TITLE>Bookmarks</TITLE>
+<H1>Bookmarks</H1>
+<DL><p>
+    <DT><H3 ADD_DATE="1" PERSONAL_TOOLBAR_FOLDER="true">Bookmarks bar</H3>
+    <DL><p>
+        <DT><A HREF="https://doc.rust-lang.org/book/" ADD_DATE="1709294400" TAGS="rust,book">The Rust Book</A>
+        <DD>Read chapter 4 again
+        <DT><H3>Research</H3>
+        <DL><p>
+            <DT><H3>Memory &amp; Safety</H3>
+            <DL><p>
+                <DT><A HREF="https://example.com/gc">Garbage collection</A>
+            </DL><p>
+            <DT><A HREF="javascript:alert(1)">Bookmarklet</A>
+            <DT><A HREF="place:sort=8">Recent tags</A>
+            <DT><A HREF="https://example.com/untitled"></A>
+            <DD>Saved without a title
+        </DL><p>
+    </DL><p>
+</DL><p>
+"#;
+
+        let items = parse_bookmarks_html(html);
+        let titles: Vec<&str> = items
+            .iter()
+            .map(|item| item.source.title.as_str())
+            .collect();
+        assert_eq!(
+            titles,
+            [
+                "The Rust Book",
+                "Garbage collection",
+                "https://example.com/untitled"
+            ]
+        );
+
+        // the bookmarks bar isn't a folder of the user
+        let book = &items[0].source;
+        assert!(folders(&items[0]).is_empty());
+        assert_eq!(book.url, "https://doc.rust-lang.org/book/");
+        assert_eq!(book.comment, "Read chapter 4 again");
+        assert_eq!(book.tags, "rust, book");
+        assert_eq!(book.viewed_date, date(2024, 3, 1));
+        assert!(book.published_date_unknown);
+
+        assert_eq!(folders(&items[1]), ["Research", "Memory & Safety"]);
+        assert_eq!(folders(&items[2]), ["Research"]);
+        assert_eq!(items[2].source.comment, "Saved without a title");
+    }
+
+    #[test]
+    fn reads_firefox_json() {
+        let json = format!(
+            r#"{{
+                "guid": "root________", "title": "", "root": "placesRoot",
+                "type": "text/x-moz-place-container",
+                "children": [{{
+                    "guid": "menu________", "title": "menu", "root": "bookmarksMenuFolder",
+                    "type": "text/x-moz-place-container",
+                    "children": [
+                        {{
+                            "title": "Research", "type": "text/x-moz-place-container",
+                            "children": [
+                                {{
+                                    "title": "The Rust Book", "type": "text/x-moz-place",
+                                    "uri": "https://doc.rust-lang.org/book/",
+                                    "dateAdded": {}, "tags": "rust,book"
+                                }},
+                                {{ "title": "Recent", "type": "text/x-moz-place", "uri": "place:sort=8" }}
+                            ]
+                        }},
+                        {{ "title": "", "type": "text/x-moz-place", "uri": "https://example.com/untitled" }}
+                    ]
+                }}]
+            }}"#,
+            ADDED * 1_000_000
+        );
+
+        let items = parse_firefox_json(&json).unwrap();
+        assert_eq!(items.len(), 2);
+
+        // the root and the menu aren't folders of the user
+        let book = &items[0].source;
+        assert_eq!(book.title, "The Rust Book");
+        assert_eq!(folders(&items[0]), ["Research"]);
+        assert_eq!(book.tags, "rust, book");
+        assert_eq!(book.viewed_date, date(2024, 3, 1));
+
+        assert_eq!(items[1].source.title, "https://example.com/untitled");
+        assert!(folders(&items[1]).is_empty());
+
+        assert!(parse_firefox_json("not json").is_err());
+    }
+
+    #[test]
+    fn reads_reading_list_html() {
+        let html = r#"<!DOCTYPE html>
+<html><head><title>Pocket Export</title></head><body>
+<h1>Unread</h1>
+<ul>
+<li><a href="https://example.com/async" time_added="1709294400" tags="rust,async">Async &amp; Await</a></li>
+<li><a href="file:///home/me/notes.html" time_added="1709294400">Local notes</a></li>
+</ul>
+<h1>Read Archive</h1>
+<ul>
+<li><a href="https://example.com/traits" time_added="1709294400" tags="">Traits</a></li>
+</ul>
+</body></html>"#;
+
+        let items = parse_reading_list_html(html);
+        assert_eq!(items.len(), 2);
+
+        let async_await = &items[0].source;
+        assert_eq!(async_await.title, "Async & Await");
+        assert_eq!(async_await.tags, "rust, async");
+        assert_eq!(async_await.viewed_date, date(2024, 3, 1));
+        assert_eq!(folders(&items[0]), ["Unread"]);
+
+        assert_eq!(items[1].source.title, "Traits");
+        assert_eq!(folders(&items[1]), ["Read Archive"]);
+    }
+
+    #[test]
+    fn reads_pocket_csv() {
+        let csv = format!(
+            "\u{feff}title,url,time_added,tags,status\n\
+             Async,https://example.com/async,{0},rust|async,unread\n\
+             Local notes,file:///home/me/notes.html,{0},,archive\n\
+             ,https://example.com/untitled,{0},,\n",
+            ADDED
+        );
+
+        let items = parse_pocket_csv(&csv).unwrap();
+        assert_eq!(items.len(), 2);
+
+        let async_await = &items[0].source;
+        assert_eq!(async_await.title, "Async");
+        assert_eq!(async_await.url, "https://example.com/async");
+        assert_eq!(async_await.tags, "rust, async");
+        assert_eq!(async_await.viewed_date, date(2024, 3, 1));
+        assert_eq!(folders(&items[0]), ["unread"]);
+
+        assert_eq!(items[1].source.title, "https://example.com/untitled");
+        assert!(folders(&items[1]).is_empty());
+    }
+
+    #[test]
+    fn reads_instapaper_csv() {
+        let csv = format!(
+            "\u{feff}URL,Title,Selection,Folder,Timestamp,Tags\n\
+             https://example.com/async,Async,\"Futures are lazy, poll them\",Unread,{0},\"[\"\"rust\"\",\"\"async\"\"]\"\n\
+             mailto:me@example.com,Mail,,Unread,{0},[]\n\
+             https://example.com/traits,Traits,,Archive,{0},\n",
+            ADDED
+        );
+
+        let items = parse_instapaper_csv(&csv).unwrap();
+        assert_eq!(items.len(), 2);
+
+        let async_await = &items[0].source;
+        assert_eq!(async_await.title, "Async");
+        assert_eq!(async_await.comment, "Futures are lazy, poll them");
+        assert_eq!(async_await.tags, "rust, async");
+        assert_eq!(async_await.viewed_date, date(2024, 3, 1));
+        assert_eq!(folders(&items[0]), ["Unread"]);
+
+        // older exports have no tags
+        assert_eq!(items[1].source.tags, "");
+        assert_eq!(folders(&items[1]), ["Archive"]);
+    }
+
+    #[test]
+    fn reads_endnote_xml() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<xml><records>
+<record>
+    <ref-type name="Journal Article">17</ref-type>
+    <contributors>
+        <authors>
+            <author><style face="normal" font="default" size="100%">Klabnik,</style><style face="normal" font="default" size="100%"> Steve</style></author>
+            <author><style face="normal" font="default" size="100%">Nichols, Carol</style></author>
+        </authors>
+    </contributors>
+    <titles>
+        <title><style face="normal" font="default" size="100%">The Rust
+            Programming</style><style face="normal" font="default" size="100%"> Language</style></title>
+    </titles>
+    <keywords>
+        <keyword><style face="normal" font="default" size="100%">rust</style></keyword>
+        <keyword><style face="normal" font="default" size="100%">systems</style><style face="normal" font="default" size="100%"> programming</style></keyword>
+    </keywords>
+    <dates>
+        <year><style face="normal" font="default" size="100%">2019</style></year>
+        <pub-dates><date><style face="normal" font="default" size="100%">March 3</style></date></pub-dates>
+    </dates>
+    <electronic-resource-num><style face="normal" font="default" size="100%">10.1000/182</style></electronic-resource-num>
+    <urls>
+        <related-urls>
+            <url><style face="normal" font="default" size="100%">https://doc.rust-lang.org/book/</style></url>
+            <url><style face="normal" font="default" size="100%">https://example.com/mirror</style></url>
+        </related-urls>
+    </urls>
+</record>
+<record>
+    <ref-type name="Book">6</ref-type>
+</record>
+</records></xml>"#;
+
+        // the record without a title or URL is skipped
+        let items = parse_endnote_xml(xml).unwrap();
+        assert_eq!(items.len(), 1);
+
+        let source = &items[0].source;
+        assert_eq!(source.title, "The Rust Programming Language");
+        assert_eq!(source.author, "Steve Klabnik, Carol Nichols");
+        assert_eq!(source.tags, "rust, systems programming");
+        assert_eq!(source.published_date, date(2019, 3, 3));
+        assert!(!source.published_date_unknown);
+        assert_eq!(source.url, "https://doc.rust-lang.org/book/");
+        assert_eq!(source.doi, "10.1000/182");
+        assert_eq!(source.source_type, SourceType::JournalArticle);
+    }
+
+    #[test]
+    fn reads_authors() {
+        for (text, expected) in [
+            // German
+            ("Müller, H.", "H. Müller"),
+            ("Müller, H. und Schmidt, K.", "H. Müller, K. Schmidt"),
+            ("Müller, H./Schmidt, K.", "H. Müller, K. Schmidt"),
+            ("Müller, H.-P.; Schmidt, K.:", "H.-P. Müller, K. Schmidt"),
+            ("o. V.", ""),
+            ("Unbekannt", ""),
+            // APA
+            ("Doe, J., & Roe, K.", "J. Doe, K. Roe"),
+            (
+                "Doe, J. A., Roe, K., & Poe, E.",
+                "J. A. Doe, K. Roe, E. Poe",
+            ),
+            ("Doe, J. and Roe, K.", "J. Doe, K. Roe"),
+            ("Anonymous", ""),
+            // "Last, F., Last, F."
+            ("Doe, J., Roe, K.", "J. Doe, K. Roe"),
+            ("Doe, J., Roe, K., Poe, E.", "J. Doe, K. Roe, E. Poe"),
+            // not in pairs
+            ("Doe, John", "John Doe"),
+            ("Rust Foundation", "Rust Foundation"),
+            (
+                "Mozilla, Rust Foundation, W3C",
+                "Mozilla, Rust Foundation, W3C",
+            ),
+        ] {
+            assert_eq!(citation_authors(text), expected, "{}", text);
+        }
+    }
+
+    #[test]
+    fn reads_citations() {
+        // reference, author, title, published date, viewed date
+        for (reference, author, title, published, viewed) in [
+            // German
+            (
+                "Müller, H. (2019): Rust im Alltag. URL: https://example.com/rust [Stand: 01. 03. 2024]",
+                "H. Müller",
+                "Rust im Alltag",
+                Some(date(2019, 1, 1)),
+                Some(date(2024, 3, 1)),
+            ),
+            (
+                "[2] Müller, H./Schmidt, K. (2020): Speicher ohne Müll. Online unter https://example.com/gc (Zugriff am 2.3.2024)",
+                "H. Müller, K. Schmidt",
+                "Speicher ohne Müll",
+                Some(date(2020, 1, 1)),
+                Some(date(2024, 3, 2)),
+            ),
+            (
+                "Müller, H. 2018. Nebenläufigkeit. Berlin: Verlag, S. 12-15.",
+                "H. Müller",
+                "Nebenläufigkeit",
+                Some(date(2018, 1, 1)),
+                None,
+            ),
+            (
+                "o. V. (o. J.): Ohne Jahr. URL: https://example.com/ [Stand: 02. 03. 2024]",
+                "",
+                "Ohne Jahr",
+                None,
+                Some(date(2024, 3, 2)),
+            ),
+            // APA
+            (
+                "Doe, J., & Roe, K. (2020, May 5). Ownership explained. Retrieved March 10, 2024, from https://example.com/ownership",
+                "J. Doe, K. Roe",
+                "Ownership explained",
+                Some(date(2020, 5, 5)),
+                Some(date(2024, 3, 10)),
+            ),
+            (
+                "Doe, J. A. (2021). Borrowing in practice [Video]. https://example.com/video",
+                "J. A. Doe",
+                "Borrowing in practice",
+                Some(date(2021, 1, 1)),
+                None,
+            ),
+            (
+                "Lifetimes. (n.d.). Retrieved March 1, 2024, from https://example.com/lifetimes",
+                "",
+                "Lifetimes",
+                None,
+                Some(date(2024, 3, 1)),
+            ),
+            // "Last, F., Last, F."
+            (
+                "3. Doe, J., Roe, K., Poe, E. (2022): Traits. https://example.com/traits",
+                "J. Doe, K. Roe, E. Poe",
+                "Traits",
+                Some(date(2022, 1, 1)),
+                None,
+            ),
+        ] {
+            let source = parse_citation(reference).unwrap();
+            assert_eq!(source.author, author, "{}", reference);
+            assert_eq!(source.title, title, "{}", reference);
+            assert_eq!(source.published_date_unknown, published.is_none(), "{}", reference);
+            if let Some(published) = published {
+                assert_eq!(source.published_date, published, "{}", reference);
+            }
+            if let Some(viewed) = viewed {
+                assert_eq!(source.viewed_date, viewed, "{}", reference);
+            }
+        }
+    }
+
+    #[test]
+    fn reads_citation_types() {
+        let video =
+            parse_citation("Doe, J. (2021). Borrowing [Video]. https://example.com/v").unwrap();
+        assert_eq!(video.source_type, SourceType::Video);
+
+        let article =
+            parse_citation("Doe, J. (2020). Ownership. Journal, 3(1). https://doi.org/10.1000/182")
+                .unwrap();
+        assert_eq!(article.doi, "10.1000/182");
+        assert_eq!(article.source_type, SourceType::JournalArticle);
+
+        let page = parse_citation("Doe, J. (2020): Page. URL: https://example.com/").unwrap();
+        assert_eq!(page.url, "https://example.com/");
+        assert_eq!(page.source_type, SourceType::Webpage);
+
+        let book = parse_citation("Doe, J. (2020): Book. Berlin: Verlag.").unwrap();
+        assert_eq!(book.source_type, SourceType::Other);
+    }
+
+    #[test]
+    fn rejects_citation_without_title() {
+        assert!(parse_citation("(2020).").is_err());
+        assert!(parse_citation("   ").is_err());
+    }
+}
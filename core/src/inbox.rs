@@ -0,0 +1,177 @@
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use chrono::{DateTime, Local};
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use sqlx::SqlitePool;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::*;
+
+use crate::crash::record_operation;
+use crate::database::{get_all_sources, import_sources};
+use crate::formats;
+use crate::import::{DuplicateIndex, ImportIds};
+use crate::source_store::SourceStore;
+
+// files other tools drop into the inbox, everything else is left alone
+pub const INBOX_EXTENSIONS: [&str; 3] = ["json", "bib", "ris"];
+const ARCHIVE_DIR: &str = "imported";
+const FAILED_DIR: &str = "failed";
+// writers often create the file first and fill it afterwards
+const SETTLE_TIME: Duration = Duration::from_secs(1);
+
+// shown on the settings page
+#[derive(Debug, Clone, Default)]
+pub struct InboxStatus {
+    pub last_import: Option<(DateTime<Local>, String, usize)>, // time, file name, sources
+    pub error: Option<String>,
+}
+
+// watched inbox folder, stopped when dropped
+pub struct Inbox {
+    _watcher: RecommendedWatcher,
+    task: JoinHandle<()>,
+    pub status: Arc<RwLock<InboxStatus>>,
+}
+
+impl Drop for Inbox {
+    fn drop(&mut self) {
+        debug!("Stopping inbox");
+        self.task.abort();
+    }
+}
+
+// needs to be called from within the tokio runtime, imports the files already in the folder
+// right away and then every file dropped into it
+pub fn start(
+    dir: PathBuf,
+    pool: Arc<SqlitePool>,
+    sources_cache: Arc<RwLock<SourceStore>>,
+) -> Result<Inbox, Box<dyn Error>> {
+    debug!("Watching inbox {}", dir.display());
+    record_operation("Start inbox");
+
+    fs::create_dir_all(&dir)?;
+
+    let (sender, mut receiver) = mpsc::unbounded_channel::<PathBuf>();
+    for entry in fs::read_dir(&dir)? {
+        let _ = sender.send(entry?.path());
+    }
+
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| match res {
+        Ok(event) if event.kind.is_create() || event.kind.is_modify() => {
+            for path in event.paths {
+                let _ = sender.send(path);
+            }
+        }
+        Ok(_) => {}
+        Err(e) => error!("Error watching inbox: {}", e),
+    })?;
+    // the archive folders are inside the inbox, so only the top level is watched
+    watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+
+    let status = Arc::new(RwLock::new(InboxStatus::default()));
+
+    let task_status = status.clone();
+    let task = tokio::spawn(async move {
+        while let Some(path) = receiver.recv().await {
+            if !is_inbox_file(&path) {
+                continue;
+            }
+            tokio::time::sleep(SETTLE_TIME).await;
+            // events of the same file queued while it was being written
+            if !path.exists() {
+                continue;
+            }
+
+            let name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let result = import_file(&path, &pool).await;
+            // the guard can't be held across the await below
+            {
+                let mut status = task_status.write().unwrap();
+                match result {
+                    Ok(imported) => {
+                        info!("Imported {} sources from inbox file {}", imported, name);
+                        status.last_import = Some((Local::now(), name, imported));
+                        status.error = None;
+                        archive(&path, ARCHIVE_DIR);
+                    }
+                    Err(e) => {
+                        error!("Error importing inbox file {}: {}", name, e);
+                        status.error = Some(format!("{}: {}", name, e));
+                        // not tried again on every change of the folder
+                        archive(&path, FAILED_DIR);
+                    }
+                }
+            }
+
+            match get_all_sources(&pool).await {
+                Ok(sources) => *sources_cache.write().unwrap() = sources.into(),
+                Err(e) => error!("Error loading sources: {}", e),
+            }
+        }
+    });
+
+    Ok(Inbox {
+        _watcher: watcher,
+        task,
+        status,
+    })
+}
+
+fn is_inbox_file(path: &Path) -> bool {
+    path.is_file()
+        && path.extension().is_some_and(|extension| {
+            INBOX_EXTENSIONS
+                .iter()
+                .any(|known| extension.eq_ignore_ascii_case(known))
+        })
+}
+
+// adds the sources of the file that aren't in the library yet, returns how many were added
+async fn import_file(
+    path: &Path,
+    pool: &SqlitePool,
+) -> Result<usize, Box<dyn Error + Send + Sync>> {
+    record_operation(format!("Import inbox file {}", path.display()));
+
+    let (_, report) = formats::read_file(path).map_err(|e| e.to_string())?;
+
+    let mut known = DuplicateIndex::new(&get_all_sources(pool).await?);
+    let mut sources = vec![];
+    for item in report.items {
+        let mut source = item.source;
+        source.add_tags(&item.folders.join(","));
+        if known.contains(&source) {
+            continue;
+        }
+        known.insert(&source);
+        sources.push(source);
+    }
+
+    import_sources(&sources, ImportIds::New, pool).await?;
+    Ok(sources.len())
+}
+
+// moves the file into a folder of the inbox, files with the same name are kept
+fn archive(path: &Path, folder: &str) {
+    let Some(dir) = path.parent().map(|parent| parent.join(folder)) else {
+        return;
+    };
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let mut target = dir.join(name.as_ref());
+    if target.exists() {
+        target = dir.join(format!("{}-{}", Local::now().format("%Y%m%d%H%M%S"), name));
+    }
+
+    if let Err(e) = fs::create_dir_all(&dir).and_then(|_| fs::rename(path, &target)) {
+        error!("Error moving {} to {}: {}", path.display(), folder, e);
+    }
+}
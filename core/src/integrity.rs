@@ -0,0 +1,341 @@
+use std::fmt::{Display, Formatter};
+
+use chrono::{Local, NaiveDate};
+use sqlx::{FromRow, SqliteConnection, SqlitePool};
+use tracing::*;
+
+use crate::crash::record_operation;
+
+// every column of the tables as JSON, for the quarantine
+const SOURCE_JSON: &str = "json_object('id', id, 'title', title, 'url', url, 'author', author, 'published_date', published_date, 'viewed_date', viewed_date, 'published_date_unknown', published_date_unknown, 'comment', comment, 'last_cited', last_cited, 'tags', tags, 'collection', collection, 'doi', doi, 'source_type', source_type, 'uuid', uuid, 'updated_at', updated_at, 'pinned', pinned, 'notes_written', notes_written, 'canonical_url', canonical_url, 'language', language)";
+const LINK_JSON: &str =
+    "json_object('id', id, 'source_id', source_id, 'target_id', target_id, 'relation', relation)";
+const QUOTE_JSON: &str =
+    "json_object('id', id, 'source_id', source_id, 'text', text, 'page', page, 'note', note)";
+const JOB_JSON: &str = "json_object('id', id, 'source_id', source_id, 'kind', kind, 'attempts', attempts, 'last_error', last_error, 'created_at', created_at)";
+
+// something the check found wrong with the library
+#[derive(Debug, Clone, PartialEq)]
+pub enum Problem {
+    Corruption(String), // reported by SQLite, can't be repaired here
+    InvalidDate {
+        id: i64,
+        title: String,
+        column: &'static str, // "published_date" or "viewed_date"
+    },
+    OrphanedLink(i64), // link to or from a source that doesn't exist
+    OrphanedQuote(i64),
+    OrphanedJob(i64),
+    DuplicateUuid {
+        id: i64, // the newer source, the older one keeps the id
+        title: String,
+    },
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Problem::Corruption(message) => write!(f, "Database damaged: {}", message),
+            Problem::InvalidDate { id, title, column } => {
+                let column = match *column {
+                    "published_date" => "date published",
+                    _ => "date viewed",
+                };
+                write!(f, "Invalid {} of source {} \"{}\"", column, id, title)
+            }
+            Problem::OrphanedLink(id) => write!(f, "Relation {} to a deleted source", id),
+            Problem::OrphanedQuote(id) => write!(f, "Quote {} of a deleted source", id),
+            Problem::OrphanedJob(id) => {
+                write!(f, "Queued network action {} of a deleted source", id)
+            }
+            Problem::DuplicateUuid { id, title } => {
+                write!(
+                    f,
+                    "Source {} \"{}\" has the sync id of another source",
+                    id, title
+                )
+            }
+        }
+    }
+}
+
+impl Problem {
+    pub fn can_fix(&self) -> bool {
+        !matches!(self, Problem::Corruption(_))
+    }
+
+    pub fn can_quarantine(&self) -> bool {
+        !matches!(self, Problem::Corruption(_) | Problem::DuplicateUuid { .. })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Repair {
+    Fix,        // repair the row where possible, delete rows that belong to nothing
+    Quarantine, // move the row to the quarantine table
+}
+
+#[derive(FromRow)]
+struct InvalidDate {
+    id: i64,
+    title: String,
+    date_column: String,
+}
+
+#[derive(FromRow)]
+struct RawDate {
+    kind: String, // SQLite type of the value
+    value: Option<String>,
+}
+
+pub async fn check(pool: &SqlitePool) -> Result<Vec<Problem>, sqlx::Error> {
+    debug!("Checking library");
+    record_operation("Check library");
+
+    let mut problems = vec![];
+
+    let messages: Vec<String> = sqlx::query_scalar("PRAGMA integrity_check")
+        .fetch_all(pool)
+        .await?;
+    problems.extend(
+        messages
+            .into_iter()
+            .filter(|message| message != "ok")
+            .map(Problem::Corruption),
+    );
+
+    // dates are stored as text like 2024-01-31, anything else can't be loaded
+    let invalid_dates: Vec<InvalidDate> = sqlx::query_as(
+        "SELECT id, COALESCE(title, '') AS title, 'published_date' AS date_column FROM sources WHERE typeof(published_date) != 'text' OR date(published_date) IS NOT published_date
+        UNION ALL
+        SELECT id, COALESCE(title, '') AS title, 'viewed_date' AS date_column FROM sources WHERE typeof(viewed_date) != 'text' OR date(viewed_date) IS NOT viewed_date",
+    )
+    .fetch_all(pool)
+    .await?;
+    problems.extend(invalid_dates.into_iter().map(|date| Problem::InvalidDate {
+        id: date.id,
+        title: date.title,
+        column: match date.date_column.as_str() {
+            "published_date" => "published_date",
+            _ => "viewed_date",
+        },
+    }));
+
+    let links: Vec<i64> = sqlx::query_scalar("SELECT id FROM source_links WHERE source_id NOT IN (SELECT id FROM sources) OR target_id NOT IN (SELECT id FROM sources)")
+        .fetch_all(pool)
+        .await?;
+    problems.extend(links.into_iter().map(Problem::OrphanedLink));
+
+    let quotes: Vec<i64> =
+        sqlx::query_scalar("SELECT id FROM quotes WHERE source_id NOT IN (SELECT id FROM sources)")
+            .fetch_all(pool)
+            .await?;
+    problems.extend(quotes.into_iter().map(Problem::OrphanedQuote));
+
+    let jobs: Vec<i64> = sqlx::query_scalar(
+        "SELECT id FROM network_jobs WHERE source_id NOT IN (SELECT id FROM sources)",
+    )
+    .fetch_all(pool)
+    .await?;
+    problems.extend(jobs.into_iter().map(Problem::OrphanedJob));
+
+    // sync would treat them as the same source
+    let duplicates: Vec<(i64, String)> = sqlx::query_as("SELECT id, COALESCE(title, '') FROM sources AS source WHERE uuid != '' AND EXISTS (SELECT 1 FROM sources AS other WHERE other.uuid = source.uuid AND other.id < source.id)")
+        .fetch_all(pool)
+        .await?;
+    problems.extend(
+        duplicates
+            .into_iter()
+            .map(|(id, title)| Problem::DuplicateUuid { id, title }),
+    );
+
+    debug!("Found {} problems", problems.len());
+    Ok(problems)
+}
+
+pub async fn repair(
+    problem: &Problem,
+    repair: Repair,
+    pool: &SqlitePool,
+) -> Result<(), sqlx::Error> {
+    debug!("Repairing {:?} ({:?})", problem, repair);
+    record_operation(format!("Repair library ({:?})", repair));
+
+    let mut transaction = pool.begin().await?;
+    let reason = problem.to_string();
+
+    match (problem, repair) {
+        (Problem::Corruption(_), _) => {}
+        (Problem::InvalidDate { id, column, .. }, Repair::Fix) => {
+            let raw: RawDate = sqlx::query_as(&format!(
+                "SELECT typeof({0}) AS kind, CAST({0} AS TEXT) AS value FROM sources WHERE id = $1",
+                column
+            ))
+            .bind(id)
+            .fetch_one(&mut *transaction)
+            .await?;
+
+            let date = parse_date(&raw);
+            let unknown = date.is_none() && *column == "published_date";
+            let date = date.unwrap_or_else(|| Local::now().date_naive());
+
+            sqlx::query(&format!("UPDATE sources SET {} = $1 WHERE id = $2", column))
+                .bind(date)
+                .bind(id)
+                .execute(&mut *transaction)
+                .await?;
+            if unknown {
+                sqlx::query("UPDATE sources SET published_date_unknown = 1 WHERE id = $1")
+                    .bind(id)
+                    .execute(&mut *transaction)
+                    .await?;
+            }
+        }
+        (Problem::InvalidDate { id, .. }, Repair::Quarantine) => {
+            // the source is moved together with what belongs to it
+            quarantine(
+                "quotes",
+                QUOTE_JSON,
+                "source_id = $1",
+                *id,
+                &reason,
+                &mut transaction,
+            )
+            .await?;
+            quarantine(
+                "source_links",
+                LINK_JSON,
+                "source_id = $1 OR target_id = $1",
+                *id,
+                &reason,
+                &mut transaction,
+            )
+            .await?;
+            quarantine(
+                "network_jobs",
+                JOB_JSON,
+                "source_id = $1",
+                *id,
+                &reason,
+                &mut transaction,
+            )
+            .await?;
+            quarantine(
+                "sources",
+                SOURCE_JSON,
+                "id = $1",
+                *id,
+                &reason,
+                &mut transaction,
+            )
+            .await?;
+        }
+        (Problem::OrphanedLink(id), Repair::Fix) => {
+            delete("source_links", *id, &mut transaction).await?
+        }
+        (Problem::OrphanedQuote(id), Repair::Fix) => {
+            delete("quotes", *id, &mut transaction).await?
+        }
+        (Problem::OrphanedJob(id), Repair::Fix) => {
+            delete("network_jobs", *id, &mut transaction).await?
+        }
+        (Problem::OrphanedLink(id), Repair::Quarantine) => {
+            quarantine(
+                "source_links",
+                LINK_JSON,
+                "id = $1",
+                *id,
+                &reason,
+                &mut transaction,
+            )
+            .await?
+        }
+        (Problem::OrphanedQuote(id), Repair::Quarantine) => {
+            quarantine(
+                "quotes",
+                QUOTE_JSON,
+                "id = $1",
+                *id,
+                &reason,
+                &mut transaction,
+            )
+            .await?
+        }
+        (Problem::OrphanedJob(id), Repair::Quarantine) => {
+            quarantine(
+                "network_jobs",
+                JOB_JSON,
+                "id = $1",
+                *id,
+                &reason,
+                &mut transaction,
+            )
+            .await?
+        }
+        // a new sync id, other devices get it as a new source
+        (Problem::DuplicateUuid { id, .. }, _) => {
+            sqlx::query("UPDATE sources SET uuid = lower(hex(randomblob(16))), updated_at = strftime('%Y-%m-%d %H:%M:%f', 'now') WHERE id = $1")
+                .bind(id)
+                .execute(&mut *transaction)
+                .await?;
+        }
+    }
+
+    transaction.commit().await
+}
+
+pub async fn quarantined_count(pool: &SqlitePool) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar("SELECT COUNT(*) FROM quarantine")
+        .fetch_one(pool)
+        .await
+}
+
+// day numbers of old exports and dates with a time can be recovered
+fn parse_date(raw: &RawDate) -> Option<NaiveDate> {
+    let value = raw.value.as_deref()?.trim();
+
+    match raw.kind.as_str() {
+        "integer" => value
+            .parse()
+            .ok()
+            .and_then(NaiveDate::from_num_days_from_ce_opt),
+        _ => value
+            .get(..10)
+            .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()),
+    }
+}
+
+// table and json are constants, never user input
+async fn quarantine(
+    table: &str,
+    json: &str,
+    condition: &str,
+    id: i64,
+    reason: &str,
+    connection: &mut SqliteConnection,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(&format!("INSERT INTO quarantine (table_name, row_id, content, reason, quarantined_at) SELECT '{0}', id, {1}, $2, strftime('%Y-%m-%d %H:%M:%f', 'now') FROM {0} WHERE {2}", table, json, condition))
+        .bind(id)
+        .bind(reason)
+        .execute(&mut *connection)
+        .await?;
+
+    sqlx::query(&format!("DELETE FROM {} WHERE {}", table, condition))
+        .bind(id)
+        .execute(connection)
+        .await?;
+
+    Ok(())
+}
+
+async fn delete(
+    table: &str,
+    id: i64,
+    connection: &mut SqliteConnection,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(&format!("DELETE FROM {} WHERE id = $1", table))
+        .bind(id)
+        .execute(connection)
+        .await
+        .map(|_| ())
+}
@@ -0,0 +1,38 @@
+// sources, the library, the config and the formats, without the GUI
+pub mod auto_export;
+pub mod bibliography;
+pub mod bundle;
+pub mod collation;
+pub mod config;
+pub mod crash;
+pub mod database;
+pub mod document;
+pub mod export;
+pub mod formats;
+pub mod history;
+pub mod hooks;
+pub mod http;
+pub mod image;
+pub mod import;
+pub mod inbox;
+pub mod integrity;
+pub mod link_check;
+pub mod locale;
+pub mod metadata;
+pub mod paste;
+pub mod pdf;
+pub mod plugins;
+pub mod queue;
+pub mod quote;
+pub mod reading_plan;
+pub mod references;
+pub mod scheduler;
+pub mod search;
+pub mod snapshot;
+pub mod source;
+pub mod source_link;
+pub mod source_store;
+pub mod sync;
+pub mod tasks;
+pub mod template;
+pub mod url_analysis;
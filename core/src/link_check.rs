@@ -0,0 +1,265 @@
+use std::error::Error;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use chrono::{DateTime, Local};
+use serde_json::Value;
+use tracing::*;
+
+use crate::crash::record_operation;
+use crate::http;
+use crate::scheduler;
+use crate::source::Source;
+use crate::url_analysis;
+
+const WAYBACK_URL: &str = "https://archive.org/wayback/available";
+
+// what the server of a source's URL answered
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkResult {
+    pub id: i64,
+    pub title: String,
+    pub url: String,
+    pub status: Option<u16>, // None if the server couldn't be reached
+    pub error: Option<String>,
+    pub redirect: Option<String>, // final URL if it differs from the saved one
+    pub archived: Option<String>, // closest snapshot of the Wayback Machine
+}
+
+impl LinkResult {
+    pub fn is_broken(&self) -> bool {
+        self.status.is_none_or(|status| status >= 400)
+    }
+
+    // "404 Not Found", "timed out"
+    pub fn status_text(&self) -> String {
+        match (self.status, &self.error) {
+            (Some(status), _) => {
+                let reason = reqwest::StatusCode::from_u16(status)
+                    .ok()
+                    .and_then(|status| status.canonical_reason());
+                match reason {
+                    Some(reason) => format!("{} {}", status, reason),
+                    None => status.to_string(),
+                }
+            }
+            (None, Some(error)) => error.clone(),
+            (None, None) => "No answer".to_string(),
+        }
+    }
+}
+
+// links checked so far, for a progress bar
+#[derive(Debug, Default)]
+pub struct CheckProgress {
+    pub checked: AtomicUsize,
+    pub total: AtomicUsize,
+}
+
+// checks the web links of all sources, sources without one are left out, fails in offline mode
+pub async fn check_all(
+    sources: &[Source],
+    progress: Arc<CheckProgress>,
+) -> Result<Vec<LinkResult>, Box<dyn Error + Send + Sync>> {
+    record_operation("Check links");
+    let client = http::client()?;
+
+    let sources: Vec<Source> = sources
+        .iter()
+        .filter(|source| url_analysis::analyze(&source.url).web)
+        .cloned()
+        .collect();
+    debug!("Checking {} links", sources.len());
+    progress.total.store(sources.len(), Ordering::Relaxed);
+    progress.checked.store(0, Ordering::Relaxed);
+
+    let mut results = Vec::with_capacity(sources.len());
+    for chunk in sources.chunks(scheduler::MAX_RUNNING) {
+        let tasks: Vec<_> = chunk
+            .iter()
+            .map(|source| {
+                let client = client.clone();
+                let source = source.clone();
+                tokio::spawn(async move { check(&client, &source).await })
+            })
+            .collect();
+        for task in tasks {
+            results.push(task.await?);
+            progress.checked.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    Ok(results)
+}
+
+async fn check(client: &reqwest::Client, source: &Source) -> LinkResult {
+    trace!("Checking link {}", source.url);
+
+    let mut result = LinkResult {
+        id: source.id,
+        title: source.title.clone(),
+        url: source.url.clone(),
+        status: None,
+        error: None,
+        redirect: None,
+        archived: None,
+    };
+
+    // GET instead of HEAD, many servers answer HEAD requests with an error, the body isn't read
+    let name = format!("Check link {}", source.url);
+    match scheduler::run(name, || request(client, &source.url)).await {
+        Ok(response) => {
+            result.status = Some(response.status().as_u16());
+            let target = response.url().as_str();
+            if target.trim_end_matches('/') != source.url.trim_end_matches('/') {
+                result.redirect = Some(target.to_string());
+            }
+        }
+        Err(e) => {
+            debug!("Error checking {}: {}", source.url, e);
+            result.error = Some(match e.downcast_ref::<reqwest::Error>() {
+                Some(e) => request_error(e),
+                None => e.to_string(),
+            });
+        }
+    }
+
+    let name = format!("Look up archived copy of {}", source.url);
+    match scheduler::run(name, || archived_snapshot(client, &source.url)).await {
+        Ok(snapshot) => result.archived = snapshot,
+        Err(e) => warn!("Error looking up archived copy of {}: {}", source.url, e),
+    }
+
+    result
+}
+
+async fn request(
+    client: &reqwest::Client,
+    url: &str,
+) -> Result<reqwest::Response, Box<dyn Error + Send + Sync>> {
+    Ok(client.get(url).send().await?)
+}
+
+fn request_error(error: &reqwest::Error) -> String {
+    if error.is_timeout() {
+        "Timed out".to_string()
+    } else if error.is_connect() {
+        "Couldn't connect".to_string()
+    } else if error.is_redirect() {
+        "Too many redirects".to_string()
+    } else {
+        error.to_string()
+    }
+}
+
+// URL of the snapshot closest to now, None if the page was never archived
+async fn archived_snapshot(
+    client: &reqwest::Client,
+    url: &str,
+) -> Result<Option<String>, Box<dyn Error + Send + Sync>> {
+    let response: Value = client
+        .get(WAYBACK_URL)
+        .query(&[("url", url)])
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    let closest = &response["archived_snapshots"]["closest"];
+    if closest["available"].as_bool() != Some(true) {
+        return Ok(None);
+    }
+    Ok(closest["url"]
+        .as_str()
+        .map(|snapshot| snapshot.replacen("http://", "https://", 1)))
+}
+
+// one row per link, broken ones first
+pub fn to_csv(results: &[LinkResult]) -> Result<String, Box<dyn Error>> {
+    let mut writer = csv::Writer::from_writer(vec![]);
+    writer.write_record([
+        "id", "title", "url", "status", "broken", "redirect", "archived",
+    ])?;
+    for result in sorted(results) {
+        writer.write_record([
+            result.id.to_string(),
+            result.title.clone(),
+            result.url.clone(),
+            result.status_text(),
+            result.is_broken().to_string(),
+            result.redirect.clone().unwrap_or_default(),
+            result.archived.clone().unwrap_or_default(),
+        ])?;
+    }
+
+    Ok(String::from_utf8(writer.into_inner()?)?)
+}
+
+// standalone page with a table, e.g. for printing it as an appendix
+pub fn to_html(results: &[LinkResult], checked_at: DateTime<Local>) -> String {
+    let broken = results.iter().filter(|result| result.is_broken()).count();
+    let mut html = format!(
+        "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>Link report</title>
+<style>
+body {{ font-family: sans-serif; }}
+table {{ border-collapse: collapse; }}
+th, td {{ border: 1px solid #ccc; padding: 4px 8px; text-align: left; vertical-align: top; }}
+td {{ word-break: break-all; }}
+.broken {{ background: #fde2e2; }}
+</style>
+</head>
+<body>
+<h1>Link report</h1>
+<p>Checked on {}: {} links, {} broken, {} redirected, {} archived.</p>
+<table>
+<tr><th>ID</th><th>Title</th><th>URL</th><th>Status</th><th>Redirect target</th><th>Archived copy</th></tr>
+",
+        checked_at.format("%Y-%m-%d %H:%M"),
+        results.len(),
+        broken,
+        results.iter().filter(|result| result.redirect.is_some()).count(),
+        results.iter().filter(|result| result.archived.is_some()).count(),
+    );
+
+    let link = |url: Option<&str>| match url {
+        Some(url) => format!("<a href=\"{0}\">{0}</a>", escape_html(url)),
+        None => String::new(),
+    };
+    for result in sorted(results) {
+        let class = match result.is_broken() {
+            true => " class=\"broken\"",
+            false => "",
+        };
+        html.push_str(&format!(
+            "<tr{}><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+            class,
+            result.id,
+            escape_html(&result.title),
+            link(Some(&result.url)),
+            escape_html(&result.status_text()),
+            link(result.redirect.as_deref()),
+            link(result.archived.as_deref()),
+        ));
+    }
+
+    html.push_str("</table>\n</body>\n</html>\n");
+    html
+}
+
+fn sorted(results: &[LinkResult]) -> Vec<&LinkResult> {
+    let mut sorted: Vec<&LinkResult> = results.iter().collect();
+    sorted.sort_by_key(|result| (!result.is_broken(), result.id));
+    sorted
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,146 @@
+use chrono::{Datelike, NaiveDate};
+
+// languages with their own terms in the built-in formats, as (code, name)
+pub const LANGUAGES: [(&str, &str); 2] = [("de", "German"), ("en", "English")];
+
+// words the built-in formats put around the fields of a source
+#[derive(Debug, PartialEq)]
+pub struct Terms {
+    pub unknown_author: &'static str,
+    pub no_date: &'static str,
+    pub page: &'static str,        // before page numbers, e.g. "S. 4"
+    pub accessed: &'static str,    // before the date viewed of the default format
+    pub date_format: &'static str, // of the date viewed of the default format
+    pub retrieved: &'static str,   // APA, before the date viewed
+    pub from: &'static str,        // APA, before the URL
+    pub literature: &'static str,  // headings of the bibliography sections by type
+    pub internet_sources: &'static str,
+    pub audiovisual_sources: &'static str,
+    pub images: &'static str,
+    pub other_sources: &'static str,
+    months: [&'static str; 12],
+}
+
+pub const GERMAN: Terms = Terms {
+    unknown_author: "Unbekannt",
+    no_date: "o. J.",
+    page: "S.",
+    accessed: "Stand",
+    date_format: "%d. %m. %Y",
+    retrieved: "Abgerufen am",
+    from: "von",
+    literature: "Literatur",
+    internet_sources: "Internetquellen",
+    audiovisual_sources: "Audiovisuelle Quellen",
+    images: "Abbildungen",
+    other_sources: "Sonstige Quellen",
+    months: [
+        "Januar",
+        "Februar",
+        "März",
+        "April",
+        "Mai",
+        "Juni",
+        "Juli",
+        "August",
+        "September",
+        "Oktober",
+        "November",
+        "Dezember",
+    ],
+};
+
+pub const ENGLISH: Terms = Terms {
+    unknown_author: "Unknown",
+    no_date: "n.d.",
+    page: "p.",
+    accessed: "Accessed",
+    date_format: "%B %-d, %Y",
+    retrieved: "Retrieved",
+    from: "from",
+    literature: "Literature",
+    internet_sources: "Internet sources",
+    audiovisual_sources: "Audiovisual sources",
+    images: "Images",
+    other_sources: "Other sources",
+    months: [
+        "January",
+        "February",
+        "March",
+        "April",
+        "May",
+        "June",
+        "July",
+        "August",
+        "September",
+        "October",
+        "November",
+        "December",
+    ],
+};
+
+// terms of the language of a source, None for an empty or unsupported language so the format
+// uses its own
+pub fn terms(language: &str) -> Option<&'static Terms> {
+    match language {
+        "de" => Some(&GERMAN),
+        "en" => Some(&ENGLISH),
+        _ => None,
+    }
+}
+
+// "German" for "de", the code itself for languages without terms
+pub fn name(language: &str) -> &str {
+    LANGUAGES
+        .iter()
+        .find(|(code, _)| *code == language)
+        .map_or(language, |(_, name)| name)
+}
+
+// "ngerman", "Deutsch", "de-DE", "English" -> "de", "de", "de", "en", other two letter codes are
+// kept, e.g. from BibTeX "langid" or RIS "LA"
+pub fn language_code(text: &str) -> String {
+    let lower = text.trim().to_lowercase();
+    let code = match lower.as_str() {
+        "german" | "ngerman" | "deutsch" | "austrian" | "naustrian" | "swissgerman"
+        | "nswissgerman" => "de",
+        "english" | "american" | "british" | "usenglish" | "ukenglish" | "englisch" => "en",
+        _ => lower.split(['-', '_']).next().unwrap_or_default(),
+    };
+
+    match code.len() == 2 && code.chars().all(|c| c.is_ascii_alphabetic()) {
+        true => code.to_string(),
+        false => String::new(),
+    }
+}
+
+// biblatex name of a language, e.g. for the "langid" field
+pub fn langid(language: &str) -> Option<&'static str> {
+    match language {
+        "de" => Some("ngerman"),
+        "en" => Some("english"),
+        _ => None,
+    }
+}
+
+impl Terms {
+    pub fn month(&self, date: &NaiveDate) -> &'static str {
+        self.months[date.month0() as usize]
+    }
+
+    // "3. Mai", "May 3"
+    pub fn day_month(&self, date: &NaiveDate) -> String {
+        match self == &GERMAN {
+            true => format!("{}. {}", date.day(), self.month(date)),
+            false => format!("{} {}", self.month(date), date.day()),
+        }
+    }
+
+    // "3. Mai 2019", "May 3, 2019"
+    pub fn long_date(&self, date: &NaiveDate) -> String {
+        match self == &GERMAN {
+            true => format!("{} {}", self.day_month(date), date.year()),
+            false => format!("{}, {}", self.day_month(date), date.year()),
+        }
+    }
+}
@@ -0,0 +1,710 @@
+use std::error::Error;
+
+use chrono::{DateTime, NaiveDate};
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use serde::Deserialize;
+use serde_json::Value;
+use tracing::*;
+
+use crate::http;
+use crate::plugins;
+use crate::source::SourceType;
+use crate::url_analysis::{Identifier, VideoHost, WikipediaArticle};
+
+const CROSSREF_URL: &str = "https://api.crossref.org/works/";
+const YOUTUBE_OEMBED_URL: &str = "https://www.youtube.com/oembed";
+const VIMEO_OEMBED_URL: &str = "https://vimeo.com/api/oembed.json";
+const ARXIV_URL: &str = "https://export.arxiv.org/api/query";
+const PUBMED_URL: &str = "https://eutils.ncbi.nlm.nih.gov/entrez/eutils/esummary.fcgi";
+const HEAD_LIMIT: usize = 256 * 1024; // enough for the head of any page
+const PAGE_LIMIT: usize = 5 * 1024 * 1024;
+
+// fields found for a source, only the known ones are set
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Metadata {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub published_date: Option<NaiveDate>,
+    pub url: Option<String>,
+    pub doi: Option<String>,
+    pub source_type: Option<SourceType>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LookupStatus {
+    Idle,
+    Fetching,
+    Fetched(Metadata, String), // waiting to be applied to the input, with the name of the service
+    Applied(String),           // name of the service the input was filled from
+    Failed(String),
+}
+
+#[derive(Deserialize)]
+struct CrossrefResponse {
+    message: CrossrefWork,
+}
+
+#[derive(Deserialize)]
+struct CrossrefWork {
+    #[serde(default)]
+    title: Vec<String>,
+    #[serde(default)]
+    author: Vec<CrossrefAuthor>,
+    issued: Option<Value>, // {"date-parts": [[2019, 5, 3]]}
+    #[serde(rename = "URL")]
+    url: Option<String>,
+    #[serde(rename = "type")]
+    work_type: Option<String>, // e.g. "journal-article"
+}
+
+#[derive(Deserialize)]
+struct OEmbed {
+    title: Option<String>,
+    author_name: Option<String>, // channel
+    upload_date: Option<String>, // only Vimeo, e.g. "2013-02-11 14:03:23"
+}
+
+#[derive(Deserialize)]
+struct CrossrefAuthor {
+    given: Option<String>,
+    family: Option<String>,
+    name: Option<String>, // organizations
+}
+
+pub async fn fetch_crossref(doi: &str) -> Result<Metadata, Box<dyn Error + Send + Sync>> {
+    debug!("Fetching Crossref metadata for {}", doi);
+
+    let response: CrossrefResponse = http::client()?
+        .get(format!("{}{}", CROSSREF_URL, doi))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    let work = response.message;
+
+    let authors: Vec<String> = work
+        .author
+        .iter()
+        .filter_map(
+            |author| match (&author.given, &author.family, &author.name) {
+                (Some(given), Some(family), _) => Some(format!("{} {}", given, family)),
+                (None, Some(family), _) => Some(family.clone()),
+                (_, _, Some(name)) => Some(name.clone()),
+                _ => None,
+            },
+        )
+        .collect();
+
+    Ok(Metadata {
+        title: work.title.into_iter().next(),
+        author: (!authors.is_empty()).then(|| authors.join(", ")),
+        published_date: work.issued.as_ref().and_then(parse_date_parts),
+        url: work.url,
+        doi: None, // already known
+        source_type: match work.work_type.as_deref() {
+            Some("journal-article") => Some(SourceType::JournalArticle),
+            Some("book" | "monograph" | "edited-book" | "reference-book") => Some(SourceType::Book),
+            _ => None,
+        },
+    })
+}
+
+// CSL style date, missing month or day count as the first one
+fn parse_date_parts(date: &Value) -> Option<NaiveDate> {
+    let parts = date.get("date-parts")?.get(0)?.as_array()?;
+    let part = |index: usize| parts.get(index).and_then(Value::as_i64);
+
+    NaiveDate::from_ymd_opt(
+        part(0)? as i32,
+        part(1).unwrap_or(1) as u32,
+        part(2).unwrap_or(1) as u32,
+    )
+}
+
+// title, channel and upload date of a YouTube or Vimeo video
+pub async fn fetch_video(
+    url: &str,
+    host: VideoHost,
+) -> Result<Metadata, Box<dyn Error + Send + Sync>> {
+    debug!("Fetching {} oEmbed data for {}", host, url);
+
+    let endpoint = match host {
+        VideoHost::YouTube => YOUTUBE_OEMBED_URL,
+        VideoHost::Vimeo => VIMEO_OEMBED_URL,
+    };
+    let oembed: OEmbed = http::client()?
+        .get(endpoint)
+        .query(&[("url", url), ("format", "json")])
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    let published_date = match (host, &oembed.upload_date) {
+        (VideoHost::Vimeo, Some(date)) => parse_date_prefix(date),
+        // YouTube's oEmbed has no date, the watch page has it in its metadata
+        (VideoHost::YouTube, _) => match fetch_youtube_upload_date(url).await {
+            Ok(date) => date,
+            Err(e) => {
+                warn!("Error fetching YouTube upload date: {}", e);
+                None
+            }
+        },
+        _ => None,
+    };
+
+    Ok(Metadata {
+        title: oembed.title,
+        author: oembed.author_name,
+        published_date,
+        url: None,
+        doi: None,
+        source_type: Some(SourceType::Video),
+    })
+}
+
+async fn fetch_youtube_upload_date(
+    url: &str,
+) -> Result<Option<NaiveDate>, Box<dyn Error + Send + Sync>> {
+    let page = http::client()?
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+
+    // <meta itemprop="uploadDate" content="2021-03-04T08:00:11-08:00"> or "uploadDate":"2021-03-04…"
+    let date = ["itemprop=\"uploadDate\" content=\"", "\"uploadDate\":\""]
+        .iter()
+        .find_map(|marker| {
+            let start = page.find(marker)? + marker.len();
+            parse_date_prefix(&page[start..])
+        });
+
+    Ok(date)
+}
+
+// date at the start of e.g. "2013-02-11 14:03:23" or "2021-03-04T08:00:11-08:00"
+fn parse_date_prefix(text: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(text.get(..10)?, "%Y-%m-%d").ok()
+}
+
+pub async fn fetch_identifier(
+    identifier: &Identifier,
+) -> Result<Metadata, Box<dyn Error + Send + Sync>> {
+    match identifier {
+        Identifier::Doi(doi) => {
+            let metadata = fetch_crossref(doi).await?;
+            Ok(Metadata {
+                doi: Some(doi.clone()),
+                ..metadata
+            })
+        }
+        Identifier::ArXiv(id) => fetch_arxiv(id).await,
+        Identifier::PubMed(id) => fetch_pubmed(id).await,
+    }
+}
+
+// the arXiv API answers with an Atom feed containing one entry
+async fn fetch_arxiv(id: &str) -> Result<Metadata, Box<dyn Error + Send + Sync>> {
+    debug!("Fetching arXiv metadata for {}", id);
+
+    let feed = http::client()?
+        .get(ARXIV_URL)
+        .query(&[("id_list", id)])
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+
+    parse_arxiv_feed(&feed)
+}
+
+fn parse_arxiv_feed(feed: &str) -> Result<Metadata, Box<dyn Error + Send + Sync>> {
+    let mut reader = Reader::from_str(feed);
+    reader.trim_text(true);
+
+    let mut path: Vec<String> = vec![];
+    let mut metadata = Metadata {
+        source_type: Some(SourceType::JournalArticle),
+        ..Default::default()
+    };
+    let mut authors: Vec<String> = vec![];
+    let mut found = false;
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(tag) => {
+                let name = String::from_utf8_lossy(tag.local_name().as_ref()).to_string();
+                found |= name == "entry";
+                path.push(name);
+            }
+            Event::End(_) => {
+                path.pop();
+            }
+            Event::Text(text) => {
+                let text = text.unescape()?;
+                // titles are wrapped over several lines
+                let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+
+                match path.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+                    [.., "entry", "title"] => metadata.title = Some(text),
+                    [.., "entry", "author", "name"] => authors.push(text),
+                    [.., "entry", "published"] => {
+                        metadata.published_date = parse_date_prefix(&text)
+                    }
+                    [.., "entry", "id"] => {
+                        // invalid ids give an entry pointing to the error description
+                        if text.contains("/api/errors") {
+                            return Err("Invalid arXiv ID".into());
+                        }
+                        metadata.url = Some(text);
+                    }
+                    [.., "entry", "doi"] => metadata.doi = Some(text),
+                    _ => {}
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    if !found {
+        return Err("No arXiv entry with this ID".into());
+    }
+    metadata.author = (!authors.is_empty()).then(|| authors.join(", "));
+
+    Ok(metadata)
+}
+
+async fn fetch_pubmed(id: &str) -> Result<Metadata, Box<dyn Error + Send + Sync>> {
+    debug!("Fetching PubMed metadata for {}", id);
+
+    let response: Value = http::client()?
+        .get(PUBMED_URL)
+        .query(&[("db", "pubmed"), ("id", id), ("retmode", "json")])
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    let summary = response
+        .get("result")
+        .and_then(|result| result.get(id))
+        .filter(|summary| summary.get("error").is_none())
+        .ok_or("No PubMed article with this PMID")?;
+    let text = |key: &str| {
+        summary
+            .get(key)
+            .and_then(Value::as_str)
+            .filter(|text| !text.is_empty())
+            .map(str::to_string)
+    };
+
+    let authors: Vec<&str> = summary
+        .get("authors")
+        .and_then(Value::as_array)
+        .map(|authors| {
+            authors
+                .iter()
+                .filter_map(|author| author.get("name")?.as_str())
+                .collect()
+        })
+        .unwrap_or_default();
+    let doi = summary
+        .get("articleids")
+        .and_then(Value::as_array)
+        .and_then(|ids| {
+            ids.iter()
+                .find(|article_id| article_id.get("idtype").and_then(Value::as_str) == Some("doi"))
+        })
+        .and_then(|article_id| article_id.get("value")?.as_str())
+        .map(str::to_string);
+
+    Ok(Metadata {
+        // titles end with a period
+        title: text("title").map(|title| title.trim_end_matches('.').to_string()),
+        author: (!authors.is_empty()).then(|| authors.join(", ")),
+        // e.g. "2019/08/26 00:00"
+        published_date: text("sortpubdate")
+            .and_then(|date| NaiveDate::parse_from_str(date.get(..10)?, "%Y/%m/%d").ok()),
+        url: Some(format!("https://pubmed.ncbi.nlm.nih.gov/{}/", id)),
+        doi,
+        source_type: Some(SourceType::JournalArticle),
+    })
+}
+
+// current revision of a Wikipedia article
+#[derive(Debug, Clone, PartialEq)]
+pub struct Revision {
+    pub permalink: String,
+    pub date: NaiveDate,
+}
+
+pub async fn fetch_wikipedia_revision(
+    article: &WikipediaArticle,
+) -> Result<Revision, Box<dyn Error + Send + Sync>> {
+    debug!(
+        "Fetching current revision of {} on {}",
+        article.title, article.host
+    );
+
+    let api = format!("https://{}/w/api.php", article.host);
+    let response: Value = http::client()?
+        .get(&api)
+        .query(&[
+            ("action", "query"),
+            ("prop", "revisions"),
+            ("rvprop", "ids|timestamp"),
+            ("titles", article.title.as_str()),
+            ("redirects", "1"),
+            ("format", "json"),
+            ("formatversion", "2"),
+        ])
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    let page = response
+        .pointer("/query/pages/0")
+        .filter(|page| page.get("missing").is_none())
+        .ok_or("Article not found")?;
+    let title = page
+        .get("title")
+        .and_then(Value::as_str)
+        .unwrap_or(&article.title);
+    let revision = page
+        .pointer("/revisions/0")
+        .ok_or("Article has no revisions")?;
+    let id = revision
+        .get("revid")
+        .and_then(Value::as_i64)
+        .ok_or("Revision without id")?;
+    let timestamp = revision
+        .get("timestamp")
+        .and_then(Value::as_str)
+        .ok_or("Revision without timestamp")?;
+
+    let permalink = reqwest::Url::parse_with_params(
+        &format!("https://{}/w/index.php", article.host),
+        &[
+            ("title", title.replace(' ', "_")),
+            ("oldid", id.to_string()),
+        ],
+    )?;
+
+    Ok(Revision {
+        permalink: permalink.to_string(),
+        date: DateTime::parse_from_rfc3339(timestamp)?.date_naive(),
+    })
+}
+
+// title, author and dates from the meta tags of a web page
+pub async fn fetch_page(
+    url: &str,
+    metadata_only: bool, // stop after the head of the page
+) -> Result<Metadata, Box<dyn Error + Send + Sync>> {
+    debug!(
+        "Fetching page metadata of {} (metadata only: {})",
+        url, metadata_only
+    );
+
+    let limit = match metadata_only {
+        true => HEAD_LIMIT,
+        false => PAGE_LIMIT,
+    };
+    let mut request = http::client()?.get(url);
+    if metadata_only {
+        // servers without range support send everything, the download is aborted after the head
+        request = request.header(reqwest::header::RANGE, format!("bytes=0-{}", limit - 1));
+    }
+    let mut response = request.send().await?.error_for_status()?;
+
+    let mut page: Vec<u8> = vec![];
+    while let Some(chunk) = response.chunk().await? {
+        page.extend_from_slice(&chunk);
+
+        if page.len() >= limit || (metadata_only && find_ignore_case(&page, b"</head>").is_some()) {
+            trace!("Stopping page download after {} bytes", page.len());
+            break;
+        }
+    }
+
+    let html = String::from_utf8_lossy(&page);
+    let mut metadata = parse_html_metadata(&html);
+    plugins::apply_metadata(url, &html, &mut metadata);
+
+    Ok(metadata)
+}
+
+// where a link leads: the <link rel="canonical"> of the page or the address after the redirects,
+// None if that's the link itself
+pub async fn resolve_canonical_url(
+    url: &str,
+) -> Result<Option<String>, Box<dyn Error + Send + Sync>> {
+    debug!("Resolving canonical URL of {}", url);
+
+    let mut response = http::client()?
+        .get(url)
+        .header(
+            reqwest::header::RANGE,
+            format!("bytes=0-{}", HEAD_LIMIT - 1),
+        )
+        .send()
+        .await?
+        .error_for_status()?;
+    let target = response.url().clone();
+
+    // the canonical link is in the head, the rest of the page isn't needed
+    let mut page: Vec<u8> = vec![];
+    while let Some(chunk) = response.chunk().await? {
+        page.extend_from_slice(&chunk);
+        if page.len() >= HEAD_LIMIT || find_ignore_case(&page, b"</head>").is_some() {
+            break;
+        }
+    }
+
+    // relative to the address the page was served from
+    let canonical = canonical_link(&String::from_utf8_lossy(&page))
+        .and_then(|href| target.join(&href).ok())
+        .filter(|canonical| matches!(canonical.scheme(), "http" | "https"))
+        .unwrap_or(target)
+        .to_string();
+
+    match canonical.trim_end_matches('/') == url.trim().trim_end_matches('/') {
+        true => Ok(None),
+        false => Ok(Some(canonical)),
+    }
+}
+
+// href of <link rel="canonical" href="…">
+fn canonical_link(html: &str) -> Option<String> {
+    let lower = html.to_ascii_lowercase();
+
+    let mut start = 0;
+    while let Some(offset) = lower[start..].find("<link") {
+        let index = start + offset;
+        let end = lower[index..]
+            .find('>')
+            .map_or(lower.len(), |end| index + end);
+        start = end;
+
+        let attributes = parse_attributes(&html[index + 5..end]);
+        let attribute = |name: &str| {
+            attributes
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.trim())
+        };
+        let canonical = attribute("rel").is_some_and(|rel| {
+            rel.split_whitespace()
+                .any(|rel| rel.eq_ignore_ascii_case("canonical"))
+        });
+        if let Some(href) = attribute("href").filter(|href| canonical && !href.is_empty()) {
+            return Some(href.to_string());
+        }
+    }
+
+    None
+}
+
+fn find_ignore_case(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window.eq_ignore_ascii_case(needle))
+}
+
+// reads Open Graph, Dublin Core, Highwire (citation_*) and schema.org meta tags
+fn parse_html_metadata(html: &str) -> Metadata {
+    // same byte offsets as the original, unlike to_lowercase
+    let lower = html.to_ascii_lowercase();
+    let mut tags: Vec<(String, String)> = vec![];
+
+    let mut start = 0;
+    while let Some(offset) = lower[start..].find("<meta") {
+        let index = start + offset;
+        let end = lower[index..]
+            .find('>')
+            .map_or(lower.len(), |end| index + end);
+        start = end;
+
+        let attributes = parse_attributes(&html[index + 5..end]);
+        let attribute = |name: &str| {
+            attributes
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.clone())
+        };
+        if let (Some(name), Some(content)) = (
+            attribute("property")
+                .or_else(|| attribute("name"))
+                .or_else(|| attribute("itemprop")),
+            attribute("content"),
+        ) {
+            if !content.trim().is_empty() {
+                tags.push((name.to_lowercase(), content.trim().to_string()));
+            }
+        }
+    }
+
+    let first = |names: &[&str]| {
+        names.iter().find_map(|name| {
+            tags.iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.clone())
+        })
+    };
+
+    let title_tag = lower.find("<title").and_then(|index| {
+        let start = index + lower[index..].find('>')? + 1;
+        let end = start + lower[start..].find("</title")?;
+        let title = decode_entities(html[start..end].trim());
+        (!title.is_empty()).then_some(title)
+    });
+
+    // several citation_author tags for multiple authors
+    let authors: Vec<String> = tags
+        .iter()
+        .filter(|(key, _)| key == "citation_author")
+        .map(|(_, value)| value.clone())
+        .collect();
+    let author = match authors.is_empty() {
+        true => first(&["author", "article:author", "dc.creator", "dcterms.creator"])
+            .filter(|author| !author.starts_with("http")), // article:author can be a profile link
+        false => Some(authors.join(", ")),
+    };
+
+    let published_date = first(&[
+        "citation_publication_date",
+        "citation_date",
+        "article:published_time",
+        "datepublished",
+        "dc.date",
+        "dcterms.date",
+        "date",
+    ])
+    .or_else(|| json_ld_value(html, "datePublished"))
+    .and_then(|date| parse_date_prefix(&date.replace('/', "-")));
+
+    let source_type = match (first(&["og:type"]), first(&["citation_journal_title"])) {
+        (_, Some(_)) => Some(SourceType::JournalArticle),
+        (Some(kind), _) if kind.starts_with("video") => Some(SourceType::Video),
+        (Some(kind), _) if kind == "book" => Some(SourceType::Book),
+        _ => None,
+    };
+
+    Metadata {
+        title: first(&["citation_title", "og:title", "twitter:title", "dc.title"]).or(title_tag),
+        author,
+        published_date,
+        url: first(&["og:url"]),
+        doi: first(&["citation_doi", "dc.identifier"])
+            .and_then(|doi| crate::url_analysis::extract_doi(&doi)),
+        source_type,
+    }
+}
+
+// key="value" pairs of a tag, values without quotes are accepted as well
+pub fn parse_attributes(tag: &str) -> Vec<(String, String)> {
+    let mut attributes = vec![];
+    let mut chars = tag.chars().peekable();
+
+    loop {
+        while chars.next_if(|c| c.is_whitespace() || *c == '/').is_some() {}
+        let key: String =
+            std::iter::from_fn(|| chars.next_if(|c| !c.is_whitespace() && *c != '=')).collect();
+        if key.is_empty() {
+            break;
+        }
+
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        if chars.next_if_eq(&'=').is_none() {
+            attributes.push((key.to_lowercase(), String::new()));
+            continue;
+        }
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+        let value: String = match chars.next_if(|c| *c == '"' || *c == '\'') {
+            Some(quote) => {
+                let value = std::iter::from_fn(|| chars.next_if(|c| *c != quote)).collect();
+                chars.next();
+                value
+            }
+            None => std::iter::from_fn(|| chars.next_if(|c| !c.is_whitespace())).collect(),
+        };
+        attributes.push((key.to_lowercase(), decode_entities(&value)));
+    }
+
+    attributes
+}
+
+// "datePublished": "2021-03-04" in JSON-LD blocks
+fn json_ld_value(html: &str, key: &str) -> Option<String> {
+    let marker = format!("\"{}\"", key);
+    let rest = &html[html.find(&marker)? + marker.len()..];
+    let rest = rest
+        .trim_start()
+        .strip_prefix(':')?
+        .trim_start()
+        .strip_prefix('"')?;
+
+    Some(rest[..rest.find('"')?].to_string())
+}
+
+pub fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(index) = rest.find('&') {
+        out.push_str(&rest[..index]);
+        rest = &rest[index..];
+
+        let entity = rest
+            .find(';')
+            .filter(|end| *end <= 10)
+            .map(|end| &rest[1..end]);
+        let decoded = entity.and_then(|entity| match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            _ => match entity
+                .strip_prefix("#x")
+                .or_else(|| entity.strip_prefix("#X"))
+            {
+                Some(hex) => u32::from_str_radix(hex, 16).ok().and_then(char::from_u32),
+                None => entity
+                    .strip_prefix('#')
+                    .and_then(|number| number.parse().ok())
+                    .and_then(char::from_u32),
+            },
+        });
+
+        match (decoded, entity) {
+            (Some(c), Some(entity)) => {
+                out.push(c);
+                rest = &rest[entity.len() + 2..];
+            }
+            _ => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+
+    out
+}
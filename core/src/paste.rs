@@ -0,0 +1,335 @@
+use chrono::NaiveDate;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use tracing::*;
+
+use crate::formats::{self, Format};
+use crate::metadata::Metadata;
+use crate::source::{Source, SourceType};
+use crate::url_analysis::{self, percent_decode, Identifier};
+
+static URL: Lazy<Regex> = Lazy::new(|| Regex::new(r#"https?://[^\s<>"']+"#).unwrap());
+// "2024-03-01"
+static ISO_DATE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\b(\d{4})-(\d{1,2})-(\d{1,2})\b").unwrap());
+// "01.03.2024"
+static DOTTED_DATE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\b(\d{1,2})\.\s?(\d{1,2})\.\s?(\d{4})\b").unwrap());
+// "1 March 2024", "1. März 2024", "Fri, 1 Mar 2024 10:30:00 +0100"
+static DAY_MONTH_DATE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\b(\d{1,2})\.?\s+(\p{L}+)\.?,?\s+(\d{4})\b").unwrap());
+// "March 1, 2024", "Mar 1st 2024"
+static MONTH_DAY_DATE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\b(\p{L}+)\.?\s+(\d{1,2})(?:st|nd|rd|th)?,?\s+(\d{4})\b").unwrap());
+
+// English and German, matched by their start
+const MONTHS: [&[&str]; 12] = [
+    &["jan"],
+    &["feb"],
+    &["mar", "mär"],
+    &["apr"],
+    &["may", "mai"],
+    &["jun"],
+    &["jul"],
+    &["aug"],
+    &["sep"],
+    &["oct", "okt"],
+    &["nov"],
+    &["dec", "dez"],
+];
+
+// prefixes mail programs add to the subject of forwarded mails
+const SUBJECT_PREFIXES: [&str; 6] = ["fwd:", "fw:", "wg:", "re:", "aw:", "tr:"];
+const MAX_TITLE_LEN: usize = 200; // longer lines are paragraphs, not titles
+
+// what was pasted into the start page, decides how the form is filled in
+pub enum Pasted {
+    // first source of something an importer reads, e.g. a BibTeX entry, with the number of
+    // sources in it
+    Source(Box<Source>, &'static dyn Format, usize),
+    Identifier(Identifier), // arXiv ID, PMID or DOI to look up
+    Url(String),
+    Text(Metadata), // fields found in other text, shown before they are filled in
+}
+
+// tries the importers first, so a copied BibTeX entry or RIS record keeps all its fields
+pub fn detect(text: &str) -> Pasted {
+    let text = text.trim_start_matches('\u{feff}');
+
+    if let Some(format) = formats::detect(text) {
+        match format.import_report(text) {
+            Ok(report) if !report.items.is_empty() => {
+                let count = report.items.len();
+                let source = report.items.into_iter().next().unwrap().source;
+                debug!("Pasted {} sources as {}", count, format.name());
+                return Pasted::Source(Box::new(source), format, count);
+            }
+            Ok(_) => debug!("Pasted {} without sources", format.name()),
+            Err(e) => debug!("Pasted text isn't {}: {}", format.name(), e),
+        }
+    }
+
+    // a single word, e.g. a copied link
+    let trimmed = text.trim();
+    if !trimmed.contains(char::is_whitespace) {
+        if let Some(identifier) = url_analysis::parse_identifier(trimmed) {
+            return Pasted::Identifier(identifier);
+        }
+        if url_analysis::analyze(trimmed).web {
+            return Pasted::Url(trimmed.to_string());
+        }
+    }
+
+    Pasted::Text(parse_text(text))
+}
+
+// fields found in a block of pasted text, e.g. a newsletter snippet, a forwarded mail with its
+// headers or a "mailto:" link of a "Share via email" button
+pub fn parse_text(text: &str) -> Metadata {
+    let (subject, body) = match parse_mailto(text) {
+        Some((subject, body)) => (subject, body),
+        None => (None, text.to_string()),
+    };
+
+    let mut headers = Headers::default();
+    let mut lines = vec![];
+    for line in body.lines() {
+        // quoted replies
+        let line = line
+            .trim_start_matches(|c: char| c == '>' || c.is_whitespace())
+            .trim();
+        if line.is_empty() || is_separator(line) || headers.read(line) {
+            continue;
+        }
+        lines.push(line);
+    }
+
+    let url = lines.iter().find_map(|line| find_url(line));
+    let doi = url
+        .as_deref()
+        .and_then(url_analysis::extract_doi)
+        .or_else(|| {
+            lines
+                .iter()
+                .find_map(|line| url_analysis::extract_doi(line))
+        });
+
+    let title = subject
+        .or(headers.subject)
+        .map(|subject| strip_subject_prefixes(&subject).to_string())
+        .filter(|title| !title.is_empty())
+        .or_else(|| {
+            lines
+                .iter()
+                .find(|line| is_title(line))
+                .map(|line| line.to_string())
+        });
+
+    let author = lines
+        .iter()
+        .find_map(|line| byline(line))
+        .or_else(|| headers.from.as_deref().and_then(sender_name));
+
+    // the date of the text itself comes before the date the mail was sent
+    let published_date = lines
+        .iter()
+        .find_map(|line| find_date(line))
+        .or_else(|| headers.date.as_deref().and_then(find_date));
+
+    let source_type = match (&doi, &url) {
+        (Some(_), _) => Some(SourceType::JournalArticle),
+        (None, Some(_)) => Some(SourceType::Webpage),
+        (None, None) => None,
+    };
+
+    let metadata = Metadata {
+        title,
+        author,
+        published_date,
+        url,
+        doi,
+        source_type,
+    };
+    debug!("Read pasted text: {:?}", metadata);
+
+    metadata
+}
+
+// mail headers of a forwarded mail, English and German
+#[derive(Default)]
+struct Headers {
+    subject: Option<String>,
+    from: Option<String>,
+    date: Option<String>,
+}
+
+impl Headers {
+    // true if the line is a header, the first of each kind is kept
+    fn read(&mut self, line: &str) -> bool {
+        let Some((name, value)) = line.split_once(':') else {
+            return false;
+        };
+        let header = match name.trim().to_lowercase().as_str() {
+            "subject" | "betreff" => &mut self.subject,
+            "from" | "von" => &mut self.from,
+            "date" | "sent" | "datum" | "gesendet" => &mut self.date,
+            "to" | "an" | "cc" | "reply-to" => return true,
+            _ => return false,
+        };
+        if header.is_none() {
+            *header = Some(value.trim().to_string());
+        }
+        true
+    }
+}
+
+// "mailto:?subject=…&body=…" -> subject and body
+fn parse_mailto(text: &str) -> Option<(Option<String>, String)> {
+    let text = text.trim();
+    if !text.get(..7)?.eq_ignore_ascii_case("mailto:") {
+        return None;
+    }
+
+    let (_, query) = text.split_once('?').unwrap_or((text, ""));
+    let mut subject = None;
+    let mut body = String::new();
+    for pair in query.split('&') {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        match key.to_lowercase().as_str() {
+            "subject" => subject = Some(percent_decode(value)),
+            "body" => body = percent_decode(value),
+            _ => {}
+        }
+    }
+
+    Some((subject, body))
+}
+
+fn strip_subject_prefixes(subject: &str) -> &str {
+    let mut subject = subject.trim();
+    while let Some(prefix) = SUBJECT_PREFIXES.iter().find(|prefix| {
+        subject
+            .get(..prefix.len())
+            .is_some_and(|start| start.eq_ignore_ascii_case(prefix))
+    }) {
+        subject = subject[prefix.len()..].trim_start();
+    }
+    subject
+}
+
+// first link of the line without the punctuation of the sentence around it
+pub fn find_url(line: &str) -> Option<String> {
+    let url = URL
+        .find(line)?
+        .as_str()
+        .trim_end_matches(['.', ',', ';', ':', '!', '?', ')', ']', '>']);
+    Some(url.to_string())
+}
+
+fn is_title(line: &str) -> bool {
+    line.chars().count() <= MAX_TITLE_LEN
+        && line.chars().filter(|c| c.is_alphabetic()).count() >= 3
+        && !URL.is_match(line)
+        && byline(line).is_none()
+        && !(line.len() < 40 && find_date(line).is_some())
+}
+
+// "---------- Forwarded message ---------", "-----Ursprüngliche Nachricht-----"
+fn is_separator(line: &str) -> bool {
+    let lower = line.to_lowercase();
+    line.starts_with("---")
+        || line.starts_with("___")
+        || [
+            "forwarded message",
+            "original message",
+            "weitergeleitete nachricht",
+        ]
+        .iter()
+        .any(|phrase| lower.contains(phrase))
+}
+
+// "By Jane Doe", "Von Hans Müller", "Author: Jane Doe"
+fn byline(line: &str) -> Option<String> {
+    let name = ["by ", "von ", "author:", "autor:", "autorin:"]
+        .iter()
+        .find_map(|prefix| {
+            line.get(..prefix.len())
+                .filter(|start| start.eq_ignore_ascii_case(prefix))
+                .map(|_| &line[prefix.len()..])
+        })?;
+    // "By Jane Doe | March 1, 2024"
+    let name = name
+        .split(['|', '·', '•'])
+        .next()
+        .unwrap_or_default()
+        .trim();
+
+    (!name.is_empty() && name.split_whitespace().count() <= 6).then(|| name.to_string())
+}
+
+// "Jane Doe <jane@example.com>" -> "Jane Doe", None for a bare address
+fn sender_name(from: &str) -> Option<String> {
+    let name = match from.find('<') {
+        Some(index) => &from[..index],
+        None if from.contains('@') => "",
+        None => from,
+    };
+    let name = name.trim().trim_matches(['"', '\'']).trim();
+
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+// first date of the line in one of the common written forms
+pub fn find_date(line: &str) -> Option<NaiveDate> {
+    let number = |text: &str| text.parse::<u32>().ok();
+
+    ISO_DATE
+        .captures_iter(line)
+        .find_map(|captures| {
+            NaiveDate::from_ymd_opt(
+                captures[1].parse().ok()?,
+                number(&captures[2])?,
+                number(&captures[3])?,
+            )
+        })
+        .or_else(|| {
+            DOTTED_DATE.captures_iter(line).find_map(|captures| {
+                NaiveDate::from_ymd_opt(
+                    captures[3].parse().ok()?,
+                    number(&captures[2])?,
+                    number(&captures[1])?,
+                )
+            })
+        })
+        .or_else(|| {
+            DAY_MONTH_DATE.captures_iter(line).find_map(|captures| {
+                NaiveDate::from_ymd_opt(
+                    captures[3].parse().ok()?,
+                    month(&captures[2])?,
+                    number(&captures[1])?,
+                )
+            })
+        })
+        .or_else(|| {
+            MONTH_DAY_DATE.captures_iter(line).find_map(|captures| {
+                NaiveDate::from_ymd_opt(
+                    captures[3].parse().ok()?,
+                    month(&captures[1])?,
+                    number(&captures[2])?,
+                )
+            })
+        })
+}
+
+// "March", "Mär", "sept" -> 3, 3, 9
+fn month(name: &str) -> Option<u32> {
+    let name = name.to_lowercase();
+    if name.chars().count() > 9 {
+        return None;
+    }
+
+    MONTHS
+        .iter()
+        .position(|prefixes| prefixes.iter().any(|prefix| name.starts_with(prefix)))
+        .map(|index| index as u32 + 1)
+}
@@ -0,0 +1,187 @@
+use std::error::Error;
+use std::path::Path;
+
+use chrono::NaiveDate;
+use lopdf::{Dictionary, Document, Object};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use tracing::*;
+
+use crate::crash::record_operation;
+use crate::metadata::{decode_entities, Metadata};
+use crate::source::SourceType;
+use crate::url_analysis::extract_doi;
+
+// elements of the XMP packet, the first <rdf:li> of lists like dc:title
+static XMP_TITLE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?s)<dc:title>.*?<rdf:li[^>]*>(.*?)</rdf:li>|<dc:title>([^<]+)</dc:title>")
+        .unwrap()
+});
+static XMP_CREATOR: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?s)<dc:creator>(.*?)</dc:creator>").unwrap());
+static XMP_LIST_ITEM: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?s)<rdf:li[^>]*>(.*?)</rdf:li>").unwrap());
+static XMP_DATE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"(?:prism:publicationDate|prism:coverDate|xmp:CreateDate)(?:>|=")(\d{4}-\d{2}-\d{2})"#,
+    )
+    .unwrap()
+});
+static XMP_DOI: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?:prism:doi|pdfx:doi)(?:>|=")([^<"]+)"#).unwrap());
+
+// titles set by the program that wrote the file, not by the author
+const GENERATED_TITLES: [&str; 3] = ["untitled", "unbenannt", "title"];
+const MIN_TITLE_WORDS: usize = 2;
+
+// title, authors, date and DOI of a PDF file, from the XMP metadata, the Info dictionary and, for
+// what they don't have, the text of the first page
+pub fn read_metadata(path: &Path) -> Result<Metadata, Box<dyn Error + Send + Sync>> {
+    debug!("Reading metadata of {}", path.display());
+    record_operation("Read PDF metadata");
+
+    let document = Document::load(path)?;
+    let info = info_dictionary(&document);
+    let xmp = xmp_packet(&document).unwrap_or_default();
+
+    let info_string = |key: &[u8]| {
+        info.and_then(|info| info.get(key).ok())
+            .and_then(|value| document.dereference(value).ok())
+            .and_then(|(_, value)| pdf_string(value))
+            .filter(|value| !value.is_empty())
+    };
+
+    let mut title = xmp_title(&xmp)
+        .or_else(|| info_string(b"Title"))
+        .filter(|title| !is_generated_title(title));
+    let author = xmp_creators(&xmp).or_else(|| info_string(b"Author"));
+    let published_date = XMP_DATE
+        .captures(&xmp)
+        .and_then(|captures| NaiveDate::parse_from_str(&captures[1], "%Y-%m-%d").ok())
+        .or_else(|| info_string(b"CreationDate").and_then(|date| pdf_date(&date)));
+    let mut doi = XMP_DOI
+        .captures(&xmp)
+        .and_then(|captures| extract_doi(&captures[1]))
+        .or_else(|| info_string(b"doi").and_then(|doi| extract_doi(&doi)));
+
+    // the first page only has to be read if the metadata is incomplete
+    if title.is_none() || doi.is_none() {
+        match document.extract_text(&[1]) {
+            Ok(text) => {
+                doi = doi.or_else(|| extract_doi(&text));
+                title = title.or_else(|| first_page_title(&text));
+            }
+            Err(e) => warn!("Error reading the first page of {}: {}", path.display(), e),
+        }
+    }
+
+    let source_type = match doi {
+        Some(_) => SourceType::JournalArticle,
+        None => SourceType::Other,
+    };
+
+    Ok(Metadata {
+        title,
+        author,
+        published_date,
+        url: None,
+        doi,
+        source_type: Some(source_type),
+    })
+}
+
+fn info_dictionary(document: &Document) -> Option<&Dictionary> {
+    let info = document.trailer.get(b"Info").ok()?;
+    let (_, info) = document.dereference(info).ok()?;
+    info.as_dict().ok()
+}
+
+// XMP packet of the catalog, compressed or not
+fn xmp_packet(document: &Document) -> Option<String> {
+    let id = document
+        .catalog()
+        .ok()?
+        .get(b"Metadata")
+        .ok()?
+        .as_reference()
+        .ok()?;
+    let stream = document.get_object(id).ok()?.as_stream().ok()?;
+    let content = stream
+        .decompressed_content()
+        .unwrap_or_else(|_| stream.content.clone());
+
+    Some(String::from_utf8_lossy(&content).to_string())
+}
+
+fn xmp_title(xmp: &str) -> Option<String> {
+    let captures = XMP_TITLE.captures(xmp)?;
+    let title = captures.get(1).or_else(|| captures.get(2))?.as_str();
+    let title = decode_entities(title.trim());
+
+    (!title.is_empty()).then_some(title)
+}
+
+// <rdf:Seq> of names -> "Jane Doe, John Roe"
+fn xmp_creators(xmp: &str) -> Option<String> {
+    let creators = &XMP_CREATOR.captures(xmp)?[1];
+    let names: Vec<String> = XMP_LIST_ITEM
+        .captures_iter(creators)
+        .map(|captures| decode_entities(captures[1].trim()))
+        .filter(|name| !name.is_empty())
+        .collect();
+
+    (!names.is_empty()).then(|| names.join(", "))
+}
+
+// text strings are UTF-16 with a byte order mark or PDFDocEncoding, which matches Latin-1 for
+// letters
+fn pdf_string(object: &Object) -> Option<String> {
+    let Object::String(bytes, _) = object else {
+        return None;
+    };
+
+    let text = match bytes.strip_prefix(&[0xfe, 0xff]) {
+        Some(utf16) => {
+            let units: Vec<u16> = utf16
+                .chunks_exact(2)
+                .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        None => match std::str::from_utf8(bytes) {
+            Ok(text) => text.to_string(),
+            Err(_) => bytes.iter().map(|&byte| byte as char).collect(),
+        },
+    };
+
+    Some(text.trim_matches(char::from(0)).trim().to_string())
+}
+
+// "D:20190503120000+02'00'" -> 2019-05-03
+fn pdf_date(date: &str) -> Option<NaiveDate> {
+    let date = date.trim().trim_start_matches("D:");
+    NaiveDate::parse_from_str(date.get(..8)?, "%Y%m%d").ok()
+}
+
+fn is_generated_title(title: &str) -> bool {
+    let lower = title.to_lowercase();
+
+    GENERATED_TITLES.contains(&lower.as_str())
+        || lower.starts_with("microsoft word - ")
+        || [".doc", ".docx", ".odt", ".tex", ".pdf", ".dvi"]
+            .iter()
+            .any(|extension| lower.ends_with(extension))
+}
+
+// first line of the page that reads like a title, page headers like a journal name and a
+// volume are usually shorter or contain digits
+fn first_page_title(text: &str) -> Option<String> {
+    text.lines()
+        .map(str::trim)
+        .find(|line| {
+            line.split_whitespace().count() >= MIN_TITLE_WORDS
+                && line.chars().count() <= 200
+                && !line.chars().any(|c| c.is_ascii_digit())
+        })
+        .map(str::to_string)
+}
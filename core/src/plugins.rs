@@ -0,0 +1,245 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+use chrono::NaiveDate;
+use directories::ProjectDirs;
+use once_cell::sync::Lazy;
+use rhai::{Dynamic, Engine, Map, Scope, AST};
+use tracing::*;
+
+use crate::metadata::Metadata;
+use crate::source::{Source, SourceType};
+
+const PLUGIN_EXTENSION: &str = "rhai";
+const MAX_OPERATIONS: u64 = 1_000_000; // keeps a script with an endless loop from hanging SaveIt
+
+// keep a script from using up the memory, the strings leave room for the HTML of large pages
+const MAX_STRING_SIZE: usize = 16 * 1024 * 1024;
+const MAX_ARRAY_SIZE: usize = 100_000;
+const MAX_MAP_SIZE: usize = 10_000;
+const MAX_EXPR_DEPTH: usize = 64;
+const MAX_FUNCTION_EXPR_DEPTH: usize = 32;
+
+static ENGINE: Lazy<Engine> = Lazy::new(|| {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.set_max_string_size(MAX_STRING_SIZE);
+    engine.set_max_array_size(MAX_ARRAY_SIZE);
+    engine.set_max_map_size(MAX_MAP_SIZE);
+    engine.set_max_expr_depths(MAX_EXPR_DEPTH, MAX_FUNCTION_EXPR_DEPTH);
+    engine
+});
+
+// loaded on first use, again with `reload`
+static PLUGINS: Lazy<RwLock<Vec<Plugin>>> = Lazy::new(|| RwLock::new(load_all()));
+
+// Rhai script from the plugins folder, adds a citation format with
+// `fn format(source, pages)` (and optionally `fn in_text(source, pages)`) and/or a metadata
+// fetcher with `fn metadata(url, html)`
+#[derive(Clone)]
+pub struct Plugin {
+    pub id: String,   // file name without the extension, stored in the config
+    pub name: String, // returned by `fn name()`, the id if the script has none
+    pub path: PathBuf,
+    pub formats: bool,
+    pub fetches: bool,
+    pub error: Option<String>, // the script couldn't be compiled
+    ast: Option<AST>,
+}
+
+impl Plugin {
+    fn has_fn(&self, name: &str, params: usize) -> bool {
+        self.ast.as_ref().is_some_and(|ast| {
+            ast.iter_functions()
+                .any(|function| function.name == name && function.params.len() == params)
+        })
+    }
+
+    fn call(&self, name: &str, args: impl rhai::FuncArgs) -> Result<Dynamic, String> {
+        let ast = self.ast.as_ref().ok_or("Plugin isn't loaded")?;
+
+        ENGINE
+            .call_fn::<Dynamic>(&mut Scope::new(), ast, name, args)
+            .map_err(|e| e.to_string())
+    }
+}
+
+pub fn plugins_dir() -> PathBuf {
+    ProjectDirs::from("com", "tgz39", "saveit")
+        .unwrap()
+        .config_dir()
+        .join("plugins")
+}
+
+// all plugins, including the ones with errors, sorted by file name
+pub fn list() -> Vec<Plugin> {
+    PLUGINS.read().unwrap().clone()
+}
+
+// plugins that can format sources, for the format selection
+pub fn formatters() -> Vec<Plugin> {
+    list().into_iter().filter(|plugin| plugin.formats).collect()
+}
+
+// reads the plugins folder again, e.g. after a plugin was added or edited
+pub fn reload() {
+    debug!("Reloading plugins");
+
+    *PLUGINS.write().unwrap() = load_all();
+}
+
+fn load_all() -> Vec<Plugin> {
+    let dir = plugins_dir();
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(_) => {
+            trace!("No plugins folder at {:?}", &dir);
+            return vec![];
+        }
+    };
+
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == PLUGIN_EXTENSION))
+        .collect();
+    paths.sort();
+
+    paths.into_iter().map(load).collect()
+}
+
+fn load(path: PathBuf) -> Plugin {
+    let id = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    debug!("Loading plugin {}", id);
+
+    let mut plugin = Plugin {
+        id: id.clone(),
+        name: id,
+        path,
+        formats: false,
+        fetches: false,
+        error: None,
+        ast: None,
+    };
+
+    let ast = fs::read_to_string(&plugin.path)
+        .map_err(|e| e.to_string())
+        .and_then(|script| ENGINE.compile(script).map_err(|e| e.to_string()));
+    match ast {
+        Ok(ast) => plugin.ast = Some(ast),
+        Err(e) => {
+            warn!("Error loading plugin {}: {}", plugin.id, e);
+            plugin.error = Some(e);
+            return plugin;
+        }
+    }
+
+    plugin.formats = plugin.has_fn("format", 2);
+    plugin.fetches = plugin.has_fn("metadata", 2);
+    if plugin.has_fn("name", 0) {
+        match plugin.call("name", ()) {
+            Ok(name) => plugin.name = name.to_string(),
+            Err(e) => plugin.error = Some(e),
+        }
+    }
+
+    plugin
+}
+
+// source as an object map, dates as "YYYY-MM-DD"
+fn to_dynamic(source: &Source) -> Dynamic {
+    rhai::serde::to_dynamic(source).unwrap_or_default()
+}
+
+fn pages_arg(pages: Option<&str>) -> Dynamic {
+    match pages.filter(|pages| !pages.is_empty()) {
+        Some(pages) => Dynamic::from(pages.to_string()),
+        None => Dynamic::UNIT,
+    }
+}
+
+// citation of the plugin with the id, errors end up in the citation like invalid custom formats
+pub fn format(id: &str, source: &Source, pages: Option<&str>) -> String {
+    trace!("Formatting source with plugin {}", id);
+
+    let plugins = PLUGINS.read().unwrap();
+    let Some(plugin) = plugins.iter().find(|plugin| plugin.id == id) else {
+        return format!("Unknown format plugin \"{}\"", id);
+    };
+
+    match plugin.call("format", (to_dynamic(source), pages_arg(pages))) {
+        Ok(citation) => citation.to_string(),
+        Err(e) => format!("Error in plugin {}: {}", plugin.name, e),
+    }
+}
+
+// None if the plugin doesn't have its own in-text citation
+pub fn format_in_text(id: &str, source: &Source, pages: Option<&str>) -> Option<String> {
+    let plugins = PLUGINS.read().unwrap();
+    let plugin = plugins
+        .iter()
+        .find(|plugin| plugin.id == id && plugin.has_fn("in_text", 2))?;
+
+    Some(
+        match plugin.call("in_text", (to_dynamic(source), pages_arg(pages))) {
+            Ok(citation) => citation.to_string(),
+            Err(e) => format!("Error in plugin {}: {}", plugin.name, e),
+        },
+    )
+}
+
+// lets the fetchers fill in or correct the fields read from a page, a fetcher returns `()` for
+// pages it doesn't know or a map with the fields it found
+pub fn apply_metadata(url: &str, html: &str, metadata: &mut Metadata) {
+    let plugins = PLUGINS.read().unwrap();
+
+    for plugin in plugins.iter().filter(|plugin| plugin.fetches) {
+        let found = match plugin.call("metadata", (url.to_string(), html.to_string())) {
+            Ok(found) => found,
+            Err(e) => {
+                warn!("Error in metadata plugin {}: {}", plugin.id, e);
+                continue;
+            }
+        };
+        let Some(found) = found.try_cast::<Map>() else {
+            continue;
+        };
+        trace!("Plugin {} found metadata for {}", plugin.id, url);
+
+        let field = |name: &str| {
+            found
+                .get(name)
+                .filter(|value| !value.is_unit())
+                .map(|value| value.to_string().trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+
+        if let Some(title) = field("title") {
+            metadata.title = Some(title);
+        }
+        if let Some(author) = field("author") {
+            metadata.author = Some(author);
+        }
+        if let Some(date) = field("published_date") {
+            match NaiveDate::parse_from_str(&date, "%Y-%m-%d") {
+                Ok(date) => metadata.published_date = Some(date),
+                Err(_) => warn!("Plugin {} returned an invalid date: {}", plugin.id, date),
+            }
+        }
+        if let Some(url) = field("url") {
+            metadata.url = Some(url);
+        }
+        if let Some(doi) = field("doi") {
+            metadata.doi = Some(doi);
+        }
+        if let Some(source_type) = field("source_type") {
+            match serde_json::from_value::<SourceType>(serde_json::Value::String(source_type)) {
+                Ok(source_type) => metadata.source_type = Some(source_type),
+                Err(e) => warn!("Plugin {} returned an invalid type: {}", plugin.id, e),
+            }
+        }
+    }
+}
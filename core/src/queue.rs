@@ -0,0 +1,199 @@
+use std::fmt::{Display, Formatter};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, SqlitePool};
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use tracing::*;
+
+use crate::database::{
+    delete_job, get_all_jobs, get_all_sources, get_source, set_job_error, update_source,
+};
+use crate::http::{self, HttpError};
+use crate::metadata::{fetch_wikipedia_revision, resolve_canonical_url};
+use crate::scheduler;
+use crate::source_store::SourceStore;
+use crate::url_analysis;
+
+const RETRY_INTERVAL: Duration = Duration::from_secs(60);
+
+// network action for a saved source that is retried until it succeeds
+#[derive(Debug, FromRow, Clone, Serialize, Deserialize)]
+pub struct NetworkJob {
+    pub id: i64,
+    pub source_id: i64,
+    pub kind: JobKind,
+    pub attempts: i64,
+    pub last_error: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, sqlx::Type, Serialize, Deserialize)]
+#[sqlx(rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum JobKind {
+    WikipediaRevision, // cite the revision of the article that was read
+    CanonicalUrl,      // store where the link leads
+}
+
+impl Display for JobKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JobKind::WikipediaRevision => write!(f, "Resolve Wikipedia revision"),
+            JobKind::CanonicalUrl => write!(f, "Resolve canonical URL"),
+        }
+    }
+}
+
+// shown in the window header while jobs are waiting
+#[derive(Debug, Clone, Default)]
+pub struct QueueStatus {
+    pub jobs: Vec<NetworkJob>,
+    pub offline: bool, // offline mode was on during the last run
+}
+
+// background worker for the queued jobs, stopped when dropped
+pub struct NetworkQueue {
+    task: JoinHandle<()>,
+    wake: Arc<Notify>,
+    pub status: Arc<RwLock<QueueStatus>>,
+}
+
+impl Drop for NetworkQueue {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+impl NetworkQueue {
+    // retries the jobs now instead of waiting for the next interval, e.g. after a job was
+    // queued or offline mode was turned off
+    pub fn wake(&self) {
+        self.wake.notify_one();
+    }
+}
+
+// queue of a read-only window, the instance owning the library runs the jobs
+pub fn stopped() -> NetworkQueue {
+    NetworkQueue {
+        task: tokio::spawn(async {}),
+        wake: Arc::new(Notify::new()),
+        status: Arc::new(RwLock::new(QueueStatus::default())),
+    }
+}
+
+// needs to be called from within the tokio runtime
+pub fn start(pool: Arc<SqlitePool>, sources_cache: Arc<RwLock<SourceStore>>) -> NetworkQueue {
+    debug!("Starting network queue");
+
+    let wake = Arc::new(Notify::new());
+    let status = Arc::new(RwLock::new(QueueStatus::default()));
+
+    let task_wake = wake.clone();
+    let task_status = status.clone();
+    let task = tokio::spawn(async move {
+        loop {
+            if let Err(e) = run_jobs(&pool, &sources_cache, &task_status).await {
+                error!("Error running network jobs: {}", e);
+            }
+
+            tokio::select! {
+                _ = tokio::time::sleep(RETRY_INTERVAL) => {}
+                _ = task_wake.notified() => {}
+            }
+        }
+    });
+
+    NetworkQueue { task, wake, status }
+}
+
+async fn run_jobs(
+    pool: &SqlitePool,
+    sources_cache: &RwLock<SourceStore>,
+    status: &RwLock<QueueStatus>,
+) -> Result<(), sqlx::Error> {
+    let jobs = get_all_jobs(pool).await?;
+    let offline = matches!(http::client(), Err(HttpError::Offline));
+
+    if !jobs.is_empty() && !offline {
+        debug!("Running {} network jobs", jobs.len());
+
+        let mut changed = false;
+        for job in &jobs {
+            match run_job(job, pool).await {
+                Ok(()) => {
+                    delete_job(job.id, pool).await?;
+                    changed = true;
+                }
+                Err(e) => {
+                    warn!("Network job {} failed: {}", job.id, e);
+                    set_job_error(job.id, &e, pool).await?;
+                }
+            }
+        }
+
+        if changed {
+            *sources_cache.write().unwrap() = get_all_sources(pool).await?.into();
+        }
+    }
+
+    *status.write().unwrap() = QueueStatus {
+        jobs: get_all_jobs(pool).await?,
+        offline,
+    };
+
+    Ok(())
+}
+
+// Err is the message stored with the job
+async fn run_job(job: &NetworkJob, pool: &SqlitePool) -> Result<(), String> {
+    trace!("Running network job {:?}", job);
+
+    let mut source = match get_source(job.source_id, pool)
+        .await
+        .map_err(|e| e.to_string())?
+    {
+        Some(source) => source,
+        None => return Ok(()), // deleted in the meantime
+    };
+
+    match job.kind {
+        JobKind::WikipediaRevision => {
+            // the link may have been edited since
+            let Some(article) = url_analysis::analyze(&source.url).wikipedia else {
+                return Ok(());
+            };
+
+            let name = format!("Resolve Wikipedia revision of {}", source.url);
+            let revision = scheduler::run(name, || fetch_wikipedia_revision(&article))
+                .await
+                .map_err(|e| e.to_string())?;
+            debug!(
+                "Citing Wikipedia revision {} for source {}",
+                revision.permalink, source.id
+            );
+
+            source.url = revision.permalink;
+            source.published_date = revision.date;
+            source.published_date_unknown = false;
+        }
+        JobKind::CanonicalUrl => {
+            if !url_analysis::analyze(&source.url).web {
+                return Ok(());
+            }
+
+            let name = format!("Resolve canonical URL of {}", source.url);
+            source.canonical_url = scheduler::run(name, || resolve_canonical_url(&source.url))
+                .await
+                .map_err(|e| e.to_string())?
+                .unwrap_or_default();
+        }
+    }
+
+    update_source(source.id, &source, pool)
+        .await
+        .map_err(|e| e.to_string())
+}
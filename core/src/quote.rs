@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use tracing::*;
+
+use crate::config::{Config, FormatStandard};
+use crate::source::Source;
+
+// excerpt of a source with an optional page reference
+#[derive(Debug, FromRow, Clone, Serialize, Deserialize)]
+pub struct Quote {
+    pub id: i64,
+    pub source_id: i64,
+    pub text: String,
+    pub page: String,
+    pub note: String,
+}
+
+impl Default for Quote {
+    fn default() -> Self {
+        Self {
+            id: -1,
+            source_id: -1,
+            text: String::new(),
+            page: String::new(),
+            note: String::new(),
+        }
+    }
+}
+
+impl Quote {
+    // the quote followed by the formatted citation of its source
+    pub fn format(&self, source: &Source, standard: &FormatStandard, config: &Config) -> String {
+        trace!("Formatting quote {}", self.id);
+
+        let mut out = format!("\"{}\"\n", self.text);
+        out.push_str(&source.format_with_pages(standard, config, Some(&self.page)));
+
+        out
+    }
+}
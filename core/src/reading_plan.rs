@@ -0,0 +1,146 @@
+use chrono::{Days, Duration, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+
+use crate::source::Source;
+
+// how the reading sessions are spread over the calendar
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pace {
+    pub start: NaiveDate,
+    pub time: NaiveTime,       // start of every session, local time
+    pub minutes: u32,          // length of a session
+    pub per_session: usize,    // sources read in a session
+    pub every_days: u64,       // days from one session to the next
+    pub reminder: Option<u32>, // minutes before the session
+}
+
+// sources read in one sitting
+pub struct Session<'a> {
+    pub start: NaiveDateTime,
+    pub sources: Vec<&'a Source>,
+}
+
+impl Session<'_> {
+    pub fn end(&self, pace: &Pace) -> NaiveDateTime {
+        self.start + Duration::minutes(pace.minutes as i64)
+    }
+}
+
+// the sources in the given order, a session every few days
+pub fn sessions<'a>(sources: &'a [Source], pace: &Pace) -> Vec<Session<'a>> {
+    sources
+        .chunks(pace.per_session.max(1))
+        .enumerate()
+        .filter_map(|(index, chunk)| {
+            let day = pace
+                .start
+                .checked_add_days(Days::new(index as u64 * pace.every_days.max(1)))?;
+            Some(Session {
+                start: day.and_time(pace.time),
+                sources: chunk.iter().collect(),
+            })
+        })
+        .collect()
+}
+
+// iCalendar file with one event per session, times are floating so they stay at the chosen time of
+// day in any time zone
+pub fn to_ics(sources: &[Source], pace: &Pace) -> String {
+    let stamp = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//SaveIt//Reading plan//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+    ];
+
+    for session in sessions(sources, pace) {
+        let summary = match session.sources.as_slice() {
+            [source] => format!("Read: {}", source.title),
+            sources => format!("Read {} sources", sources.len()),
+        };
+        let description: Vec<String> = session
+            .sources
+            .iter()
+            .map(|source| match source.url.is_empty() {
+                true => source.title.clone(),
+                false => format!("{} ({})", source.title, source.url),
+            })
+            .collect();
+        let ids: Vec<String> = session
+            .sources
+            .iter()
+            .map(|source| source.id.to_string())
+            .collect();
+
+        lines.push("BEGIN:VEVENT".to_string());
+        lines.push(format!(
+            "UID:saveit-{}-{}@saveit",
+            session.start.format("%Y%m%d"),
+            ids.join("-")
+        ));
+        lines.push(format!("DTSTAMP:{}", stamp));
+        lines.push(format!("DTSTART:{}", session.start.format("%Y%m%dT%H%M%S")));
+        lines.push(format!(
+            "DTEND:{}",
+            session.end(pace).format("%Y%m%dT%H%M%S")
+        ));
+        lines.push(format!("SUMMARY:{}", escape_text(&summary)));
+        lines.push(format!(
+            "DESCRIPTION:{}",
+            escape_text(&description.join("\n"))
+        ));
+        if let Some(minutes) = pace.reminder {
+            lines.push("BEGIN:VALARM".to_string());
+            lines.push("ACTION:DISPLAY".to_string());
+            lines.push(format!("DESCRIPTION:{}", escape_text(&summary)));
+            lines.push(format!("TRIGGER:-PT{}M", minutes));
+            lines.push("END:VALARM".to_string());
+        }
+        lines.push("END:VEVENT".to_string());
+    }
+
+    lines.push("END:VCALENDAR".to_string());
+
+    let mut out = String::new();
+    for line in lines {
+        out.push_str(&fold(&line));
+        out.push_str("\r\n");
+    }
+    out
+}
+
+// characters with a meaning in iCalendar text values
+fn escape_text(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '\\' | ';' | ',' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '\n' => out.push_str("\\n"),
+            '\r' => {}
+            c => out.push(c),
+        }
+    }
+
+    out
+}
+
+// lines are at most 75 bytes, continued on the next line after a space
+fn fold(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut length = 0;
+
+    for c in line.chars() {
+        if length + c.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            length = 1;
+        }
+        out.push(c);
+        length += c.len_utf8();
+    }
+
+    out
+}
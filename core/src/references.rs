@@ -0,0 +1,149 @@
+use std::error::Error;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::config::{Config, FormatStandard};
+use crate::search::normalize;
+use crate::source::Source;
+
+// keys of the BibTeX export, e.g. "saveit12" in "[@saveit12]" or "\cite{saveit3,saveit12}"
+static CITE_KEY: Lazy<Regex> = Lazy::new(|| Regex::new(r"\bsaveit\d+\b").unwrap());
+static DOI: Lazy<Regex> = Lazy::new(|| Regex::new(r"\b10\.\d{4,9}/[^\s,;\]\)\}]+").unwrap());
+// text of only IDs, e.g. "12, 3 7"
+static ID_LIST: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[\d\s,;]+$").unwrap());
+static ID: Lazy<Regex> = Lazy::new(|| Regex::new(r"\d+").unwrap());
+
+// sources cited in a manuscript in the order of their first citation
+#[derive(Debug, Default)]
+pub struct ReferenceList {
+    pub sources: Vec<Source>,
+    pub unknown: Vec<String>, // cited keys, IDs or DOIs that aren't in the library
+}
+
+impl ReferenceList {
+    // "[1] citation" per line
+    pub fn numbered(&self, standard: &FormatStandard, config: &Config) -> String {
+        let mut out = String::new();
+
+        for (index, source) in self.sources.iter().enumerate() {
+            let number = index as i64 + 1;
+            match standard {
+                // starts with "[id]" already, which becomes the number
+                FormatStandard::Default => {
+                    let source = Source {
+                        id: number,
+                        ..source.clone()
+                    };
+                    out.push_str(&source.format(standard, config));
+                }
+                _ => out.push_str(&format!("[{}] {}", number, source.format(standard, config))),
+            }
+            out.push('\n');
+        }
+
+        out
+    }
+
+    // number and reference of every source, for numbering footnotes by hand
+    pub fn mapping(&self, standard: &FormatStandard, config: &Config) -> Vec<(usize, String)> {
+        self.sources
+            .iter()
+            .enumerate()
+            .map(|(index, source)| {
+                let citation = source.format(standard, config);
+                // the default format starts with "[id]", the number is a column of its own
+                let reference = citation
+                    .strip_prefix(&format!("[{}] ", source.id))
+                    .unwrap_or(&citation)
+                    .to_string();
+                (index + 1, reference)
+            })
+            .collect()
+    }
+
+    pub fn mapping_csv(
+        &self,
+        standard: &FormatStandard,
+        config: &Config,
+    ) -> Result<String, Box<dyn Error>> {
+        let mut writer = csv::Writer::from_writer(vec![]);
+        writer.write_record(["number", "reference"])?;
+        for (number, reference) in self.mapping(standard, config) {
+            writer.write_record([number.to_string(), reference])?;
+        }
+
+        Ok(String::from_utf8(writer.into_inner()?)?)
+    }
+
+    // table with one row per number
+    pub fn mapping_markdown(&self, standard: &FormatStandard, config: &Config) -> String {
+        let mut out = String::from("| Number | Reference |\n|---:|---|\n");
+        for (number, reference) in self.mapping(standard, config) {
+            let reference = reference.replace('|', "\\|").replace('\n', " ");
+            out.push_str(&format!("| {} | {} |\n", number, reference));
+        }
+
+        out
+    }
+}
+
+enum Citation<'a> {
+    Id(&'a str), // "saveit12" or "12"
+    Doi(&'a str),
+}
+
+// cite keys and DOIs anywhere in the text, or a list of IDs
+pub fn resolve(text: &str, sources: &[Source]) -> ReferenceList {
+    // start in the text and what is cited there
+    let mut citations: Vec<(usize, Citation)> = match ID_LIST.is_match(text) {
+        true => ID
+            .find_iter(text)
+            .map(|found| (found.start(), Citation::Id(found.as_str())))
+            .collect(),
+        false => CITE_KEY
+            .find_iter(text)
+            .map(|found| (found.start(), Citation::Id(found.as_str())))
+            .chain(DOI.find_iter(text).map(|found| {
+                let doi = found.as_str().trim_end_matches('.');
+                (found.start(), Citation::Doi(doi))
+            }))
+            .collect(),
+    };
+    citations.sort_by_key(|(start, _)| *start);
+
+    let mut list = ReferenceList::default();
+    for (_, citation) in citations {
+        let found = match &citation {
+            Citation::Id(id) => id
+                .trim_start_matches("saveit")
+                .parse::<i64>()
+                .ok()
+                .and_then(|id| sources.iter().find(|source| source.id == id)),
+            Citation::Doi(doi) => {
+                let doi = normalize(doi);
+                sources
+                    .iter()
+                    .find(|source| !source.doi.is_empty() && normalize(&source.doi) == doi)
+            }
+        };
+
+        match found {
+            Some(source) => {
+                if !list.sources.iter().any(|other| other.id == source.id) {
+                    list.sources.push(source.clone());
+                }
+            }
+            None => {
+                let unknown = match citation {
+                    Citation::Id(id) | Citation::Doi(id) => id.to_string(),
+                };
+                if !list.unknown.contains(&unknown) {
+                    list.unknown.push(unknown);
+                }
+            }
+        }
+    }
+
+    list
+}
@@ -0,0 +1,190 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::future::Future;
+use std::io::ErrorKind;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+use once_cell::sync::Lazy;
+use tokio::sync::Semaphore;
+use tracing::*;
+
+// network jobs running at the same time over all features, more would look like an attack to
+// some servers
+pub const MAX_RUNNING: usize = 8;
+const MAX_ATTEMPTS: u32 = 3;
+const FIRST_BACKOFF: Duration = Duration::from_secs(2); // doubled after every failed attempt
+const KEPT_FAILED: usize = 50; // failed jobs shown in the status panel
+
+static SCHEDULER: Lazy<Scheduler> = Lazy::new(|| Scheduler {
+    permits: Semaphore::new(MAX_RUNNING),
+    jobs: RwLock::new(vec![]),
+    next_id: AtomicU64::new(1),
+});
+
+struct Scheduler {
+    permits: Semaphore,
+    jobs: RwLock<Vec<Job>>, // queued, running and failed, finished jobs are removed
+    next_id: AtomicU64,
+}
+
+// network request of a feature, e.g. a link check or a metadata lookup
+#[derive(Debug, Clone)]
+pub struct Job {
+    pub id: u64,
+    pub name: String, // e.g. "Check link https://…"
+    pub state: JobState,
+    pub attempts: u32,
+    pub since: Instant, // when the job entered its state
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum JobState {
+    Queued,
+    Running,
+    Retrying(Duration), // waiting before the next attempt after a temporary error
+    Failed(String),
+}
+
+impl Display for JobState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JobState::Queued => write!(f, "Queued"),
+            JobState::Running => write!(f, "Running"),
+            JobState::Retrying(backoff) => write!(f, "Retrying in {} s", backoff.as_secs()),
+            JobState::Failed(e) => write!(f, "Failed: {}", e),
+        }
+    }
+}
+
+// runs the task once no more than MAX_RUNNING other jobs run, temporary errors like timeouts are
+// retried with a growing delay, the error of the last attempt is returned
+pub async fn run<T, F, Fut>(
+    name: impl Into<String>,
+    mut task: F,
+) -> Result<T, Box<dyn Error + Send + Sync>>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, Box<dyn Error + Send + Sync>>>,
+{
+    let name = name.into();
+    let id = SCHEDULER.next_id.fetch_add(1, Ordering::Relaxed);
+    trace!("Queueing job {}: {}", id, name);
+    SCHEDULER.jobs.write().unwrap().push(Job {
+        id,
+        name,
+        state: JobState::Queued,
+        attempts: 0,
+        since: Instant::now(),
+    });
+
+    let mut backoff = FIRST_BACKOFF;
+    loop {
+        let result = {
+            let _permit = SCHEDULER
+                .permits
+                .acquire()
+                .await
+                .expect("Scheduler semaphore closed");
+            update(id, |job| {
+                job.state = JobState::Running;
+                job.attempts += 1;
+            });
+            task().await
+        };
+
+        let attempts = job_attempts(id);
+        match result {
+            Ok(value) => {
+                SCHEDULER.jobs.write().unwrap().retain(|job| job.id != id);
+                return Ok(value);
+            }
+            Err(e) if attempts < MAX_ATTEMPTS && is_temporary(&*e) => {
+                debug!("Job {} failed, retrying in {:?}: {}", id, backoff, e);
+                update(id, |job| job.state = JobState::Retrying(backoff));
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+                update(id, |job| job.state = JobState::Queued);
+            }
+            Err(e) => {
+                debug!("Job {} failed: {}", id, e);
+                update(id, |job| job.state = JobState::Failed(e.to_string()));
+                forget_old_failures();
+                return Err(e);
+            }
+        }
+    }
+}
+
+// queued, running and failed jobs, oldest first
+pub fn jobs() -> Vec<Job> {
+    SCHEDULER.jobs.read().unwrap().clone()
+}
+
+pub fn clear_failed() {
+    SCHEDULER
+        .jobs
+        .write()
+        .unwrap()
+        .retain(|job| !matches!(job.state, JobState::Failed(_)));
+}
+
+// timeouts, refused connections and server errors may work a moment later, missing pages won't
+pub fn is_temporary(error: &(dyn Error + Send + Sync + 'static)) -> bool {
+    if let Some(e) = error.downcast_ref::<reqwest::Error>() {
+        return e.is_timeout()
+            || e.is_connect()
+            || e.status().is_some_and(|status| {
+                status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+            });
+    }
+    if let Some(e) = error.downcast_ref::<std::io::Error>() {
+        return matches!(
+            e.kind(),
+            ErrorKind::TimedOut | ErrorKind::ConnectionRefused | ErrorKind::ConnectionReset
+        );
+    }
+
+    false
+}
+
+fn update(id: u64, change: impl FnOnce(&mut Job)) {
+    if let Some(job) = SCHEDULER
+        .jobs
+        .write()
+        .unwrap()
+        .iter_mut()
+        .find(|job| job.id == id)
+    {
+        change(job);
+        job.since = Instant::now();
+    }
+}
+
+fn job_attempts(id: u64) -> u32 {
+    SCHEDULER
+        .jobs
+        .read()
+        .unwrap()
+        .iter()
+        .find(|job| job.id == id)
+        .map_or(0, |job| job.attempts)
+}
+
+fn forget_old_failures() {
+    let mut jobs = SCHEDULER.jobs.write().unwrap();
+    let failed = jobs
+        .iter()
+        .filter(|job| matches!(job.state, JobState::Failed(_)))
+        .count();
+
+    let mut excess = failed.saturating_sub(KEPT_FAILED);
+    jobs.retain(|job| {
+        let forget = excess > 0 && matches!(job.state, JobState::Failed(_));
+        if forget {
+            excess -= 1;
+        }
+        !forget
+    });
+}
@@ -0,0 +1,423 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::mem::discriminant;
+use std::ops::Range;
+
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use strsim::damerau_levenshtein;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::source::{split_tags, Source, SourceType};
+use crate::source_store::SourceView;
+
+// compiled regexes larger than this are rejected instead of slowing down every frame
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+// most used tags offered as filter chips
+const FILTER_TAGS: usize = 8;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum SearchMode {
+    #[default]
+    Text, // the query is contained in a field, ignoring case and diacritics
+    Regex,
+    Fuzzy, // like Text, but words with a few typos match as well
+}
+
+// what the search and the filters read of a source, so the sources of the store can be filtered
+// before they are copied
+pub trait Searchable {
+    fn id(&self) -> i64;
+    // title, URL, author, tags, DOI, collection and canonical URL
+    fn fields(&self) -> [&str; 7];
+    fn tags(&self) -> &str;
+    fn source_type(&self) -> SourceType;
+    fn cited(&self) -> bool;
+    fn published_date_unknown(&self) -> bool;
+    fn notes_written(&self) -> bool;
+    fn shared(&self) -> bool;
+}
+
+impl Searchable for Source {
+    fn id(&self) -> i64 {
+        self.id
+    }
+
+    fn fields(&self) -> [&str; 7] {
+        [
+            &self.title,
+            &self.url,
+            &self.author,
+            &self.tags,
+            &self.doi,
+            &self.collection,
+            &self.canonical_url,
+        ]
+    }
+
+    fn tags(&self) -> &str {
+        &self.tags
+    }
+
+    fn source_type(&self) -> SourceType {
+        self.source_type
+    }
+
+    fn cited(&self) -> bool {
+        self.last_cited.is_some()
+    }
+
+    fn published_date_unknown(&self) -> bool {
+        self.published_date_unknown
+    }
+
+    fn notes_written(&self) -> bool {
+        self.notes_written
+    }
+
+    fn shared(&self) -> bool {
+        self.shared
+    }
+}
+
+impl Searchable for SourceView<'_> {
+    fn id(&self) -> i64 {
+        SourceView::id(self)
+    }
+
+    fn fields(&self) -> [&str; 7] {
+        [
+            self.title(),
+            self.url(),
+            self.author(),
+            SourceView::tags(self),
+            self.doi(),
+            self.collection(),
+            self.canonical_url(),
+        ]
+    }
+
+    fn tags(&self) -> &str {
+        SourceView::tags(self)
+    }
+
+    fn source_type(&self) -> SourceType {
+        SourceView::source_type(self)
+    }
+
+    fn cited(&self) -> bool {
+        self.last_cited().is_some()
+    }
+
+    fn published_date_unknown(&self) -> bool {
+        SourceView::published_date_unknown(self)
+    }
+
+    fn notes_written(&self) -> bool {
+        SourceView::notes_written(self)
+    }
+
+    fn shared(&self) -> bool {
+        SourceView::shared(self)
+    }
+}
+
+// query prepared once for all sources
+pub enum Matcher {
+    All, // empty query
+    Text(String),
+    Regex(Regex),
+    Fuzzy(String),
+}
+
+impl Matcher {
+    pub fn new(query: &str, mode: SearchMode) -> Result<Self, String> {
+        if query.is_empty() {
+            return Ok(Matcher::All);
+        }
+
+        match mode {
+            SearchMode::Text => Ok(Matcher::Text(query.to_string())),
+            SearchMode::Regex => RegexBuilder::new(query)
+                .case_insensitive(true)
+                .size_limit(REGEX_SIZE_LIMIT)
+                .build()
+                .map(Matcher::Regex)
+                .map_err(|e| format!("Invalid regex: {}", e)),
+            SearchMode::Fuzzy => match query.trim() {
+                "" => Ok(Matcher::All),
+                query => Ok(Matcher::Fuzzy(normalize(query))),
+            },
+        }
+    }
+
+    // searched fields of the source, like Source::contains
+    pub fn matches(&self, source: &(impl Searchable + ?Sized)) -> bool {
+        source.fields().iter().any(|field| self.is_match(field))
+    }
+
+    fn is_match(&self, text: &str) -> bool {
+        match self {
+            Matcher::All => true,
+            // also matches regexes like "x*" that only match empty strings
+            Matcher::Regex(regex) => regex.is_match(text),
+            _ => !self.find(text).is_empty(),
+        }
+    }
+
+    // byte ranges of the text that match, for highlighting
+    pub fn find(&self, text: &str) -> Vec<Range<usize>> {
+        match self {
+            Matcher::All => vec![],
+            Matcher::Text(query) => find_text(text, query),
+            Matcher::Regex(regex) => regex
+                .find_iter(text)
+                .map(|found| found.range())
+                .filter(|range| !range.is_empty())
+                .collect(),
+            Matcher::Fuzzy(query) => find_fuzzy(text, query),
+        }
+    }
+}
+
+// chip under the search bar, toggled without typing a query
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum Filter {
+    Tag(String),
+    Type(SourceType),
+    Status(Status),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum Status {
+    Cited,
+    NotCited,
+    DateUnknown, // date published unknown
+    NotesWritten,
+    NoNotes,
+}
+
+impl Status {
+    pub const ALL: [Status; 5] = [
+        Status::Cited,
+        Status::NotCited,
+        Status::DateUnknown,
+        Status::NotesWritten,
+        Status::NoNotes,
+    ];
+}
+
+impl Display for Status {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Status::Cited => write!(f, "Cited"),
+            Status::NotCited => write!(f, "Not cited"),
+            Status::DateUnknown => write!(f, "Date unknown"),
+            Status::NotesWritten => write!(f, "Notes written"),
+            Status::NoNotes => write!(f, "No notes"),
+        }
+    }
+}
+
+impl Display for Filter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Filter::Tag(tag) => write!(f, "#{}", tag),
+            Filter::Type(source_type) => write!(f, "{}", source_type),
+            Filter::Status(status) => write!(f, "{}", status),
+        }
+    }
+}
+
+impl Filter {
+    pub fn matches(&self, source: &(impl Searchable + ?Sized)) -> bool {
+        match self {
+            Filter::Tag(tag) => {
+                let tag = normalize(tag);
+                split_tags(source.tags())
+                    .iter()
+                    .any(|other| normalize(other) == tag)
+            }
+            Filter::Type(source_type) => source.source_type() == *source_type,
+            Filter::Status(Status::Cited) => source.cited(),
+            Filter::Status(Status::NotCited) => !source.cited(),
+            Filter::Status(Status::DateUnknown) => source.published_date_unknown(),
+            Filter::Status(Status::NotesWritten) => source.notes_written(),
+            Filter::Status(Status::NoNotes) => !source.notes_written(),
+        }
+    }
+}
+
+// a source has to match one of the filters of each kind, e.g. tag "a" or "b" and type book
+pub fn matches_filters(filters: &[Filter], source: &(impl Searchable + ?Sized)) -> bool {
+    filters.iter().all(|filter| {
+        filters
+            .iter()
+            .filter(|other| discriminant(*other) == discriminant(filter))
+            .any(|other| other.matches(source))
+    })
+}
+
+// chips with the number of sources they match: the most used tags, then the types and statuses
+// that occur in the library
+pub fn quick_filters(sources: &[&dyn Searchable]) -> Vec<(Filter, usize)> {
+    let mut tags: HashMap<&str, usize> = HashMap::new();
+    for source in sources {
+        for tag in split_tags(source.tags()) {
+            *tags.entry(tag).or_default() += 1;
+        }
+    }
+    let mut tags: Vec<(&str, usize)> = tags.into_iter().collect();
+    tags.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+
+    let count = |filter: &Filter| {
+        sources
+            .iter()
+            .filter(|source| filter.matches(**source))
+            .count()
+    };
+    let types = SourceType::ALL.into_iter().map(Filter::Type);
+    let statuses = Status::ALL.into_iter().map(Filter::Status);
+
+    tags.into_iter()
+        .take(FILTER_TAGS)
+        .map(|(tag, count)| (Filter::Tag(tag.to_string()), count))
+        .chain(
+            types
+                .chain(statuses)
+                .map(|filter| {
+                    let count = count(&filter);
+                    (filter, count)
+                })
+                .filter(|(_, count)| *count > 0),
+        )
+        .collect()
+}
+
+// text for comparing, ignoring case and diacritics: "Müller" and "muller" or "façade" and
+// "FACADE" are the same, used by the search and the duplicate detection
+pub fn normalize(text: &str) -> String {
+    text.chars().flat_map(normalize_char).collect()
+}
+
+// compatibility decomposition without the combining marks, lowercase, letters that don't
+// decompose are spelled out
+fn normalize_char(c: char) -> impl Iterator<Item = char> {
+    let decomposed: Vec<char> = match c {
+        'ß' | 'ẞ' => vec!['s', 's'],
+        'æ' | 'Æ' => vec!['a', 'e'],
+        'œ' | 'Œ' => vec!['o', 'e'],
+        'ø' | 'Ø' => vec!['o'],
+        'ł' | 'Ł' => vec!['l'],
+        'đ' | 'Đ' => vec!['d'],
+        c => c.nfkd().filter(|c| !is_combining_mark(*c)).collect(),
+    };
+
+    decomposed.into_iter().flat_map(char::to_lowercase)
+}
+
+// byte ranges of the text containing the query, ignoring case and diacritics
+pub fn find_text(text: &str, query: &str) -> Vec<Range<usize>> {
+    let query: Vec<char> = normalize(query).chars().collect();
+    if query.is_empty() {
+        return vec![];
+    }
+
+    // normalized characters with the index of the character of the text they come from, as
+    // normalizing can change the length
+    let lower: Vec<(usize, char)> = text
+        .char_indices()
+        .flat_map(|(index, c)| normalize_char(c).map(move |lower| (index, lower)))
+        .collect();
+
+    let mut ranges = vec![];
+    let mut start = 0;
+    while start + query.len() <= lower.len() {
+        let window = &lower[start..start + query.len()];
+        if !window.iter().map(|(_, c)| *c).eq(query.iter().copied()) {
+            start += 1;
+            continue;
+        }
+
+        let end = lower
+            .get(start + query.len())
+            .map(|(index, _)| *index)
+            .unwrap_or(text.len());
+        let overlaps = ranges
+            .last()
+            .is_some_and(|last: &Range<usize>| last.end > window[0].0);
+        if window[0].0 < end && !overlaps {
+            ranges.push(window[0].0..end);
+        }
+        start += query.len();
+    }
+
+    ranges
+}
+
+// more typos are allowed in longer queries, none in very short ones
+fn allowed_typos(query: &str) -> usize {
+    match query.chars().count() {
+        0..=2 => 0,
+        3..=7 => 1,
+        _ => 2,
+    }
+}
+
+// substrings containing the normalized query and runs of as many words as the query has that are
+// at most a few typos away from it, or whose start is
+fn find_fuzzy(text: &str, query: &str) -> Vec<Range<usize>> {
+    let exact = find_text(text, query);
+    if !exact.is_empty() {
+        return exact;
+    }
+
+    let typos = allowed_typos(query);
+    if typos == 0 {
+        return vec![];
+    }
+
+    let query_chars = query.chars().count();
+    let query_words = query.split_whitespace().count().max(1);
+    let words = word_ranges(text);
+
+    let mut ranges: Vec<Range<usize>> = vec![];
+    for run in words.windows(query_words) {
+        let range = run[0].start..run[run.len() - 1].end;
+        let candidate = normalize(&text[range.clone()]);
+        let prefix: String = candidate.chars().take(query_chars).collect();
+
+        let close = damerau_levenshtein(query, &candidate) <= typos
+            || damerau_levenshtein(query, &prefix) <= typos;
+        let overlaps = ranges.last().is_some_and(|last| last.end > range.start);
+        if close && !overlaps {
+            ranges.push(range);
+        }
+    }
+
+    ranges
+}
+
+// words of the text, separated by anything that isn't a letter or a digit
+fn word_ranges(text: &str) -> Vec<Range<usize>> {
+    let mut words = vec![];
+    let mut start = None;
+
+    for (index, c) in text.char_indices() {
+        match (c.is_alphanumeric(), start) {
+            (true, None) => start = Some(index),
+            (false, Some(word_start)) => {
+                words.push(word_start..index);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(word_start) = start {
+        words.push(word_start..text.len());
+    }
+
+    words
+}
@@ -0,0 +1,177 @@
+use std::collections::HashSet;
+use std::error::Error;
+
+use chrono::NaiveDateTime;
+use sqlx::FromRow;
+use tracing::*;
+
+use crate::crash::record_operation;
+use crate::http;
+use crate::metadata::decode_entities;
+use crate::scheduler;
+
+const PAGE_LIMIT: usize = 5 * 1024 * 1024;
+// pages less similar than this to their snapshot changed significantly, menus and dates of a page
+// change all the time
+pub const SIGNIFICANT: f64 = 0.8;
+// words compared together, single words would match the same text in another order
+const SHINGLE_WORDS: usize = 3;
+// content of these is never shown
+const HIDDEN_TAGS: [&str; 4] = ["script", "style", "noscript", "template"];
+// tags that start a new paragraph
+const BLOCK_TAGS: [&str; 19] = [
+    "p",
+    "div",
+    "br",
+    "li",
+    "tr",
+    "td",
+    "th",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "section",
+    "article",
+    "blockquote",
+    "pre",
+    "header",
+    "footer",
+];
+
+// text of the page of a source when it was saved
+#[derive(Debug, FromRow, Clone)]
+pub struct Snapshot {
+    pub source_id: i64,
+    pub url: String,
+    pub text: String,
+    pub fetched_at: NaiveDateTime, // UTC
+    pub similarity: Option<f64>,   // of the page at the last re-check
+    pub checked_at: Option<NaiveDateTime>,
+}
+
+impl Snapshot {
+    pub fn changed(&self) -> bool {
+        self.similarity
+            .is_some_and(|similarity| similarity < SIGNIFICANT)
+    }
+}
+
+// how the page differs from its snapshot
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recheck {
+    pub similarity: f64,      // 1.0 for the same text
+    pub removed: Vec<String>, // paragraphs of the snapshot that aren't on the page anymore
+    pub added: Vec<String>,
+}
+
+// downloads the page and returns its visible text, fails in offline mode
+pub async fn fetch_text(url: &str) -> Result<String, Box<dyn Error + Send + Sync>> {
+    record_operation(format!("Fetch snapshot of {}", url));
+    scheduler::run(format!("Fetch snapshot of {}", url), || download(url)).await
+}
+
+async fn download(url: &str) -> Result<String, Box<dyn Error + Send + Sync>> {
+    debug!("Fetching snapshot of {}", url);
+
+    let mut response = http::client()?.get(url).send().await?.error_for_status()?;
+
+    let mut page: Vec<u8> = vec![];
+    while let Some(chunk) = response.chunk().await? {
+        page.extend_from_slice(&chunk);
+        if page.len() >= PAGE_LIMIT {
+            trace!("Stopping page download after {} bytes", page.len());
+            break;
+        }
+    }
+
+    Ok(page_text(&String::from_utf8_lossy(&page)))
+}
+
+// visible text of an HTML page, a paragraph per line
+pub fn page_text(html: &str) -> String {
+    let lower = html.to_ascii_lowercase();
+    let mut text = String::with_capacity(html.len() / 2);
+
+    let mut index = 0;
+    while let Some(offset) = lower[index..].find('<') {
+        text.push_str(&html[index..index + offset]);
+        let start = index + offset;
+        let end = lower[start..]
+            .find('>')
+            .map_or(lower.len(), |end| start + end + 1);
+
+        let tag: String = lower[start + 1..end]
+            .trim_start_matches('/')
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect();
+        index = end;
+
+        if HIDDEN_TAGS.contains(&tag.as_str()) && !lower[start + 1..].starts_with('/') {
+            // skip to the closing tag
+            let closing = format!("</{}", tag);
+            index = lower[end..]
+                .find(&closing)
+                .map_or(lower.len(), |close| end + close);
+        } else if BLOCK_TAGS.contains(&tag.as_str()) {
+            text.push('\n');
+        }
+    }
+    if index < html.len() {
+        text.push_str(&html[index..]);
+    }
+
+    decode_entities(&text)
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// similarity of the words of both texts and the paragraphs only one of them has
+pub fn compare(old: &str, new: &str) -> Recheck {
+    let old_shingles = shingles(old);
+    let new_shingles = shingles(new);
+    let union = old_shingles.union(&new_shingles).count();
+    let similarity = match union {
+        0 => 1.0,
+        union => old_shingles.intersection(&new_shingles).count() as f64 / union as f64,
+    };
+
+    let old_paragraphs: HashSet<&str> = old.lines().collect();
+    let new_paragraphs: HashSet<&str> = new.lines().collect();
+
+    Recheck {
+        similarity,
+        removed: old
+            .lines()
+            .filter(|paragraph| !new_paragraphs.contains(paragraph))
+            .map(str::to_string)
+            .collect(),
+        added: new
+            .lines()
+            .filter(|paragraph| !old_paragraphs.contains(paragraph))
+            .map(str::to_string)
+            .collect(),
+    }
+}
+
+// every run of SHINGLE_WORDS words, texts shorter than that are a single shingle
+fn shingles(text: &str) -> HashSet<Vec<String>> {
+    let words: Vec<String> = text.split_whitespace().map(str::to_lowercase).collect();
+    if words.len() < SHINGLE_WORDS {
+        return match words.is_empty() {
+            true => HashSet::new(),
+            false => HashSet::from([words]),
+        };
+    }
+
+    words
+        .windows(SHINGLE_WORDS)
+        .map(|window| window.to_vec())
+        .collect()
+}
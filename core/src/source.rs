@@ -0,0 +1,389 @@
+use chrono::{Local, NaiveDate, NaiveDateTime};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::sync::Mutex;
+use tracing::*;
+
+use crate::config::{Config, FormatStandard};
+use crate::locale::{self, GERMAN};
+use crate::plugins;
+use crate::search::normalize;
+use crate::template::Template;
+
+// parsed custom formats, so the same template isn't parsed on every format call
+static TEMPLATE_CACHE: Lazy<Mutex<HashMap<String, Template>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+const TEMPLATE_CACHE_SIZE: usize = 16;
+
+#[derive(Debug, FromRow, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Source {
+    pub id: i64,
+    pub title: String,
+    pub url: String,
+    pub author: String,
+    pub published_date: NaiveDate,
+    pub viewed_date: NaiveDate,
+    pub published_date_unknown: bool,
+    pub comment: String,
+    #[sqlx(default)] // shared libraries can have an older schema
+    pub last_cited: Option<NaiveDateTime>,
+    #[sqlx(default)]
+    pub tags: String, // comma separated
+    #[sqlx(default)]
+    pub collection: String,
+    #[sqlx(default)]
+    #[serde(default)]
+    pub doi: String, // without the https://doi.org/ prefix, e.g. "10.1000/182"
+    #[sqlx(default)]
+    #[serde(default)]
+    pub source_type: SourceType,
+    #[sqlx(default)]
+    pub uuid: String, // stable id across devices for LAN sync, set by the database
+    #[sqlx(default)]
+    pub updated_at: Option<NaiveDateTime>, // UTC, set by the database
+    #[sqlx(default)]
+    #[serde(default)]
+    pub pinned: bool, // listed above all other sources, not synced
+    #[sqlx(default)]
+    #[serde(default)]
+    pub notes_written: bool, // the notes of the source are done
+    #[sqlx(default)]
+    #[serde(default)]
+    pub canonical_url: String, // where the URL leads, e.g. for a t.co link, empty if it's the URL
+    #[sqlx(default)]
+    #[serde(default)]
+    pub language: String, // e.g. "de", see locale::terms, empty to use the terms of the format
+    #[sqlx(skip)]
+    #[serde(skip)]
+    pub shared: bool, // from the read-only shared library or another library
+    #[sqlx(skip)]
+    #[serde(skip)]
+    pub library: String, // name of the library a shared source is from, empty for own sources
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, sqlx::Type, Serialize, Deserialize)]
+#[sqlx(rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum SourceType {
+    #[default]
+    Webpage,
+    Video,
+    JournalArticle,
+    Book,
+    Image, // photos, figures and screenshots
+    Other,
+}
+
+impl SourceType {
+    pub const ALL: [SourceType; 6] = [
+        SourceType::Webpage,
+        SourceType::Video,
+        SourceType::JournalArticle,
+        SourceType::Book,
+        SourceType::Image,
+        SourceType::Other,
+    ];
+}
+
+impl Display for SourceType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SourceType::Webpage => write!(f, "Web page"),
+            SourceType::Video => write!(f, "Video"),
+            SourceType::JournalArticle => write!(f, "Journal article"),
+            SourceType::Book => write!(f, "Book"),
+            SourceType::Image => write!(f, "Image"),
+            SourceType::Other => write!(f, "Other"),
+        }
+    }
+}
+
+impl Source {
+    pub fn format(&self, standard: &FormatStandard, config: &Config) -> String {
+        self.format_with_pages(standard, config, None)
+    }
+
+    // formats the source with a page/paragraph reference, e.g. for quotes
+    pub fn format_with_pages(
+        &self,
+        standard: &FormatStandard,
+        config: &Config,
+        pages: Option<&str>,
+    ) -> String {
+        let standard = config.standard_for(self, standard);
+        trace!("Formatting source with: {:?}", standard);
+
+        match standard {
+            FormatStandard::Default => {
+                // sources without a language keep the German terms, but leave out an unknown year
+                let language = locale::terms(&self.language);
+                let terms = language.unwrap_or(&GERMAN);
+                let mut out = String::new();
+
+                out.push_str(format!("[{}]", self.id).as_str());
+
+                match self.author.is_empty() {
+                    true => out.push_str(format!(" {}", terms.unknown_author).as_str()),
+                    false => out.push_str(format!(" {}", self.author).as_str()),
+                }
+
+                match (self.published_date_unknown, language) {
+                    (false, _) => {
+                        out.push_str(format!(" ({})", self.published_date.format("%Y")).as_str())
+                    }
+                    (true, Some(terms)) => out.push_str(format!(" ({})", terms.no_date).as_str()),
+                    (true, None) => {}
+                }
+
+                out.push_str(format!(": {}", self.title).as_str());
+
+                if let Some(pages) = pages.filter(|pages| !pages.is_empty()) {
+                    out.push_str(format!(", {} {}", terms.page, pages).as_str());
+                }
+
+                out.push_str(
+                    format!(
+                        " URL: {} [{}: {}]",
+                        self.cited_url(),
+                        terms.accessed,
+                        self.viewed_date.format(terms.date_format)
+                    )
+                    .as_str(),
+                );
+
+                out
+            }
+            FormatStandard::Custom => self.format_custom(&config.custom_format, pages),
+            FormatStandard::Plugin => plugins::format(&config.plugin_format, self, pages),
+        }
+    }
+
+    // formats the source with the given custom format string
+    pub fn format_custom(&self, custom_format: &str, pages: Option<&str>) -> String {
+        trace!("Formatting source with custom format: {}", custom_format);
+
+        let mut cache = TEMPLATE_CACHE.lock().unwrap();
+
+        if let Some(template) = cache.get(custom_format) {
+            return template.render(self, pages);
+        }
+
+        match Template::parse(custom_format) {
+            Ok(template) => {
+                let out = template.render(self, pages);
+
+                // typing in the settings creates a new format per keystroke
+                if cache.len() >= TEMPLATE_CACHE_SIZE {
+                    cache.clear();
+                }
+                cache.insert(custom_format.to_string(), template);
+
+                out
+            }
+            Err(e) => format!("Invalid custom format: {}", e),
+        }
+    }
+
+    // short form for the text body, e.g. "[12]" or "(Müller 2021, S. 4)"
+    pub fn format_in_text(
+        &self,
+        standard: &FormatStandard,
+        config: &Config,
+        pages: Option<&str>,
+    ) -> String {
+        let standard = config.standard_for(self, standard);
+        trace!("Formatting in-text citation with: {:?}", standard);
+
+        match standard {
+            FormatStandard::Default => self.default_in_text(pages),
+            FormatStandard::Custom => self.format_custom(&config.custom_in_text_format, pages),
+            // plugins without their own in-text citation use the default one
+            FormatStandard::Plugin => plugins::format_in_text(&config.plugin_format, self, pages)
+                .unwrap_or_else(|| self.default_in_text(pages)),
+        }
+    }
+
+    // "[12]" or "[12, S. 4]"
+    fn default_in_text(&self, pages: Option<&str>) -> String {
+        match pages.filter(|pages| !pages.is_empty()) {
+            Some(pages) => format!(
+                "[{}, {} {}]",
+                self.id,
+                locale::terms(&self.language).unwrap_or(&GERMAN).page,
+                pages
+            ),
+            None => format!("[{}]", self.id),
+        }
+    }
+
+    // ignores case and diacritics, see search::normalize. The fields are listed in the search
+    // command of the CLI and search_sources of the local server
+    pub fn contains(&self, query: &str) -> bool {
+        let query = normalize(query);
+
+        [
+            &self.title,
+            &self.url,
+            &self.author,
+            &self.tags,
+            &self.doi,
+            &self.collection,
+            &self.canonical_url,
+        ]
+        .iter()
+        .any(|field| normalize(field).contains(&query))
+    }
+}
+
+impl Source {
+    // id in the own library, None for sources of the shared and other libraries
+    pub fn library_id(&self) -> Option<i64> {
+        match self.shared {
+            true => None,
+            false => Some(self.id),
+        }
+    }
+
+    // the canonical URL if it was resolved, citations shouldn't contain shortened links
+    pub fn cited_url(&self) -> &str {
+        match self.canonical_url.is_empty() {
+            true => &self.url,
+            false => &self.canonical_url,
+        }
+    }
+
+    pub fn tag_list(&self) -> Vec<&str> {
+        split_tags(&self.tags)
+    }
+
+    // adds tags that the source doesn't have yet
+    pub fn add_tags(&mut self, tags: &str) {
+        let mut list: Vec<String> = self.tag_list().iter().map(|tag| tag.to_string()).collect();
+
+        for tag in split_tags(tags) {
+            if !list
+                .iter()
+                .any(|existing| existing.eq_ignore_ascii_case(tag))
+            {
+                list.push(tag.to_string());
+            }
+        }
+
+        self.tags = list.join(", ");
+    }
+}
+
+// splits a comma separated tag string, ignoring empty entries
+pub fn split_tags(tags: &str) -> Vec<&str> {
+    tags.split(',')
+        .map(|tag| tag.trim())
+        .filter(|tag| !tag.is_empty())
+        .collect()
+}
+
+impl Default for Source {
+    fn default() -> Self {
+        trace!("Creating new Source");
+
+        Self {
+            id: -1,
+            title: String::new(),
+            author: String::new(),
+            url: String::new(),
+            published_date: Local::now().date_naive(), // current date
+            viewed_date: Local::now().date_naive(),    // current date
+            published_date_unknown: false,
+            comment: String::new(),
+            last_cited: None,
+            tags: String::new(),
+            collection: String::new(),
+            doi: String::new(),
+            source_type: SourceType::Webpage,
+            uuid: String::new(),
+            updated_at: None,
+            pinned: false,
+            notes_written: false,
+            canonical_url: String::new(),
+            language: String::new(),
+            shared: false,
+            library: String::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+
+    #[test]
+    fn contains_searches_listed_fields() {
+        let source = Source {
+            title: "The Rust Book".to_string(),
+            url: "https://doc.rust-lang.org/book/".to_string(),
+            author: "Klabnik".to_string(),
+            tags: "programming".to_string(),
+            doi: "10.1000/182".to_string(),
+            collection: "Thesis".to_string(),
+            canonical_url: "https://rust-book.example/".to_string(),
+            comment: "borrowed from the library".to_string(),
+            ..Source::default()
+        };
+
+        for query in [
+            "rust book",
+            "doc.rust-lang",
+            "KLABNIK",
+            "programming",
+            "10.1000",
+            "thesis",
+            "rust-book.example",
+        ] {
+            assert!(source.contains(query), "{}", query);
+        }
+        assert!(!source.contains("borrowed"));
+    }
+
+    // cargo test -p saveit-core format_time -- --ignored --nocapture
+    #[test]
+    #[ignore = "measures formatting a large library"]
+    fn format_time() {
+        const SOURCES: usize = 5000;
+
+        let sources: Vec<Source> = (0..SOURCES)
+            .map(|index| Source {
+                id: index as i64,
+                title: format!("Source {}", index),
+                url: format!("https://example.com/{}", index),
+                author: "Steve Klabnik, Carol Nichols".to_string(),
+                ..Source::default()
+            })
+            .collect();
+        let config = Config {
+            custom_format:
+                "{AUTHOR} ({P_DATE(%Y)}): {UPPER(TRUNC(TITLE, 40))}. {URL} [{V_DATE(%d. %m. %Y)}]"
+                    .to_string(),
+            ..Config::default()
+        };
+
+        for standard in [FormatStandard::Default, FormatStandard::Custom] {
+            let started = Instant::now();
+            let length: usize = sources
+                .iter()
+                .map(|source| source.format(&standard, &config).len())
+                .sum();
+            assert!(length > 0);
+            println!(
+                "{:?}: {:?} per source, {:?} for {} sources",
+                standard,
+                started.elapsed() / SOURCES as u32,
+                started.elapsed(),
+                SOURCES
+            );
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use std::fmt::{Display, Formatter};
+
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+// typed relationship from one source to another
+#[derive(Debug, FromRow, Clone, Serialize, Deserialize)]
+pub struct SourceLink {
+    pub id: i64,
+    pub source_id: i64,
+    pub target_id: i64,
+    pub relation: Relation,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, sqlx::Type, Serialize, Deserialize)]
+#[sqlx(rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum Relation {
+    Cites,
+    RespondsTo,
+    Contradicts,
+    SeeAlso,
+}
+
+impl Relation {
+    pub const ALL: [Relation; 4] = [
+        Relation::Cites,
+        Relation::RespondsTo,
+        Relation::Contradicts,
+        Relation::SeeAlso,
+    ];
+
+    // label seen from the target of the link
+    pub fn inverse_label(&self) -> &'static str {
+        match self {
+            Relation::Cites => "Cited by",
+            Relation::RespondsTo => "Responded to by",
+            Relation::Contradicts => "Contradicted by",
+            Relation::SeeAlso => "See also",
+        }
+    }
+}
+
+impl Display for Relation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Relation::Cites => write!(f, "Cites"),
+            Relation::RespondsTo => write!(f, "Responds to"),
+            Relation::Contradicts => write!(f, "Contradicts"),
+            Relation::SeeAlso => write!(f, "See also"),
+        }
+    }
+}
@@ -0,0 +1,400 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use chrono::{NaiveDate, NaiveDateTime};
+
+use crate::source::{Source, SourceType};
+
+// sources of the library as the app keeps them in memory, repeated strings like authors,
+// collections, tags and domains are stored once and the other strings without spare capacity
+#[derive(Debug, Default)]
+pub struct SourceStore {
+    rows: Vec<Row>,
+    positions: HashMap<i64, usize>, // id to index of its row
+    strings: Interner,
+}
+
+// a source without its repeated strings
+#[derive(Debug)]
+struct Row {
+    id: i64,
+    title: Box<str>,
+    url: Box<str>,
+    author: Sym,
+    domain: Sym, // empty without a host
+    published_date: NaiveDate,
+    viewed_date: NaiveDate,
+    published_date_unknown: bool,
+    comment: Box<str>,
+    last_cited: Option<NaiveDateTime>,
+    tags: Sym,
+    collection: Sym,
+    doi: Box<str>,
+    source_type: SourceType,
+    uuid: Box<str>,
+    updated_at: Option<NaiveDateTime>,
+    pinned: bool,
+    notes_written: bool,
+    canonical_url: Box<str>,
+    language: Sym,
+    shared: bool,
+    library: Sym,
+}
+
+// index of an interned string
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Sym(u32);
+
+#[derive(Debug, Default)]
+struct Interner {
+    strings: Vec<Arc<str>>,
+    index: HashMap<Arc<str>, Sym>,
+}
+
+impl Interner {
+    fn intern(&mut self, text: &str) -> Sym {
+        if let Some(sym) = self.index.get(text) {
+            return *sym;
+        }
+
+        let sym = Sym(self.strings.len() as u32);
+        let text: Arc<str> = Arc::from(text);
+        self.strings.push(text.clone());
+        self.index.insert(text, sym);
+        sym
+    }
+
+    fn resolve(&self, sym: Sym) -> &str {
+        &self.strings[sym.0 as usize]
+    }
+}
+
+// borrowed source of the store, reading its fields doesn't allocate
+#[derive(Clone, Copy)]
+pub struct SourceView<'a> {
+    row: &'a Row,
+    strings: &'a Interner,
+}
+
+impl<'a> SourceView<'a> {
+    pub fn id(&self) -> i64 {
+        self.row.id
+    }
+
+    pub fn title(&self) -> &'a str {
+        &self.row.title
+    }
+
+    pub fn url(&self) -> &'a str {
+        &self.row.url
+    }
+
+    pub fn author(&self) -> &'a str {
+        self.strings.resolve(self.row.author)
+    }
+
+    pub fn domain(&self) -> &'a str {
+        self.strings.resolve(self.row.domain)
+    }
+
+    pub fn tags(&self) -> &'a str {
+        self.strings.resolve(self.row.tags)
+    }
+
+    pub fn collection(&self) -> &'a str {
+        self.strings.resolve(self.row.collection)
+    }
+
+    pub fn pinned(&self) -> bool {
+        self.row.pinned
+    }
+
+    pub fn doi(&self) -> &'a str {
+        &self.row.doi
+    }
+
+    pub fn canonical_url(&self) -> &'a str {
+        &self.row.canonical_url
+    }
+
+    pub fn source_type(&self) -> SourceType {
+        self.row.source_type
+    }
+
+    pub fn last_cited(&self) -> Option<NaiveDateTime> {
+        self.row.last_cited
+    }
+
+    pub fn published_date_unknown(&self) -> bool {
+        self.row.published_date_unknown
+    }
+
+    pub fn notes_written(&self) -> bool {
+        self.row.notes_written
+    }
+
+    pub fn shared(&self) -> bool {
+        self.row.shared
+    }
+
+    // an owned copy for the code that works with sources, e.g. formats and exporters
+    pub fn to_source(&self) -> Source {
+        let row = self.row;
+        let string = |sym: Sym| self.strings.resolve(sym).to_string();
+
+        Source {
+            id: row.id,
+            title: row.title.to_string(),
+            url: row.url.to_string(),
+            author: string(row.author),
+            published_date: row.published_date,
+            viewed_date: row.viewed_date,
+            published_date_unknown: row.published_date_unknown,
+            comment: row.comment.to_string(),
+            last_cited: row.last_cited,
+            tags: string(row.tags),
+            collection: string(row.collection),
+            doi: row.doi.to_string(),
+            source_type: row.source_type,
+            uuid: row.uuid.to_string(),
+            updated_at: row.updated_at,
+            pinned: row.pinned,
+            notes_written: row.notes_written,
+            canonical_url: row.canonical_url.to_string(),
+            language: string(row.language),
+            shared: row.shared,
+            library: string(row.library),
+        }
+    }
+}
+
+impl SourceStore {
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    pub fn contains(&self, id: i64) -> bool {
+        self.positions.contains_key(&id)
+    }
+
+    // in the order they were loaded in
+    pub fn iter(&self) -> impl Iterator<Item = SourceView<'_>> + '_ {
+        self.rows.iter().map(|row| SourceView {
+            row,
+            strings: &self.strings,
+        })
+    }
+
+    // owned copies in the order they were loaded in, each one is built when it's reached
+    pub fn sources(&self) -> impl Iterator<Item = Source> + '_ {
+        self.iter().map(|view| view.to_source())
+    }
+
+    pub fn to_vec(&self) -> Vec<Source> {
+        self.sources().collect()
+    }
+
+    pub fn view(&self, id: i64) -> Option<SourceView<'_>> {
+        let row = &self.rows[*self.positions.get(&id)?];
+        Some(SourceView {
+            row,
+            strings: &self.strings,
+        })
+    }
+
+    pub fn get(&self, id: i64) -> Option<Source> {
+        self.view(id).map(|view| view.to_source())
+    }
+
+    // adds the source or replaces the one with its id
+    pub fn upsert(&mut self, source: &Source) {
+        let row = self.row(source);
+        match self.positions.get(&source.id) {
+            Some(&index) => self.rows[index] = row,
+            None => {
+                self.positions.insert(source.id, self.rows.len());
+                self.rows.push(row);
+            }
+        }
+    }
+
+    pub fn remove(&mut self, id: i64) {
+        if let Some(index) = self.positions.remove(&id) {
+            self.rows.remove(index);
+            for position in self.positions.values_mut() {
+                if *position > index {
+                    *position -= 1;
+                }
+            }
+        }
+    }
+
+    fn row(&mut self, source: &Source) -> Row {
+        let strings = &mut self.strings;
+
+        Row {
+            id: source.id,
+            title: source.title.as_str().into(),
+            url: source.url.as_str().into(),
+            author: strings.intern(&source.author),
+            domain: strings.intern(domain(&source.url).unwrap_or_default()),
+            published_date: source.published_date,
+            viewed_date: source.viewed_date,
+            published_date_unknown: source.published_date_unknown,
+            comment: source.comment.as_str().into(),
+            last_cited: source.last_cited,
+            tags: strings.intern(&source.tags),
+            collection: strings.intern(&source.collection),
+            doi: source.doi.as_str().into(),
+            source_type: source.source_type,
+            uuid: source.uuid.as_str().into(),
+            updated_at: source.updated_at,
+            pinned: source.pinned,
+            notes_written: source.notes_written,
+            canonical_url: source.canonical_url.as_str().into(),
+            language: strings.intern(&source.language),
+            shared: source.shared,
+            library: strings.intern(&source.library),
+        }
+    }
+}
+
+impl From<Vec<Source>> for SourceStore {
+    fn from(sources: Vec<Source>) -> Self {
+        let mut store = SourceStore {
+            rows: Vec::with_capacity(sources.len()),
+            positions: HashMap::with_capacity(sources.len()),
+            strings: Interner::default(),
+        };
+        for source in &sources {
+            store.upsert(source);
+        }
+        store
+    }
+}
+
+// host of a URL without "www.", e.g. "doc.rust-lang.org"
+pub fn domain(url: &str) -> Option<&str> {
+    let url = url.trim();
+    let url = match url.find("://") {
+        Some(index) => &url[index + 3..],
+        None => url,
+    };
+
+    let host = url.split(['/', '?', '#']).next()?;
+    let host = host.rsplit('@').next()?; // user info
+    let host = host.split(':').next()?; // port
+    let host = host.strip_prefix("www.").unwrap_or(host);
+
+    match host.is_empty() {
+        true => None,
+        false => Some(host),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(id: i64, title: &str) -> Source {
+        Source {
+            id,
+            title: title.to_string(),
+            url: format!("https://www.example.com/{}", id),
+            author: "Ferris".to_string(),
+            tags: "rust".to_string(),
+            ..Source::default()
+        }
+    }
+
+    fn ids(store: &SourceStore) -> Vec<i64> {
+        store.iter().map(|view| view.id()).collect()
+    }
+
+    #[test]
+    fn removes_from_the_middle() {
+        let mut store = SourceStore::from(vec![source(1, "a"), source(2, "b"), source(3, "c")]);
+
+        store.remove(2);
+        assert_eq!(ids(&store), [1, 3]);
+        assert_eq!(store.len(), 2);
+        assert!(!store.contains(2));
+        assert_eq!(store.get(1).unwrap().title, "a");
+        assert_eq!(store.get(3).unwrap().title, "c");
+
+        // unknown ids are ignored
+        store.remove(2);
+        assert_eq!(ids(&store), [1, 3]);
+    }
+
+    #[test]
+    fn finds_ids_after_remove() {
+        let mut store = SourceStore::from((1..=5).map(|id| source(id, "")).collect::<Vec<_>>());
+
+        store.remove(1);
+        store.remove(4);
+        for id in [2, 3, 5] {
+            assert_eq!(store.view(id).map(|view| view.id()), Some(id));
+        }
+        assert!(store.view(1).is_none());
+        assert!(store.view(4).is_none());
+
+        store.upsert(&source(6, "f"));
+        assert_eq!(ids(&store), [2, 3, 5, 6]);
+        assert_eq!(store.get(6).unwrap().title, "f");
+        assert_eq!(store.get(5).unwrap().id, 5);
+    }
+
+    #[test]
+    fn upsert_replaces_in_place() {
+        let mut store = SourceStore::from(vec![source(1, "a"), source(2, "b"), source(3, "c")]);
+
+        let mut edited = source(2, "edited");
+        edited.author = "Corro".to_string();
+        store.upsert(&edited);
+        assert_eq!(ids(&store), [1, 2, 3]);
+        assert_eq!(store.get(2).unwrap().title, "edited");
+        assert_eq!(store.view(2).unwrap().author(), "Corro");
+        assert_eq!(store.view(1).unwrap().author(), "Ferris");
+
+        // removed and added again, it's appended
+        store.remove(1);
+        store.upsert(&source(1, "again"));
+        assert_eq!(ids(&store), [2, 3, 1]);
+        assert_eq!(store.get(1).unwrap().title, "again");
+    }
+
+    #[test]
+    fn keeps_every_field() {
+        let mut original = source(7, "Title");
+        original.comment = "comment".to_string();
+        original.collection = "Thesis".to_string();
+        original.doi = "10.1000/182".to_string();
+        original.language = "en".to_string();
+        original.pinned = true;
+        let store = SourceStore::from(vec![original.clone()]);
+
+        let copy = store.get(7).unwrap();
+        assert_eq!(copy.title, original.title);
+        assert_eq!(copy.url, original.url);
+        assert_eq!(copy.author, original.author);
+        assert_eq!(copy.comment, original.comment);
+        assert_eq!(copy.collection, original.collection);
+        assert_eq!(copy.doi, original.doi);
+        assert_eq!(copy.language, original.language);
+        assert_eq!(copy.pinned, original.pinned);
+        assert_eq!(store.view(7).unwrap().domain(), "example.com");
+    }
+
+    #[test]
+    fn stores_repeated_strings_once() {
+        let store = SourceStore::from((1..=100).map(|id| source(id, "")).collect::<Vec<_>>());
+
+        // author, domain, tags and the empty collection, language and library
+        assert_eq!(store.strings.strings.len(), 4);
+    }
+}
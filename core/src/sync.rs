@@ -0,0 +1,722 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::future::Future;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use chrono::{DateTime, Local, Utc};
+use hmac::{Hmac, Mac};
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use sqlx::SqlitePool;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use tracing::*;
+use uuid::Uuid;
+
+use crate::crash::record_operation;
+use crate::database::{
+    apply_changes, get_all_sources, get_changes_since, get_last_sync, set_last_sync, Tombstone,
+};
+use crate::scheduler;
+use crate::source::Source;
+use crate::source_store::SourceStore;
+
+const SERVICE_TYPE: &str = "_saveit._tcp.local.";
+const SYNC_INTERVAL: Duration = Duration::from_secs(60);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10); // connecting and the handshake
+const CHANGES_TIMEOUT: Duration = Duration::from_secs(5 * 60); // sending and applying the changes
+                                                               // longest line read from a peer, the handshake is read before the peer is known to have the key
+const MAX_HANDSHAKE_LINE: u64 = 4 * 1024;
+const MAX_CHANGES_LINE: u64 = 256 * 1024 * 1024;
+
+// what each proof and signature is for, so one can't be replayed as another
+const CLIENT_PROOF: &str = "client proof";
+const SERVER_PROOF: &str = "server proof";
+const CLIENT_CHANGES: &str = "client changes";
+const SERVER_CHANGES: &str = "server changes";
+
+// the MAC key is derived from the typed sync key with PBKDF2, so a device that learns a proof has
+// to spend this many HMACs on every guess of the key
+const KEY_ROUNDS: u32 = 100_000;
+const KEY_SALT: &[u8] = b"SaveIt LAN sync";
+
+// changes of one device
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct SyncMessage {
+    sources: Vec<Source>,
+    deleted: Vec<Tombstone>,
+}
+
+// one JSON line; the device that connects sends Hello, Proof and Changes, the other one
+// Challenge, Proof and Changes. The sync key never leaves the device, both prove they have it
+// with an HMAC over the nonces of the connection before any changes are sent. The connecting
+// device proves it first, so anyone can connect but only learns that the key is wrong
+#[derive(Serialize, Deserialize, Debug)]
+enum Frame {
+    Hello {
+        device_id: String,
+        nonce: String,
+    },
+    Challenge {
+        device_id: String,
+        nonce: String,
+    },
+    Proof(String),
+    Changes {
+        message: String, // SyncMessage as JSON
+        signature: String,
+    },
+    Rejected(String),
+}
+
+// derived MAC key and the ids and nonces of both devices of a connection
+struct Session {
+    key: Vec<u8>,
+    parts: [String; 4],
+}
+
+impl Session {
+    fn new(key: &[u8], client: (&str, &str), server: (&str, &str)) -> Self {
+        Session {
+            key: key.to_vec(),
+            parts: [
+                client.0.to_string(),
+                client.1.to_string(),
+                server.0.to_string(),
+                server.1.to_string(),
+            ],
+        }
+    }
+
+    fn mac(&self, purpose: &str, content: &str) -> Hmac<Sha256> {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC takes keys of any length");
+        // length prefixed, so the parts can't be shifted into each other
+        for part in [purpose]
+            .into_iter()
+            .chain(self.parts.iter().map(String::as_str))
+            .chain([content])
+        {
+            mac.update(&(part.len() as u64).to_be_bytes());
+            mac.update(part.as_bytes());
+        }
+        mac
+    }
+
+    fn sign(&self, purpose: &str, content: &str) -> String {
+        hex::encode(self.mac(purpose, content).finalize().into_bytes())
+    }
+
+    // compared in constant time
+    fn verify(&self, purpose: &str, content: &str, signature: &str) -> bool {
+        hex::decode(signature)
+            .is_ok_and(|signature| self.mac(purpose, content).verify_slice(&signature).is_ok())
+    }
+
+    fn changes(&self, purpose: &str, message: &SyncMessage) -> Result<Frame, serde_json::Error> {
+        let message = serde_json::to_string(message)?;
+        Ok(Frame::Changes {
+            signature: self.sign(purpose, &message),
+            message,
+        })
+    }
+
+    fn open(
+        &self,
+        purpose: &str,
+        message: &str,
+        signature: &str,
+    ) -> Result<SyncMessage, Box<dyn Error + Send + Sync>> {
+        match self.verify(purpose, message, signature) {
+            true => Ok(serde_json::from_str(message)?),
+            false => Err("Changes with an invalid signature".into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PeerStatus {
+    pub address: Option<SocketAddr>,
+    pub last_sync: Option<DateTime<Local>>,
+    pub error: Option<String>,
+}
+
+// running LAN sync, stopped when dropped
+pub struct LanSync {
+    daemon: ServiceDaemon,
+    tasks: Vec<JoinHandle<()>>,
+    pub peers: Arc<RwLock<BTreeMap<String, PeerStatus>>>, // by device id
+}
+
+impl Drop for LanSync {
+    fn drop(&mut self) {
+        debug!("Stopping LAN sync");
+
+        for task in &self.tasks {
+            task.abort();
+        }
+        if let Err(e) = self.daemon.shutdown() {
+            warn!("Error stopping mDNS daemon: {}", e);
+        }
+    }
+}
+
+#[derive(Clone)]
+struct SyncContext {
+    device_id: String,
+    key: Vec<u8>, // derived with derive_key
+    pool: Arc<SqlitePool>,
+    sources_cache: Arc<RwLock<SourceStore>>,
+    peers: Arc<RwLock<BTreeMap<String, PeerStatus>>>,
+    syncing: Arc<Mutex<()>>, // one sync at a time so changes aren't applied twice
+}
+
+// announces this device via mDNS and syncs with every other device using the same key
+pub fn start(
+    device_id: String,
+    key: String,
+    pool: Arc<SqlitePool>,
+    sources_cache: Arc<RwLock<SourceStore>>,
+) -> Result<LanSync, Box<dyn Error>> {
+    debug!("Starting LAN sync as {}", device_id);
+    record_operation("Start LAN sync");
+
+    let listener = std::net::TcpListener::bind("0.0.0.0:0")?;
+    listener.set_nonblocking(true)?;
+    let port = listener.local_addr()?.port();
+    let listener = TcpListener::from_std(listener)?;
+
+    let daemon = ServiceDaemon::new()?;
+    let service = ServiceInfo::new(
+        SERVICE_TYPE,
+        &device_id,
+        &format!("saveit-{}.local.", device_id),
+        "",
+        port,
+        [("id", device_id.as_str())].as_slice(),
+    )?
+    .enable_addr_auto();
+    daemon.register(service)?;
+    let events = daemon.browse(SERVICE_TYPE)?;
+
+    let context = SyncContext {
+        device_id,
+        key: derive_key(&key),
+        pool,
+        sources_cache,
+        peers: Arc::new(RwLock::new(BTreeMap::new())),
+        syncing: Arc::new(Mutex::new(())),
+    };
+    let mut tasks = vec![];
+
+    // incoming syncs
+    let accept_context = context.clone();
+    tasks.push(tokio::spawn(async move {
+        loop {
+            let (stream, address) = match listener.accept().await {
+                Ok(connection) => connection,
+                Err(e) => {
+                    warn!("Error accepting sync connection: {}", e);
+                    continue;
+                }
+            };
+
+            let context = accept_context.clone();
+            tokio::spawn(async move {
+                if let Err(e) = handle_incoming(stream, &context).await {
+                    warn!("Error in sync from {}: {}", address, e);
+                }
+            });
+        }
+    }));
+
+    // discovered devices
+    let browse_context = context.clone();
+    tasks.push(tokio::spawn(async move {
+        while let Ok(event) = events.recv_async().await {
+            match event {
+                ServiceEvent::ServiceResolved(info) => {
+                    let peer_id = match info.get_property_val_str("id") {
+                        Some(id) if id != browse_context.device_id => id.to_string(),
+                        _ => continue,
+                    };
+                    let address = match preferred_address(info.get_addresses().iter()) {
+                        Some(ip) => SocketAddr::new(ip, info.get_port()),
+                        None => continue,
+                    };
+
+                    debug!("Found SaveIt device {} at {}", peer_id, address);
+                    browse_context
+                        .peers
+                        .write()
+                        .unwrap()
+                        .entry(peer_id.clone())
+                        .or_default()
+                        .address = Some(address);
+
+                    sync_with_peer(&peer_id, address, &browse_context).await;
+                }
+                ServiceEvent::ServiceRemoved(_, fullname) => {
+                    let mut peers = browse_context.peers.write().unwrap();
+                    if let Some(peer) = peers
+                        .iter_mut()
+                        .find(|(id, _)| fullname.starts_with(id.as_str()))
+                        .map(|(_, peer)| peer)
+                    {
+                        peer.address = None;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }));
+
+    // periodic sync with known devices
+    let interval_context = context.clone();
+    tasks.push(tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SYNC_INTERVAL);
+        loop {
+            interval.tick().await;
+
+            let peers: Vec<(String, SocketAddr)> = interval_context
+                .peers
+                .read()
+                .unwrap()
+                .iter()
+                .filter_map(|(id, peer)| Some((id.clone(), peer.address?)))
+                .collect();
+            for (peer_id, address) in peers {
+                sync_with_peer(&peer_id, address, &interval_context).await;
+            }
+        }
+    }));
+
+    Ok(LanSync {
+        daemon,
+        tasks,
+        peers: context.peers,
+    })
+}
+
+// prefer IPv4, link local IPv6 addresses would need a scope id
+fn preferred_address<'a>(addresses: impl Iterator<Item = &'a IpAddr>) -> Option<IpAddr> {
+    let addresses: Vec<IpAddr> = addresses.copied().collect();
+    addresses
+        .iter()
+        .find(|ip| ip.is_ipv4())
+        .or(addresses.first())
+        .copied()
+}
+
+async fn sync_with_peer(peer_id: &str, address: SocketAddr, context: &SyncContext) {
+    let name = format!("Sync with {}", peer_id);
+    let result = scheduler::run(name, || sync_outgoing(peer_id, address, context)).await;
+
+    let mut peers = context.peers.write().unwrap();
+    let peer = peers.entry(peer_id.to_string()).or_default();
+    match result {
+        Ok(()) => {
+            peer.last_sync = Some(Local::now());
+            peer.error = None;
+        }
+        Err(e) => {
+            warn!("Error syncing with {}: {}", peer_id, e);
+            peer.error = Some(e.to_string());
+        }
+    }
+}
+
+// fails with "Timed out" if the future takes longer
+async fn within<T>(
+    timeout: Duration,
+    future: impl Future<Output = Result<T, Box<dyn Error + Send + Sync>>>,
+) -> Result<T, Box<dyn Error + Send + Sync>> {
+    tokio::time::timeout(timeout, future)
+        .await
+        .unwrap_or_else(|_| Err("Timed out".into()))
+}
+
+async fn sync_outgoing(
+    peer_id: &str,
+    address: SocketAddr,
+    context: &SyncContext,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    debug!("Syncing with {} at {}", peer_id, address);
+    let _syncing = context.syncing.lock().await;
+
+    let started = Utc::now().naive_utc();
+    let (mut reader, mut writer, session) =
+        within(CONNECT_TIMEOUT, connect(peer_id, address, context)).await?;
+
+    within(CHANGES_TIMEOUT, async {
+        let message = changes_for(peer_id, context).await?;
+        write_line(&mut writer, &session.changes(CLIENT_CHANGES, &message)?).await?;
+
+        let reply = match read_line(&mut reader, MAX_CHANGES_LINE).await? {
+            Frame::Changes { message, signature } => {
+                session.open(SERVER_CHANGES, &message, &signature)?
+            }
+            Frame::Rejected(reason) => return Err(reason.into()),
+            _ => return Err("Unexpected message".into()),
+        };
+        apply(peer_id, &reply, context).await?;
+
+        set_last_sync(peer_id, started, &context.pool).await?;
+        Ok(())
+    })
+    .await
+}
+
+// handshake of the connecting device, proves it has the key and checks that the peer has it too
+async fn connect(
+    peer_id: &str,
+    address: SocketAddr,
+    context: &SyncContext,
+) -> Result<(BufReader<OwnedReadHalf>, OwnedWriteHalf, Session), Box<dyn Error + Send + Sync>> {
+    let stream = TcpStream::connect(address).await?;
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+
+    let nonce = new_nonce();
+    let hello = Frame::Hello {
+        device_id: context.device_id.clone(),
+        nonce: nonce.clone(),
+    };
+    write_line(&mut writer, &hello).await?;
+
+    let peer_nonce = match read_line(&mut reader, MAX_HANDSHAKE_LINE).await? {
+        Frame::Challenge { device_id, nonce } if device_id == peer_id => nonce,
+        Frame::Challenge { .. } => return Err("Device id changed".into()),
+        Frame::Rejected(reason) => return Err(reason.into()),
+        _ => return Err("Unexpected message".into()),
+    };
+    let session = Session::new(
+        &context.key,
+        (&context.device_id, &nonce),
+        (peer_id, &peer_nonce),
+    );
+    write_line(&mut writer, &Frame::Proof(session.sign(CLIENT_PROOF, ""))).await?;
+
+    // a device announcing itself without the key gets no changes
+    match read_line(&mut reader, MAX_HANDSHAKE_LINE).await? {
+        Frame::Proof(proof) if session.verify(SERVER_PROOF, "", &proof) => {}
+        Frame::Proof(_) => return Err("Sync keys don't match".into()),
+        Frame::Rejected(reason) => return Err(reason.into()),
+        _ => return Err("Unexpected message".into()),
+    }
+
+    Ok((reader, writer, session))
+}
+
+async fn handle_incoming(
+    stream: TcpStream,
+    context: &SyncContext,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+
+    let (peer_id, session) = within(CONNECT_TIMEOUT, async {
+        let (peer_id, peer_nonce) = match read_line(&mut reader, MAX_HANDSHAKE_LINE).await? {
+            Frame::Hello { device_id, nonce } => (device_id, nonce),
+            _ => return Err("Unexpected message".into()),
+        };
+        let nonce = new_nonce();
+        let session = Session::new(
+            &context.key,
+            (&peer_id, &peer_nonce),
+            (&context.device_id, &nonce),
+        );
+        let challenge = Frame::Challenge {
+            device_id: context.device_id.clone(),
+            nonce,
+        };
+        write_line(&mut writer, &challenge).await?;
+
+        let proof = match read_line(&mut reader, MAX_HANDSHAKE_LINE).await? {
+            Frame::Proof(proof) => proof,
+            _ => return Err("Unexpected message".into()),
+        };
+        if !session.verify(CLIENT_PROOF, "", &proof) {
+            let rejected = Frame::Rejected("Sync keys don't match".to_string());
+            write_line(&mut writer, &rejected).await?;
+            return Err(format!("Rejected {}, sync keys don't match", peer_id).into());
+        }
+
+        // only a device that proved it has the key gets a proof back
+        write_line(&mut writer, &Frame::Proof(session.sign(SERVER_PROOF, ""))).await?;
+        Ok((peer_id, session))
+    })
+    .await?;
+
+    debug!("Incoming sync from {}", peer_id);
+
+    // both devices syncing with each other at the same time would wait for each other
+    let _syncing = match context.syncing.try_lock() {
+        Ok(guard) => guard,
+        Err(_) => {
+            write_line(&mut writer, &Frame::Rejected("Busy".to_string())).await?;
+            return Ok(());
+        }
+    };
+
+    within(CHANGES_TIMEOUT, async {
+        let message = match read_line(&mut reader, MAX_CHANGES_LINE).await? {
+            Frame::Changes { message, signature } => {
+                session.open(CLIENT_CHANGES, &message, &signature)?
+            }
+            _ => return Err("Unexpected message".into()),
+        };
+
+        let started = Utc::now().naive_utc();
+        let reply = changes_for(&peer_id, context).await?;
+        apply(&peer_id, &message, context).await?;
+        write_line(&mut writer, &session.changes(SERVER_CHANGES, &reply)?).await?;
+
+        set_last_sync(&peer_id, started, &context.pool).await?;
+        Ok(())
+    })
+    .await?;
+
+    let mut peers = context.peers.write().unwrap();
+    let peer = peers.entry(peer_id).or_default();
+    peer.last_sync = Some(Local::now());
+    peer.error = None;
+
+    Ok(())
+}
+
+// everything that changed since the last sync with the peer
+async fn changes_for(peer_id: &str, context: &SyncContext) -> Result<SyncMessage, sqlx::Error> {
+    let since = get_last_sync(peer_id, &context.pool).await?;
+    let (sources, deleted) = get_changes_since(since, &context.pool).await?;
+
+    Ok(SyncMessage { sources, deleted })
+}
+
+async fn apply(
+    peer_id: &str,
+    message: &SyncMessage,
+    context: &SyncContext,
+) -> Result<(), sqlx::Error> {
+    let applied = apply_changes(&message.sources, &message.deleted, &context.pool).await?;
+
+    if applied > 0 {
+        info!("Applied {} changes from {}", applied, peer_id);
+        *context.sources_cache.write().unwrap() = get_all_sources(&context.pool).await?.into();
+    }
+
+    Ok(())
+}
+
+async fn write_line(
+    writer: &mut (impl AsyncWriteExt + Unpin),
+    value: &impl Serialize,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut line = serde_json::to_string(value)?;
+    line.push('\n');
+    writer.write_all(line.as_bytes()).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+// one line of at most limit bytes
+async fn read_line<T: DeserializeOwned>(
+    reader: &mut (impl AsyncBufRead + Unpin),
+    limit: u64,
+) -> Result<T, Box<dyn Error + Send + Sync>> {
+    let mut line = vec![];
+    reader.take(limit).read_until(b'\n', &mut line).await?;
+
+    match line.last() {
+        Some(b'\n') => Ok(serde_json::from_slice(&line)?),
+        _ if line.len() as u64 == limit => Err("Message too long".into()),
+        _ => Err("Connection closed".into()),
+    }
+}
+
+// the same key on every device using the same sync key
+fn derive_key(key: &str) -> Vec<u8> {
+    pbkdf2(key.as_bytes(), KEY_SALT, KEY_ROUNDS)
+}
+
+// PBKDF2-HMAC-SHA256 with one block of output
+fn pbkdf2(password: &[u8], salt: &[u8], rounds: u32) -> Vec<u8> {
+    let mac = |content: &[&[u8]]| {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(password).expect("HMAC takes keys of any length");
+        for part in content {
+            mac.update(part);
+        }
+        mac.finalize().into_bytes()
+    };
+
+    let mut block = mac(&[salt, &1u32.to_be_bytes()]);
+    let mut derived = block;
+    for _ in 1..rounds {
+        block = mac(&[&block]);
+        for (derived, byte) in derived.iter_mut().zip(block) {
+            *derived ^= byte;
+        }
+    }
+    derived.to_vec()
+}
+
+// unpredictable, so proofs of earlier connections can't be replayed
+fn new_nonce() -> String {
+    Uuid::new_v4().simple().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::insert_source;
+    use crate::database::tests::pool;
+
+    // derive_key with a single round, the full rounds take seconds without optimizations
+    fn test_key(key: &str) -> Vec<u8> {
+        pbkdf2(key.as_bytes(), KEY_SALT, 1)
+    }
+
+    async fn context(device_id: &str, key: &str, title: &str) -> SyncContext {
+        let pool = pool().await;
+        let source = Source {
+            title: title.to_string(),
+            ..Source::default()
+        };
+        insert_source(&source, &pool).await.unwrap();
+
+        SyncContext {
+            device_id: device_id.to_string(),
+            key: test_key(key),
+            pool: Arc::new(pool),
+            sources_cache: Arc::default(),
+            peers: Arc::default(),
+            syncing: Arc::default(),
+        }
+    }
+
+    async fn titles(context: &SyncContext) -> Vec<String> {
+        let mut titles: Vec<String> = get_all_sources(&context.pool)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|source| source.title)
+            .collect();
+        titles.sort();
+        titles
+    }
+
+    // answers one incoming sync on localhost
+    async fn serve(context: SyncContext) -> (SocketAddr, JoinHandle<Result<(), String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let task = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            handle_incoming(stream, &context)
+                .await
+                .map_err(|e| e.to_string())
+        });
+        (address, task)
+    }
+
+    #[tokio::test]
+    async fn syncs_with_same_key() {
+        let server = context("server", "secret", "Server").await;
+        let client = context("client", "secret", "Client").await;
+        let (address, task) = serve(server.clone()).await;
+
+        sync_outgoing("server", address, &client).await.unwrap();
+        task.await.unwrap().unwrap();
+
+        assert_eq!(titles(&server).await, ["Client", "Server"]);
+        assert_eq!(titles(&client).await, ["Client", "Server"]);
+        assert_eq!(client.sources_cache.read().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn other_key_gets_no_changes() {
+        let server = context("server", "secret", "Server").await;
+        let client = context("client", "guess", "Client").await;
+        let (address, task) = serve(server.clone()).await;
+
+        let error = sync_outgoing("server", address, &client).await.unwrap_err();
+        assert_eq!(error.to_string(), "Sync keys don't match");
+        assert!(task.await.unwrap().is_err());
+
+        assert_eq!(titles(&server).await, ["Server"]);
+        assert_eq!(titles(&client).await, ["Client"]);
+    }
+
+    #[tokio::test]
+    async fn rejects_forged_proof() {
+        let server = context("server", "secret", "Server").await;
+        let (address, task) = serve(server.clone()).await;
+
+        let stream = TcpStream::connect(address).await.unwrap();
+        let (reader, mut writer) = stream.into_split();
+        let mut reader = BufReader::new(reader);
+        let hello = Frame::Hello {
+            device_id: "client".to_string(),
+            nonce: new_nonce(),
+        };
+        write_line(&mut writer, &hello).await.unwrap();
+        // the server doesn't prove anything before the client did
+        let challenge: Frame = read_line(&mut reader, MAX_HANDSHAKE_LINE).await.unwrap();
+        assert!(matches!(challenge, Frame::Challenge { .. }));
+
+        // a proof of another connection
+        let key = test_key("secret");
+        let session = Session::new(&key, ("client", &new_nonce()), ("server", "nonce"));
+        let proof = Frame::Proof(session.sign(CLIENT_PROOF, ""));
+        write_line(&mut writer, &proof).await.unwrap();
+        let reply: Frame = read_line(&mut reader, MAX_HANDSHAKE_LINE).await.unwrap();
+        assert!(matches!(reply, Frame::Rejected(_)));
+        assert!(task.await.unwrap().is_err());
+    }
+
+    #[tokio::test]
+    async fn limits_line_length() {
+        let mut reader = BufReader::new(&[b'a'; 100][..]);
+        let error = read_line::<Frame>(&mut reader, 10).await.unwrap_err();
+        assert_eq!(error.to_string(), "Message too long");
+
+        let mut reader = BufReader::new(&b"\"Proof\""[..]);
+        let error = read_line::<Frame>(&mut reader, 10).await.unwrap_err();
+        assert_eq!(error.to_string(), "Connection closed");
+    }
+
+    #[test]
+    fn derives_pbkdf2_keys() {
+        // test vectors of RFC 7914 and RFC 6070 with SHA-256
+        assert_eq!(
+            hex::encode(pbkdf2(b"passwd", b"salt", 1)),
+            "55ac046e56e3089fec1691c22544b605f94185216dde0465e68b9d57c20dacbc"
+        );
+        assert_eq!(
+            hex::encode(pbkdf2(b"password", b"salt", 4096)),
+            "c5e478d59288c841aa530db6845c4c8d962893a001ce4e11a4963873aa98134a"
+        );
+    }
+
+    #[test]
+    fn signatures_depend_on_key_nonces_and_purpose() {
+        let session = Session::new(b"secret", ("client", "1"), ("server", "2"));
+        let signature = session.sign(CLIENT_CHANGES, "{}");
+        assert!(session.verify(CLIENT_CHANGES, "{}", &signature));
+
+        assert!(!session.verify(SERVER_CHANGES, "{}", &signature));
+        assert!(!session.verify(CLIENT_CHANGES, "{ }", &signature));
+        let other_key = Session::new(b"guess", ("client", "1"), ("server", "2"));
+        assert!(!other_key.verify(CLIENT_CHANGES, "{}", &signature));
+        let other_nonce = Session::new(b"secret", ("client", "1"), ("server", "3"));
+        assert!(!other_nonce.verify(CLIENT_CHANGES, "{}", &signature));
+        assert!(!session.verify(CLIENT_CHANGES, "{}", "not hex"));
+    }
+}
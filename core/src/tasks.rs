@@ -0,0 +1,62 @@
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use once_cell::sync::Lazy;
+use tokio::runtime::Handle;
+use tokio::task::JoinHandle;
+use tracing::*;
+
+// don't keep the window from closing forever if a task hangs
+const EXIT_TIMEOUT: Duration = Duration::from_secs(10);
+
+// writes (DB, config) that have to finish before the process exits
+static PENDING_TASKS: Lazy<Mutex<Vec<JoinHandle<()>>>> = Lazy::new(|| Mutex::new(vec![]));
+
+// spawns a task that is waited for on exit
+pub fn spawn_tracked<F>(future: F)
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    let handle = tokio::task::spawn(future);
+
+    let mut pending = PENDING_TASKS.lock().unwrap();
+    pending.retain(|handle| !handle.is_finished());
+    pending.push(handle);
+}
+
+// tracked tasks that haven't finished yet
+pub fn pending() -> usize {
+    PENDING_TASKS
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|handle| !handle.is_finished())
+        .count()
+}
+
+// blocks until all tracked tasks completed
+pub fn wait_for_pending() {
+    let handles = std::mem::take(&mut *PENDING_TASKS.lock().unwrap());
+    if handles.is_empty() {
+        return;
+    }
+
+    debug!("Waiting for {} pending tasks", handles.len());
+
+    tokio::task::block_in_place(|| {
+        Handle::current().block_on(async {
+            let all = async {
+                for handle in handles {
+                    if let Err(e) = handle.await {
+                        error!("Pending task failed: {}", e);
+                    }
+                }
+            };
+
+            if tokio::time::timeout(EXIT_TIMEOUT, all).await.is_err() {
+                warn!("Timed out waiting for pending tasks");
+            }
+        })
+    });
+}
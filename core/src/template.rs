@@ -0,0 +1,479 @@
+use std::fmt::{Display, Formatter, Write};
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::NaiveDate;
+use tracing::*;
+
+use crate::source::Source;
+
+const DEFAULT_DATE_FORMAT: &str = "%d. %m. %Y";
+
+// parsed custom format string, e.g. "{AUTHOR} ({P_DATE(%Y)}): {UPPER(TITLE)}"
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Text(String),
+    Expr(Expr),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Field(Field),
+    PublishedDate(String),
+    ViewedDate(String),
+    Pages(String), // prefix, only written if a page is given
+    Upper(Box<Expr>),
+    Lower(Box<Expr>),
+    Truncate(Box<Expr>, usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field {
+    Index,
+    Title,
+    Url,
+    Author,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub position: usize, // 1-based character position
+    pub message: String,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl Template {
+    pub fn parse(input: &str) -> Result<Self, ParseError> {
+        trace!("Parsing template: {}", input);
+
+        Parser::new(input).parse()
+    }
+
+    // pages is the page/paragraph reference entered when copying, if any
+    pub fn render(&self, source: &Source, pages: Option<&str>) -> String {
+        let mut out = String::new();
+
+        for node in &self.nodes {
+            match node {
+                Node::Text(text) => out.push_str(text),
+                Node::Expr(expr) => out.push_str(&expr.eval(source, pages)),
+            }
+        }
+
+        out
+    }
+}
+
+impl Expr {
+    fn eval(&self, source: &Source, pages: Option<&str>) -> String {
+        match self {
+            Expr::Field(Field::Index) => source.id.to_string(),
+            Expr::Field(Field::Title) => source.title.clone(),
+            Expr::Field(Field::Url) => source.cited_url().to_string(),
+            Expr::Field(Field::Author) => source.author.clone(),
+            Expr::PublishedDate(format) => match source.published_date_unknown {
+                true => "Unknown".to_string(),
+                false => format_date(&source.published_date, format),
+            },
+            Expr::ViewedDate(format) => format_date(&source.viewed_date, format),
+            Expr::Pages(prefix) => match pages {
+                Some(pages) if !pages.is_empty() => format!("{}{}", prefix, pages),
+                _ => String::new(),
+            },
+            Expr::Upper(expr) => expr.eval(source, pages).to_uppercase(),
+            Expr::Lower(expr) => expr.eval(source, pages).to_lowercase(),
+            Expr::Truncate(expr, len) => {
+                let text = expr.eval(source, pages);
+
+                match text.chars().count() > *len {
+                    true => {
+                        let mut out: String = text.chars().take(*len).collect();
+                        out.push('…');
+                        out
+                    }
+                    false => text,
+                }
+            }
+        }
+    }
+}
+
+// formats a date without panicking on invalid format strings
+fn format_date(date: &NaiveDate, format: &str) -> String {
+    let mut out = String::new();
+
+    match write!(out, "{}", date.format(format)) {
+        Ok(_) => out,
+        Err(_) => "Invalid date format".to_string(),
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str) -> Self {
+        Self {
+            input,
+            chars: input.char_indices().peekable(),
+        }
+    }
+
+    // 1-based character position of a byte offset
+    fn position(&self, offset: usize) -> usize {
+        self.input[..offset].chars().count() + 1
+    }
+
+    fn error<T>(&self, offset: usize, message: impl Into<String>) -> Result<T, ParseError> {
+        Err(ParseError {
+            position: self.position(offset),
+            message: message.into(),
+        })
+    }
+
+    // offset of the next char or the end of the input
+    fn offset(&mut self) -> usize {
+        self.chars
+            .peek()
+            .map(|(offset, _)| *offset)
+            .unwrap_or(self.input.len())
+    }
+
+    fn parse(mut self) -> Result<Template, ParseError> {
+        let mut nodes = vec![];
+        let mut text = String::new();
+
+        while let Some((offset, c)) = self.chars.next() {
+            match c {
+                '{' => {
+                    // "{{" is an escaped literal brace
+                    if let Some((_, '{')) = self.chars.peek() {
+                        self.chars.next();
+                        text.push('{');
+                        continue;
+                    }
+
+                    if !text.is_empty() {
+                        nodes.push(Node::Text(std::mem::take(&mut text)));
+                    }
+
+                    let expr = self.parse_expr()?;
+                    match self.chars.next() {
+                        Some((_, '}')) => nodes.push(Node::Expr(expr)),
+                        Some((offset, c)) => {
+                            return self.error(offset, format!("Expected '}}', found '{}'", c))
+                        }
+                        None => return self.error(offset, "Unclosed '{'"),
+                    }
+                }
+                '}' => {
+                    // "}}" is an escaped literal brace
+                    if let Some((_, '}')) = self.chars.peek() {
+                        self.chars.next();
+                        text.push('}');
+                        continue;
+                    }
+
+                    return self.error(offset, "Unmatched '}' (use '}}' for a literal brace)");
+                }
+                c => text.push(c),
+            }
+        }
+
+        if !text.is_empty() {
+            nodes.push(Node::Text(text));
+        }
+
+        Ok(Template { nodes })
+    }
+
+    fn parse_ident(&mut self) -> (usize, String) {
+        let start = self.offset();
+        let mut ident = String::new();
+
+        while let Some((_, c)) = self.chars.peek() {
+            if c.is_ascii_alphanumeric() || *c == '_' {
+                ident.push(*c);
+                self.chars.next();
+            } else {
+                break;
+            }
+        }
+
+        (start, ident)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some((_, c)) = self.chars.peek() {
+            if !c.is_whitespace() {
+                break;
+            }
+            self.chars.next();
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), ParseError> {
+        self.skip_whitespace();
+
+        let offset = self.offset();
+        match self.chars.next() {
+            Some((_, c)) if c == expected => Ok(()),
+            Some((_, c)) => self.error(offset, format!("Expected '{}', found '{}'", expected, c)),
+            None => self.error(offset, format!("Expected '{}'", expected)),
+        }
+    }
+
+    fn parse_expr(&mut self) -> Result<Expr, ParseError> {
+        self.skip_whitespace();
+        let (start, ident) = self.parse_ident();
+
+        if ident.is_empty() {
+            return match self.chars.peek().copied() {
+                Some((offset, c)) => {
+                    self.error(offset, format!("Expected placeholder, found '{}'", c))
+                }
+                None => self.error(start, "Expected placeholder"),
+            };
+        }
+
+        let expr = match ident.as_str() {
+            "INDEX" => Expr::Field(Field::Index),
+            "TITLE" => Expr::Field(Field::Title),
+            "URL" => Expr::Field(Field::Url),
+            "AUTHOR" => Expr::Field(Field::Author),
+            "P_DATE" => Expr::PublishedDate(self.parse_date_format()?),
+            "V_DATE" => Expr::ViewedDate(self.parse_date_format()?),
+            "PAGES" => match self.chars.peek() {
+                Some((_, '(')) => Expr::Pages(self.parse_raw_argument()?),
+                _ => Expr::Pages(String::new()),
+            },
+            "UPPER" => {
+                self.expect('(')?;
+                let expr = self.parse_expr()?;
+                self.expect(')')?;
+                Expr::Upper(Box::new(expr))
+            }
+            "LOWER" => {
+                self.expect('(')?;
+                let expr = self.parse_expr()?;
+                self.expect(')')?;
+                Expr::Lower(Box::new(expr))
+            }
+            "TRUNC" => {
+                self.expect('(')?;
+                let expr = self.parse_expr()?;
+                self.expect(',')?;
+                let len = self.parse_number()?;
+                self.expect(')')?;
+                Expr::Truncate(Box::new(expr), len)
+            }
+            _ => return self.error(start, format!("Unknown placeholder '{}'", ident)),
+        };
+
+        self.skip_whitespace();
+        Ok(expr)
+    }
+
+    fn parse_number(&mut self) -> Result<usize, ParseError> {
+        self.skip_whitespace();
+
+        let start = self.offset();
+        let mut number = String::new();
+        while let Some((_, c)) = self.chars.peek() {
+            if !c.is_ascii_digit() {
+                break;
+            }
+            number.push(*c);
+            self.chars.next();
+        }
+
+        match number.parse() {
+            Ok(number) => Ok(number),
+            Err(_) => self.error(start, "Expected a number"),
+        }
+    }
+
+    // raw text up to the closing ')', e.g. the "S. " of PAGES(S. )
+    fn parse_raw_argument(&mut self) -> Result<String, ParseError> {
+        self.expect('(')?;
+
+        let start = self.offset();
+        let mut argument = String::new();
+        loop {
+            match self.chars.next() {
+                Some((_, ')')) => return Ok(argument),
+                Some((_, c)) => argument.push(c),
+                None => return self.error(start, "Unclosed '('"),
+            }
+        }
+    }
+
+    // date functions take the raw chrono format, e.g. P_DATE(%d. %m. %Y)
+    fn parse_date_format(&mut self) -> Result<String, ParseError> {
+        let start = self.offset() + 1;
+        let format = self.parse_raw_argument()?;
+
+        if format.is_empty() {
+            return Ok(DEFAULT_DATE_FORMAT.to_string());
+        }
+
+        if StrftimeItems::new(&format).any(|item| item == Item::Error) {
+            return self.error(start, format!("Invalid date format \"{}\"", format));
+        }
+
+        Ok(format)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source() -> Source {
+        Source {
+            id: 7,
+            title: "The Rust Programming Language".to_string(),
+            url: "https://doc.rust-lang.org/book/".to_string(),
+            author: "Steve Klabnik".to_string(),
+            published_date: NaiveDate::from_ymd_opt(2021, 4, 2).unwrap(),
+            viewed_date: NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(),
+            ..Source::default()
+        }
+    }
+
+    fn render(input: &str) -> String {
+        Template::parse(input).unwrap().render(&source(), None)
+    }
+
+    fn error(input: &str) -> String {
+        Template::parse(input).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn renders_fields() {
+        assert_eq!(
+            render("[{INDEX}] {AUTHOR}: {TITLE}, { URL }"),
+            "[7] Steve Klabnik: The Rust Programming Language, https://doc.rust-lang.org/book/"
+        );
+        assert_eq!(render(""), "");
+    }
+
+    #[test]
+    fn escapes_braces() {
+        assert_eq!(render("{{TITLE}}"), "{TITLE}");
+        assert_eq!(render("{{{AUTHOR}}}"), "{Steve Klabnik}");
+        assert_eq!(render("}}{{"), "}{");
+    }
+
+    #[test]
+    fn changes_case() {
+        assert_eq!(render("{UPPER(AUTHOR)}"), "STEVE KLABNIK");
+        assert_eq!(render("{LOWER(AUTHOR)}"), "steve klabnik");
+    }
+
+    #[test]
+    fn truncates() {
+        assert_eq!(render("{TRUNC(TITLE, 8)}"), "The Rust…");
+        assert_eq!(render("{TRUNC(AUTHOR, 13)}"), "Steve Klabnik");
+        assert_eq!(render("{TRUNC(AUTHOR, 0)}"), "…");
+    }
+
+    #[test]
+    fn nests_functions() {
+        assert_eq!(render("{UPPER(TRUNC(TITLE, 8))}"), "THE RUST…");
+        assert_eq!(render("{ LOWER( UPPER( AUTHOR ) ) }"), "steve klabnik");
+        assert_eq!(render("{TRUNC(LOWER(TITLE), 3)}"), "the…");
+    }
+
+    #[test]
+    fn formats_dates() {
+        assert_eq!(render("{P_DATE(%Y)}"), "2021");
+        assert_eq!(render("{P_DATE()}"), "02. 04. 2021");
+        assert_eq!(render("{V_DATE(%d.%m.)}"), "01.03.");
+        assert_eq!(render("{V_DATE()}"), "01. 03. 2024");
+
+        let unknown = Source {
+            published_date_unknown: true,
+            ..source()
+        };
+        let template = Template::parse("{P_DATE(%Y)}").unwrap();
+        assert_eq!(template.render(&unknown, None), "Unknown");
+    }
+
+    #[test]
+    fn writes_pages_if_given() {
+        let template = Template::parse("{TITLE}{PAGES(, S. )}").unwrap();
+        assert_eq!(
+            template.render(&source(), Some("12")),
+            "The Rust Programming Language, S. 12"
+        );
+        assert_eq!(
+            template.render(&source(), Some("")),
+            "The Rust Programming Language"
+        );
+        assert_eq!(
+            template.render(&source(), None),
+            "The Rust Programming Language"
+        );
+    }
+
+    #[test]
+    fn reports_error_positions() {
+        assert_eq!(error("{TITLE"), "Unclosed '{' at position 1");
+        assert_eq!(
+            error("a}b"),
+            "Unmatched '}' (use '}}' for a literal brace) at position 2"
+        );
+        assert_eq!(error("{}"), "Expected placeholder, found '}' at position 2");
+        assert_eq!(
+            error("{TITEL}"),
+            "Unknown placeholder 'TITEL' at position 2"
+        );
+        assert_eq!(
+            error("{UPPER TITLE}"),
+            "Expected '(', found 'T' at position 8"
+        );
+        assert_eq!(
+            error("{UPPER(TITLE}"),
+            "Expected ')', found '}' at position 13"
+        );
+        assert_eq!(
+            error("{TRUNC(TITLE, x)}"),
+            "Expected a number at position 15"
+        );
+        assert_eq!(
+            error("{TITLE TITLE}"),
+            "Expected '}', found 'T' at position 8"
+        );
+        assert_eq!(
+            error("{P_DATE(%Q)}"),
+            "Invalid date format \"%Q\" at position 9"
+        );
+        assert_eq!(error("{P_DATE(%Y"), "Unclosed '(' at position 9");
+    }
+
+    #[test]
+    fn counts_positions_in_characters() {
+        let error = Template::parse("äöü {TITLE} }").unwrap_err();
+        assert_eq!(error.position, 13);
+        assert_eq!(
+            error.message,
+            "Unmatched '}' (use '}}' for a literal brace)"
+        );
+    }
+}
@@ -0,0 +1,271 @@
+use std::fmt::{Display, Formatter};
+
+use reqwest::Url;
+use tracing::*;
+
+// what can be told about a pasted URL without fetching it, shared by everything that takes URLs
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UrlAnalysis {
+    pub web: bool, // http(s) link that can be fetched
+    pub doi: Option<String>,
+    pub video: Option<VideoHost>,
+    pub wikipedia: Option<WikipediaArticle>, // only links to the live article, not to a revision
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WikipediaArticle {
+    pub host: String, // e.g. "de.wikipedia.org"
+    pub title: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VideoHost {
+    YouTube,
+    Vimeo,
+}
+
+impl Display for VideoHost {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VideoHost::YouTube => write!(f, "YouTube"),
+            VideoHost::Vimeo => write!(f, "Vimeo"),
+        }
+    }
+}
+
+pub fn analyze(url: &str) -> UrlAnalysis {
+    let analysis = UrlAnalysis {
+        web: Url::parse(url.trim())
+            .is_ok_and(|url| matches!(url.scheme(), "http" | "https") && url.has_host()),
+        doi: extract_doi(url),
+        video: video_host(url),
+        wikipedia: wikipedia_article(url),
+    };
+    trace!("Analyzed URL {}: {:?}", url, analysis);
+
+    analysis
+}
+
+// finds a DOI in a doi.org link, a publisher URL or plain text, e.g.
+// "https://doi.org/10.1000/182" or "doi:10.1038/nphys1170" -> "10.1000/182", "10.1038/nphys1170"
+pub fn extract_doi(text: &str) -> Option<String> {
+    // doi.org links sometimes encode the slash
+    let text = text.replace("%2F", "/").replace("%2f", "/");
+
+    let mut start = 0;
+    while let Some(offset) = text[start..].find("10.") {
+        let index = start + offset;
+        start = index + 3;
+
+        // "10." has to start the DOI, not be part of a longer number
+        if text[..index]
+            .chars()
+            .next_back()
+            .is_some_and(|c| c.is_ascii_alphanumeric())
+        {
+            continue;
+        }
+
+        // registrant code, e.g. "1000" or "1000.10"
+        let rest = &text[index + 3..];
+        let registrant_len = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(rest.len());
+        let registrant = &rest[..registrant_len];
+        if registrant.chars().take_while(char::is_ascii_digit).count() < 4
+            || !rest[registrant_len..].starts_with('/')
+        {
+            continue;
+        }
+
+        let suffix = &rest[registrant_len + 1..];
+        let suffix_len = suffix
+            .find(|c: char| c.is_whitespace() || matches!(c, '"' | '<' | '>' | '?' | '#'))
+            .unwrap_or(suffix.len());
+        let suffix = trim_suffix(&suffix[..suffix_len]);
+        if suffix.is_empty() {
+            continue;
+        }
+
+        return Some(format!("10.{}/{}", registrant, suffix));
+    }
+
+    None
+}
+
+// drops punctuation that ends the surrounding sentence instead of the DOI
+fn trim_suffix(suffix: &str) -> &str {
+    let mut suffix = suffix.trim_end_matches(['.', ',', ';', ':']);
+
+    // keep balanced parentheses like in "10.1016/0370-2693(95)01162-6"
+    while suffix.ends_with(')') && suffix.matches(')').count() > suffix.matches('(').count() {
+        suffix = suffix[..suffix.len() - 1].trim_end_matches(['.', ',', ';', ':']);
+    }
+
+    suffix
+}
+
+// videos that can be looked up via oEmbed, channel pages and the like are ignored
+fn video_host(url: &str) -> Option<VideoHost> {
+    let url = Url::parse(url.trim()).ok()?;
+    let host = url
+        .host_str()?
+        .trim_start_matches("www.")
+        .trim_start_matches("m.");
+    let path = url.path();
+
+    match host {
+        "youtube.com" | "music.youtube.com" => {
+            let video = (path == "/watch" && url.query_pairs().any(|(key, _)| key == "v"))
+                || path.starts_with("/shorts/")
+                || path.starts_with("/live/");
+            video.then_some(VideoHost::YouTube)
+        }
+        "youtu.be" => (path.len() > 1).then_some(VideoHost::YouTube),
+        "vimeo.com" | "player.vimeo.com" => path
+            .split('/')
+            .any(|segment| !segment.is_empty() && segment.chars().all(|c| c.is_ascii_digit()))
+            .then_some(VideoHost::Vimeo),
+        _ => None,
+    }
+}
+
+fn wikipedia_article(url: &str) -> Option<WikipediaArticle> {
+    let url = Url::parse(url.trim()).ok()?;
+    let host = url.host_str()?;
+    if !host.ends_with(".wikipedia.org") {
+        return None;
+    }
+
+    let title = match url.path().strip_prefix("/wiki/") {
+        Some(title) => percent_decode(title),
+        None if url.path() == "/w/index.php" => url
+            .query_pairs()
+            .find(|(key, _)| key == "title")
+            .map(|(_, title)| title.to_string())?,
+        None => return None,
+    };
+    if title.is_empty() || url.query_pairs().any(|(key, _)| key == "oldid") {
+        return None;
+    }
+
+    Some(WikipediaArticle {
+        host: host.replace(".m.wikipedia.org", ".wikipedia.org"),
+        title: title.replace('_', " "),
+    })
+}
+
+// identifier typed into the lookup box of the start page
+#[derive(Debug, Clone, PartialEq)]
+pub enum Identifier {
+    Doi(String),
+    ArXiv(String), // e.g. "2101.00001v2" or "hep-th/9901001"
+    PubMed(String),
+}
+
+impl Display for Identifier {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Identifier::Doi(doi) => write!(f, "DOI {}", doi),
+            Identifier::ArXiv(id) => write!(f, "arXiv {}", id),
+            Identifier::PubMed(id) => write!(f, "PMID {}", id),
+        }
+    }
+}
+
+// recognizes arXiv IDs, PMIDs and DOIs, with or without prefix or as a link, e.g.
+// "arXiv:2101.00001", "https://arxiv.org/abs/hep-th/9901001", "PMID: 31452104", "31452104"
+pub fn parse_identifier(text: &str) -> Option<Identifier> {
+    let text = text.trim();
+    let lower = text.to_lowercase();
+
+    if let Some(id) = ["arxiv.org/abs/", "arxiv.org/pdf/", "arxiv:"]
+        .iter()
+        .find_map(|prefix| Some(&text[lower.find(prefix)? + prefix.len()..]))
+    {
+        let id = id.trim().trim_end_matches(".pdf").trim_end_matches('/');
+        return is_arxiv_id(id).then(|| Identifier::ArXiv(id.to_string()));
+    }
+
+    if let Some(id) = ["pubmed.ncbi.nlm.nih.gov/", "pmid:", "pmid "]
+        .iter()
+        .find_map(|prefix| Some(&text[lower.find(prefix)? + prefix.len()..]))
+    {
+        let id = id.trim().trim_end_matches('/');
+        return is_pmid(id).then(|| Identifier::PubMed(id.to_string()));
+    }
+
+    if let Some(doi) = extract_doi(text) {
+        return Some(Identifier::Doi(doi));
+    }
+    if is_arxiv_id(text) {
+        return Some(Identifier::ArXiv(text.to_string()));
+    }
+    if is_pmid(text) {
+        return Some(Identifier::PubMed(text.to_string()));
+    }
+
+    None
+}
+
+// new style "YYMM.NNNNN" with optional version, or old style "archive/YYMMNNN"
+fn is_arxiv_id(id: &str) -> bool {
+    let id = match id.rsplit_once('v') {
+        Some((id, version))
+            if !version.is_empty() && version.chars().all(|c| c.is_ascii_digit()) =>
+        {
+            id
+        }
+        _ => id,
+    };
+
+    match id.split_once('/') {
+        Some((archive, number)) => {
+            !archive.is_empty()
+                && archive
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c == '-' || c == '.')
+                && number.len() == 7
+                && number.chars().all(|c| c.is_ascii_digit())
+        }
+        None => match id.split_once('.') {
+            Some((month, number)) => {
+                month.len() == 4
+                    && month.chars().all(|c| c.is_ascii_digit())
+                    && (4..=5).contains(&number.len())
+                    && number.chars().all(|c| c.is_ascii_digit())
+            }
+            None => false,
+        },
+    }
+}
+
+fn is_pmid(id: &str) -> bool {
+    (1..=9).contains(&id.len()) && id.chars().all(|c| c.is_ascii_digit())
+}
+
+// decodes %XX escapes of a URL, invalid ones are kept as they are
+pub fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+
+    let mut index = 0;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'%' if index + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[index + 1..index + 3]).unwrap_or("");
+                match u8::from_str_radix(hex, 16) {
+                    Ok(byte) => {
+                        out.push(byte);
+                        index += 2;
+                    }
+                    Err(_) => out.push(b'%'),
+                }
+            }
+            byte => out.push(byte),
+        }
+        index += 1;
+    }
+
+    String::from_utf8_lossy(&out).to_string()
+}
@@ -1,4 +1,14 @@
-use clap::Parser;
+#[cfg(feature = "cli")]
+use std::path::PathBuf;
+
+#[cfg(feature = "cli")]
+use clap::builder::PossibleValuesParser;
+use clap::{Parser, Subcommand};
+
+#[cfg(feature = "cli")]
+use crate::formats;
+#[cfg(feature = "cli")]
+use crate::import::ImportIds;
 
 #[derive(Parser)]
 #[clap(author, version, about, long_about = None)]
@@ -14,6 +24,116 @@ pub struct CliArgs {
     /// Set logging verbosity level
     #[clap(value_enum, long, default_value_t = VerbosityLevel::INFO)]
     pub verbosity: VerbosityLevel,
+
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Prints the sources matching the query without opening the GUI
+    #[cfg(feature = "cli")]
+    Search {
+        /// Searched in title, author, URL, canonical URL, DOI, tags and collection
+        query: String,
+
+        /// Output format
+        #[clap(value_enum, long, default_value_t = SearchFormat::Text)]
+        format: SearchFormat,
+    },
+
+    /// Adds the sources of a file to the library, skipping sources already in it
+    #[cfg(feature = "cli")]
+    Import {
+        file: PathBuf,
+
+        /// Detected from the content if not given
+        #[clap(long, value_parser = PossibleValuesParser::new(formats::importers().map(|format| format.id())))]
+        format: Option<String>,
+
+        /// Also import sources whose URL or DOI is already in the library
+        #[clap(long, action)]
+        include_duplicates: bool,
+
+        /// What happens to the ids of the file, keeping them keeps citation numbers valid
+        #[clap(value_enum, long, default_value_t = IdsArg::New)]
+        ids: IdsArg,
+    },
+
+    /// Writes all sources to a file or to stdout
+    #[cfg(feature = "cli")]
+    Export {
+        #[clap(long, default_value = "saveit-json", value_parser = PossibleValuesParser::new(formats::exporters().map(|format| format.id())))]
+        format: String,
+
+        /// Printed to stdout if not given
+        #[clap(long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Checks the links of all sources and writes a report with their status, redirect target and
+    /// archived copy
+    #[cfg(feature = "cli")]
+    CheckLinks {
+        /// Taken from the extension of the output file if not given, CSV otherwise
+        #[clap(value_enum, long)]
+        format: Option<ReportFormat>,
+
+        /// Printed to stdout if not given
+        #[clap(long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Serves the library on localhost for external tools without opening the GUI
+    #[cfg(feature = "server")]
+    Serve {
+        /// Port of the local server setting if not given
+        #[clap(long)]
+        port: Option<u16>,
+    },
+}
+
+#[cfg(feature = "cli")]
+#[derive(Clone, Copy, Debug, clap::ValueEnum)]
+pub enum SearchFormat {
+    /// One citation per line, e.g. for wofi, rofi or dmenu
+    Text,
+    /// List of the matching sources
+    Json,
+    /// Alfred script filter items, also understood by Raycast
+    AlfredJson,
+}
+
+#[cfg(feature = "cli")]
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum)]
+pub enum ReportFormat {
+    /// Page with a table, e.g. for printing
+    Html,
+    /// One row per link, e.g. for spreadsheets
+    Csv,
+}
+
+// ImportIds of the core, which doesn't depend on clap
+#[cfg(feature = "cli")]
+#[derive(Clone, Copy, Debug, clap::ValueEnum)]
+pub enum IdsArg {
+    /// Imported sources get new ids
+    New,
+    /// Keep the ids of the file, stop the import if an id is already used
+    Keep,
+    /// Keep the ids of the file, sources whose id is already used get a new one
+    Remap,
+}
+
+#[cfg(feature = "cli")]
+impl From<IdsArg> for ImportIds {
+    fn from(ids: IdsArg) -> Self {
+        match ids {
+            IdsArg::New => ImportIds::New,
+            IdsArg::Keep => ImportIds::Keep,
+            IdsArg::Remap => ImportIds::Remap,
+        }
+    }
 }
 
 #[allow(clippy::upper_case_acronyms)]
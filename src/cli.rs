@@ -0,0 +1,211 @@
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use chrono::Local;
+use serde_json::json;
+use sqlx::SqlitePool;
+use tracing::*;
+
+use crate::args::{ReportFormat, SearchFormat};
+use crate::config::Config;
+use crate::database::{get_all_sources, import_sources, stream_import};
+use crate::formats;
+use crate::hooks::{self, HookEvent};
+use crate::http::{self, HttpSettings};
+use crate::import::{DuplicateIndex, ImportIds, ImportProgress};
+use crate::link_check::{self, CheckProgress};
+
+// prints the matching sources to stdout, e.g. for launchers like Alfred, Raycast or wofi
+pub async fn search(query: &str, format: SearchFormat, pool: &SqlitePool) {
+    debug!("Searching sources for {:?}", query);
+
+    let config = Config::get_config();
+    let sources: Vec<_> = get_all_sources(pool)
+        .await
+        .expect("Error loading sources")
+        .into_iter()
+        .filter(|source| source.contains(query))
+        .collect();
+
+    match format {
+        SearchFormat::Text => {
+            for source in &sources {
+                println!("{}", source.format(&config.format_standard, &config));
+            }
+        }
+        SearchFormat::Json => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&sources).expect("Error converting sources to json")
+            );
+        }
+        SearchFormat::AlfredJson => {
+            // selecting an item passes "arg" on, e.g. to a copy to clipboard action
+            let items: Vec<_> = sources
+                .iter()
+                .map(|source| {
+                    let citation = source.format(&config.format_standard, &config);
+                    let subtitle = match source.author.is_empty() {
+                        true => source.url.clone(),
+                        false => format!("{} · {}", source.author, source.url),
+                    };
+
+                    json!({
+                        "uid": source.id.to_string(),
+                        "title": source.title,
+                        "subtitle": subtitle,
+                        "arg": citation,
+                        "autocomplete": source.title,
+                        "quicklookurl": source.url,
+                        "text": { "copy": citation, "largetype": citation },
+                    })
+                })
+                .collect();
+
+            println!("{}", json!({ "items": items }));
+        }
+    }
+}
+
+pub async fn import(
+    file: &Path,
+    format: Option<&str>,
+    include_duplicates: bool,
+    ids: ImportIds,
+    pool: &SqlitePool,
+) -> Result<(), Box<dyn Error>> {
+    debug!("Importing {}", file.display());
+
+    let format = match format {
+        Some(id) => formats::by_id(id).ok_or("Unknown format")?,
+        None => formats::detect_prefix(file)?.ok_or("Unknown file format, use --format")?,
+    };
+
+    // doesn't hold the whole file in memory
+    if format.can_stream() {
+        let progress = Arc::new(ImportProgress::default());
+        stream_import(
+            file,
+            format,
+            !include_duplicates,
+            ids,
+            progress.clone(),
+            pool,
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+
+        println!(
+            "Imported {} sources from {} ({} duplicates skipped)",
+            progress.imported.load(Ordering::Relaxed),
+            format.name(),
+            progress.duplicates.load(Ordering::Relaxed)
+        );
+        for error in progress.skipped.read().unwrap().iter() {
+            eprintln!("Skipped: {}", error);
+        }
+        return Ok(());
+    }
+
+    let content = fs::read_to_string(file)?;
+    let content = content.trim_start_matches('\u{feff}');
+
+    let mut known = DuplicateIndex::new(&get_all_sources(pool).await?);
+    let mut sources = vec![];
+    let mut duplicates = 0;
+    for source in format.import(content)? {
+        if !include_duplicates && known.contains(&source) {
+            duplicates += 1;
+            continue;
+        }
+        known.insert(&source);
+        sources.push(source);
+    }
+
+    import_sources(&sources, ids, pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    println!(
+        "Imported {} sources from {} ({} duplicates skipped)",
+        sources.len(),
+        format.name(),
+        duplicates
+    );
+
+    Ok(())
+}
+
+pub async fn export(
+    format: &str,
+    output: Option<&Path>,
+    pool: &SqlitePool,
+) -> Result<(), Box<dyn Error>> {
+    let format = formats::by_id(format).ok_or("Unknown format")?;
+    let sources = get_all_sources(pool).await?;
+    let content = format.export(&sources)?;
+
+    match output {
+        Some(path) => {
+            debug!("Exporting {} sources to {}", sources.len(), path.display());
+            fs::write(path, content)?;
+
+            hooks::run_blocking(
+                HookEvent::ExportFinished,
+                hooks::export_payload(path, format, &sources),
+                &Config::get_config(),
+            );
+        }
+        None => print!("{}", content),
+    }
+
+    Ok(())
+}
+
+pub async fn check_links(
+    format: Option<ReportFormat>,
+    output: Option<&Path>,
+    pool: &SqlitePool,
+) -> Result<(), Box<dyn Error>> {
+    let format = format.unwrap_or_else(|| {
+        let html = output
+            .and_then(|path| path.extension())
+            .is_some_and(|extension| {
+                extension.eq_ignore_ascii_case("html") || extension.eq_ignore_ascii_case("htm")
+            });
+        match html {
+            true => ReportFormat::Html,
+            false => ReportFormat::Csv,
+        }
+    });
+
+    http::configure(&HttpSettings::from_config(&Config::get_config()));
+    let sources = get_all_sources(pool).await?;
+    let progress = Arc::new(CheckProgress::default());
+    let results = link_check::check_all(&sources, progress)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let content = match format {
+        ReportFormat::Html => link_check::to_html(&results, Local::now()),
+        ReportFormat::Csv => link_check::to_csv(&results)?,
+    };
+    match output {
+        Some(path) => {
+            debug!("Writing link report to {}", path.display());
+            fs::write(path, content)?;
+        }
+        None => print!("{}", content),
+    }
+
+    // stderr, so it isn't part of a report printed to stdout
+    eprintln!(
+        "Checked {} links, {} broken",
+        results.len(),
+        results.iter().filter(|result| result.is_broken()).count()
+    );
+
+    Ok(())
+}
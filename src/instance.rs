@@ -0,0 +1,140 @@
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use directories::ProjectDirs;
+use egui::{Context, ViewportCommand};
+use tracing::*;
+
+const LOCK_NAME: &str = "saveit.lock";
+const PORT_NAME: &str = "instance.port"; // port of the running instance, read by a second one
+const SHOW_REQUEST: &str = "show";
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(1);
+
+// only one SaveIt at a time writes to the library, a second one asks it to show its window or
+// opens read-only
+
+// held by the instance that owns the library, the OS releases it when the process ends, even
+// after a crash
+pub struct InstanceLock {
+    _file: File,
+}
+
+fn data_dir() -> PathBuf {
+    ProjectDirs::from("com", "tgz39", "saveit")
+        .unwrap()
+        .data_dir()
+        .to_owned()
+}
+
+// None if another instance owns the library
+pub fn acquire() -> Option<InstanceLock> {
+    let path = data_dir().join(LOCK_NAME);
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&path);
+    let file = match file {
+        Ok(file) => file,
+        Err(e) => {
+            // better than not starting at all
+            warn!("Error creating lock file {}: {}", path.display(), e);
+            return None;
+        }
+    };
+
+    match file.try_lock() {
+        Ok(()) => {
+            debug!("Acquired instance lock");
+            Some(InstanceLock { _file: file })
+        }
+        Err(TryLockError::WouldBlock) => {
+            debug!("Another instance holds the lock");
+            None
+        }
+        Err(TryLockError::Error(e)) => {
+            warn!("Error locking {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+// asks the running instance to show its window, false if it doesn't answer
+pub fn show_running() -> bool {
+    let port = match fs::read_to_string(data_dir().join(PORT_NAME)) {
+        Ok(port) => port,
+        Err(e) => {
+            debug!("Error reading the port of the running instance: {}", e);
+            return false;
+        }
+    };
+    let port: u16 = match port.trim().parse() {
+        Ok(port) => port,
+        Err(_) => return false,
+    };
+
+    let address = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+    let result = TcpStream::connect_timeout(&address, CONNECT_TIMEOUT).and_then(|mut stream| {
+        stream.set_read_timeout(Some(CONNECT_TIMEOUT))?;
+        writeln!(stream, "{}", SHOW_REQUEST)?;
+
+        // the answer makes sure it's SaveIt listening on the port
+        let mut answer = String::new();
+        BufReader::new(stream).read_line(&mut answer)?;
+        Ok(answer.trim() == SHOW_REQUEST)
+    });
+
+    match result {
+        Ok(shown) => shown,
+        Err(e) => {
+            debug!("Error contacting the running instance: {}", e);
+            false
+        }
+    }
+}
+
+// brings the window to the front when a second instance is started, runs until the app exits
+pub fn listen(ctx: &Context) {
+    let listener = match TcpListener::bind((Ipv4Addr::LOCALHOST, 0)) {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("Error listening for other instances: {}", e);
+            return;
+        }
+    };
+
+    let port = listener.local_addr().map(|address| address.port());
+    let written = port.and_then(|port| fs::write(data_dir().join(PORT_NAME), port.to_string()));
+    if let Err(e) = written {
+        error!("Error writing the instance port: {}", e);
+        return;
+    }
+
+    let ctx = ctx.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            if let Err(e) = stream.set_read_timeout(Some(CONNECT_TIMEOUT)) {
+                debug!("Error setting the timeout: {}", e);
+                continue;
+            }
+
+            let mut request = String::new();
+            let mut reader = BufReader::new(&stream);
+            if reader.read_line(&mut request).is_err() || request.trim() != SHOW_REQUEST {
+                continue;
+            }
+
+            debug!("Another instance was started, showing the window");
+            ctx.send_viewport_cmd(ViewportCommand::Minimized(false));
+            ctx.send_viewport_cmd(ViewportCommand::Focus);
+            ctx.request_repaint();
+
+            if let Err(e) = writeln!(&stream, "{}", SHOW_REQUEST) {
+                debug!("Error answering the other instance: {}", e);
+            }
+        }
+    });
+}
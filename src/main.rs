@@ -1,22 +1,55 @@
-#![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // hide console window
+#![cfg_attr(
+    all(not(debug_assertions), feature = "gui"),
+    windows_subsystem = "windows"
+)] // hide console window
 #![allow(non_snake_case)]
 
+#[cfg(any(feature = "cli", feature = "server"))]
+use crate::args::Command;
 use crate::args::{CliArgs, VerbosityLevel};
 use crate::config::CONFIG_NAME;
+use crate::crash::install_panic_hook;
 use clap::Parser;
 use directories::ProjectDirs;
+use sqlx::SqlitePool;
 use std::fs;
 use std::sync::Arc;
 use tracing::*;
 
-use crate::database::establish_connection;
+use crate::database::{establish_connection, lazy_connection, MIGRATOR};
+#[cfg(feature = "gui")]
 use crate::ui::open_gui;
+#[cfg(feature = "gui")]
+use crate::update::check_update_start;
+
+// the GUI, CLI and local server on top of saveit-core, each module only where a feature uses it
+#[cfg(feature = "server")]
+use saveit_core::export;
+#[cfg(feature = "gui")]
+use saveit_core::{
+    auto_export, bibliography, bundle, collation, document, history, image, inbox, integrity,
+    locale, metadata, paste, pdf, plugins, queue, quote, reading_plan, references, scheduler,
+    search, snapshot, source_link, source_store, sync, tasks, template,
+};
+use saveit_core::{config, crash, database, db_version};
+#[cfg(any(feature = "gui", feature = "cli"))]
+use saveit_core::{formats, hooks, http, import, link_check};
+#[cfg(any(feature = "gui", feature = "server"))]
+use saveit_core::{source, url_analysis};
 
 mod args;
-mod config;
-mod database;
-mod source;
+#[cfg(feature = "cli")]
+mod cli;
+#[cfg(feature = "gui")]
+mod instance;
+#[cfg(feature = "server")]
+mod rpc;
+#[cfg(feature = "server")]
+mod server;
+#[cfg(feature = "gui")]
 mod ui;
+#[cfg(feature = "gui")]
+mod update;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -27,6 +60,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let subscriber = tracing_subscriber::fmt()
         .with_file(false)
         .with_line_number(false)
+        .with_writer(std::io::stderr) // keeps stdout clean for CLI output
         .with_max_level(match args.verbosity {
             VerbosityLevel::TRACE => Level::TRACE,
             VerbosityLevel::DEBUG => Level::DEBUG,
@@ -38,6 +72,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     subscriber::set_global_default(subscriber).unwrap();
 
+    // write crash reports for panics, including ones in spawned tasks
+    install_panic_hook();
+
     if args.reset_database || args.reset_config {
         if args.reset_database {
             debug!("Deleting DB file");
@@ -65,22 +102,100 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         return Ok(());
     }
 
-    // setup database
-    debug!("Executing database migrations...");
-    let pool = Arc::new(
-        establish_connection()
-            .await
-            .expect("Error connection to database"),
-    );
+    // goes back to the previous version if the last update didn't start
+    #[cfg(feature = "gui")]
+    check_update_start();
 
-    // setup table
-    sqlx::migrate!("./migrations")
-        .run(&*pool)
-        .await
-        .expect("Error executing database migrations");
+    // setup database, the window opens right away and runs the migrations itself
+    let pool = match args.command.is_none() {
+        true => Arc::new(lazy_connection().expect("Error connecting to database")),
+        false => {
+            debug!("Executing database migrations...");
+            let pool = establish_connection()
+                .await
+                .expect("Error connection to database");
+
+            // setup table
+            MIGRATOR
+                .run(&pool)
+                .await
+                .expect("Error executing database migrations");
+            Arc::new(pool)
+        }
+    };
+
+    match args.command {
+        #[cfg(feature = "cli")]
+        Some(Command::Search { query, format }) => {
+            cli::search(&query, format, &pool).await;
+            return Ok(());
+        }
+        #[cfg(feature = "cli")]
+        Some(Command::Import {
+            file,
+            format,
+            include_duplicates,
+            ids,
+        }) => {
+            cli::import(
+                &file,
+                format.as_deref(),
+                include_duplicates,
+                ids.into(),
+                &pool,
+            )
+            .await?;
+            return Ok(());
+        }
+        #[cfg(feature = "cli")]
+        Some(Command::Export { format, output }) => {
+            cli::export(&format, output.as_deref(), &pool).await?;
+            return Ok(());
+        }
+        #[cfg(feature = "cli")]
+        Some(Command::CheckLinks { format, output }) => {
+            cli::check_links(format, output.as_deref(), &pool).await?;
+            return Ok(());
+        }
+        #[cfg(feature = "server")]
+        Some(Command::Serve { port }) => {
+            server::serve(port, pool).await?;
+            return Ok(());
+        }
+        None => {}
+    }
+
+    run_gui(pool)
+}
+
+#[cfg(feature = "gui")]
+fn run_gui(pool: Arc<SqlitePool>) -> Result<(), Box<dyn std::error::Error>> {
+    http::configure(&http::HttpSettings::from_config(
+        &config::Config::get_config(),
+    ));
+
+    // a second window would work with stale copies of the sources
+    let lock = instance::acquire();
+    let read_only = match lock {
+        Some(_) => false,
+        None if instance::show_running() => {
+            info!("SaveIt is already running, showing its window");
+            return Ok(());
+        }
+        None => {
+            warn!("SaveIt is already running but doesn't answer, opening read-only");
+            true
+        }
+    };
 
     // open GUI
-    open_gui(pool.clone()).expect("Error opening GUI");
+    open_gui(pool.clone(), read_only).expect("Error opening GUI");
+    drop(lock);
 
     Ok(())
 }
+
+#[cfg(not(feature = "gui"))]
+fn run_gui(_pool: Arc<SqlitePool>) -> Result<(), Box<dyn std::error::Error>> {
+    Err("SaveIt was built without the window, see --help for the commands".into())
+}
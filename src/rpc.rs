@@ -0,0 +1,201 @@
+use std::sync::RwLock;
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sqlx::SqlitePool;
+use tokio::runtime::Handle;
+use tracing::*;
+
+use crate::config::{Config, FormatStandard};
+use crate::database::get_all_sources;
+use crate::export::{to_apa, to_bibtex, to_csl_json};
+use crate::source::Source;
+
+const DEFAULT_SEARCH_LIMIT: usize = 20;
+
+// JSON-RPC 2.0 error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+
+#[derive(Deserialize)]
+struct RpcRequest {
+    jsonrpc: String,
+    #[serde(default)]
+    id: Value,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Deserialize)]
+struct SearchParams {
+    query: String,
+    limit: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct FormatParams {
+    id: i64,
+    style: Option<String>, // configured format if not given
+    pages: Option<String>,
+}
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+// answers a JSON-RPC request for writing assistants and editors, e.g.
+// {"jsonrpc": "2.0", "id": 1, "method": "search_sources", "params": {"query": "rust"}}
+pub fn handle(body: &str, pool: &SqlitePool, config: &RwLock<Config>, runtime: &Handle) -> Value {
+    let request: RpcRequest = match serde_json::from_str(body) {
+        Ok(request) => request,
+        Err(e) => return error_reply(Value::Null, RpcError::new(PARSE_ERROR, e.to_string())),
+    };
+    if request.jsonrpc != "2.0" {
+        return error_reply(
+            request.id,
+            RpcError::new(INVALID_REQUEST, "Only JSON-RPC 2.0 is supported"),
+        );
+    }
+
+    debug!("RPC call: {}", request.method);
+
+    let result =
+        match request.method.as_str() {
+            "list_methods" => Ok(list_methods()),
+            "search_sources" => params(request.params)
+                .and_then(|params| search_sources(params, pool, config, runtime)),
+            "format_source" => params(request.params)
+                .and_then(|params| format_source(params, pool, config, runtime)),
+            method => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("Unknown method \"{}\"", method),
+            )),
+        };
+
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": request.id, "result": result }),
+        Err(error) => error_reply(request.id, error),
+    }
+}
+
+fn error_reply(id: Value, error: RpcError) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": error.code, "message": error.message },
+    })
+}
+
+fn params<T: for<'de> Deserialize<'de>>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+fn load_sources(pool: &SqlitePool, runtime: &Handle) -> Result<Vec<Source>, RpcError> {
+    runtime.block_on(get_all_sources(pool)).map_err(|e| {
+        error!("Error loading sources for RPC call: {}", e);
+        RpcError::new(INTERNAL_ERROR, "Error loading sources")
+    })
+}
+
+// short description of every method so assistants can discover them
+fn list_methods() -> Value {
+    json!([
+        {
+            "name": "search_sources",
+            "description": "Find sources whose title, author, URL, canonical URL, DOI, tags or collection contain the query",
+            "params": { "query": "string", "limit": "number, optional" },
+        },
+        {
+            "name": "format_source",
+            "description": "Format the source with the given index as a citation",
+            "params": {
+                "id": "number",
+                "style": "default | custom | in_text | apa | bibtex | csl, optional",
+                "pages": "string, optional",
+            },
+        },
+    ])
+}
+
+fn search_sources(
+    params: SearchParams,
+    pool: &SqlitePool,
+    config: &RwLock<Config>,
+    runtime: &Handle,
+) -> Result<Value, RpcError> {
+    let sources = load_sources(pool, runtime)?;
+    let config = config.read().unwrap();
+
+    let results: Vec<Value> = sources
+        .iter()
+        .filter(|source| source.contains(&params.query))
+        .take(params.limit.unwrap_or(DEFAULT_SEARCH_LIMIT))
+        .map(|source| {
+            json!({
+                "id": source.id,
+                "title": source.title,
+                "author": source.author,
+                "url": source.url,
+                "tags": source.tag_list(),
+                "citation": source.format(&config.format_standard, &config),
+            })
+        })
+        .collect();
+
+    Ok(Value::Array(results))
+}
+
+fn format_source(
+    params: FormatParams,
+    pool: &SqlitePool,
+    config: &RwLock<Config>,
+    runtime: &Handle,
+) -> Result<Value, RpcError> {
+    let sources = load_sources(pool, runtime)?;
+    let source = sources
+        .iter()
+        .find(|source| source.id == params.id)
+        .ok_or_else(|| {
+            RpcError::new(
+                INVALID_PARAMS,
+                format!("No source with index {}", params.id),
+            )
+        })?;
+
+    let config = config.read().unwrap();
+    let pages = params.pages.as_deref();
+
+    let citation = match params.style.as_deref() {
+        None => json!(source.format_with_pages(&config.format_standard, &config, pages)),
+        Some("default") => {
+            json!(source.format_with_pages(&FormatStandard::Default, &config, pages))
+        }
+        Some("custom") => json!(source.format_custom(&config.custom_format, pages)),
+        Some("in_text") => json!(source.format_in_text(&config.format_standard, &config, pages)),
+        Some("apa") => json!(to_apa(source)),
+        Some("bibtex") => json!(to_bibtex(std::slice::from_ref(source))),
+        Some("csl") => to_csl_json(std::slice::from_ref(source))[0].clone(),
+        Some(style) => {
+            return Err(RpcError::new(
+                INVALID_PARAMS,
+                format!("Unknown style \"{}\"", style),
+            ))
+        }
+    };
+
+    Ok(json!({ "id": source.id, "citation": citation }))
+}
@@ -0,0 +1,262 @@
+use std::error::Error;
+use std::io::Read;
+use std::sync::{Arc, RwLock};
+use std::thread::JoinHandle;
+
+use sqlx::SqlitePool;
+use tiny_http::{Header, Method, Request, Response, Server};
+use tokio::runtime::Handle;
+use tracing::*;
+
+use crate::config::Config;
+use crate::crash::record_operation;
+use crate::database::get_all_sources;
+use crate::export::{to_bibtex, to_csl_json};
+use crate::rpc;
+use crate::url_analysis::percent_decode;
+
+// largest request body the server reads, bigger ones get a 413
+const MAX_BODY: u64 = 1024 * 1024;
+
+// optional HTTP server on localhost for external tools, stopped when dropped
+pub struct LocalServer {
+    server: Arc<Server>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for LocalServer {
+    fn drop(&mut self) {
+        debug!("Stopping local server");
+
+        self.server.unblock();
+        if let Some(thread) = self.thread.take() {
+            if thread.join().is_err() {
+                warn!("Local server thread panicked");
+            }
+        }
+    }
+}
+
+// needs to be called from within the tokio runtime
+pub fn start(
+    port: u16,
+    pool: Arc<SqlitePool>,
+    config: Arc<RwLock<Config>>,
+) -> Result<LocalServer, Box<dyn Error>> {
+    debug!("Starting local server on port {}", port);
+    record_operation(format!("Start local server on port {}", port));
+
+    // only reachable from this machine
+    let server = Arc::new(Server::http(("127.0.0.1", port)).map_err(|e| e.to_string())?);
+    let runtime = Handle::current();
+
+    let thread_server = server.clone();
+    let thread = std::thread::spawn(move || {
+        for mut request in thread_server.incoming_requests() {
+            trace!("{} {}", request.method(), request.url());
+
+            let response = handle_request(&mut request, port, &pool, &config, &runtime);
+            if let Err(e) = request.respond(response) {
+                warn!("Error responding to request: {}", e);
+            }
+        }
+    });
+
+    Ok(LocalServer {
+        server,
+        thread: Some(thread),
+    })
+}
+
+// runs the local server without the GUI until Ctrl+C is pressed, for `saveit serve`
+pub async fn serve(port: Option<u16>, pool: Arc<SqlitePool>) -> Result<(), Box<dyn Error>> {
+    let config = Config::get_config();
+    let port = port.unwrap_or(config.local_server_port);
+
+    let _server = start(port, pool, Arc::new(RwLock::new(config)))?;
+    println!("Listening on http://127.0.0.1:{}/sources", port);
+
+    tokio::signal::ctrl_c().await?;
+    Ok(())
+}
+
+fn handle_request(
+    request: &mut Request,
+    port: u16,
+    pool: &SqlitePool,
+    config: &RwLock<Config>,
+    runtime: &Handle,
+) -> Response<std::io::Cursor<Vec<u8>>> {
+    // a DNS rebinding page in the browser reaches us with its own host name or origin
+    let header = |name: &'static str| {
+        request
+            .headers()
+            .iter()
+            .find(|header| header.field.equiv(name))
+            .map(|header| header.value.as_str().to_string())
+    };
+    if !is_local_host(header("Host").as_deref(), port) {
+        warn!(
+            "Rejected local server request with host {:?}",
+            header("Host")
+        );
+        return text_response(403, "Forbidden");
+    }
+    if !is_same_origin(header("Origin").as_deref(), port) {
+        warn!(
+            "Rejected local server request from origin {:?}",
+            header("Origin")
+        );
+        return text_response(403, "Forbidden");
+    }
+
+    let url = request.url().to_string();
+    let (path, query) = match url.split_once('?') {
+        Some((path, query)) => (path, query),
+        None => (url.as_str(), ""),
+    };
+
+    match (request.method(), path) {
+        (Method::Get, "/sources") => get_sources(query, pool, runtime),
+        (_, "/sources") => text_response(405, "Method not allowed"),
+        (_, "/rpc") if !config.read().unwrap().assistant_api => text_response(404, "Not found"),
+        (Method::Post, "/rpc") => {
+            let mut body = Vec::new();
+            if let Err(e) = request
+                .as_reader()
+                .take(MAX_BODY + 1)
+                .read_to_end(&mut body)
+            {
+                warn!("Error reading request body: {}", e);
+                return text_response(400, "Invalid request body");
+            }
+            if body.len() as u64 > MAX_BODY {
+                return text_response(413, "Request body too large");
+            }
+            let Ok(body) = String::from_utf8(body) else {
+                return text_response(400, "Invalid request body");
+            };
+
+            let reply = rpc::handle(&body, pool, config, runtime);
+            response(200, reply.to_string(), "application/json; charset=utf-8")
+        }
+        (_, "/rpc") => text_response(405, "Method not allowed"),
+        _ => text_response(404, "Not found"),
+    }
+}
+
+// the Host header has to name this machine and port
+fn is_local_host(host: Option<&str>, port: u16) -> bool {
+    host.is_some_and(|host| {
+        host == format!("127.0.0.1:{}", port) || host == format!("localhost:{}", port)
+    })
+}
+
+// requests without an Origin header don't come from a web page
+fn is_same_origin(origin: Option<&str>, port: u16) -> bool {
+    origin.is_none_or(|origin| {
+        origin == format!("http://127.0.0.1:{}", port)
+            || origin == format!("http://localhost:{}", port)
+    })
+}
+
+// GET /sources?query=…&format=bibtex|json|csl
+fn get_sources(
+    query: &str,
+    pool: &SqlitePool,
+    runtime: &Handle,
+) -> Response<std::io::Cursor<Vec<u8>>> {
+    let parameters = parse_query(query);
+    let parameter = |name: &str| {
+        parameters
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    };
+
+    let sources = match runtime.block_on(get_all_sources(pool)) {
+        Ok(sources) => sources,
+        Err(e) => {
+            error!("Error loading sources for local server: {}", e);
+            return text_response(500, "Error loading sources");
+        }
+    };
+    let sources: Vec<_> = match parameter("query") {
+        Some(search) if !search.is_empty() => sources
+            .into_iter()
+            .filter(|source| source.contains(search))
+            .collect(),
+        _ => sources,
+    };
+
+    match parameter("format").unwrap_or("json") {
+        "json" => response(
+            200,
+            serde_json::to_string_pretty(&sources).expect("Error converting sources to json"),
+            "application/json; charset=utf-8",
+        ),
+        "csl" => response(
+            200,
+            to_csl_json(&sources).to_string(),
+            "application/vnd.citationstyles.csl+json; charset=utf-8",
+        ),
+        "bibtex" => response(
+            200,
+            to_bibtex(&sources),
+            "application/x-bibtex; charset=utf-8",
+        ),
+        format => text_response(400, &format!("Unknown format \"{}\"", format)),
+    }
+}
+
+fn response(status: u16, body: String, content_type: &str) -> Response<std::io::Cursor<Vec<u8>>> {
+    let header = Header::from_bytes("Content-Type", content_type).expect("Error creating header");
+
+    Response::from_string(body)
+        .with_status_code(status)
+        .with_header(header)
+}
+
+fn text_response(status: u16, text: &str) -> Response<std::io::Cursor<Vec<u8>>> {
+    response(status, text.to_string(), "text/plain; charset=utf-8")
+}
+
+// key/value pairs of a URL query, e.g. "query=rust%20book&format=csl"
+fn parse_query(query: &str) -> Vec<(String, String)> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| match pair.split_once('=') {
+            Some((key, value)) => (decode_component(key), decode_component(value)),
+            None => (decode_component(pair), String::new()),
+        })
+        .collect()
+}
+
+// "+" stands for a space in queries
+fn decode_component(text: &str) -> String {
+    percent_decode(&text.replace('+', " "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_only_local_hosts() {
+        assert!(is_local_host(Some("127.0.0.1:8787"), 8787));
+        assert!(is_local_host(Some("localhost:8787"), 8787));
+        assert!(!is_local_host(Some("localhost:8788"), 8787));
+        assert!(!is_local_host(Some("evil.example:8787"), 8787));
+        assert!(!is_local_host(None, 8787));
+    }
+
+    #[test]
+    fn refuses_other_origins() {
+        assert!(is_same_origin(None, 8787));
+        assert!(is_same_origin(Some("http://127.0.0.1:8787"), 8787));
+        assert!(is_same_origin(Some("http://localhost:8787"), 8787));
+        assert!(!is_same_origin(Some("http://evil.example:8787"), 8787));
+        assert!(!is_same_origin(Some("null"), 8787));
+    }
+}
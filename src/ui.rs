@@ -1,42 +1,241 @@
+use std::collections::{BTreeMap, HashSet};
 use std::default::Default;
-use std::fmt::{Display, Formatter};
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
 
 use arboard::Clipboard;
 use chrono::{Local, NaiveDate};
 use eframe::Theme;
 use egui::TextStyle::*;
-use egui::{CentralPanel, Context, FontFamily, FontId};
+use egui::{
+    CentralPanel, ComboBox, Context, FontFamily, FontId, Key, Modifiers, Response, RichText, Ui,
+    WidgetInfo, WidgetType,
+};
+use egui_commonmark::CommonMarkCache;
+use notify::RecommendedWatcher;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
 use sqlx::SqlitePool;
+use tokio::runtime::Handle;
 use tracing::*;
+use uuid::Uuid;
 
-use crate::config::{Config, FormatStandard};
-use crate::database::get_all_sources;
-use crate::source::Source;
+use crate::auto_export::{self, AutoExport};
+use crate::collation::{COLLATION_SETTING, DEFAULT_COLLATION};
+use crate::config::{self, watch_config, Config, FormatStandard};
+use crate::crash::{delete_crash_report, load_crash_report, CrashReport};
+use crate::database::{
+    get_all_cited_in, get_all_documents, get_all_links, get_all_quotes, get_all_snapshots,
+    get_all_sources, get_library_setting, get_shared_sources, MIGRATOR,
+};
+use crate::document::{CitedIn, Document};
+use crate::hooks::{self, HookEvent};
+use crate::http::{self, HttpSettings};
+use crate::inbox::{self, Inbox};
+use crate::instance;
+use crate::locale;
+use crate::metadata::LookupStatus;
+use crate::queue::{self, NetworkQueue};
+use crate::quote::Quote;
+use crate::search::{Filter, SearchMode};
+#[cfg(feature = "server")]
+use crate::server::{self, LocalServer};
+use crate::snapshot::Snapshot;
+use crate::source::{Source, SourceType};
+use crate::source_link::{Relation, SourceLink};
+use crate::source_store::SourceStore;
+use crate::sync::{self, LanSync};
+use crate::tasks::wait_for_pending;
+use crate::update::{self, Download, UpdateStatus};
+
+pub use crate::config::{AppPage, GroupBy, SortBy};
+
+use actions::Action;
+use debug_overlay::timed;
+use handlers::handle_mark_cited;
 
 mod start_page;
 
 mod list_page;
 
+mod timeline_page;
+
+mod graph_page;
+
 mod settings_page;
 
+mod help_page;
+
+mod about_dialog;
+
+mod draft;
+
+mod bulk_edit;
+
+mod find_replace;
+
+mod refetch;
+
+mod import_dialog;
+
+mod file_dialog;
+
+mod actions;
+
+mod commands;
+
+mod command_palette;
+
+mod library_check;
+
+mod link_check;
+
+mod jobs_panel;
+
+mod library_info;
+
+mod layout;
+
+mod history;
+
+mod snapshot;
+
+mod reference_list;
+
+mod bibliography;
+
+mod reading_plan;
+
+mod documents;
+
+mod handlers;
+
+mod debug_overlay;
+
+mod toasts;
+
+#[cfg(all(test, feature = "ui-tests"))]
+mod harness;
+
 const TEXT_INPUT_WIDTH: f32 = 450.0;
+const DRAFT_SAVE_INTERVAL: Duration = Duration::from_secs(5);
+// between separate clips, clipboard histories would miss a clip that is replaced too quickly
+const CLIP_DELAY: Duration = Duration::from_millis(750);
+pub const MIN_UI_SCALE: f32 = 0.5;
+pub const MAX_UI_SCALE: f32 = 3.0;
 
 pub struct Application {
     source_input: SourceInput, // start page
+    saved_draft: SourceInput,  // last start page input written to disk
+    draft_saved_at: Instant,
+    lookup: Arc<RwLock<LookupStatus>>, // metadata lookup of the start page input
+    identifier_input: String,          // arXiv ID, PMID or DOI to look up
+    smart_paste: start_page::SmartPaste,
     curr_page: AppPage,
-    pub sources_cache: Arc<RwLock<Vec<Source>>>,
+    pub sources_cache: Arc<RwLock<SourceStore>>,
+    pub links_cache: Arc<RwLock<Vec<SourceLink>>>,
+    pub quotes_cache: Arc<RwLock<Vec<Quote>>>,
+    pub documents_cache: Arc<RwLock<Vec<Document>>>,
+    pub cited_in_cache: Arc<RwLock<Vec<CitedIn>>>,
+    // without their texts
+    pub snapshots_cache: Arc<RwLock<Vec<Snapshot>>>,
+    pub shared_cache: Arc<RwLock<Vec<Source>>>, // sources of the read-only shared library
+    shared_error: Arc<RwLock<Option<String>>>,  // error loading the shared library
+    library_cache: Arc<RwLock<Vec<Source>>>, // sources of the other libraries, for searching everywhere
+    library_errors: Arc<RwLock<Vec<String>>>, // other libraries that couldn't be loaded
     search_query: String,
-    edit_modal: EditModal, // edit modal
-    settings: Settings,    // settings page
+    search_mode: SearchMode,
+    search_everywhere: bool, // also search the other libraries
+    filters: Vec<Filter>,    // filter chips of the list page
+    group_by: GroupBy,       // list page
+    sort_by: SortBy,
+    collation: Arc<RwLock<String>>, // of the own library, see collation::COLLATIONS
+    timeline_zoom: f32,             // pixels per year
+    graph: graph_page::GraphView,
+    edit_modal: EditModal,  // edit modal
+    page_copy: PageCopy,    // "copy with page" popup
+    selected: HashSet<i64>, // ids of the sources selected on the list page
+    bulk_edit: bulk_edit::BulkEdit,
+    find_replace: find_replace::FindReplace,
+    refetch: refetch::Refetch,
+    import_dialog: import_dialog::ImportDialog,
+    file_error: Option<String>, // last import, export or bundle error
+    file_dialogs: file_dialog::FileDialogs,
+    command_palette: command_palette::CommandPalette, // Ctrl+P
+    undo_stack: commands::UndoStack,                  // Ctrl+Z
+    library_check: library_check::LibraryCheck,
+    link_check: link_check::LinkCheck,
+    library_info: library_info::LibraryInfo,
+    reference_list: reference_list::ReferenceListDialog,
+    bibliography: bibliography::BibliographyDialog,
+    reading_plan: reading_plan::ReadingPlanDialog,
+    documents: documents::Documents,
+    lan_sync: Option<LanSync>, // running while enabled in the settings
+    #[cfg(feature = "server")]
+    local_server: Option<LocalServer>, // running while enabled in the settings
+    auto_export: Option<AutoExport>, // running while enabled with an interval
+    inbox: Option<Inbox>,      // running while enabled in the settings
+    ui_scale: f32,             // zoom factor of the last frame
+    pub queue: NetworkQueue,   // retries failed network actions
+    pub read_only: bool,       // another instance owns the library
+    startup: Arc<RwLock<StartupStatus>>,
+    services_pending: bool, // started by the first frame after the library is loaded
+    settings: Settings,     // settings page
     pub pool: Arc<SqlitePool>,
+    pub config: Arc<RwLock<Config>>, // shared config, written to disk on save and on exit
+    crash_report: Option<CrashReport>, // report from the previous run
+    markdown_cache: CommonMarkCache, // help page
+    about: AboutDialog,              // about dialog
+    jobs_panel_open: bool,           // running and failed network jobs
+    debug_overlay: debug_overlay::DebugOverlay,
+    pub toasts: toasts::Toasts, // errors of writes the list already showed
+    clips_left: Arc<AtomicUsize>, // separate clips that are still to be copied
+    config_changed: Arc<AtomicBool>, // set when the config file was written, also by the app itself
+    _config_watcher: Option<RecommendedWatcher>, // needs to be kept alive
+}
+
+struct AboutDialog {
+    open: bool,
+    update_status: Arc<RwLock<UpdateStatus>>,
+    confirm_install: Option<(String, Download)>, // version and download awaiting confirmation
 }
 
 struct EditModal {
     source: Source,
     open: bool,
+    link_relation: Relation,
+    link_target: Option<i64>,
+    quote: Quote, // quote being added or edited
+    focus: bool,  // the title input gets the keyboard focus when the modal opens
+    tab: EditTab,
+    history: history::History,
+    snapshot: snapshot::SnapshotTab,
+    resolve: Arc<RwLock<LookupStatus>>, // canonical URL of the source, the URL is the metadata
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum EditTab {
+    Details,
+    History,
+    Snapshot,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+enum StartupStatus {
+    Loading,
+    Ready,
+    Failed(String), // the migrations failed, e.g. a library of a newer version
+}
+
+struct PageCopy {
+    source: Option<Source>, // popup is open while set
+    pages: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
 struct SourceInput {
     title: String,
     url: String,
@@ -45,44 +244,265 @@ struct SourceInput {
     published_date_unknown: bool,
     viewed_date: NaiveDate,
     comment: String,
+    #[serde(default)]
+    tags: String,
+    #[serde(default)]
+    collection: String,
+    #[serde(default)]
+    doi: String,
+    #[serde(default)]
+    source_type: SourceType,
+    #[serde(default)]
+    language: String,
 }
 
 struct Settings {
     format_standard: FormatStandard,
     custom_format: String,
+    custom_in_text_format: String,
+    plugin_format: String,
+    collection_formats: BTreeMap<String, FormatStandard>,
+    update_viewed_on_copy: bool,
+    shared_library: String,
+    other_libraries: String, // one path per line
+    lan_sync: bool,
+    sync_key: String,
+    sync_error: Option<String>,
+    local_server: bool,
+    local_server_port: u16,
+    assistant_api: bool,
+    #[cfg(feature = "server")]
+    server_error: Option<String>,
+    metadata_only: bool,
+    resolve_urls: bool,
+    http_timeout: u64,
+    http_proxy: String,
+    http_user_agent: String,
+    offline_mode: bool,
+    auto_export: bool,
+    auto_export_path: String,
+    auto_export_format: String,
+    auto_export_interval: u64,
+    auto_export_on_exit: bool,
+    inbox: bool,
+    inbox_dir: String,
+    hook_source_saved: String,
+    hook_citation_copied: String,
+    hook_export_finished: String,
+    auto_export_error: Option<String>,
+    inbox_error: Option<String>,
+    ui_scale: f32,
+    format_errors: Vec<String>,
+    transfer_error: Option<String>,      // settings import/export
+    imported_hooks: Option<[String; 3]>, // commands of imported settings awaiting confirmation
+    export_hooks: bool,                  // the commands are left out of exported settings otherwise
+}
+
+impl Default for SourceInput {
+    fn default() -> Self {
+        Self {
+            title: String::new(),
+            url: String::new(),
+            author: String::new(),
+            published_date: Local::now().date_naive(),
+            published_date_unknown: false,
+            viewed_date: Local::now().date_naive(),
+            comment: String::new(),
+            tags: String::new(),
+            collection: String::new(),
+            doi: String::new(),
+            source_type: SourceType::Webpage,
+            language: String::new(),
+        }
+    }
+}
+
+impl SourceInput {
+    // nothing worth keeping was typed
+    fn is_empty(&self) -> bool {
+        self.title.is_empty()
+            && self.url.is_empty()
+            && self.author.is_empty()
+            && self.comment.is_empty()
+            && self.tags.is_empty()
+            && self.collection.is_empty()
+            && self.doi.is_empty()
+    }
 }
 
 impl Application {
-    fn new(ctx: &Context, pool: Arc<SqlitePool>) -> Self {
+    fn new(ctx: &Context, pool: Arc<SqlitePool>, read_only: bool) -> Self {
         debug!("Creating new Application");
+        let mut app = Self::build(ctx, pool, read_only, Config::get_config());
+
+        // reload settings when the config file changes
+        app._config_watcher = {
+            let config_changed = app.config_changed.clone();
+            let ctx = ctx.clone();
+
+            watch_config(move || {
+                config_changed.store(true, Ordering::Relaxed);
+                ctx.request_repaint();
+            })
+            .map_err(|e| error!("Error watching config file: {}", e))
+            .ok()
+        };
+
+        // restore the start page input if the app was closed while typing
+        if let Some(source_input) = draft::load() {
+            app.saved_draft = source_input.clone();
+            app.source_input = source_input;
+        }
+        app.crash_report = load_crash_report();
+
+        if !read_only {
+            instance::listen(ctx);
+        }
+        app.services_pending = true;
+
+        app
+    }
+
+    // the window without what reaches outside the library: the config file, the draft, the
+    // crash report, other instances and the network, which the test harness leaves out
+    fn build(ctx: &Context, pool: Arc<SqlitePool>, read_only: bool, config: Config) -> Self {
         // make font bigger
         configure_fonts(ctx);
 
-        let config = Config::get_config();
+        actions::set_theme(config.dark_theme, ctx);
+        ctx.set_zoom_factor(config.ui_scale.clamp(MIN_UI_SCALE, MAX_UI_SCALE));
 
-        Self {
-            source_input: SourceInput {
-                title: String::new(),
-                url: String::new(),
-                author: String::new(),
-                published_date: Local::now().date_naive(),
-                published_date_unknown: false,
-                viewed_date: Local::now().date_naive(),
-                comment: String::new(),
-            },
-            curr_page: AppPage::Start,
-            sources_cache: Arc::new(RwLock::new(vec![])),
-            search_query: String::new(),
+        let app = Self {
+            saved_draft: SourceInput::default(),
+            draft_saved_at: Instant::now(),
+            source_input: SourceInput::default(),
+            curr_page: config.last_page,
+            sources_cache: Arc::new(RwLock::new(SourceStore::default())),
+            links_cache: Arc::new(RwLock::new(vec![])),
+            quotes_cache: Arc::new(RwLock::new(vec![])),
+            documents_cache: Arc::new(RwLock::new(vec![])),
+            cited_in_cache: Arc::new(RwLock::new(vec![])),
+            snapshots_cache: Arc::new(RwLock::new(vec![])),
+            shared_cache: Arc::new(RwLock::new(vec![])),
+            shared_error: Arc::new(RwLock::new(None)),
+            library_cache: Arc::new(RwLock::new(vec![])),
+            library_errors: Arc::new(RwLock::new(vec![])),
+            search_query: config.search_query.clone(),
+            search_mode: config.search_mode,
+            search_everywhere: config.search_everywhere,
+            filters: config.filters.clone(),
+            group_by: config.group_by,
+            sort_by: config.sort_by,
+            collation: Arc::new(RwLock::new(DEFAULT_COLLATION.to_string())),
+            timeline_zoom: 100.0,
+            graph: graph_page::GraphView::new(),
             edit_modal: EditModal {
                 source: Source::default(),
                 open: false,
+                link_relation: Relation::Cites,
+                link_target: None,
+                quote: Quote::default(),
+                focus: false,
+                tab: EditTab::Details,
+                history: history::History::default(),
+                snapshot: snapshot::SnapshotTab::default(),
+                resolve: Arc::new(RwLock::new(LookupStatus::Idle)),
+            },
+            page_copy: PageCopy {
+                source: None,
+                pages: String::new(),
             },
             settings: Settings {
-                custom_format: config.custom_format,
+                custom_format: config.custom_format.clone(),
+                custom_in_text_format: config.custom_in_text_format.clone(),
+                plugin_format: config.plugin_format.clone(),
+                collection_formats: config.collection_formats.clone(),
+                update_viewed_on_copy: config.update_viewed_on_copy,
+                shared_library: config.shared_library.clone(),
+                other_libraries: config.other_libraries.join("\n"),
+                lan_sync: config.lan_sync,
+                sync_key: config.sync_key.clone(),
+                sync_error: None,
+                local_server: config.local_server,
+                local_server_port: config.local_server_port,
+                assistant_api: config.assistant_api,
+                #[cfg(feature = "server")]
+                server_error: None,
+                metadata_only: config.metadata_only,
+                resolve_urls: config.resolve_urls,
+                http_timeout: config.http_timeout,
+                http_proxy: config.http_proxy.clone(),
+                http_user_agent: config.http_user_agent.clone(),
+                offline_mode: config.offline_mode,
+                auto_export: config.auto_export,
+                auto_export_path: config.auto_export_path.clone(),
+                auto_export_format: config.auto_export_format.clone(),
+                auto_export_interval: config.auto_export_interval,
+                auto_export_on_exit: config.auto_export_on_exit,
+                inbox: config.inbox,
+                inbox_dir: config.inbox_dir.clone(),
+                hook_source_saved: config.hook_source_saved.clone(),
+                hook_citation_copied: config.hook_citation_copied.clone(),
+                hook_export_finished: config.hook_export_finished.clone(),
+                ui_scale: config.ui_scale,
+                auto_export_error: None,
+                inbox_error: None,
                 format_standard: config.format_standard,
+                format_errors: vec![],
+                transfer_error: None,
+                imported_hooks: None,
+                export_hooks: false,
             },
+            selected: HashSet::new(),
+            bulk_edit: bulk_edit::BulkEdit::default(),
+            find_replace: find_replace::FindReplace::default(),
+            refetch: refetch::Refetch::default(),
+            import_dialog: import_dialog::ImportDialog::default(),
+            file_error: None,
+            file_dialogs: file_dialog::FileDialogs::default(),
+            command_palette: command_palette::CommandPalette::default(),
+            undo_stack: commands::UndoStack::default(),
+            library_check: library_check::LibraryCheck::default(),
+            link_check: link_check::LinkCheck::default(),
+            library_info: library_info::LibraryInfo::default(),
+            reference_list: reference_list::ReferenceListDialog::default(),
+            bibliography: bibliography::BibliographyDialog::default(),
+            reading_plan: reading_plan::ReadingPlanDialog::default(),
+            documents: documents::Documents::default(),
+            lan_sync: None,
+            #[cfg(feature = "server")]
+            local_server: None,
+            auto_export: None,
+            inbox: None,
+            ui_scale: config.ui_scale.clamp(MIN_UI_SCALE, MAX_UI_SCALE),
+            queue: queue::stopped(),
+            read_only,
+            startup: Arc::new(RwLock::new(StartupStatus::Loading)),
+            services_pending: false,
+            lookup: Arc::new(RwLock::new(LookupStatus::Idle)),
+            identifier_input: String::new(),
+            smart_paste: start_page::SmartPaste::default(),
             pool,
-        }
+            crash_report: None,
+            markdown_cache: CommonMarkCache::default(),
+            about: AboutDialog {
+                open: false,
+                update_status: Arc::new(RwLock::new(UpdateStatus::NotChecked)),
+                confirm_install: None,
+            },
+            jobs_panel_open: false,
+            debug_overlay: debug_overlay::DebugOverlay::default(),
+            toasts: toasts::Toasts::default(),
+            clips_left: Arc::new(AtomicUsize::new(0)),
+            config: Arc::new(RwLock::new(config)),
+            config_changed: Arc::new(AtomicBool::new(false)),
+            _config_watcher: None,
+        };
+
+        app.start_loading(ctx);
+        app.update_library_cache();
+
+        app
     }
 
     // get input source from user
@@ -98,9 +518,54 @@ impl Application {
             viewed_date: self.source_input.viewed_date,
             published_date_unknown: self.source_input.published_date_unknown,
             comment: self.source_input.comment.clone(),
+            last_cited: None,
+            tags: self.source_input.tags.clone(),
+            collection: self.source_input.collection.clone(),
+            doi: self.source_input.doi.clone(),
+            source_type: self.source_input.source_type,
+            uuid: String::new(),
+            updated_at: None,
+            pinned: false,
+            notes_written: false,
+            canonical_url: String::new(),
+            language: self.source_input.language.clone(),
+            shared: false,
+            library: String::new(),
         }
     }
 
+    // fills the start page input with an existing source
+    fn set_input(&mut self, source: &Source) {
+        trace!("Setting user source input to {}", source.id);
+
+        self.source_input = SourceInput {
+            title: source.title.clone(),
+            url: source.url.clone(),
+            author: source.author.clone(),
+            published_date: source.published_date,
+            published_date_unknown: source.published_date_unknown,
+            viewed_date: source.viewed_date,
+            comment: source.comment.clone(),
+            tags: source.tags.clone(),
+            collection: source.collection.clone(),
+            doi: source.doi.clone(),
+            source_type: source.source_type,
+            language: source.language.clone(),
+        };
+    }
+
+    // opens the edit modal of the list page for a source
+    fn open_edit_modal(&mut self, source: &Source) {
+        self.edit_modal.source = source.clone();
+        self.edit_modal.open = true;
+        self.edit_modal.link_target = None;
+        self.edit_modal.quote = Quote::default();
+        self.edit_modal.focus = true;
+        self.edit_modal.tab = EditTab::Details;
+        self.edit_modal.snapshot = snapshot::SnapshotTab::default();
+        self.edit_modal.resolve = Arc::new(RwLock::new(LookupStatus::Idle));
+    }
+
     // clears text fields and reset date to now
     fn clear_input(&mut self) {
         trace!("Clearing user source input");
@@ -112,28 +577,417 @@ impl Application {
         self.source_input.viewed_date = Local::now().date_naive();
         self.source_input.published_date_unknown = false;
         self.source_input.comment.clear();
+        self.source_input.tags.clear();
+        self.source_input.collection.clear();
+        self.source_input.doi.clear();
+        self.source_input.source_type = SourceType::Webpage;
+        self.source_input.language.clear();
+        *self.lookup.write().unwrap() = LookupStatus::Idle;
+    }
+
+    // applies settings from a config file that was changed outside the app
+    fn reload_config(&mut self, ctx: &Context) {
+        // the watcher also fires for the app's own saves, e.g. after zooming
+        if config::is_own_save() {
+            trace!("Ignoring own config save");
+            return;
+        }
+        debug!("Reloading config");
+
+        let config = match Config::try_load() {
+            Ok(config) => config,
+            Err(e) => {
+                warn!("Ignoring invalid config file: {}", e);
+                return;
+            }
+        };
+        self.settings.format_standard = config.format_standard;
+        self.settings.custom_format = config.custom_format.clone();
+        self.settings.custom_in_text_format = config.custom_in_text_format.clone();
+        self.settings.plugin_format = config.plugin_format.clone();
+        self.settings.collection_formats = config.collection_formats.clone();
+        self.settings.update_viewed_on_copy = config.update_viewed_on_copy;
+        self.settings.shared_library = config.shared_library.clone();
+        self.settings.other_libraries = config.other_libraries.join("\n");
+        self.settings.lan_sync = config.lan_sync;
+        self.settings.sync_key = config.sync_key.clone();
+        self.settings.local_server = config.local_server;
+        self.settings.local_server_port = config.local_server_port;
+        self.settings.assistant_api = config.assistant_api;
+        self.settings.metadata_only = config.metadata_only;
+        self.settings.resolve_urls = config.resolve_urls;
+        self.settings.http_timeout = config.http_timeout;
+        self.settings.http_proxy = config.http_proxy.clone();
+        self.settings.http_user_agent = config.http_user_agent.clone();
+        self.settings.offline_mode = config.offline_mode;
+        self.settings.auto_export = config.auto_export;
+        self.settings.auto_export_path = config.auto_export_path.clone();
+        self.settings.auto_export_format = config.auto_export_format.clone();
+        self.settings.auto_export_interval = config.auto_export_interval;
+        self.settings.auto_export_on_exit = config.auto_export_on_exit;
+        self.settings.inbox = config.inbox;
+        self.settings.inbox_dir = config.inbox_dir.clone();
+        self.settings.hook_source_saved = config.hook_source_saved.clone();
+        self.settings.hook_citation_copied = config.hook_citation_copied.clone();
+        self.settings.hook_export_finished = config.hook_export_finished.clone();
+        self.settings.ui_scale = config.ui_scale;
+        self.settings.format_errors.clear();
+
+        // the zoom factor follows config.ui_scale in sync_ui_scale, later in this frame
+        actions::set_theme(config.dark_theme, ctx);
+        http::configure(&HttpSettings::from_config(&config));
+        self.queue.wake();
+        *self.config.write().unwrap() = config;
+        self.update_shared_cache();
+        self.update_library_cache();
+        self.restart_lan_sync();
+        self.restart_local_server();
+        self.restart_auto_export();
+        self.restart_inbox();
+    }
+
+    // keeps the zoom factor and the config in sync, both the settings and Ctrl + Plus / Minus / 0
+    // change it
+    fn sync_ui_scale(&mut self, ctx: &Context) {
+        let zoom = ctx.zoom_factor();
+        let mut config = self.config.write().unwrap();
+
+        // zoomed with the keyboard
+        if zoom != self.ui_scale {
+            config.ui_scale = zoom.clamp(MIN_UI_SCALE, MAX_UI_SCALE);
+            debug!("UI scale changed to {}", config.ui_scale);
+            self.settings.ui_scale = config.ui_scale;
+            config.save();
+        }
+
+        // changed in the settings or the config file
+        let scale = config.ui_scale.clamp(MIN_UI_SCALE, MAX_UI_SCALE);
+        if scale != zoom {
+            ctx.set_zoom_factor(scale);
+        }
+        self.ui_scale = scale;
+    }
+
+    // periodically writes the start page input to disk
+    fn autosave_draft(&mut self, ctx: &Context) {
+        if self.read_only || self.source_input == self.saved_draft {
+            return;
+        }
+
+        if self.draft_saved_at.elapsed() < DRAFT_SAVE_INTERVAL {
+            // make sure the last change gets saved even if nothing else happens
+            ctx.request_repaint_after(DRAFT_SAVE_INTERVAL);
+            return;
+        }
+
+        draft::save(&self.source_input);
+        self.saved_draft = self.source_input.clone();
+        self.draft_saved_at = Instant::now();
     }
 
     fn update_source_cache(&self) {
         trace!("Updating source cache");
 
+        tokio::task::spawn(self.load_source_cache());
+        self.update_shared_cache();
+    }
+
+    // sources of the library and everything shown with them
+    fn load_source_cache(&self) -> impl Future<Output = ()> + Send + 'static {
         let sources = self.sources_cache.clone();
+        let links = self.links_cache.clone();
+        let quotes = self.quotes_cache.clone();
+        let documents = self.documents_cache.clone();
+        let cited_in = self.cited_in_cache.clone();
+        let snapshots = self.snapshots_cache.clone();
+        let collation = self.collation.clone();
         let pool = self.pool.clone();
+        let queries = self.debug_overlay.queries.clone();
+
+        async move {
+            *sources.write().unwrap() = timed("Load sources", &queries, get_all_sources(&pool))
+                .await
+                .expect("Error loading sources")
+                .into();
+            *links.write().unwrap() = timed("Load links", &queries, get_all_links(&pool))
+                .await
+                .expect("Error loading links");
+            *quotes.write().unwrap() = timed("Load quotes", &queries, get_all_quotes(&pool))
+                .await
+                .expect("Error loading quotes");
+            *documents.write().unwrap() =
+                timed("Load documents", &queries, get_all_documents(&pool))
+                    .await
+                    .expect("Error loading documents");
+            *cited_in.write().unwrap() = timed("Load citations", &queries, get_all_cited_in(&pool))
+                .await
+                .expect("Error loading document citations");
+            *snapshots.write().unwrap() =
+                timed("Load snapshots", &queries, get_all_snapshots(&pool))
+                    .await
+                    .expect("Error loading snapshots");
+            *collation.write().unwrap() = get_library_setting(COLLATION_SETTING, &pool)
+                .await
+                .expect("Error loading library settings")
+                .unwrap_or_else(|| DEFAULT_COLLATION.to_string());
+        }
+    }
+
+    // creates the tables of a new library and loads the sources while the window shows a
+    // spinner, so the list isn't empty when it's first opened
+    fn start_loading(&self, ctx: &Context) {
+        debug!("Loading library");
+
+        let load = self.load_source_cache();
+        let status = self.startup.clone();
+        let pool = self.pool.clone();
+        let ctx = ctx.clone();
 
         tokio::task::spawn(async move {
-            *sources.write().unwrap() =
-                get_all_sources(&pool).await.expect("Error loading sources");
+            if let Err(e) = MIGRATOR.run(&*pool).await {
+                error!("Error executing database migrations: {}", e);
+                *status.write().unwrap() = StartupStatus::Failed(e.to_string());
+                ctx.request_repaint();
+                return;
+            }
+
+            load.await;
+            *status.write().unwrap() = StartupStatus::Ready;
+            ctx.request_repaint();
+        });
+
+        self.update_shared_cache();
+    }
+
+    // background services of the library, started once it's loaded
+    fn start_services(&mut self) {
+        debug!("Starting background services");
+
+        // the window is open and the library migrated, so an update that was just installed works
+        update::confirm_update_start();
+
+        if !self.read_only {
+            self.queue = queue::start(self.pool.clone(), self.sources_cache.clone());
+        }
+
+        self.restart_lan_sync();
+        self.restart_local_server();
+        self.restart_auto_export();
+        self.restart_inbox();
+    }
+
+    // stops LAN sync and starts it again if enabled, e.g. after the sync key or offline mode
+    // changed
+    fn restart_lan_sync(&mut self) {
+        self.lan_sync = None;
+        if self.read_only {
+            return;
+        }
+
+        let mut config = self.config.write().unwrap();
+        if !config.lan_sync || config.sync_key.is_empty() || config.offline_mode {
+            return;
+        }
+
+        if config.sync_device_id.is_empty() {
+            config.sync_device_id = Uuid::new_v4().simple().to_string();
+            config.save();
+        }
+
+        match sync::start(
+            config.sync_device_id.clone(),
+            config.sync_key.clone(),
+            self.pool.clone(),
+            self.sources_cache.clone(),
+        ) {
+            Ok(lan_sync) => {
+                self.lan_sync = Some(lan_sync);
+                self.settings.sync_error = None;
+            }
+            Err(e) => {
+                error!("Error starting LAN sync: {}", e);
+                self.settings.sync_error = Some(format!("Error starting LAN sync: {}", e));
+            }
+        }
+    }
+
+    // stops the automatic export and starts it again if enabled, e.g. after the file changed
+    fn restart_auto_export(&mut self) {
+        self.auto_export = None;
+        self.settings.auto_export_error = None;
+        if self.read_only {
+            return;
+        }
+
+        let config = self.config.read().unwrap();
+        match auto_export::target(&config) {
+            Ok(Some((path, format))) if config.auto_export_interval > 0 => {
+                self.auto_export = Some(auto_export::start(
+                    path,
+                    format,
+                    config.auto_export_interval,
+                    self.pool.clone(),
+                ));
+            }
+            Ok(_) => {}
+            Err(e) => {
+                warn!("Not starting automatic export: {}", e);
+                self.settings.auto_export_error = Some(e);
+            }
+        }
+    }
+
+    // stops watching the inbox and starts again if enabled, e.g. after the folder changed
+    fn restart_inbox(&mut self) {
+        self.inbox = None;
+        self.settings.inbox_error = None;
+        if self.read_only {
+            return;
+        }
+
+        let config = self.config.read().unwrap();
+        if !config.inbox {
+            return;
+        }
+        if config.inbox_dir.is_empty() {
+            self.settings.inbox_error = Some("Choose a folder for the inbox".to_string());
+            return;
+        }
+
+        match inbox::start(
+            PathBuf::from(&config.inbox_dir),
+            self.pool.clone(),
+            self.sources_cache.clone(),
+        ) {
+            Ok(inbox) => self.inbox = Some(inbox),
+            Err(e) => {
+                error!("Error watching inbox: {}", e);
+                self.settings.inbox_error = Some(format!("Error watching inbox: {}", e));
+            }
+        }
+    }
+
+    // stops the local server and starts it again if enabled, e.g. after the port changed
+    #[cfg(feature = "server")]
+    fn restart_local_server(&mut self) {
+        // free the port before binding it again
+        self.local_server = None;
+        if self.read_only {
+            return;
+        }
+
+        let config = self.config.read().unwrap();
+        if !config.local_server {
+            return;
+        }
+
+        match server::start(
+            config.local_server_port,
+            self.pool.clone(),
+            self.config.clone(),
+        ) {
+            Ok(local_server) => {
+                self.local_server = Some(local_server);
+                self.settings.server_error = None;
+            }
+            Err(e) => {
+                error!("Error starting local server: {}", e);
+                self.settings.server_error = Some(format!(
+                    "Error starting local server on port {}: {}",
+                    config.local_server_port, e
+                ));
+            }
+        }
+    }
+
+    // built without the local server
+    #[cfg(not(feature = "server"))]
+    fn restart_local_server(&mut self) {}
+
+    // reloads the shared library, errors are shown on the list page instead of panicking
+    fn update_shared_cache(&self) {
+        let path = PathBuf::from(&self.config.read().unwrap().shared_library);
+        let sources = self.shared_cache.clone();
+        let error = self.shared_error.clone();
+
+        if path.as_os_str().is_empty() {
+            sources.write().unwrap().clear();
+            *error.write().unwrap() = None;
+            return;
+        }
+
+        trace!("Updating shared source cache");
+        tokio::task::spawn(async move {
+            match get_shared_sources(&path, "Shared library").await {
+                Ok(shared) => {
+                    *sources.write().unwrap() = shared;
+                    *error.write().unwrap() = None;
+                }
+                Err(e) => {
+                    warn!("Error loading shared library: {}", e);
+                    sources.write().unwrap().clear();
+                    *error.write().unwrap() = Some(format!("Error loading shared library: {}", e));
+                }
+            }
+        });
+    }
+
+    // reloads the other libraries while searching everywhere, they aren't loaded otherwise
+    fn update_library_cache(&self) {
+        let sources = self.library_cache.clone();
+        let errors = self.library_errors.clone();
+        let paths: Vec<PathBuf> = match self.search_everywhere {
+            true => self
+                .config
+                .read()
+                .unwrap()
+                .other_libraries
+                .iter()
+                .map(PathBuf::from)
+                .collect(),
+            false => vec![],
+        };
+
+        if paths.is_empty() {
+            sources.write().unwrap().clear();
+            errors.write().unwrap().clear();
+            return;
+        }
+
+        trace!("Updating library cache");
+        tokio::task::spawn(async move {
+            let mut loaded = vec![];
+            let mut failed = vec![];
+
+            for path in paths {
+                // e.g. "thesis" for thesis.db
+                let library = path
+                    .file_stem()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_else(|| path.to_string_lossy().to_string());
+
+                match get_shared_sources(&path, &library).await {
+                    Ok(shared) => loaded.extend(shared),
+                    Err(e) => {
+                        warn!("Error loading library {}: {}", path.display(), e);
+                        failed.push(format!("Error loading library {}: {}", path.display(), e));
+                    }
+                }
+            }
+
+            *sources.write().unwrap() = loaded;
+            *errors.write().unwrap() = failed;
         });
     }
 }
 
-pub fn open_gui(pool: Arc<SqlitePool>) -> Result<(), eframe::Error> {
+pub fn open_gui(pool: Arc<SqlitePool>, read_only: bool) -> Result<(), eframe::Error> {
     // set up logging
     env_logger::init();
 
     let mut viewport = egui::ViewportBuilder::default()
         .with_inner_size([750.0, 500.0])
-        .with_min_inner_size([715.0, 280.0]);
+        .with_min_inner_size([360.0, 280.0]);
 
     // load icon
     let icon = eframe::icon_data::from_png_bytes(include_bytes!("../assets/icon.png"));
@@ -153,7 +1007,7 @@ pub fn open_gui(pool: Arc<SqlitePool>) -> Result<(), eframe::Error> {
     eframe::run_native(
         format!("SaveIt v{}", env!("CARGO_PKG_VERSION")).as_str(),
         options,
-        Box::new(|cc| Box::new(Application::new(&cc.egui_ctx, pool))),
+        Box::new(move |cc| Box::new(Application::new(&cc.egui_ctx, pool, read_only))),
     )
 }
 
@@ -174,75 +1028,111 @@ fn configure_fonts(ctx: &Context) {
     ctx.set_style(style);
 }
 
+// single line cut off with "…", egui caches the galley while the text and width stay the same
 #[macro_export]
 macro_rules! text_label_wrapped {
     ($text:expr, $ui:expr) => {
-        let mut job = LayoutJob::single_section($text.to_string(), TextFormat::default());
-
-        job.wrap = text::TextWrapping {
-            max_width: 0.0,
-            max_rows: 1,
-            break_anywhere: true,
-            overflow_character: Some('…'),
-        };
-        $ui.label(job);
+        $ui.add(egui::Label::new($text).truncate(true));
     };
 }
 
-#[derive(PartialOrd, PartialEq)]
-enum AppPage {
-    Start,
-    List,
-    Settings,
-}
+impl eframe::App for Application {
+    // flush pending writes and remember the working context for the next start
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        // let pending DB writes and config saves finish first
+        wait_for_pending();
 
-impl Display for AppPage {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match self {
-            AppPage::Start => {
-                write!(f, "Start")
-            }
-            AppPage::List => {
-                write!(f, "List")
-            }
-            AppPage::Settings => {
-                write!(f, "Settings")
+        // the draft, session and export belong to the instance owning the library
+        if self.read_only {
+            return;
+        }
+
+        draft::save(&self.source_input);
+
+        debug!("Saving session state");
+
+        let mut config = self.config.write().unwrap();
+        config.last_page = self.curr_page;
+        config.search_query = self.search_query.clone();
+        config.search_mode = self.search_mode;
+        config.search_everywhere = self.search_everywhere;
+        config.filters = self.filters.clone();
+        config.group_by = self.group_by;
+        config.sort_by = self.sort_by;
+        config.save_blocking();
+
+        if config.auto_export_on_exit {
+            if let Ok(Some((path, format))) = auto_export::target(&config) {
+                let result = tokio::task::block_in_place(|| {
+                    Handle::current().block_on(auto_export::export(&path, format, &self.pool))
+                });
+                if let Err(e) = result {
+                    error!("Error exporting library on exit: {}", e);
+                }
             }
         }
     }
-}
 
-impl eframe::App for Application {
     // runs every frame
     fn update(&mut self, ctx: &Context, _frame: &mut eframe::Frame) {
+        if self.config_changed.swap(false, Ordering::Relaxed) {
+            self.reload_config(ctx);
+        }
+
+        self.autosave_draft(ctx);
+        self.show(ctx);
+    }
+}
+
+impl Application {
+    // the pages and dialogs of a frame
+    fn show(&mut self, ctx: &Context) {
+        let started = Instant::now();
+        self.sync_ui_scale(ctx);
+
+        if !render_startup(self, ctx) {
+            return;
+        }
+        if std::mem::take(&mut self.services_pending) {
+            self.start_services();
+        }
+
+        // the palette is on top of the other dialogs, so it gets Escape first
+        commands::handle_shortcuts(self, ctx);
+        command_palette::render(self, ctx);
+
+        render_crash_report(self, ctx);
+        about_dialog::render(self, ctx);
+        library_check::render(self, ctx);
+        link_check::render(self, ctx);
+        jobs_panel::render(self, ctx);
+        file_dialog::render(self, ctx);
+
         CentralPanel::default().show(ctx, |ui| {
+            if self.read_only {
+                render_read_only_warning(self, ui);
+            }
+
             // Page selection
             ui.horizontal(|ui| {
-                // Start page
-                ui.selectable_value(
-                    &mut self.curr_page,
-                    AppPage::Start,
-                    AppPage::Start.to_string(),
-                );
-
-                // List page
-                let list_page = ui.selectable_value(
-                    &mut self.curr_page,
-                    AppPage::List,
-                    AppPage::List.to_string(),
-                );
-
-                if list_page.clicked() {
-                    // update source cache
-                    self.update_source_cache();
+                for (page, _) in actions::PAGES {
+                    if ui
+                        .selectable_label(self.curr_page == page, page.to_string())
+                        .clicked()
+                    {
+                        commands::run(Action::ShowPage(page), self, ctx);
+                    }
                 }
 
-                // Settings page
-                ui.selectable_value(
-                    &mut self.curr_page,
-                    AppPage::Settings,
-                    AppPage::Settings.to_string(),
-                );
+                // About dialog
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if ui.button("About").clicked() {
+                        commands::run(Action::About, self, ctx);
+                    }
+
+                    render_queue_status(self, ui);
+                    jobs_panel::render_status(self, ui);
+                });
             });
 
             ui.separator();
@@ -251,31 +1141,304 @@ impl eframe::App for Application {
             match self.curr_page {
                 AppPage::Start => start_page::render(self, ui),
                 AppPage::List => list_page::render(self, ui, ctx),
+                AppPage::Timeline => timeline_page::render(self, ui),
+                AppPage::Graph => graph_page::render(self, ui),
                 AppPage::Settings => settings_page::render(self, ui),
+                AppPage::Help => help_page::render(self, ui),
             }
         });
+
+        toasts::render(self, ctx);
+        debug_overlay::render(self, ctx);
+        self.debug_overlay.record_frame(started.elapsed());
+    }
+}
+
+// name read by screen readers instead of the button text, for buttons like "Copy" that are
+// repeated for every source
+pub fn accessible_name(response: Response, name: impl Into<String>) -> Response {
+    let name = name.into();
+    response.widget_info(|| WidgetInfo::labeled(WidgetType::Button, &name));
+    response
+}
+
+// closes the dialog like its close button, the first dialog asking for it gets the key press
+pub fn escape_pressed(ctx: &Context) -> bool {
+    ctx.input_mut(|i| i.consume_key(Modifiers::NONE, Key::Escape))
+}
+
+// text cut to max_chars characters with "…" appended if it was longer
+pub fn truncated(text: &str, max_chars: usize) -> String {
+    match text.chars().count() > max_chars {
+        true => {
+            let mut out: String = text.chars().take(max_chars).collect();
+            out.push('…');
+            out
+        }
+        false => text.to_string(),
+    }
+}
+
+// language of a source, the first entry uses the terms of the format
+pub fn language_combo(id: &str, language: &mut String, ui: &mut Ui) -> Response {
+    let selected = match language.is_empty() {
+        true => "Format default",
+        false => locale::name(language),
+    };
+
+    ComboBox::from_id_source(id)
+        .selected_text(selected.to_string())
+        .show_ui(ui, |ui| {
+            ui.selectable_value(language, String::new(), "Format default");
+            for (code, name) in locale::LANGUAGES {
+                ui.selectable_value(language, code.to_string(), name);
+            }
+        })
+        .response
+}
+
+// the other instance keeps changing the library, so the caches can be reloaded
+fn render_read_only_warning(app: &Application, ui: &mut Ui) {
+    ui.horizontal_wrapped(|ui| {
+        ui.colored_label(
+            ui.visuals().warn_fg_color,
+            "SaveIt is already running, changes made in this window aren't saved",
+        );
+        if ui.button("Reload sources").clicked() {
+            trace!("Reload sources clicked");
+            app.update_source_cache();
+        }
+    });
+    ui.separator();
+}
+
+// number of queued network actions next to the About button, hidden while the queue is empty
+fn render_queue_status(app: &Application, ui: &mut Ui) {
+    let status = app.queue.status.read().unwrap();
+    if status.jobs.is_empty() {
+        return;
+    }
+
+    let text = match status.offline {
+        true => format!("{} queued (offline)", status.jobs.len()),
+        false => format!("{} queued", status.jobs.len()),
+    };
+    let details = status
+        .jobs
+        .iter()
+        .map(|job| {
+            let error = match job.last_error.is_empty() {
+                true => String::new(),
+                false => format!(": {}", job.last_error),
+            };
+            format!(
+                "{} for source {} ({} attempts){}",
+                job.kind, job.source_id, job.attempts, error
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    ui.label(RichText::new(text).weak()).on_hover_text(format!(
+        "Network actions that are retried when the connection is back:\n{}",
+        details
+    ));
+}
+
+// spinner while the library is opened, false until it's loaded
+fn render_startup(app: &Application, ctx: &Context) -> bool {
+    let status = app.startup.read().unwrap().clone();
+    if status == StartupStatus::Ready {
+        return true;
+    }
+
+    CentralPanel::default().show(ctx, |ui| {
+        ui.centered_and_justified(|ui| match status {
+            StartupStatus::Failed(e) => {
+                ui.colored_label(
+                    ui.visuals().error_fg_color,
+                    format!("Error opening the library: {}", e),
+                );
+            }
+            _ => {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label("Opening the library…");
+                });
+            }
+        });
+    });
+
+    false
+}
+
+// dialog offering the crash report of the previous run
+fn render_crash_report(app: &mut Application, ctx: &Context) {
+    let report = match &app.crash_report {
+        None => return,
+        Some(report) => report,
+    };
+
+    let mut open = true;
+    let mut dismissed = false;
+
+    egui::Window::new("SaveIt crashed")
+        .collapsible(false)
+        .resizable(true)
+        .open(&mut open)
+        .show(ctx, |ui| {
+            ui.label("SaveIt crashed the last time it was running. Please consider reporting this on GitHub with the crash report below.");
+            ui.add_space(5.0);
+
+            egui::ScrollArea::vertical()
+                .max_height(250.0)
+                .show(ui, |ui| {
+                    ui.monospace(&report.content);
+                });
+
+            ui.add_space(5.0);
+
+            ui.horizontal(|ui| {
+                if ui.button("Copy report").clicked() {
+                    trace!("Copy crash report clicked");
+                    let mut clipboard = Clipboard::new().unwrap();
+                    clipboard.set_text(report.content.clone()).unwrap();
+                }
+
+                if ui.button("Open report").clicked() {
+                    trace!("Open crash report clicked");
+                    if let Err(e) = open::that(&report.path) {
+                        error!("Error opening crash report: {}", e);
+                    }
+                }
+
+                if ui.button("Dismiss").clicked() {
+                    trace!("Dismiss crash report clicked");
+                    dismissed = true;
+                }
+            });
+        });
+
+    if dismissed {
+        delete_crash_report();
+    }
+    if !open || dismissed {
+        app.crash_report = None;
+    }
+}
+
+// source as it will be after copying, with today as viewed date if enabled in the settings
+pub fn prepare_copy(source: &Source, app: &Application) -> Source {
+    let mut source = source.clone();
+
+    if app.config.read().unwrap().update_viewed_on_copy {
+        source.viewed_date = Local::now().date_naive();
+    }
+
+    source
+}
+
+// sets the clipboard and marks the copied sources as cited if enabled in the settings
+pub fn copy_citation(text: String, ids: &[i64], app: &Application) {
+    debug!("Setting clipboard: {}", &text);
+
+    let mut clipboard = Clipboard::new().unwrap();
+    clipboard.set_text(text.clone()).unwrap();
+
+    let copied: HashSet<i64> = ids.iter().copied().collect();
+    let sources: Vec<Source> = app
+        .sources_cache
+        .read()
+        .unwrap()
+        .iter()
+        .filter(|source| copied.contains(&source.id()))
+        .map(|source| source.to_source())
+        .collect();
+    hooks::run(
+        HookEvent::CitationCopied,
+        json!({ "citation": text, "sources": sources }),
+        &app.config.read().unwrap(),
+    );
+
+    if app.config.read().unwrap().update_viewed_on_copy {
+        handle_mark_cited(ids.to_vec(), app);
     }
 }
 
 pub fn set_clipboard(source: &Source, app: &Application) {
     debug!("Setting clipboard: {:?}", source);
 
-    let mut clipboard = Clipboard::new().unwrap();
-    let text = source.format(&app.settings.format_standard);
+    let source = prepare_copy(source, app);
+    let text = source.format(&app.settings.format_standard, &app.config.read().unwrap());
 
-    clipboard.set_text(text).unwrap();
+    copy_citation(text, source.library_id().as_slice(), app);
 }
 
 pub fn set_all_clipboard(sources: &[Source], app: &Application) {
     debug!("Setting clipboard with all sources");
 
-    let mut clipboard = Clipboard::new().unwrap();
-    let mut text = "".to_string();
+    let text = all_citations(sources, app);
+    let ids: Vec<i64> = sources.iter().filter_map(Source::library_id).collect();
+    copy_citation(text, &ids, app);
+}
+
+// one citation per line, the text "Copy all" copies
+pub fn all_citations(sources: &[Source], app: &Application) -> String {
+    let sources: Vec<Source> = sources
+        .iter()
+        .map(|source| prepare_copy(source, app))
+        .collect();
+    let config = app.config.read().unwrap();
+    let mut text = String::new();
 
-    for source in sources {
-        text.push_str(source.format(&app.settings.format_standard).as_str());
+    for source in &sources {
+        text.push_str(&source.format(&app.settings.format_standard, &config));
         text.push('\n');
     }
 
-    clipboard.set_text(text).unwrap();
+    text
+}
+
+// copies every citation as a clipboard entry of its own, one after another, so the clipboard
+// history (Win + V on Windows, a clipboard manager elsewhere) has them to paste one at a time
+pub fn set_separate_clipboard(sources: &[Source], app: &Application) {
+    debug!("Setting {} separate clips", sources.len());
+
+    let sources: Vec<Source> = sources
+        .iter()
+        .map(|source| prepare_copy(source, app))
+        .collect();
+    let config = app.config.read().unwrap().clone();
+    let clips: Vec<String> = sources
+        .iter()
+        .map(|source| source.format(&app.settings.format_standard, &config))
+        .collect();
+    let clips_left = app.clips_left.clone();
+    clips_left.store(clips.len(), Ordering::Relaxed);
+
+    let ids: Vec<i64> = sources.iter().filter_map(Source::library_id).collect();
+    if config.update_viewed_on_copy {
+        handle_mark_cited(ids, app);
+    }
+
+    tokio::task::spawn(async move {
+        for (index, (clip, source)) in clips.into_iter().zip(sources).enumerate() {
+            if index > 0 {
+                tokio::time::sleep(CLIP_DELAY).await;
+            }
+            debug!("Setting clipboard: {}", &clip);
+
+            let copied =
+                Clipboard::new().and_then(|mut clipboard| clipboard.set_text(clip.clone()));
+            if let Err(e) = copied {
+                error!("Error copying clip {}: {}", index + 1, e);
+            }
+            hooks::run(
+                HookEvent::CitationCopied,
+                json!({ "citation": clip, "sources": [source] }),
+                &config,
+            );
+            clips_left.fetch_sub(1, Ordering::Relaxed);
+        }
+    });
 }
@@ -0,0 +1,201 @@
+use egui::{Context, Grid};
+use tracing::*;
+
+use crate::ui::{escape_pressed, Application};
+use crate::update::{self, check_for_update, install_update, Download, UpdateStatus};
+
+pub fn render(app: &mut Application, ctx: &Context) {
+    let mut open = app.about.open;
+
+    egui::Window::new("About SaveIt")
+        .collapsible(false)
+        .resizable(false)
+        .open(&mut open)
+        .show(ctx, |ui| {
+            Grid::new("AboutInfo").num_columns(2).show(ui, |ui| {
+                ui.label("Version:");
+                ui.label(env!("CARGO_PKG_VERSION"));
+                ui.end_row();
+
+                ui.label("Build:");
+                ui.label(format!(
+                    "{} {} ({})",
+                    std::env::consts::OS,
+                    std::env::consts::ARCH,
+                    if cfg!(debug_assertions) {
+                        "debug"
+                    } else {
+                        "release"
+                    }
+                ));
+                ui.end_row();
+
+                ui.label("Authors:");
+                ui.label(env!("CARGO_PKG_AUTHORS"));
+                ui.end_row();
+
+                ui.label("License:");
+                ui.label("GNU General Public License v2.0");
+                ui.end_row();
+
+                ui.label("Source code:");
+                ui.hyperlink("https://github.com/TgZ39/SaveIt");
+                ui.end_row();
+            });
+
+            ui.add_space(5.0);
+            ui.separator();
+            ui.add_space(5.0);
+
+            let status = app.about.update_status.read().unwrap().clone();
+            // the version replaced by the last update
+            let restorable = !matches!(
+                status,
+                UpdateStatus::Installing | UpdateStatus::Installed(_) | UpdateStatus::Restored
+            );
+
+            ui.horizontal(|ui| {
+                let checking = matches!(status, UpdateStatus::Checking | UpdateStatus::Installing);
+
+                if ui
+                    .add_enabled(!checking, egui::Button::new("Check for updates"))
+                    .clicked()
+                {
+                    trace!("Check for updates clicked");
+                    handle_update_check(app);
+                }
+
+                match status {
+                    UpdateStatus::NotChecked => {}
+                    UpdateStatus::Checking => {
+                        ui.spinner();
+                    }
+                    UpdateStatus::UpToDate => {
+                        ui.label("SaveIt is up to date");
+                    }
+                    UpdateStatus::Available {
+                        version,
+                        url,
+                        download,
+                    } => {
+                        ui.label(format!("Version {} is available:", version));
+                        ui.hyperlink_to("Download", url);
+
+                        if let Some(download) = download {
+                            if ui.button("Install").clicked() {
+                                trace!("Install update clicked");
+                                app.about.confirm_install = Some((version, download));
+                            }
+                        }
+                    }
+                    UpdateStatus::Installing => {
+                        ui.spinner();
+                        ui.label("Installing update...");
+                    }
+                    UpdateStatus::Installed(version) => {
+                        ui.label(format!(
+                            "Version {} was installed, restart SaveIt to use it",
+                            version
+                        ));
+                    }
+                    UpdateStatus::InstallFailed(e) => {
+                        ui.colored_label(
+                            ui.visuals().error_fg_color,
+                            format!("Error installing update: {}", e),
+                        );
+                    }
+                    UpdateStatus::Failed(e) => {
+                        ui.colored_label(
+                            ui.visuals().error_fg_color,
+                            format!("Error checking for updates: {}", e),
+                        );
+                    }
+                    UpdateStatus::Restored => {
+                        ui.label("The previous version was restored, restart SaveIt to use it");
+                    }
+                    UpdateStatus::RestoreFailed(e) => {
+                        ui.colored_label(
+                            ui.visuals().error_fg_color,
+                            format!("Error restoring the previous version: {}", e),
+                        );
+                    }
+                }
+            });
+
+            if restorable && update::has_backup() && ui.button("Restore previous version").clicked()
+            {
+                trace!("Restore previous version clicked");
+                *app.about.update_status.write().unwrap() = match update::restore_backup() {
+                    Ok(()) => UpdateStatus::Restored,
+                    Err(e) => {
+                        error!("Error restoring previous version: {}", e);
+                        UpdateStatus::RestoreFailed(e.to_string())
+                    }
+                };
+            }
+        });
+
+    if open && escape_pressed(ctx) {
+        open = false;
+    }
+    app.about.open = open;
+
+    render_install_confirmation(app, ctx);
+}
+
+fn render_install_confirmation(app: &mut Application, ctx: &Context) {
+    let (version, download) = match &app.about.confirm_install {
+        None => return,
+        Some(update) => update.clone(),
+    };
+
+    let mut close = false;
+
+    egui::Window::new("Install update")
+        .collapsible(false)
+        .resizable(false)
+        .show(ctx, |ui| {
+            ui.label(format!(
+                "SaveIt will download version {} and replace the current program. Your sources and settings are kept.",
+                version
+            ));
+            ui.add_space(5.0);
+
+            ui.horizontal(|ui| {
+                if ui.button("Install").clicked() {
+                    trace!("Install update confirmed");
+                    handle_install_update(app, version.clone(), download.clone());
+                    close = true;
+                }
+                if ui.button("Cancel").clicked() {
+                    close = true;
+                }
+            });
+        });
+
+    if close {
+        app.about.confirm_install = None;
+    }
+}
+
+// async update install
+fn handle_install_update(app: &Application, version: String, download: Download) {
+    let status = app.about.update_status.clone();
+    *status.write().unwrap() = UpdateStatus::Installing;
+
+    tokio::task::spawn(async move {
+        let result = install_update(version, download).await;
+        *status.write().unwrap() = result;
+    });
+}
+
+// async update check
+fn handle_update_check(app: &Application) {
+    let status = app.about.update_status.clone();
+    *status.write().unwrap() = UpdateStatus::Checking;
+
+    tokio::task::spawn(async move {
+        let result = check_for_update().await;
+        *status.write().unwrap() = result;
+    });
+}
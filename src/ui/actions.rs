@@ -0,0 +1,274 @@
+use egui::gui_zoom::{self, kb_shortcuts};
+use egui::{Context, Key, KeyboardShortcut, Modifiers, Visuals};
+use tracing::*;
+
+use crate::crash::record_operation;
+use crate::formats::{self, Format};
+use crate::ui::commands::{self, Command};
+use crate::ui::file_dialog::{self, FilePurpose, FileRequest};
+use crate::ui::handlers::handle_recheck_snapshots;
+use crate::ui::{
+    import_dialog, library_check, link_check, set_all_clipboard, set_clipboard, truncated, AppPage,
+    Application,
+};
+
+// pages in the order of the page selection and their shortcuts
+pub const PAGES: [(AppPage, Key); 6] = [
+    (AppPage::Start, Key::Num1),
+    (AppPage::List, Key::Num2),
+    (AppPage::Timeline, Key::Num5),
+    (AppPage::Graph, Key::Num6),
+    (AppPage::Settings, Key::Num3),
+    (AppPage::Help, Key::Num4),
+];
+
+// commands that can't be undone, listed in the command palette
+#[derive(Clone, Copy)]
+pub enum Action {
+    ShowPage(AppPage),
+    CopyAll,
+    CopySource(i64), // by index
+    Import,
+    Export(&'static dyn Format),
+    ReferenceList,
+    Bibliography,
+    ReadingPlan,
+    Documents,
+    ImportBundle,
+    FindReplace,
+    CheckLibrary,
+    CheckLinks,
+    RecheckSnapshots,
+    NetworkJobs,
+    DebugOverlay, // hidden, only the shortcut
+    ToggleTheme,
+    ZoomIn,
+    ZoomOut,
+    ResetZoom,
+    About,
+    CommandPalette,
+    Undo,
+}
+
+impl Command for Action {
+    fn name(&self, app: &Application) -> String {
+        match self {
+            Action::ShowPage(page) => match page {
+                AppPage::Start => "Open the start page",
+                AppPage::List => "Open the source list",
+                AppPage::Timeline => "Open the timeline",
+                AppPage::Graph => "Open the graph",
+                AppPage::Settings => "Open the settings",
+                AppPage::Help => "Open the help page",
+            }
+            .to_string(),
+            Action::CopyAll => "Copy all sources".to_string(),
+            Action::CopySource(id) => {
+                let sources = app.sources_cache.read().unwrap();
+                match sources.view(*id) {
+                    Some(source) => format!("Copy \"{}\"", truncated(source.title(), 60)),
+                    None => format!("Copy source {}", id),
+                }
+            }
+            Action::Import => "Import sources".to_string(),
+            Action::Export(format) => format!("Export as {}", format.name()),
+            Action::ReferenceList => "Numbered references of a manuscript".to_string(),
+            Action::Bibliography => "Alphabetical bibliography of the listed sources".to_string(),
+            Action::ReadingPlan => "Reading plan for the sources without notes".to_string(),
+            Action::Documents => "Documents".to_string(),
+            Action::ImportBundle => "Import bundle".to_string(),
+            Action::FindReplace => "Find & replace".to_string(),
+            Action::CheckLibrary => "Check library".to_string(),
+            Action::CheckLinks => "Check links of all sources".to_string(),
+            Action::RecheckSnapshots => "Re-check the pages of all snapshots".to_string(),
+            Action::NetworkJobs => "Network jobs".to_string(),
+            Action::DebugOverlay => "Toggle the debug overlay".to_string(),
+            Action::ToggleTheme => "Toggle dark/light theme".to_string(),
+            Action::ZoomIn => "Zoom in".to_string(),
+            Action::ZoomOut => "Zoom out".to_string(),
+            Action::ResetZoom => "Reset zoom".to_string(),
+            Action::About => "About SaveIt".to_string(),
+            Action::CommandPalette => "Command palette".to_string(),
+            Action::Undo => match app.undo_stack.last() {
+                Some(command) => format!("Undo {}", command.name(app).to_lowercase()),
+                None => "Undo".to_string(),
+            },
+        }
+    }
+
+    fn shortcut(&self) -> Option<KeyboardShortcut> {
+        match self {
+            Action::ShowPage(page) => PAGES
+                .iter()
+                .find(|(other, _)| other == page)
+                .map(|(_, key)| KeyboardShortcut::new(Modifiers::COMMAND, *key)),
+            Action::CommandPalette => Some(KeyboardShortcut::new(Modifiers::COMMAND, Key::P)),
+            Action::Undo => Some(KeyboardShortcut::new(Modifiers::COMMAND, Key::Z)),
+            Action::DebugOverlay => Some(KeyboardShortcut::new(
+                Modifiers::COMMAND | Modifiers::SHIFT,
+                Key::D,
+            )),
+            // handled by egui itself
+            Action::ZoomIn => Some(kb_shortcuts::ZOOM_IN),
+            Action::ZoomOut => Some(kb_shortcuts::ZOOM_OUT),
+            Action::ResetZoom => Some(kb_shortcuts::ZOOM_RESET),
+            _ => None,
+        }
+    }
+
+    fn execute(&mut self, app: &mut Application, ctx: &Context) {
+        match *self {
+            Action::ShowPage(page) => {
+                trace!("Show page {}", page);
+                // these pages show the sources
+                if matches!(page, AppPage::List | AppPage::Timeline | AppPage::Graph) {
+                    app.update_source_cache();
+                }
+                app.curr_page = page;
+            }
+            Action::CopyAll => {
+                trace!("Copy all");
+                let sources = app.sources_cache.read().unwrap().to_vec();
+                set_all_clipboard(&sources, app);
+            }
+            Action::CopySource(id) => {
+                trace!("Copy source {}", id);
+                let source = app.sources_cache.read().unwrap().get(id);
+                if let Some(source) = source {
+                    set_clipboard(&source, app);
+                }
+            }
+            Action::Import => import_dialog::pick_file(app, ctx),
+            Action::Export(format) => {
+                record_operation(format!("Export sources as {}", format.id()));
+                let request = FileRequest::save(
+                    FilePurpose::ExportSources(format),
+                    "Select file",
+                    format!("export.{}", format.extensions()[0]),
+                )
+                .add_filter(format.name(), format.extensions());
+                file_dialog::show(request, app, ctx);
+            }
+            Action::ReferenceList => {
+                trace!("Numbered references");
+                app.update_source_cache();
+                app.reference_list.open = true;
+            }
+            Action::Bibliography => {
+                trace!("Alphabetical bibliography");
+                app.update_source_cache();
+                app.bibliography.open = true;
+                app.curr_page = AppPage::List;
+            }
+            Action::ReadingPlan => {
+                trace!("Reading plan");
+                app.update_source_cache();
+                app.reading_plan.open = true;
+                app.curr_page = AppPage::List;
+            }
+            Action::Documents => {
+                trace!("Documents");
+                app.update_source_cache();
+                app.documents.open = true;
+            }
+            Action::ImportBundle => {
+                record_operation("Import bundle");
+                let request = FileRequest::open(FilePurpose::ImportBundle, "Select File")
+                    .add_filter("SaveIt bundle", &["zip"]);
+                file_dialog::show(request, app, ctx);
+            }
+            Action::FindReplace => {
+                trace!("Find & replace");
+                app.find_replace.open = true;
+            }
+            Action::CheckLibrary => {
+                trace!("Check library");
+                library_check::start(app);
+            }
+            Action::CheckLinks => {
+                trace!("Check links");
+                app.update_source_cache();
+                link_check::start(app);
+            }
+            Action::RecheckSnapshots => {
+                trace!("Re-check snapshots");
+                handle_recheck_snapshots(app);
+            }
+            Action::NetworkJobs => {
+                trace!("Network jobs");
+                app.jobs_panel_open = true;
+            }
+            Action::DebugOverlay => {
+                app.debug_overlay.open = !app.debug_overlay.open;
+                debug!("Debug overlay: {}", app.debug_overlay.open);
+            }
+            Action::ToggleTheme => {
+                let mut config = app.config.write().unwrap();
+                config.dark_theme = !config.dark_theme;
+                debug!("Dark theme: {}", config.dark_theme);
+                set_theme(config.dark_theme, ctx);
+                config.save();
+            }
+            // saved to the config by the next frame
+            Action::ZoomIn => gui_zoom::zoom_in(ctx),
+            Action::ZoomOut => gui_zoom::zoom_out(ctx),
+            Action::ResetZoom => ctx.set_zoom_factor(1.0),
+            Action::About => {
+                trace!("About");
+                app.about.open = true;
+            }
+            Action::CommandPalette => {
+                trace!("Command palette");
+                app.command_palette.open();
+            }
+            Action::Undo => commands::undo(app),
+        }
+    }
+}
+
+// every action that can currently be run, one copy action per source
+pub fn all(app: &Application) -> Vec<Action> {
+    let mut actions: Vec<Action> = PAGES
+        .iter()
+        .map(|(page, _)| Action::ShowPage(*page))
+        .collect();
+
+    actions.extend([Action::CopyAll, Action::Import]);
+    actions.extend(formats::exporters().map(Action::Export));
+    actions.extend([
+        Action::ReferenceList,
+        Action::Bibliography,
+        Action::ReadingPlan,
+        Action::Documents,
+        Action::ImportBundle,
+        Action::FindReplace,
+        Action::CheckLibrary,
+        Action::CheckLinks,
+        Action::RecheckSnapshots,
+        Action::NetworkJobs,
+        Action::ToggleTheme,
+        Action::ZoomIn,
+        Action::ZoomOut,
+        Action::ResetZoom,
+        Action::About,
+    ]);
+    if !app.undo_stack.is_empty() {
+        actions.push(Action::Undo);
+    }
+    actions.extend(
+        app.sources_cache
+            .read()
+            .unwrap()
+            .iter()
+            .map(|source| Action::CopySource(source.id())),
+    );
+
+    actions
+}
+
+pub fn set_theme(dark: bool, ctx: &Context) {
+    ctx.set_visuals(match dark {
+        true => Visuals::dark(),
+        false => Visuals::light(),
+    });
+}
@@ -0,0 +1,169 @@
+use std::fs;
+use std::path::Path;
+
+use egui::{Button, ComboBox, Context, ScrollArea, TextEdit};
+use tracing::*;
+
+use crate::bibliography::{self, RepeatedAuthors, SectionBy};
+use crate::source::split_tags;
+use crate::source::Source;
+use crate::ui::file_dialog::{self, FilePurpose, FileRequest};
+use crate::ui::{copy_citation, escape_pressed, prepare_copy, Application, TEXT_INPUT_WIDTH};
+
+// dialog of "Alphabetical bibliography", the final list of the listed sources, optionally in
+// sections with headings
+#[derive(Default)]
+pub struct BibliographyDialog {
+    pub open: bool,
+    repeated: Option<RepeatedAuthors>, // None for the convention of the selected format
+    section_by: SectionBy,
+    section_tags: String, // tags of the sections in their order, e.g. "primary, secondary"
+    sources: Vec<Source>, // listed sources when the dialog was last shown
+    error: Option<String>, // last save error
+}
+
+// the list page passes the sources it lists, including the pinned ones
+pub fn render(app: &mut Application, ctx: &Context, sources: Vec<Source>) {
+    if !app.bibliography.open {
+        return;
+    }
+
+    let mut open = true;
+    let mut save = false;
+    app.bibliography.sources = sources;
+    let text = alphabetical(app);
+
+    egui::Window::new("Alphabetical bibliography")
+        .collapsible(false)
+        .open(&mut open)
+        .show(ctx, |ui| {
+            ui.label("The listed sources sorted by the surname of the first author, or by title without an author:");
+
+            let mut repeated = repeated_authors(app);
+            ComboBox::from_label("Repeated authors")
+                .selected_text(repeated.to_string())
+                .show_ui(ui, |ui| {
+                    for option in RepeatedAuthors::ALL {
+                        ui.selectable_value(&mut repeated, option, option.to_string());
+                    }
+                });
+            if repeated != repeated_authors(app) {
+                app.bibliography.repeated = Some(repeated);
+            }
+
+            ui.horizontal(|ui| {
+                let dialog = &mut app.bibliography;
+                ComboBox::from_label("Sections")
+                    .selected_text(dialog.section_by.to_string())
+                    .show_ui(ui, |ui| {
+                        for section_by in SectionBy::ALL {
+                            ui.selectable_value(
+                                &mut dialog.section_by,
+                                section_by,
+                                section_by.to_string(),
+                            );
+                        }
+                    });
+
+                if dialog.section_by == SectionBy::Tag {
+                    let input_tags = TextEdit::singleline(&mut dialog.section_tags)
+                        .hint_text("Tags in order, e.g. primary, secondary")
+                        .desired_width(TEXT_INPUT_WIDTH);
+                    ui.add(input_tags).on_hover_text(
+                        "A source is listed under the first of these tags it has, empty for a section per tag",
+                    );
+                }
+            });
+
+            ui.separator();
+
+            ui.label(format!("{} sources", app.bibliography.sources.len()));
+            ScrollArea::vertical()
+                .id_source("Bibliography")
+                .max_height(300.0)
+                .show(ui, |ui| {
+                    ui.label(&text);
+                });
+
+            ui.horizontal(|ui| {
+                let any = !app.bibliography.sources.is_empty();
+                if ui.add_enabled(any, Button::new("Copy")).clicked() {
+                    trace!("Copy bibliography clicked");
+                    let ids: Vec<i64> = app
+                        .bibliography
+                        .sources
+                        .iter()
+                        .filter_map(Source::library_id)
+                        .collect();
+                    copy_citation(text.clone(), &ids, app);
+                }
+
+                if ui.add_enabled(any, Button::new("Save as file")).clicked() {
+                    trace!("Save bibliography clicked");
+                    save = true;
+                }
+            });
+
+            if let Some(error) = &app.bibliography.error {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+            }
+        });
+
+    if save {
+        let request =
+            FileRequest::save(FilePurpose::Bibliography, "Select file", "bibliography.txt")
+                .add_filter("Text", &["txt"]);
+        file_dialog::show(request, app, ctx);
+    }
+
+    if !open || escape_pressed(ctx) {
+        app.bibliography.open = false;
+    }
+}
+
+// writes the bibliography of the listed sources to the file
+pub fn save(path: &Path, app: &mut Application) {
+    let text = alphabetical(app);
+
+    app.bibliography.error = match fs::write(path, text) {
+        Ok(()) => None,
+        Err(e) => {
+            error!("Error saving bibliography: {}", e);
+            Some(format!("Error saving {}: {}", path.display(), e))
+        }
+    };
+}
+
+fn repeated_authors(app: &Application) -> RepeatedAuthors {
+    app.bibliography
+        .repeated
+        .unwrap_or_else(|| RepeatedAuthors::for_standard(&app.settings.format_standard))
+}
+
+// the viewed date is updated like for any other copy
+fn alphabetical(app: &Application) -> String {
+    let sources: Vec<Source> = app
+        .bibliography
+        .sources
+        .iter()
+        .map(|source| prepare_copy(source, app))
+        .collect();
+
+    let standard = &app.settings.format_standard;
+    let collation = app.collation.read().unwrap();
+    let sections = bibliography::sections(
+        &sources,
+        app.bibliography.section_by,
+        &split_tags(&app.bibliography.section_tags),
+        bibliography::headings(standard),
+        &collation,
+    );
+
+    bibliography::sectioned(
+        &sections,
+        standard,
+        &app.config.read().unwrap(),
+        repeated_authors(app),
+        &collation,
+    )
+}
@@ -0,0 +1,179 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use chrono::{Local, NaiveDate};
+use egui::{Context, Grid, ProgressBar, TextEdit};
+use egui_extras::DatePickerButton;
+use tracing::*;
+
+use crate::source::Source;
+use crate::ui::handlers::handle_bulk_edit;
+use crate::ui::{escape_pressed, Application, TEXT_INPUT_WIDTH};
+
+pub struct BulkEdit {
+    pub open: bool,
+    set_author: bool,
+    author: String,
+    add_tags: bool,
+    tags: String,
+    set_collection: bool,
+    collection: String,
+    set_viewed_date: bool,
+    viewed_date: NaiveDate,
+    progress: Arc<AtomicUsize>, // sources written by the running bulk edit
+    total: usize,               // sources in the running bulk edit, 0 if none ran yet
+}
+
+impl Default for BulkEdit {
+    fn default() -> Self {
+        Self {
+            open: false,
+            set_author: false,
+            author: String::new(),
+            add_tags: false,
+            tags: String::new(),
+            set_collection: false,
+            collection: String::new(),
+            set_viewed_date: false,
+            viewed_date: Local::now().date_naive(),
+            progress: Arc::new(AtomicUsize::new(0)),
+            total: 0,
+        }
+    }
+}
+
+impl BulkEdit {
+    fn is_running(&self) -> bool {
+        self.total != 0 && self.progress.load(Ordering::Relaxed) < self.total
+    }
+
+    fn has_changes(&self) -> bool {
+        self.set_author || self.add_tags || self.set_collection || self.set_viewed_date
+    }
+
+    fn apply(&self, source: &mut Source) {
+        if self.set_author {
+            source.author = self.author.clone();
+        }
+        if self.add_tags {
+            source.add_tags(&self.tags);
+        }
+        if self.set_collection {
+            source.collection = self.collection.clone();
+        }
+        if self.set_viewed_date {
+            source.viewed_date = self.viewed_date;
+        }
+    }
+}
+
+pub fn render(app: &mut Application, ctx: &Context) {
+    if !app.bulk_edit.open {
+        return;
+    }
+
+    let mut open = app.bulk_edit.open;
+    let selected = app.selected.len();
+
+    egui::Window::new(format!("Bulk edit ({} sources)", selected))
+        .collapsible(false)
+        .resizable(false)
+        .open(&mut open)
+        .show(ctx, |ui| {
+            let running = app.bulk_edit.is_running();
+
+            ui.add_enabled_ui(!running, |ui| {
+                Grid::new("BulkEditGrid").num_columns(2).show(ui, |ui| {
+                    let bulk_edit = &mut app.bulk_edit;
+
+                    let author_check = ui.checkbox(&mut bulk_edit.set_author, "Set author:");
+                    let input_author =
+                        TextEdit::singleline(&mut bulk_edit.author).desired_width(TEXT_INPUT_WIDTH);
+                    ui.add_enabled(bulk_edit.set_author, input_author)
+                        .labelled_by(author_check.id);
+                    ui.end_row();
+
+                    let tags_check = ui.checkbox(&mut bulk_edit.add_tags, "Add tags:");
+                    let input_tags = TextEdit::singleline(&mut bulk_edit.tags)
+                        .hint_text("Separated by commas")
+                        .desired_width(TEXT_INPUT_WIDTH);
+                    ui.add_enabled(bulk_edit.add_tags, input_tags)
+                        .labelled_by(tags_check.id);
+                    ui.end_row();
+
+                    let collection_check =
+                        ui.checkbox(&mut bulk_edit.set_collection, "Set collection:");
+                    let input_collection = TextEdit::singleline(&mut bulk_edit.collection)
+                        .desired_width(TEXT_INPUT_WIDTH);
+                    ui.add_enabled(bulk_edit.set_collection, input_collection)
+                        .labelled_by(collection_check.id);
+                    ui.end_row();
+
+                    let viewed_check =
+                        ui.checkbox(&mut bulk_edit.set_viewed_date, "Set date viewed:");
+                    ui.add_enabled(
+                        bulk_edit.set_viewed_date,
+                        DatePickerButton::new(&mut bulk_edit.viewed_date)
+                            .id_source("BulkEditViewedDate"),
+                    )
+                    .labelled_by(viewed_check.id);
+                    ui.end_row();
+                });
+
+                ui.add_space(5.0);
+
+                let can_apply = selected > 0 && app.bulk_edit.has_changes();
+                if ui
+                    .add_enabled(can_apply, egui::Button::new("Apply"))
+                    .clicked()
+                {
+                    trace!("Bulk edit apply clicked");
+
+                    let sources: Vec<Source> = app
+                        .sources_cache
+                        .read()
+                        .unwrap()
+                        .iter()
+                        .filter(|source| app.selected.contains(&source.id()))
+                        .map(|source| source.to_source())
+                        .map(|mut source| {
+                            app.bulk_edit.apply(&mut source);
+                            source
+                        })
+                        .collect();
+
+                    app.bulk_edit.total = sources.len();
+                    handle_bulk_edit(sources, app.bulk_edit.progress.clone(), app);
+                }
+            });
+
+            // progress of the running or last bulk edit
+            if app.bulk_edit.total != 0 {
+                let done = app
+                    .bulk_edit
+                    .progress
+                    .load(Ordering::Relaxed)
+                    .min(app.bulk_edit.total);
+                let progress = done as f32 / app.bulk_edit.total as f32;
+
+                ui.add(ProgressBar::new(progress).text(format!(
+                    "{} / {} sources updated",
+                    done, app.bulk_edit.total
+                )));
+
+                if running {
+                    ctx.request_repaint();
+                }
+            }
+        });
+
+    if open && escape_pressed(ctx) {
+        open = false;
+    }
+
+    // don't show the last run's progress when reopened
+    if !open {
+        app.bulk_edit.total = 0;
+    }
+    app.bulk_edit.open = open;
+}
@@ -0,0 +1,158 @@
+use egui::{Align2, Context, Key, Modifiers, ScrollArea, TextEdit};
+use tracing::*;
+
+use crate::ui::actions::{self, Action};
+use crate::ui::commands::{self, Command};
+use crate::ui::{Application, TEXT_INPUT_WIDTH};
+
+const MAX_RESULTS: usize = 12;
+
+// overlay for running any action by typing part of its name
+#[derive(Default)]
+pub struct CommandPalette {
+    open: bool,
+    query: String,
+    selected: usize, // index into the matching actions
+}
+
+impl CommandPalette {
+    pub fn open(&mut self) {
+        self.open = true;
+        self.query.clear();
+        self.selected = 0;
+    }
+}
+
+pub fn render(app: &mut Application, ctx: &Context) {
+    if !app.command_palette.open {
+        return;
+    }
+
+    let mut matches: Vec<(i32, Action, String)> = actions::all(app)
+        .into_iter()
+        .filter_map(|action| {
+            let name = action.name(app);
+            fuzzy_score(&app.command_palette.query, &name).map(|score| (score, action, name))
+        })
+        .collect();
+    // stable, so equally good matches keep the order of the registry
+    matches.sort_by_key(|(score, _, _)| -score);
+    matches.truncate(MAX_RESULTS);
+
+    // handled before the text input gets them
+    let (up, down, enter, escape) = ctx.input_mut(|i| {
+        (
+            i.consume_key(Modifiers::NONE, Key::ArrowUp),
+            i.consume_key(Modifiers::NONE, Key::ArrowDown),
+            i.consume_key(Modifiers::NONE, Key::Enter),
+            i.consume_key(Modifiers::NONE, Key::Escape),
+        )
+    });
+
+    let palette = &mut app.command_palette;
+    if down && palette.selected + 1 < matches.len() {
+        palette.selected += 1;
+    }
+    if up {
+        palette.selected = palette.selected.saturating_sub(1);
+    }
+    palette.selected = palette.selected.min(matches.len().saturating_sub(1));
+
+    let mut chosen = match enter {
+        true => matches.get(palette.selected).map(|(_, action, _)| *action),
+        false => None,
+    };
+
+    egui::Window::new("Command palette")
+        .title_bar(false)
+        .collapsible(false)
+        .resizable(false)
+        .anchor(Align2::CENTER_TOP, [0.0, 40.0])
+        .show(ctx, |ui| {
+            let label = ui.label("Command:");
+            let input = TextEdit::singleline(&mut palette.query)
+                .hint_text("Type a command or the title of a source")
+                .desired_width(TEXT_INPUT_WIDTH);
+            let response = ui.add(input).labelled_by(label.id);
+            response.request_focus();
+            if response.changed() {
+                palette.selected = 0;
+            }
+
+            ui.separator();
+
+            if matches.is_empty() {
+                ui.label("No matching commands");
+                return;
+            }
+
+            ScrollArea::vertical().max_height(320.0).show(ui, |ui| {
+                for (index, (_, action, name)) in matches.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        let label = ui.selectable_label(index == palette.selected, name);
+                        if label.clicked() {
+                            chosen = Some(*action);
+                        }
+
+                        if let Some(shortcut) = action.shortcut() {
+                            ui.weak(ui.ctx().format_shortcut(&shortcut));
+                        }
+                    });
+                }
+            });
+        });
+
+    if escape {
+        trace!("Command palette closed");
+        app.command_palette.open = false;
+    }
+
+    if let Some(action) = chosen {
+        app.command_palette.open = false;
+        commands::run(action, app, ctx);
+    }
+}
+
+// None if the characters of the query don't appear in the text in order, higher is better:
+// consecutive characters and characters at the start of words count more
+fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    if query.trim().is_empty() {
+        return Some(0);
+    }
+
+    let mut score = 0;
+
+    let mut text_chars = text.chars().flat_map(char::to_lowercase);
+    let mut previous: Option<char> = None;
+    let mut consecutive = false;
+
+    for query_char in query.chars().flat_map(char::to_lowercase) {
+        if query_char.is_whitespace() {
+            continue;
+        }
+
+        loop {
+            let text_char = text_chars.next()?;
+            let word_start = previous.is_none_or(|c| !c.is_alphanumeric());
+            previous = Some(text_char);
+
+            if text_char == query_char {
+                score += 1;
+                if consecutive {
+                    score += 2;
+                }
+                if word_start {
+                    score += 3;
+                }
+                consecutive = true;
+                break;
+            }
+
+            consecutive = false;
+        }
+    }
+
+    // shorter texts are closer matches
+    let rest = text_chars.count() as i32;
+    Some(score * 100 - rest)
+}
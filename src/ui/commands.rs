@@ -0,0 +1,236 @@
+use std::sync::Arc;
+
+use egui::{Context, KeyboardShortcut};
+use once_cell::sync::OnceCell;
+use tracing::*;
+
+use crate::document::CitedIn;
+use crate::quote::Quote;
+use crate::source::Source;
+use crate::source_link::SourceLink;
+use crate::ui::actions::{Action, PAGES};
+use crate::ui::handlers::{
+    handle_delete_source, handle_restore_source, handle_source_save, handle_update_source,
+};
+use crate::ui::{truncated, Application};
+
+// commands that can be undone with Ctrl+Z, oldest are dropped first
+const UNDO_LIMIT: usize = 50;
+
+// something the app does, shared by buttons, keyboard shortcuts, the command palette and the
+// undo stack
+pub trait Command {
+    // shown in the command palette and after "Undo"
+    fn name(&self, app: &Application) -> String;
+
+    fn shortcut(&self) -> Option<KeyboardShortcut> {
+        None
+    }
+
+    fn execute(&mut self, app: &mut Application, ctx: &Context);
+
+    // commands that can be undone are kept on the undo stack after executing them
+    fn can_undo(&self) -> bool {
+        false
+    }
+
+    fn undo(&mut self, _app: &mut Application) {}
+}
+
+#[derive(Default)]
+pub struct UndoStack {
+    commands: Vec<Box<dyn Command>>,
+}
+
+impl UndoStack {
+    pub fn last(&self) -> Option<&dyn Command> {
+        self.commands.last().map(|command| command.as_ref())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    fn push(&mut self, command: Box<dyn Command>) {
+        self.commands.push(command);
+        if self.commands.len() > UNDO_LIMIT {
+            self.commands.remove(0);
+        }
+    }
+}
+
+pub fn run(mut command: impl Command + 'static, app: &mut Application, ctx: &Context) {
+    debug!("Running command: {}", command.name(app));
+    command.execute(app, ctx);
+
+    if command.can_undo() {
+        app.undo_stack.push(Box::new(command));
+    }
+}
+
+pub fn undo(app: &mut Application) {
+    let mut command = match app.undo_stack.commands.pop() {
+        None => return,
+        Some(command) => command,
+    };
+
+    debug!("Undoing command: {}", command.name(app));
+    command.undo(app);
+}
+
+// runs the first command whose shortcut was pressed
+pub fn handle_shortcuts(app: &mut Application, ctx: &Context) {
+    // Ctrl+Z in a text input undoes typing instead
+    let typing = ctx.memory(|memory| memory.focused().is_some());
+
+    let action = ctx.input_mut(|i| {
+        PAGES
+            .iter()
+            .map(|(page, _)| Action::ShowPage(*page))
+            .chain([Action::CommandPalette, Action::DebugOverlay])
+            .chain((!typing).then_some(Action::Undo))
+            .find(|action| {
+                action
+                    .shortcut()
+                    .is_some_and(|shortcut| i.consume_shortcut(&shortcut))
+            })
+    });
+
+    if let Some(action) = action {
+        run(action, app, ctx);
+    }
+}
+
+// source of the start page saved to the library
+pub struct SaveSource {
+    title: String,
+    inserted: Option<Arc<OnceCell<i64>>>, // id once the source is in the library
+}
+
+impl SaveSource {
+    pub fn new(app: &Application) -> Self {
+        Self {
+            title: app.get_source().title,
+            inserted: None,
+        }
+    }
+}
+
+impl Command for SaveSource {
+    fn name(&self, _app: &Application) -> String {
+        format!("Save \"{}\"", truncated(&self.title, 60))
+    }
+
+    fn execute(&mut self, app: &mut Application, _ctx: &Context) {
+        self.inserted = Some(handle_source_save(app));
+    }
+
+    fn can_undo(&self) -> bool {
+        true
+    }
+
+    fn undo(&mut self, app: &mut Application) {
+        match self.inserted.as_ref().and_then(|inserted| inserted.get()) {
+            Some(id) => handle_delete_source(*id, app),
+            None => warn!("Can't undo saving a source that isn't saved yet"),
+        }
+    }
+}
+
+// changes of the edit modal
+pub struct UpdateSource {
+    before: Source,
+    after: Source,
+}
+
+impl UpdateSource {
+    pub fn new(before: Source, after: Source) -> Self {
+        Self { before, after }
+    }
+}
+
+impl Command for UpdateSource {
+    fn name(&self, _app: &Application) -> String {
+        format!("Edit \"{}\"", truncated(&self.after.title, 60))
+    }
+
+    fn execute(&mut self, app: &mut Application, _ctx: &Context) {
+        handle_update_source(self.after.id, &self.after, app);
+    }
+
+    fn can_undo(&self) -> bool {
+        true
+    }
+
+    fn undo(&mut self, app: &mut Application) {
+        handle_update_source(self.before.id, &self.before, app);
+    }
+}
+
+// deleted source, kept with its quotes and links so they can be restored
+pub struct DeleteSource {
+    source: Source,
+    quotes: Vec<Quote>,
+    links: Vec<SourceLink>,
+    cited_in: Vec<CitedIn>,
+}
+
+impl DeleteSource {
+    pub fn new(source: Source, app: &Application) -> Self {
+        let quotes = app
+            .quotes_cache
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|quote| quote.source_id == source.id)
+            .cloned()
+            .collect();
+        let links = app
+            .links_cache
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|link| link.source_id == source.id || link.target_id == source.id)
+            .cloned()
+            .collect();
+        let cited_in = app
+            .cited_in_cache
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|cited_in| cited_in.source_id == source.id)
+            .cloned()
+            .collect();
+
+        Self {
+            source,
+            quotes,
+            links,
+            cited_in,
+        }
+    }
+}
+
+impl Command for DeleteSource {
+    fn name(&self, _app: &Application) -> String {
+        format!("Delete \"{}\"", truncated(&self.source.title, 60))
+    }
+
+    fn execute(&mut self, app: &mut Application, _ctx: &Context) {
+        handle_delete_source(self.source.id, app);
+    }
+
+    fn can_undo(&self) -> bool {
+        true
+    }
+
+    fn undo(&mut self, app: &mut Application) {
+        handle_restore_source(
+            self.source.clone(),
+            self.quotes.clone(),
+            self.links.clone(),
+            self.cited_in.clone(),
+            app,
+        );
+    }
+}
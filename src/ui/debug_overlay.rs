@@ -0,0 +1,104 @@
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use egui::{Align2, Context, Grid, RichText};
+
+use crate::scheduler;
+use crate::tasks;
+use crate::ui::Application;
+
+const MAX_FRAMES: usize = 120; // frames the average and the slowest frame are of
+const MAX_QUERIES: usize = 10;
+
+// frame time, cache sizes and the last queries of the list, toggled with Ctrl+Shift+D
+#[derive(Default)]
+pub struct DebugOverlay {
+    pub open: bool,
+    frames: VecDeque<Duration>, // time spent building each frame, newest last
+    pub queries: Queries,
+}
+
+pub type Queries = Arc<Mutex<VecDeque<(&'static str, Duration)>>>;
+
+impl DebugOverlay {
+    pub fn record_frame(&mut self, duration: Duration) {
+        if self.frames.len() == MAX_FRAMES {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(duration);
+    }
+}
+
+// awaits the query and remembers how long it took
+pub async fn timed<T>(name: &'static str, queries: &Queries, query: impl Future<Output = T>) -> T {
+    let started = Instant::now();
+    let result = query.await;
+
+    let mut queries = queries.lock().unwrap();
+    if queries.len() == MAX_QUERIES {
+        queries.pop_front();
+    }
+    queries.push_back((name, started.elapsed()));
+
+    result
+}
+
+pub fn render(app: &mut Application, ctx: &Context) {
+    if !app.debug_overlay.open {
+        return;
+    }
+
+    let frames = &app.debug_overlay.frames;
+    let last = frames.back().copied().unwrap_or_default();
+    let average = frames.iter().sum::<Duration>() / frames.len().max(1) as u32;
+    let slowest = frames.iter().max().copied().unwrap_or_default();
+
+    let caches = [
+        ("Sources", app.sources_cache.read().unwrap().len()),
+        ("Links", app.links_cache.read().unwrap().len()),
+        ("Quotes", app.quotes_cache.read().unwrap().len()),
+        ("Documents", app.documents_cache.read().unwrap().len()),
+        ("Citations", app.cited_in_cache.read().unwrap().len()),
+        ("Snapshots", app.snapshots_cache.read().unwrap().len()),
+        ("Shared", app.shared_cache.read().unwrap().len()),
+        ("Other libraries", app.library_cache.read().unwrap().len()),
+    ];
+    let queries = app.debug_overlay.queries.lock().unwrap().clone();
+
+    egui::Area::new("DebugOverlay".into())
+        .anchor(Align2::RIGHT_BOTTOM, [-10.0, -10.0])
+        .order(egui::Order::Foreground)
+        .interactable(false)
+        .show(ctx, |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                Grid::new("DebugOverlayGrid").num_columns(2).show(ui, |ui| {
+                    row(ui, "Frame", format!("{:.1} ms", ms(last)));
+                    row(
+                        ui,
+                        format!("Last {} frames", frames.len()),
+                        format!("{:.1} ms avg, {:.1} ms max", ms(average), ms(slowest)),
+                    );
+                    for (name, size) in caches {
+                        row(ui, name, size.to_string());
+                    }
+                    row(ui, "Pending DB tasks", tasks::pending().to_string());
+                    row(ui, "Network jobs", scheduler::jobs().len().to_string());
+                    for (name, duration) in queries.iter().rev() {
+                        row(ui, *name, format!("{:.1} ms", ms(*duration)));
+                    }
+                });
+            });
+        });
+}
+
+fn row(ui: &mut egui::Ui, name: impl Into<String>, value: String) {
+    ui.label(RichText::new(name).monospace());
+    ui.label(RichText::new(value).monospace());
+    ui.end_row();
+}
+
+fn ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
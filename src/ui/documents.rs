@@ -0,0 +1,315 @@
+use std::fs;
+use std::path::Path;
+
+use egui::{Button, ComboBox, Context, Grid, ScrollArea, TextEdit, Ui};
+use tracing::*;
+
+use crate::document::CitedIn;
+use crate::formats::{self, Format};
+use crate::hooks::{self, HookEvent};
+use crate::search::Searchable;
+use crate::source::Source;
+use crate::ui::file_dialog::{self, FilePurpose, FileRequest};
+use crate::ui::handlers::{
+    handle_delete_document, handle_insert_document, handle_rename_document, handle_set_cited_in,
+};
+use crate::ui::{escape_pressed, set_all_clipboard, AppPage, Application, TEXT_INPUT_WIDTH};
+
+// dialog of "Documents", the texts the sources are cited in
+#[derive(Default)]
+pub struct Documents {
+    pub open: bool,
+    pub filter: Option<i64>, // document whose sources the list page shows, None for all
+    new_name: String,
+    renaming: Option<(i64, String)>, // document and its new name
+    error: Option<String>,           // last export error
+}
+
+// sources cited in the document
+pub fn cited_sources(id: i64, app: &Application) -> Vec<Source> {
+    let cited_in = app.cited_in_cache.read().unwrap();
+
+    app.sources_cache
+        .read()
+        .unwrap()
+        .iter()
+        .filter(|source| {
+            cited_in
+                .iter()
+                .any(|cited| cited.document_id == id && cited.source_id == source.id())
+        })
+        .map(|source| source.to_source())
+        .collect()
+}
+
+// names of the documents the source is cited in
+pub fn document_names(source_id: i64, app: &Application) -> Vec<String> {
+    let cited_in = app.cited_in_cache.read().unwrap();
+
+    app.documents_cache
+        .read()
+        .unwrap()
+        .iter()
+        .filter(|document| {
+            cited_in
+                .iter()
+                .any(|cited| cited.document_id == document.id && cited.source_id == source_id)
+        })
+        .map(|document| document.name.clone())
+        .collect()
+}
+
+pub fn render(app: &mut Application, ctx: &Context) {
+    if !app.documents.open {
+        return;
+    }
+
+    let mut open = true;
+    let documents = app.documents_cache.read().unwrap().clone();
+    let name_taken = |name: &str, id: i64| {
+        documents
+            .iter()
+            .any(|document| document.id != id && document.name == name.trim())
+    };
+
+    egui::Window::new("Documents")
+        .collapsible(false)
+        .open(&mut open)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                let name_label = ui.label("New document:");
+                let input_name = TextEdit::singleline(&mut app.documents.new_name)
+                    .hint_text("Thesis chapter 3")
+                    .desired_width(TEXT_INPUT_WIDTH / 2.0);
+                ui.add(input_name).labelled_by(name_label.id);
+
+                let name = app.documents.new_name.trim().to_string();
+                let can_add = !app.read_only && !name.is_empty() && !name_taken(&name, -1);
+                if ui.add_enabled(can_add, Button::new("Add")).clicked() {
+                    trace!("Add document clicked");
+                    handle_insert_document(name, app);
+                    app.documents.new_name.clear();
+                }
+            });
+
+            ui.separator();
+
+            if documents.is_empty() {
+                ui.label(
+                    "No documents yet, add one and mark the sources it cites in the edit modal.",
+                );
+            }
+
+            ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                Grid::new("DocumentsGrid").num_columns(3).show(ui, |ui| {
+                    for document in &documents {
+                        let sources = cited_sources(document.id, app);
+
+                        match &mut app.documents.renaming {
+                            Some((id, name)) if *id == document.id => {
+                                ui.add(
+                                    TextEdit::singleline(name)
+                                        .desired_width(TEXT_INPUT_WIDTH / 2.0),
+                                );
+                            }
+                            _ => {
+                                ui.label(&document.name);
+                            }
+                        }
+                        ui.label(format!("{} sources", sources.len()));
+
+                        ui.horizontal(|ui| {
+                            render_document_buttons(app, ui, document.id, &sources, &name_taken);
+                        });
+                        ui.end_row();
+                    }
+                });
+            });
+
+            if let Some(error) = &app.documents.error {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+            }
+        });
+
+    if !open || escape_pressed(ctx) {
+        app.documents.open = false;
+        app.documents.renaming = None;
+    }
+}
+
+fn render_document_buttons(
+    app: &mut Application,
+    ui: &mut Ui,
+    id: i64,
+    sources: &[Source],
+    name_taken: &dyn Fn(&str, i64) -> bool,
+) {
+    if let Some((renaming, name)) = app.documents.renaming.clone() {
+        if renaming == id {
+            let name = name.trim().to_string();
+            let can_save = !name.is_empty() && !name_taken(&name, id);
+            if ui.add_enabled(can_save, Button::new("Save")).clicked() {
+                trace!("Rename document clicked");
+                handle_rename_document(id, name, app);
+                app.documents.renaming = None;
+            }
+            if ui.button("Cancel").clicked() {
+                app.documents.renaming = None;
+            }
+            return;
+        }
+    }
+
+    if ui
+        .add_enabled(!sources.is_empty(), Button::new("Copy"))
+        .clicked()
+    {
+        trace!("Copy document sources clicked");
+        set_all_clipboard(sources, app);
+    }
+
+    ui.add_enabled_ui(!sources.is_empty(), |ui| {
+        ui.menu_button("Export", |ui| {
+            for format in formats::exporters() {
+                if ui.button(format.name()).clicked() {
+                    ui.close_menu();
+                    let request = FileRequest::save(
+                        FilePurpose::ExportDocument(format, id),
+                        "Select file",
+                        format!("document.{}", format.extensions()[0]),
+                    )
+                    .add_filter(format.name(), format.extensions());
+                    file_dialog::show(request, app, ui.ctx());
+                }
+            }
+        });
+    });
+
+    if ui
+        .button("Show")
+        .on_hover_text("Only list the sources cited in the document")
+        .clicked()
+    {
+        trace!("Show document sources clicked");
+        app.documents.filter = Some(id);
+        app.curr_page = AppPage::List;
+    }
+
+    let can_edit = !app.read_only;
+    if ui.add_enabled(can_edit, Button::new("Rename")).clicked() {
+        let name = app
+            .documents_cache
+            .read()
+            .unwrap()
+            .iter()
+            .find(|document| document.id == id)
+            .map(|document| document.name.clone())
+            .unwrap_or_default();
+        app.documents.renaming = Some((id, name));
+    }
+
+    if ui
+        .add_enabled(can_edit, Button::new("Delete"))
+        .on_hover_text("The sources stay in the library")
+        .clicked()
+    {
+        trace!("Delete document clicked");
+        if app.documents.filter == Some(id) {
+            app.documents.filter = None;
+        }
+        handle_delete_document(id, app);
+    }
+}
+
+// "Cited in" filter of the list page, hidden without documents
+pub fn render_filter(app: &mut Application, ui: &mut Ui) {
+    let documents = app.documents_cache.read().unwrap().clone();
+    if documents.is_empty() {
+        return;
+    }
+
+    let selected = documents
+        .iter()
+        .find(|document| Some(document.id) == app.documents.filter)
+        .map(|document| document.name.clone())
+        .unwrap_or("Any document".to_string());
+
+    ComboBox::from_label("Cited in")
+        .selected_text(selected)
+        .show_ui(ui, |ui| {
+            ui.selectable_value(&mut app.documents.filter, None, "Any document");
+            for document in &documents {
+                ui.selectable_value(&mut app.documents.filter, Some(document.id), &document.name);
+            }
+        });
+}
+
+// whether the source passes the "Cited in" filter
+pub fn filter_matches(source: &impl Searchable, app: &Application) -> bool {
+    match app.documents.filter {
+        None => true,
+        Some(id) => {
+            !source.shared()
+                && app
+                    .cited_in_cache
+                    .read()
+                    .unwrap()
+                    .iter()
+                    .any(|cited| cited.document_id == id && cited.source_id == source.id())
+        }
+    }
+}
+
+// "Cited in" section of the edit modal, saved right away like relations and quotes
+pub fn render_cited_in(app: &mut Application, ui: &mut Ui) {
+    let source_id = app.edit_modal.source.id;
+    let documents = app.documents_cache.read().unwrap().clone();
+
+    ui.horizontal_wrapped(|ui| {
+        ui.label("Cited in:");
+
+        if documents.is_empty() && ui.link("Add a document").clicked() {
+            app.documents.open = true;
+        }
+
+        for document in &documents {
+            let cited_in = CitedIn {
+                document_id: document.id,
+                source_id,
+            };
+            let mut cited = app.cited_in_cache.read().unwrap().contains(&cited_in);
+            let checkbox = ui.add_enabled(
+                !app.read_only,
+                egui::Checkbox::new(&mut cited, &document.name),
+            );
+            if checkbox.changed() {
+                trace!("Cited in {} changed to {}", document.id, cited);
+                handle_set_cited_in(cited_in, cited, app);
+            }
+        }
+    });
+}
+
+// writes the sources cited in the document to a file of the format, not added to the recent
+// exports, which always export the whole library
+pub fn export(format: &dyn Format, id: i64, path: &Path, app: &mut Application) {
+    let sources = cited_sources(id, app);
+    let result = format
+        .export(&sources)
+        .and_then(|content| Ok(fs::write(path, content)?));
+
+    app.documents.error = match result {
+        Ok(()) => {
+            hooks::run(
+                HookEvent::ExportFinished,
+                hooks::export_payload(path, format, &sources),
+                &app.config.read().unwrap(),
+            );
+            None
+        }
+        Err(e) => {
+            error!("Error exporting document sources: {}", e);
+            Some(format!("Error exporting sources: {}", e))
+        }
+    };
+}
@@ -0,0 +1,62 @@
+use std::fs;
+use std::path::PathBuf;
+
+use directories::ProjectDirs;
+use tracing::*;
+
+use crate::ui::SourceInput;
+
+const DRAFT_NAME: &str = "draft.json";
+
+fn draft_path() -> PathBuf {
+    ProjectDirs::from("com", "tgz39", "saveit")
+        .unwrap()
+        .data_dir()
+        .join(DRAFT_NAME)
+}
+
+// start page input left behind by the previous run
+pub fn load() -> Option<SourceInput> {
+    let content = fs::read_to_string(draft_path()).ok()?;
+
+    match serde_json::from_str(&content) {
+        Ok(input) => {
+            debug!("Restoring start page draft");
+            Some(input)
+        }
+        Err(e) => {
+            warn!("Ignoring invalid draft: {}", e);
+            None
+        }
+    }
+}
+
+pub fn save(input: &SourceInput) {
+    if input.is_empty() {
+        delete();
+        return;
+    }
+
+    trace!("Saving start page draft");
+
+    let path = draft_path();
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+
+    let json = serde_json::to_string(input).expect("Error serializing draft");
+    if let Err(e) = fs::write(path, json) {
+        error!("Error saving draft: {}", e);
+    }
+}
+
+pub fn delete() {
+    let path = draft_path();
+
+    if path.exists() {
+        trace!("Deleting start page draft");
+        if let Err(e) = fs::remove_file(path) {
+            error!("Error deleting draft: {}", e);
+        }
+    }
+}
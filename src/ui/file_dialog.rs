@@ -0,0 +1,322 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::RwLock;
+
+use directories::UserDirs;
+use egui::{Context, Key, TextEdit};
+use once_cell::sync::Lazy;
+use rfd::AsyncFileDialog;
+use tracing::*;
+
+use crate::config::Config;
+use crate::formats::Format;
+use crate::ui::{
+    bibliography, documents, escape_pressed, import_dialog, link_check, list_page, reading_plan,
+    reference_list, settings_page, start_page, Application, TEXT_INPUT_WIDTH,
+};
+
+// whether a native dialog can be shown, checked once
+static DIALOG_AVAILABLE: Lazy<bool> = Lazy::new(dialog_available);
+
+// what the picked file is used for
+#[derive(Clone, Copy)]
+pub enum FilePurpose {
+    ImportSources,
+    ExportSources(&'static dyn Format),
+    ShareBundle,
+    ImportBundle,
+    SharedLibrary,
+    OtherLibrary,
+    ExportSettings,
+    ImportSettings,
+    AutoExportFile,
+    InboxFolder,
+    SourceFile, // PDF or image that fills in the start page
+    ReferenceList,
+    ReferenceMapping, // number to reference of the numbered references
+    Bibliography,
+    ReadingPlan,
+    LinkReport,
+    ExportDocument(&'static dyn Format, i64), // sources cited in the document
+}
+
+pub struct FileRequest {
+    purpose: FilePurpose,
+    title: &'static str,
+    save: bool,
+    folder: bool, // picks a folder instead of a file
+    file_name: Option<String>,
+    filters: Vec<(String, Vec<String>)>,
+}
+
+impl FileRequest {
+    pub fn open(purpose: FilePurpose, title: &'static str) -> Self {
+        Self {
+            purpose,
+            title,
+            save: false,
+            folder: false,
+            file_name: None,
+            filters: vec![],
+        }
+    }
+
+    pub fn folder(purpose: FilePurpose, title: &'static str) -> Self {
+        Self {
+            folder: true,
+            ..Self::open(purpose, title)
+        }
+    }
+
+    pub fn save(purpose: FilePurpose, title: &'static str, file_name: impl Into<String>) -> Self {
+        Self {
+            purpose,
+            title,
+            save: true,
+            folder: false,
+            file_name: Some(file_name.into()),
+            filters: vec![],
+        }
+    }
+
+    pub fn add_filter(mut self, name: &str, extensions: &[&str]) -> Self {
+        self.filters.push((
+            name.to_string(),
+            extensions.iter().map(|ext| ext.to_string()).collect(),
+        ));
+        self
+    }
+}
+
+// picked file, sent by the dialog task or the fallback input
+struct FileEvent {
+    purpose: FilePurpose,
+    save: bool,
+    path: Option<PathBuf>, // None if cancelled
+}
+
+// path input shown instead of the dialog if no dialog provider exists
+struct Fallback {
+    request: FileRequest,
+    path: String,
+}
+
+// file dialogs run in a task so they don't block the UI, their result comes back through the channel
+pub struct FileDialogs {
+    sender: Sender<FileEvent>,
+    receiver: Receiver<FileEvent>,
+    pending: bool, // one dialog at a time
+    fallback: Option<Fallback>,
+}
+
+impl Default for FileDialogs {
+    fn default() -> Self {
+        let (sender, receiver) = channel();
+
+        Self {
+            sender,
+            receiver,
+            pending: false,
+            fallback: None,
+        }
+    }
+}
+
+// opens the dialog in the last used import or export directory
+pub fn show(request: FileRequest, app: &mut Application, ctx: &Context) {
+    let dialogs = &mut app.file_dialogs;
+    if dialogs.pending {
+        debug!("File dialog already open");
+        return;
+    }
+    dialogs.pending = true;
+
+    let directory = start_directory(request.save, &app.config.read().unwrap());
+    if !*DIALOG_AVAILABLE {
+        debug!("No file dialog available, asking for a path");
+        let path = match &request.file_name {
+            Some(file_name) => directory.join(file_name),
+            None => directory,
+        };
+        dialogs.fallback = Some(Fallback {
+            request,
+            path: path.to_string_lossy().to_string(),
+        });
+        return;
+    }
+
+    let mut dialog = AsyncFileDialog::new()
+        .set_title(request.title)
+        .set_directory(&directory);
+    for (name, extensions) in &request.filters {
+        dialog = dialog.add_filter(name, extensions);
+    }
+    if let Some(file_name) = &request.file_name {
+        dialog = dialog.set_file_name(file_name);
+    }
+
+    let sender = dialogs.sender.clone();
+    let ctx = ctx.clone();
+    let purpose = request.purpose;
+    tokio::spawn(async move {
+        let file = match (request.save, request.folder) {
+            (true, _) => dialog.save_file().await,
+            (false, true) => dialog.pick_folder().await,
+            (false, false) => dialog.pick_file().await,
+        };
+
+        let event = FileEvent {
+            purpose,
+            save: request.save,
+            path: file.map(|file| file.path().to_path_buf()),
+        };
+        if sender.send(event).is_err() {
+            warn!("Error sending picked file, the app was closed");
+        }
+        ctx.request_repaint();
+    });
+}
+
+// handles picked files and shows the fallback input, called every frame
+pub fn render(app: &mut Application, ctx: &Context) {
+    render_fallback(app, ctx);
+
+    while let Ok(event) = app.file_dialogs.receiver.try_recv() {
+        app.file_dialogs.pending = false;
+
+        let path = match event.path {
+            None => {
+                trace!("File dialog cancelled");
+                continue;
+            }
+            Some(path) => path,
+        };
+        debug!("Picked file {}", path.display());
+        remember_directory(&path, event.save, &app.config);
+
+        match event.purpose {
+            FilePurpose::ImportSources => import_dialog::open_file(&path, app),
+            FilePurpose::ExportSources(format) => list_page::export_sources(format, &path, app),
+            FilePurpose::ShareBundle => list_page::share_bundle(&path, app),
+            FilePurpose::ImportBundle => list_page::import_bundle(&path, app),
+            FilePurpose::SharedLibrary => {
+                app.settings.shared_library = path.to_string_lossy().to_string();
+            }
+            FilePurpose::OtherLibrary => {
+                let libraries = &mut app.settings.other_libraries;
+                if !libraries.trim().is_empty() && !libraries.ends_with('\n') {
+                    libraries.push('\n');
+                }
+                libraries.push_str(&path.to_string_lossy());
+            }
+            FilePurpose::ExportSettings => settings_page::export_settings(&path, app),
+            FilePurpose::ImportSettings => settings_page::import_settings(&path, app),
+            FilePurpose::AutoExportFile => {
+                app.settings.auto_export_path = path.to_string_lossy().to_string();
+            }
+            FilePurpose::InboxFolder => {
+                app.settings.inbox_dir = path.to_string_lossy().to_string();
+            }
+            FilePurpose::SourceFile => start_page::open_file(path, app),
+            FilePurpose::ReferenceList => reference_list::save(&path, app),
+            FilePurpose::ReferenceMapping => reference_list::save_mapping(&path, app),
+            FilePurpose::Bibliography => bibliography::save(&path, app),
+            FilePurpose::ReadingPlan => reading_plan::save(&path, app),
+            FilePurpose::LinkReport => link_check::save(&path, app),
+            FilePurpose::ExportDocument(format, id) => documents::export(format, id, &path, app),
+        }
+    }
+}
+
+fn render_fallback(app: &mut Application, ctx: &Context) {
+    let fallback = match &mut app.file_dialogs.fallback {
+        None => return,
+        Some(fallback) => fallback,
+    };
+
+    let mut open = true;
+    let mut path = None;
+    egui::Window::new(fallback.request.title)
+        .collapsible(false)
+        .open(&mut open)
+        .show(ctx, |ui| {
+            ui.label("No file dialog is available, enter the path of the file:");
+
+            let label = ui.label("Path:");
+            let input = TextEdit::singleline(&mut fallback.path).desired_width(TEXT_INPUT_WIDTH);
+            let response = ui.add(input).labelled_by(label.id);
+            let entered = response.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter));
+
+            ui.horizontal(|ui| {
+                let confirm = match fallback.request.save {
+                    true => "Save",
+                    false => "Open",
+                };
+                if ui.button(confirm).clicked() || entered {
+                    path = Some(PathBuf::from(fallback.path.trim()));
+                }
+            });
+        });
+
+    // the dialog closing counts as cancelled
+    if path.is_some() || !open || escape_pressed(ctx) {
+        let event = FileEvent {
+            purpose: fallback.request.purpose,
+            save: fallback.request.save,
+            path,
+        };
+        app.file_dialogs.fallback = None;
+        app.file_dialogs
+            .sender
+            .send(event)
+            .expect("Error sending entered path");
+    }
+}
+
+// last directory used for saving or opening files if it still exists, the home directory otherwise
+fn start_directory(save: bool, config: &Config) -> PathBuf {
+    let directory = match save {
+        true => &config.export_dir,
+        false => &config.import_dir,
+    };
+
+    match !directory.is_empty() && Path::new(directory).is_dir() {
+        true => PathBuf::from(directory),
+        false => UserDirs::new()
+            .map(|dirs| dirs.home_dir().to_path_buf())
+            .unwrap_or_default(),
+    }
+}
+
+fn remember_directory(path: &Path, save: bool, config: &RwLock<Config>) {
+    let directory = match path.parent() {
+        Some(directory) if !directory.as_os_str().is_empty() => directory,
+        _ => return,
+    };
+    let directory = directory.to_string_lossy().to_string();
+
+    let mut config = config.write().unwrap();
+    let last = match save {
+        true => &mut config.export_dir,
+        false => &mut config.import_dir,
+    };
+    if *last != directory {
+        *last = directory;
+        config.save();
+    }
+}
+
+// Windows and macOS always have a dialog, elsewhere it needs the desktop portal (via D-Bus) or zenity
+fn dialog_available() -> bool {
+    if cfg!(any(target_os = "windows", target_os = "macos")) {
+        return true;
+    }
+
+    let dbus = std::env::var_os("DBUS_SESSION_BUS_ADDRESS").is_some();
+    let zenity = std::env::var_os("PATH")
+        .map(|paths| std::env::split_paths(&paths).any(|dir| dir.join("zenity").is_file()))
+        .unwrap_or(false);
+    debug!("File dialog providers: D-Bus {}, zenity {}", dbus, zenity);
+
+    dbus || zenity
+}
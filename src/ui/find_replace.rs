@@ -0,0 +1,227 @@
+use std::sync::atomic::AtomicUsize;
+use std::sync::Arc;
+
+use egui::{Context, Grid, ScrollArea, TextEdit};
+use tracing::*;
+
+use crate::source::Source;
+use crate::ui::handlers::handle_bulk_edit;
+use crate::ui::{escape_pressed, Application, TEXT_INPUT_WIDTH};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FindField {
+    Title,
+    Url,
+    Author,
+    Comment,
+    Tags,
+    Collection,
+}
+
+impl FindField {
+    const ALL: [FindField; 6] = [
+        FindField::Title,
+        FindField::Url,
+        FindField::Author,
+        FindField::Comment,
+        FindField::Tags,
+        FindField::Collection,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            FindField::Title => "Title",
+            FindField::Url => "URL",
+            FindField::Author => "Author",
+            FindField::Comment => "Comment",
+            FindField::Tags => "Tags",
+            FindField::Collection => "Collection",
+        }
+    }
+
+    fn value_mut<'a>(&self, source: &'a mut Source) -> &'a mut String {
+        match self {
+            FindField::Title => &mut source.title,
+            FindField::Url => &mut source.url,
+            FindField::Author => &mut source.author,
+            FindField::Comment => &mut source.comment,
+            FindField::Tags => &mut source.tags,
+            FindField::Collection => &mut source.collection,
+        }
+    }
+}
+
+pub struct FindReplace {
+    pub open: bool,
+    find: String,
+    replace: String,
+    match_case: bool,
+    fields: Vec<FindField>,
+}
+
+impl Default for FindReplace {
+    fn default() -> Self {
+        Self {
+            open: false,
+            find: String::new(),
+            replace: String::new(),
+            match_case: true,
+            fields: vec![FindField::Title, FindField::Url, FindField::Author],
+        }
+    }
+}
+
+// a source affected by the replacement
+struct Change {
+    source: Source,                           // source with the replacement applied
+    fields: Vec<(FindField, String, String)>, // field, old value, new value
+}
+
+impl FindReplace {
+    fn changes(&self, sources: &[Source]) -> Vec<Change> {
+        if self.find.is_empty() {
+            return vec![];
+        }
+
+        let mut changes = vec![];
+        for source in sources {
+            let mut changed = source.clone();
+            let mut fields = vec![];
+
+            for field in &self.fields {
+                let value = field.value_mut(&mut changed);
+                let new = replace(value, &self.find, &self.replace, self.match_case);
+
+                if new != *value {
+                    fields.push((*field, value.clone(), new.clone()));
+                    *value = new;
+                }
+            }
+
+            if !fields.is_empty() {
+                changes.push(Change {
+                    source: changed,
+                    fields,
+                });
+            }
+        }
+
+        changes
+    }
+}
+
+// replaces all occurrences of find, optionally ignoring case
+fn replace(text: &str, find: &str, replace: &str, match_case: bool) -> String {
+    if match_case {
+        return text.replace(find, replace);
+    }
+
+    let find: Vec<char> = find.chars().flat_map(char::to_lowercase).collect();
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+
+    'outer: while !rest.is_empty() {
+        // try to match find at the start of rest
+        let mut lowered = rest.char_indices().flat_map(|(offset, c)| {
+            c.to_lowercase()
+                .map(move |lower| (offset + c.len_utf8(), lower))
+        });
+        let mut end = 0;
+        for expected in &find {
+            match lowered.next() {
+                Some((offset, lower)) if lower == *expected => end = offset,
+                _ => {
+                    let c = rest.chars().next().unwrap();
+                    out.push(c);
+                    rest = &rest[c.len_utf8()..];
+                    continue 'outer;
+                }
+            }
+        }
+
+        out.push_str(replace);
+        rest = &rest[end..];
+    }
+
+    out
+}
+
+pub fn render(app: &mut Application, ctx: &Context) {
+    if !app.find_replace.open {
+        return;
+    }
+
+    let mut open = app.find_replace.open;
+    let changes = app
+        .find_replace
+        .changes(&app.sources_cache.read().unwrap().to_vec());
+
+    egui::Window::new("Find & replace")
+        .collapsible(false)
+        .open(&mut open)
+        .show(ctx, |ui| {
+            let find_replace = &mut app.find_replace;
+
+            Grid::new("FindReplaceGrid").num_columns(2).show(ui, |ui| {
+                let find_label = ui.label("Find:");
+                let input_find =
+                    TextEdit::singleline(&mut find_replace.find).desired_width(TEXT_INPUT_WIDTH);
+                ui.add(input_find).labelled_by(find_label.id);
+                ui.end_row();
+
+                let replace_label = ui.label("Replace with:");
+                let input_replace =
+                    TextEdit::singleline(&mut find_replace.replace).desired_width(TEXT_INPUT_WIDTH);
+                ui.add(input_replace).labelled_by(replace_label.id);
+                ui.end_row();
+
+                ui.label("Fields:");
+                ui.horizontal_wrapped(|ui| {
+                    for field in FindField::ALL {
+                        let mut checked = find_replace.fields.contains(&field);
+                        if ui.checkbox(&mut checked, field.label()).changed() {
+                            match checked {
+                                true => find_replace.fields.push(field),
+                                false => find_replace.fields.retain(|f| *f != field),
+                            }
+                        }
+                    }
+                });
+                ui.end_row();
+
+                ui.label("");
+                ui.checkbox(&mut find_replace.match_case, "Match case");
+                ui.end_row();
+            });
+
+            ui.separator();
+
+            // preview
+            ui.label(format!("{} sources affected", changes.len()));
+            ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                for change in &changes {
+                    ui.strong(format!("[{}] {}", change.source.id, change.source.title));
+                    for (field, old, new) in &change.fields {
+                        ui.label(format!("{}: {} → {}", field.label(), old, new));
+                    }
+                    ui.add_space(5.0);
+                }
+            });
+
+            ui.separator();
+
+            if ui
+                .add_enabled(!changes.is_empty(), egui::Button::new("Replace all"))
+                .clicked()
+            {
+                trace!("Replace all clicked");
+                let sources = changes.into_iter().map(|change| change.source).collect();
+                handle_bulk_edit(sources, Arc::new(AtomicUsize::new(0)), app);
+            }
+        });
+
+    if open && escape_pressed(ctx) {
+        open = false;
+    }
+    app.find_replace.open = open;
+}
@@ -0,0 +1,362 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, RwLock};
+
+use egui::{Align2, CentralPanel, Context, FontId, Id, Rect, Sense, Slider, Stroke, Ui, Vec2};
+use tracing::*;
+
+use crate::source::Source;
+use crate::ui::{accessible_name, truncated, AppPage, Application};
+
+const NODE_RADIUS: f32 = 8.0;
+const SIMULATION_STEPS: usize = 300;
+const MAX_LAYOUT_WORK: usize = 20_000_000; // node pairs times steps
+const MAX_LABEL_CHARS: usize = 25;
+
+// node positions of the graph page, kept between frames
+#[derive(Default)]
+pub struct GraphView {
+    positions: HashMap<i64, Vec2>, // relative to the center of the canvas
+    pan: Vec2,
+    zoom: f32,
+    show_tags: bool,
+    show_links: bool,
+    edges: Vec<Edge>,
+    edges_key: Option<u64>, // hash of the ids, tags and links the edges were built from
+    layout_key: Vec<i64>,   // source ids the last layout was started for
+    layout: Arc<RwLock<Option<Layout>>>, // computed in the background, taken by the next frame
+    computing: bool,
+    dragged: Option<i64>,
+}
+
+// source ids and their positions
+type Layout = (Vec<i64>, HashMap<i64, Vec2>);
+
+impl GraphView {
+    pub fn new() -> Self {
+        Self {
+            zoom: 1.0,
+            show_tags: true,
+            show_links: true,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum EdgeKind {
+    Tag,
+    Link,
+}
+
+struct Edge {
+    from: i64,
+    to: i64,
+    kind: EdgeKind,
+    label: String,
+}
+
+pub fn render(app: &mut Application, ui: &mut Ui) {
+    let sources = app.sources_cache.read().unwrap().to_vec();
+
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut app.graph.show_tags, "Shared tags");
+        ui.checkbox(&mut app.graph.show_links, "Relations");
+        ui.add(
+            Slider::new(&mut app.graph.zoom, 0.2..=4.0)
+                .logarithmic(true)
+                .text("Zoom"),
+        );
+
+        if ui.button("Re-layout").clicked() {
+            trace!("Graph re-layout clicked");
+            app.graph.layout_key.clear();
+        }
+
+        if app.graph.computing {
+            ui.spinner();
+        }
+    });
+
+    ui.add_space(10.0);
+
+    if sources.is_empty() {
+        CentralPanel::default().show_inside(ui, |ui| {
+            ui.vertical_centered(|ui| {
+                ui.heading("Empty");
+            });
+        });
+        return;
+    }
+
+    // a tag shared by many sources has many edges, so they are only built again after changes
+    let edges_key = edges_key(app, &sources);
+    if app.graph.edges_key != Some(edges_key) {
+        app.graph.edges = edges(app, &sources);
+        app.graph.edges_key = Some(edges_key);
+    }
+
+    // recompute the layout when sources were added or removed
+    let layout_key: Vec<i64> = sources.iter().map(|source| source.id).collect();
+    if app.graph.layout_key != layout_key {
+        handle_layout(app, layout_key.clone(), ui.ctx().clone());
+        app.graph.layout_key = layout_key;
+    }
+    let finished = app.graph.layout.write().unwrap().take();
+    if let Some((ids, positions)) = finished {
+        // layouts started for sources that changed since are dropped
+        if ids == app.graph.layout_key {
+            app.graph.positions = positions;
+            app.graph.computing = false;
+        }
+    }
+
+    let (response, painter) = ui.allocate_painter(ui.available_size(), Sense::drag());
+    let center = response.rect.center() + app.graph.pan;
+    let zoom = app.graph.zoom;
+    let to_screen = |position: Vec2| center + position * zoom;
+    let visuals = ui.visuals().clone();
+
+    // edges
+    for edge in &app.graph.edges {
+        if (edge.kind == EdgeKind::Tag && !app.graph.show_tags)
+            || (edge.kind == EdgeKind::Link && !app.graph.show_links)
+        {
+            continue;
+        }
+
+        let (from, to) = match (
+            app.graph.positions.get(&edge.from),
+            app.graph.positions.get(&edge.to),
+        ) {
+            (Some(from), Some(to)) => (to_screen(*from), to_screen(*to)),
+            _ => continue,
+        };
+
+        let stroke = match edge.kind {
+            EdgeKind::Tag => Stroke::new(1.0, visuals.widgets.noninteractive.bg_stroke.color),
+            EdgeKind::Link => Stroke::new(2.0, visuals.hyperlink_color),
+        };
+        painter.line_segment([from, to], stroke);
+
+        if edge.kind == EdgeKind::Link {
+            painter.text(
+                from + (to - from) / 2.0,
+                Align2::CENTER_CENTER,
+                &edge.label,
+                FontId::proportional(12.0),
+                visuals.weak_text_color(),
+            );
+        }
+    }
+
+    // nodes
+    let mut node_dragged = false;
+    for source in &sources {
+        let position = match app.graph.positions.get(&source.id) {
+            Some(position) => *position,
+            None => continue,
+        };
+        let screen = to_screen(position);
+
+        let node = ui.interact(
+            Rect::from_center_size(screen, Vec2::splat(NODE_RADIUS * 2.0)),
+            Id::new("GraphNode").with(source.id),
+            Sense::click_and_drag(),
+        );
+        let node = accessible_name(node, format!("Edit \"{}\"", source.title))
+            .on_hover_text(hover_text(source));
+
+        // move nodes by dragging them
+        if node.drag_started() {
+            app.graph.dragged = Some(source.id);
+        }
+        if app.graph.dragged == Some(source.id) {
+            node_dragged = true;
+            if let Some(position) = app.graph.positions.get_mut(&source.id) {
+                *position += node.drag_delta() / zoom;
+            }
+        }
+        if node.drag_stopped() {
+            app.graph.dragged = None;
+        }
+
+        // nodes can be reached with Tab as well
+        let color = match node.hovered() || node.has_focus() {
+            true => visuals.selection.bg_fill,
+            false => visuals.hyperlink_color,
+        };
+        painter.circle_filled(screen, NODE_RADIUS, color);
+        painter.text(
+            screen + Vec2::new(0.0, NODE_RADIUS + 2.0),
+            Align2::CENTER_TOP,
+            truncated(&source.title, MAX_LABEL_CHARS),
+            FontId::proportional(13.0),
+            visuals.text_color(),
+        );
+
+        // opens the source on the list page
+        if node.clicked() {
+            trace!("Graph node {} clicked", source.id);
+            app.open_edit_modal(source);
+            app.curr_page = AppPage::List;
+        }
+    }
+
+    // pan by dragging the background
+    if !node_dragged && response.dragged() {
+        app.graph.pan += response.drag_delta();
+    }
+
+    // ctrl + scroll zooms
+    if ui.rect_contains_pointer(response.rect) {
+        let delta = ui.input(|i| i.zoom_delta());
+        if delta != 1.0 {
+            app.graph.zoom = (app.graph.zoom * delta).clamp(0.2, 4.0);
+        }
+    }
+}
+
+// changes when sources are added or removed, their tags are edited or links change
+fn edges_key(app: &Application, sources: &[Source]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+
+    for source in sources {
+        source.id.hash(&mut hasher);
+        source.tags.hash(&mut hasher);
+    }
+    for link in app.links_cache.read().unwrap().iter() {
+        link.id.hash(&mut hasher);
+        link.source_id.hash(&mut hasher);
+        link.target_id.hash(&mut hasher);
+        (link.relation as u8).hash(&mut hasher);
+    }
+
+    hasher.finish()
+}
+
+// edges for every pair of sources sharing a tag and every link
+fn edges(app: &Application, sources: &[Source]) -> Vec<Edge> {
+    let mut edges = vec![];
+
+    let mut by_tag: HashMap<String, Vec<i64>> = HashMap::new();
+    for source in sources {
+        for tag in source.tag_list() {
+            by_tag
+                .entry(tag.to_lowercase())
+                .or_default()
+                .push(source.id);
+        }
+    }
+    for (tag, ids) in by_tag {
+        for (index, from) in ids.iter().enumerate() {
+            for to in &ids[index + 1..] {
+                edges.push(Edge {
+                    from: *from,
+                    to: *to,
+                    kind: EdgeKind::Tag,
+                    label: tag.clone(),
+                });
+            }
+        }
+    }
+
+    for link in app.links_cache.read().unwrap().iter() {
+        edges.push(Edge {
+            from: link.source_id,
+            to: link.target_id,
+            kind: EdgeKind::Link,
+            label: link.relation.to_string(),
+        });
+    }
+
+    edges
+}
+
+// computes the layout on a blocking thread, it takes seconds for large libraries
+fn handle_layout(app: &mut Application, ids: Vec<i64>, ctx: Context) {
+    let edges: Vec<(i64, i64)> = app
+        .graph
+        .edges
+        .iter()
+        .map(|edge| (edge.from, edge.to))
+        .collect();
+    let layout = app.graph.layout.clone();
+    app.graph.computing = true;
+
+    tokio::task::spawn_blocking(move || {
+        let positions = self::layout(&ids, &edges);
+        *layout.write().unwrap() = Some((ids, positions));
+        ctx.request_repaint();
+    });
+}
+
+// simple force directed layout: nodes repel each other, edges pull them together
+fn layout(ids: &[i64], edges: &[(i64, i64)]) -> HashMap<i64, Vec2> {
+    debug!("Computing graph layout for {} sources", ids.len());
+
+    let index: HashMap<i64, usize> = ids
+        .iter()
+        .enumerate()
+        .map(|(index, id)| (*id, index))
+        .collect();
+    let edges: Vec<(usize, usize)> = edges
+        .iter()
+        .filter_map(|(from, to)| Some((*index.get(from)?, *index.get(to)?)))
+        .collect();
+
+    // start on a circle so the result is deterministic
+    let count = ids.len();
+    let spread = 40.0 * (count as f32).sqrt();
+    let mut positions: Vec<Vec2> = (0..count)
+        .map(|index| Vec2::angled(index as f32 / count as f32 * std::f32::consts::TAU) * spread)
+        .collect();
+
+    // fewer steps for large libraries, repulsion is quadratic
+    let steps = (MAX_LAYOUT_WORK / (count * count).max(1)).clamp(20, SIMULATION_STEPS);
+    let ideal = 80.0;
+
+    for step in 0..steps {
+        let temperature = 10.0 * (1.0 - step as f32 / steps as f32);
+        let mut forces: Vec<Vec2> = positions.iter().map(|position| -*position * 0.01).collect(); // gravity towards the center
+
+        for a in 0..count {
+            for b in a + 1..count {
+                let delta = positions[a] - positions[b];
+                let distance = delta.length().max(1.0);
+                let push = delta / distance * (ideal * ideal / distance);
+                forces[a] += push;
+                forces[b] -= push;
+            }
+        }
+
+        for (from, to) in &edges {
+            let delta = positions[*to] - positions[*from];
+            let distance = delta.length().max(1.0);
+            let pull = delta / distance * (distance * distance / ideal);
+            forces[*from] += pull;
+            forces[*to] -= pull;
+        }
+
+        for (position, force) in positions.iter_mut().zip(forces) {
+            let length = force.length();
+            if length > 0.0 {
+                *position += force / length * length.min(temperature);
+            }
+        }
+    }
+
+    ids.iter().copied().zip(positions).collect()
+}
+
+fn hover_text(source: &Source) -> String {
+    let mut text = format!("[{}] {}", source.id, source.title);
+    if !source.author.is_empty() {
+        text.push_str(&format!("\n{}", source.author));
+    }
+    if !source.tags.is_empty() {
+        text.push_str(&format!("\nTags: {}", source.tag_list().join(", ")));
+    }
+    text
+}
@@ -0,0 +1,678 @@
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+
+use once_cell::sync::OnceCell;
+use serde_json::json;
+use sqlx::SqlitePool;
+use tracing::*;
+
+use crate::bundle::Manifest;
+use crate::collation::COLLATION_SETTING;
+use crate::crash::record_operation;
+use crate::database::{
+    bulk_update, delete_document, delete_link, delete_quote, delete_source, get_all_cited_in,
+    get_all_documents, get_all_jobs, get_all_links, get_all_quotes, get_all_snapshots,
+    get_all_sources, get_snapshot, get_source, import_bundle, import_sources, insert_document,
+    insert_job, insert_link, insert_quote, insert_source, mark_cited, rename_document,
+    restore_source, set_cited_in, set_library_setting, set_pinned, stream_import, update_quote,
+    update_source,
+};
+use crate::document::{CitedIn, Document};
+use crate::formats::Format;
+use crate::hooks::{self, HookEvent};
+use crate::import::{ImportIds, ImportProgress};
+use crate::metadata::{fetch_wikipedia_revision, resolve_canonical_url};
+use crate::queue::JobKind;
+use crate::quote::Quote;
+use crate::scheduler;
+use crate::source::Source;
+use crate::source_link::SourceLink;
+use crate::source_store::SourceStore;
+use crate::tasks::spawn_tracked;
+use crate::ui::snapshot::{recheck, update_cache as update_snapshot_cache};
+use crate::ui::toasts::{self, Toasts};
+use crate::ui::Application;
+use crate::url_analysis;
+
+// writes are left to the instance owning the library, see instance.rs
+fn read_only(app: &Application) -> bool {
+    if app.read_only {
+        warn!("Not writing to the library, SaveIt is read-only");
+    }
+    app.read_only
+}
+
+// the list shows an edit or a delete before it's written, a failed write puts the sources back
+// as they were and tells why, sources changed again in the meantime are kept
+fn roll_back(
+    ids: &[i64],
+    before: Vec<Source>,
+    shown: Vec<Source>, // what the write showed, a deleted source isn't in it
+    error: String,
+    source_cache: &RwLock<SourceStore>,
+    toasts: &Toasts,
+) {
+    error!("{}", error);
+    let find = |sources: &[Source], id: i64| sources.iter().find(|source| source.id == id).cloned();
+
+    let mut cache = source_cache.write().unwrap();
+    for id in ids {
+        if cache.get(*id) != find(&shown, *id) {
+            continue;
+        }
+        match find(&before, *id) {
+            Some(source) => cache.upsert(&source),
+            None => cache.remove(*id),
+        }
+    }
+    toasts::push(toasts, error);
+}
+
+// async delete source, it's removed from the list right away
+pub fn handle_delete_source(id: i64, app: &Application) {
+    if read_only(app) {
+        return;
+    }
+    record_operation(format!("Delete source {}", id));
+    let before: Vec<Source> = app
+        .sources_cache
+        .read()
+        .unwrap()
+        .get(id)
+        .into_iter()
+        .collect();
+    app.sources_cache.write().unwrap().remove(id);
+    let source_cache = app.sources_cache.clone();
+    let links_cache = app.links_cache.clone();
+    let quotes_cache = app.quotes_cache.clone();
+    let toasts = app.toasts.clone();
+    let pool = app.pool.clone();
+
+    spawn_tracked(async move {
+        if let Err(e) = delete_source(id, &pool).await {
+            let error = format!("Error deleting source {}: {}", id, e);
+            roll_back(&[id], before, vec![], error, &source_cache, &toasts);
+            return;
+        }
+
+        // links and quotes of the source are deleted with it
+        *links_cache.write().unwrap() = get_all_links(&pool).await.expect("Error loading links");
+        *quotes_cache.write().unwrap() = get_all_quotes(&pool).await.expect("Error loading quotes");
+    });
+}
+
+// async restore source
+pub fn handle_restore_source(
+    source: Source,
+    quotes: Vec<Quote>,
+    links: Vec<SourceLink>,
+    cited_in: Vec<CitedIn>,
+    app: &Application,
+) {
+    if read_only(app) {
+        return;
+    }
+    record_operation(format!("Restore source {}", source.id));
+    let source_cache = app.sources_cache.clone();
+    let links_cache = app.links_cache.clone();
+    let quotes_cache = app.quotes_cache.clone();
+    let documents_cache = app.documents_cache.clone();
+    let cited_in_cache = app.cited_in_cache.clone();
+    let pool = app.pool.clone();
+
+    spawn_tracked(async move {
+        restore_source(&source, &quotes, &links, &cited_in, &pool)
+            .await
+            .expect("Error restoring source");
+        reload_documents(documents_cache, cited_in_cache, &pool).await;
+
+        // update caches
+        *source_cache.write().unwrap() = get_all_sources(&pool)
+            .await
+            .expect("Error loading sources")
+            .into();
+        *links_cache.write().unwrap() = get_all_links(&pool).await.expect("Error loading links");
+        *quotes_cache.write().unwrap() = get_all_quotes(&pool).await.expect("Error loading quotes");
+    });
+}
+
+// async pin or unpin, shown right away
+pub fn handle_set_pinned(id: i64, pinned: bool, app: &Application) {
+    if read_only(app) {
+        return;
+    }
+    record_operation(format!("Pin source {}", id));
+    let Some(before) = app.sources_cache.read().unwrap().get(id) else {
+        return;
+    };
+    let shown = Source {
+        pinned,
+        ..before.clone()
+    };
+    app.sources_cache.write().unwrap().upsert(&shown);
+    let source_cache = app.sources_cache.clone();
+    let toasts = app.toasts.clone();
+    let pool = app.pool.clone();
+
+    spawn_tracked(async move {
+        if let Err(e) = set_pinned(id, pinned, &pool).await {
+            let error = format!("Error pinning source {}: {}", id, e);
+            roll_back(
+                &[id],
+                vec![before],
+                vec![shown],
+                error,
+                &source_cache,
+                &toasts,
+            );
+        }
+    });
+}
+
+// stored in the library, the list is sorted with it right away
+pub fn handle_set_collation(collation: &str, app: &Application) {
+    if read_only(app) {
+        return;
+    }
+    record_operation("Set collation");
+    *app.collation.write().unwrap() = collation.to_string();
+    let collation = collation.to_string();
+    let pool = app.pool.clone();
+
+    spawn_tracked(async move {
+        set_library_setting(COLLATION_SETTING, &collation, &pool)
+            .await
+            .expect("Error saving collation");
+    });
+}
+
+// async re-check of the pages of all sources with a snapshot, one after another
+pub fn handle_recheck_snapshots(app: &Application) {
+    if read_only(app) {
+        return;
+    }
+    record_operation("Re-check snapshots");
+    let snapshots = app.snapshots_cache.clone();
+    let pool = app.pool.clone();
+
+    spawn_tracked(async move {
+        let ids: Vec<i64> = get_all_snapshots(&pool)
+            .await
+            .expect("Error loading snapshots")
+            .iter()
+            .map(|snapshot| snapshot.source_id)
+            .collect();
+
+        for id in ids {
+            let Some(saved) = get_snapshot(id, &pool)
+                .await
+                .expect("Error loading snapshot")
+            else {
+                continue;
+            };
+
+            // unreachable pages are left as they were checked last
+            match recheck(saved, &pool).await {
+                Ok((checked, _)) => update_snapshot_cache(&checked, &snapshots),
+                Err(e) => warn!("Error re-checking snapshot of source {}: {}", id, e),
+            }
+        }
+    });
+}
+
+// async update source, the list shows the edit right away
+pub fn handle_update_source(id: i64, source: &Source, app: &Application) {
+    if read_only(app) {
+        return;
+    }
+    record_operation(format!("Update source {}", id));
+    let source = Source {
+        id,
+        ..source.clone()
+    };
+    let before: Vec<Source> = app
+        .sources_cache
+        .read()
+        .unwrap()
+        .get(id)
+        .into_iter()
+        .collect();
+    app.sources_cache.write().unwrap().upsert(&source);
+    let source_cache = app.sources_cache.clone();
+    let toasts = app.toasts.clone();
+    let pool = app.pool.clone();
+    let config = app.config.read().unwrap().clone();
+
+    spawn_tracked(async move {
+        if let Err(e) = update_source(id, &source, &pool).await {
+            let error = format!("Error saving \"{}\": {}", source.title, e);
+            let shown = vec![source.clone()];
+            roll_back(&[id], before, shown, error, &source_cache, &toasts);
+            return;
+        }
+        hooks::run(HookEvent::SourceSaved, json!({ "source": source }), &config);
+
+        // with the time of the change set by the library
+        match get_source(id, &pool).await {
+            Ok(Some(saved)) => source_cache.write().unwrap().upsert(&saved),
+            Ok(None) => {}
+            Err(e) => error!("Error loading source {}: {}", id, e),
+        }
+    });
+}
+
+// async save source, the id is set once the source is inserted
+pub fn handle_source_save(app: &Application) -> Arc<OnceCell<i64>> {
+    if read_only(app) {
+        return Arc::new(OnceCell::new());
+    }
+    record_operation("Save source");
+    let mut source = app.get_source();
+    let source_cache = app.sources_cache.clone();
+    let pool = app.pool.clone();
+    let queue = app.queue.status.clone();
+    let config = app.config.read().unwrap().clone();
+    let inserted = Arc::new(OnceCell::new());
+
+    let task_inserted = inserted.clone();
+    spawn_tracked(async move {
+        // cite the revision that was read instead of the live article
+        let mut failed_job = None;
+        let analysis = url_analysis::analyze(&source.url);
+        if let Some(article) = analysis.wikipedia {
+            let name = format!("Resolve Wikipedia revision of {}", source.url);
+            match scheduler::run(name, || fetch_wikipedia_revision(&article)).await {
+                Ok(revision) => {
+                    debug!("Citing Wikipedia revision {}", revision.permalink);
+                    source.url = revision.permalink;
+                    source.published_date = revision.date;
+                    source.published_date_unknown = false;
+                }
+                Err(e) => {
+                    warn!("Error resolving Wikipedia revision, queueing it: {}", e);
+                    failed_job = Some((JobKind::WikipediaRevision, e.to_string()));
+                }
+            }
+        } else if config.resolve_urls && analysis.web {
+            // cite where shortened and redirected links lead
+            let name = format!("Resolve canonical URL of {}", source.url);
+            match scheduler::run(name, || resolve_canonical_url(&source.url)).await {
+                Ok(canonical) => source.canonical_url = canonical.unwrap_or_default(),
+                Err(e) => {
+                    warn!("Error resolving canonical URL, queueing it: {}", e);
+                    failed_job = Some((JobKind::CanonicalUrl, e.to_string()));
+                }
+            }
+        }
+
+        let id = insert_source(&source, &*pool)
+            .await
+            .expect("Error inserting source in database");
+        let _ = task_inserted.set(id);
+        hooks::run(
+            HookEvent::SourceSaved,
+            json!({ "source": Source { id, ..source.clone() } }),
+            &config,
+        );
+
+        // retried in the background when the connection is back
+        if let Some((kind, error)) = failed_job {
+            insert_job(id, kind, &error, &pool)
+                .await
+                .expect("Error queueing network job");
+            queue.write().unwrap().jobs = get_all_jobs(&pool).await.expect("Error loading jobs");
+        }
+
+        // update source cache
+        *source_cache.write().unwrap() = get_all_sources(&pool)
+            .await
+            .expect("Error loading sources")
+            .into();
+    });
+
+    inserted
+}
+
+// async insert source link
+pub fn handle_insert_link(link: SourceLink, app: &Application) {
+    if read_only(app) {
+        return;
+    }
+    record_operation(format!(
+        "Link source {} to {}",
+        link.source_id, link.target_id
+    ));
+    let links_cache = app.links_cache.clone();
+    let pool = app.pool.clone();
+
+    spawn_tracked(async move {
+        insert_link(&link, &*pool)
+            .await
+            .expect("Error inserting source link in database");
+
+        // update links cache
+        *links_cache.write().unwrap() = get_all_links(&pool).await.expect("Error loading links");
+    });
+}
+
+// async delete source link
+pub fn handle_delete_link(id: i64, app: &Application) {
+    if read_only(app) {
+        return;
+    }
+    record_operation(format!("Delete source link {}", id));
+    let links_cache = app.links_cache.clone();
+    let pool = app.pool.clone();
+
+    spawn_tracked(async move {
+        delete_link(id, &pool)
+            .await
+            .expect("Error deleting source link");
+
+        // update links cache
+        *links_cache.write().unwrap() = get_all_links(&pool).await.expect("Error loading links");
+    });
+}
+
+// async insert or update quote, quotes with id -1 are new
+pub fn handle_save_quote(quote: Quote, app: &Application) {
+    if read_only(app) {
+        return;
+    }
+    record_operation(format!(
+        "Save quote {} of source {}",
+        quote.id, quote.source_id
+    ));
+    let quotes_cache = app.quotes_cache.clone();
+    let pool = app.pool.clone();
+
+    spawn_tracked(async move {
+        match quote.id {
+            -1 => insert_quote(&quote, &*pool).await,
+            id => update_quote(id, &quote, &pool).await,
+        }
+        .expect("Error saving quote in database");
+
+        // update quotes cache
+        *quotes_cache.write().unwrap() = get_all_quotes(&pool).await.expect("Error loading quotes");
+    });
+}
+
+// async delete quote
+pub fn handle_delete_quote(id: i64, app: &Application) {
+    if read_only(app) {
+        return;
+    }
+    record_operation(format!("Delete quote {}", id));
+    let quotes_cache = app.quotes_cache.clone();
+    let pool = app.pool.clone();
+
+    spawn_tracked(async move {
+        delete_quote(id, &pool).await.expect("Error deleting quote");
+
+        // update quotes cache
+        *quotes_cache.write().unwrap() = get_all_quotes(&pool).await.expect("Error loading quotes");
+    });
+}
+
+// reloads the documents and their citations after a change
+async fn reload_documents(
+    documents_cache: Arc<RwLock<Vec<Document>>>,
+    cited_in_cache: Arc<RwLock<Vec<CitedIn>>>,
+    pool: &SqlitePool,
+) {
+    *documents_cache.write().unwrap() = get_all_documents(pool)
+        .await
+        .expect("Error loading documents");
+    *cited_in_cache.write().unwrap() = get_all_cited_in(pool)
+        .await
+        .expect("Error loading document citations");
+}
+
+// async insert document
+pub fn handle_insert_document(name: String, app: &Application) {
+    if read_only(app) {
+        return;
+    }
+    record_operation("Insert document");
+    let documents_cache = app.documents_cache.clone();
+    let cited_in_cache = app.cited_in_cache.clone();
+    let pool = app.pool.clone();
+
+    spawn_tracked(async move {
+        insert_document(&name, &pool)
+            .await
+            .expect("Error inserting document");
+
+        reload_documents(documents_cache, cited_in_cache, &pool).await;
+    });
+}
+
+// async rename document
+pub fn handle_rename_document(id: i64, name: String, app: &Application) {
+    if read_only(app) {
+        return;
+    }
+    record_operation(format!("Rename document {}", id));
+    let documents_cache = app.documents_cache.clone();
+    let cited_in_cache = app.cited_in_cache.clone();
+    let pool = app.pool.clone();
+
+    spawn_tracked(async move {
+        rename_document(id, &name, &pool)
+            .await
+            .expect("Error renaming document");
+
+        reload_documents(documents_cache, cited_in_cache, &pool).await;
+    });
+}
+
+// async delete document
+pub fn handle_delete_document(id: i64, app: &Application) {
+    if read_only(app) {
+        return;
+    }
+    record_operation(format!("Delete document {}", id));
+    let documents_cache = app.documents_cache.clone();
+    let cited_in_cache = app.cited_in_cache.clone();
+    let pool = app.pool.clone();
+
+    spawn_tracked(async move {
+        delete_document(id, &pool)
+            .await
+            .expect("Error deleting document");
+
+        reload_documents(documents_cache, cited_in_cache, &pool).await;
+    });
+}
+
+// async mark a source as cited or not cited in a document
+pub fn handle_set_cited_in(cited_in: CitedIn, cited: bool, app: &Application) {
+    if read_only(app) {
+        return;
+    }
+    record_operation(format!(
+        "Cite source {} in document {}",
+        cited_in.source_id, cited_in.document_id
+    ));
+    let documents_cache = app.documents_cache.clone();
+    let cited_in_cache = app.cited_in_cache.clone();
+    let pool = app.pool.clone();
+
+    spawn_tracked(async move {
+        set_cited_in(&cited_in, cited, &pool)
+            .await
+            .expect("Error setting document citation");
+
+        reload_documents(documents_cache, cited_in_cache, &pool).await;
+    });
+}
+
+// async mark sources as cited
+pub fn handle_mark_cited(ids: Vec<i64>, app: &Application) {
+    if read_only(app) {
+        return;
+    }
+    record_operation(format!("Mark {} sources as cited", ids.len()));
+    let source_cache = app.sources_cache.clone();
+    let pool = app.pool.clone();
+
+    spawn_tracked(async move {
+        mark_cited(&ids, &pool)
+            .await
+            .expect("Error marking sources as cited");
+
+        // update source cache
+        *source_cache.write().unwrap() = get_all_sources(&pool)
+            .await
+            .expect("Error loading sources")
+            .into();
+    });
+}
+
+// async bulk edit, progress is reset and counts up to sources.len(), the list shows the edits
+// right away
+pub fn handle_bulk_edit(sources: Vec<Source>, progress: Arc<AtomicUsize>, app: &Application) {
+    if read_only(app) {
+        return;
+    }
+    record_operation(format!("Bulk edit {} sources", sources.len()));
+    let ids: Vec<i64> = sources.iter().map(|source| source.id).collect();
+    let before: Vec<Source> = {
+        let mut cache = app.sources_cache.write().unwrap();
+        let before = ids.iter().filter_map(|id| cache.get(*id)).collect();
+        for source in &sources {
+            cache.upsert(source);
+        }
+        before
+    };
+    let source_cache = app.sources_cache.clone();
+    let toasts = app.toasts.clone();
+    let pool = app.pool.clone();
+    progress.store(0, Ordering::Relaxed);
+
+    spawn_tracked(async move {
+        if let Err(e) = bulk_update(&sources, &progress, &pool).await {
+            let error = format!("Error editing {} sources: {}", sources.len(), e);
+            roll_back(&ids, before, sources, error, &source_cache, &toasts);
+            return;
+        }
+
+        // update source cache
+        *source_cache.write().unwrap() = get_all_sources(&pool)
+            .await
+            .expect("Error loading sources")
+            .into();
+    });
+}
+
+// async bundle import
+pub fn handle_import_bundle(manifest: Manifest, app: &Application) {
+    if read_only(app) {
+        return;
+    }
+    record_operation(format!(
+        "Import bundle with {} sources",
+        manifest.sources.len()
+    ));
+    let source_cache = app.sources_cache.clone();
+    let links_cache = app.links_cache.clone();
+    let quotes_cache = app.quotes_cache.clone();
+    let pool = app.pool.clone();
+
+    spawn_tracked(async move {
+        import_bundle(&manifest, &pool)
+            .await
+            .expect("Error importing bundle");
+
+        // update caches
+        *source_cache.write().unwrap() = get_all_sources(&pool)
+            .await
+            .expect("Error loading sources")
+            .into();
+        *links_cache.write().unwrap() = get_all_links(&pool).await.expect("Error loading links");
+        *quotes_cache.write().unwrap() = get_all_quotes(&pool).await.expect("Error loading quotes");
+    });
+}
+
+// async streaming import of a file too large for the import dialog's preview
+pub fn handle_stream_import(
+    path: PathBuf,
+    format: &'static dyn Format,
+    ids: ImportIds,
+    progress: Arc<ImportProgress>,
+    app: &Application,
+) {
+    if read_only(app) {
+        *progress.error.write().unwrap() = Some("SaveIt is read-only".to_string());
+        progress.done.store(true, Ordering::Relaxed);
+        return;
+    }
+    record_operation(format!("Stream import of {}", format.id()));
+    let source_cache = app.sources_cache.clone();
+    let pool = app.pool.clone();
+
+    spawn_tracked(async move {
+        if let Err(e) = stream_import(&path, format, true, ids, progress.clone(), &pool).await {
+            error!("Error importing {}: {}", path.display(), e);
+            *progress.error.write().unwrap() = Some(e.to_string());
+        }
+        progress.done.store(true, Ordering::Relaxed);
+
+        // update source cache
+        *source_cache.write().unwrap() = get_all_sources(&pool)
+            .await
+            .expect("Error loading sources")
+            .into();
+    });
+}
+
+// async import of sources read by the import dialog
+pub fn handle_import_sources(sources: Vec<Source>, ids: ImportIds, app: &Application) {
+    if read_only(app) {
+        return;
+    }
+    record_operation(format!("Import {} sources", sources.len()));
+    let source_cache = app.sources_cache.clone();
+    let pool = app.pool.clone();
+
+    spawn_tracked(async move {
+        import_sources(&sources, ids, &pool)
+            .await
+            .expect("Error importing sources");
+
+        // update source cache
+        *source_cache.write().unwrap() = get_all_sources(&pool)
+            .await
+            .expect("Error loading sources")
+            .into();
+    });
+}
+
+// async copy of a shared source into the own library
+pub fn handle_copy_shared_source(source: &Source, app: &Application) {
+    if read_only(app) {
+        return;
+    }
+    record_operation(format!("Copy shared source {}", source.id));
+    let source = Source {
+        shared: false,
+        library: String::new(),
+        ..source.clone()
+    };
+    let source_cache = app.sources_cache.clone();
+    let pool = app.pool.clone();
+
+    spawn_tracked(async move {
+        insert_source(&source, &*pool)
+            .await
+            .expect("Error copying shared source");
+
+        // update source cache
+        *source_cache.write().unwrap() = get_all_sources(&pool)
+            .await
+            .expect("Error loading sources")
+            .into();
+    });
+}
@@ -0,0 +1,412 @@
+use std::fs;
+use std::mem;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use egui::accesskit::{self, ActionRequest, Node, NodeId};
+use egui::{Context, Event, Key, Modifiers, Pos2, RawInput, Rect, Vec2};
+use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::SqlitePool;
+use tokio::runtime::Handle;
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::database::{get_all_sources, MIGRATOR};
+use crate::source::Source;
+use crate::tasks::wait_for_pending;
+use crate::ui::{AppPage, Application};
+
+// background work of a frame taking longer than this fails the test
+const SETTLE_TIMEOUT: Duration = Duration::from_secs(10);
+
+// the window without a real one for tests: a temporary library, the default config and an egui
+// context, widgets are clicked through the names screen readers get
+pub struct Harness {
+    pub app: Application,
+    ctx: Context,
+    dir: PathBuf,               // temporary folder of the library and test files
+    nodes: Vec<(NodeId, Node)>, // widgets of the last frame
+    events: Vec<Event>,         // input of the next frame
+    idle_tasks: usize,          // tasks that keep running, like the maintenance of the pool
+}
+
+impl Harness {
+    // needs a multi-threaded tokio runtime, the DB writes are waited for with block_in_place
+    pub async fn new() -> Self {
+        let dir = std::env::temp_dir().join(format!("saveit-test-{}", Uuid::new_v4().simple()));
+        fs::create_dir_all(&dir).expect("Error creating test folder");
+
+        let options = SqliteConnectOptions::new()
+            .filename(dir.join("library.db"))
+            .create_if_missing(true);
+        let pool = SqlitePool::connect_with(options)
+            .await
+            .expect("Error creating test library");
+        MIGRATOR
+            .run(&pool)
+            .await
+            .expect("Error executing database migrations");
+        let idle_tasks = Handle::current().metrics().num_alive_tasks();
+
+        let ctx = Context::default();
+        ctx.enable_accesskit();
+        // saving doesn't go online to resolve the links of the test sources
+        let config = Config {
+            resolve_urls: false,
+            ..Config::default()
+        };
+        let app = Application::build(&ctx, Arc::new(pool), false, config);
+
+        let mut harness = Self {
+            app,
+            ctx,
+            dir,
+            nodes: vec![],
+            events: vec![],
+            idle_tasks,
+        };
+        harness.settle();
+        harness
+    }
+
+    // file in the temporary folder, removed with the harness
+    pub fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(name)
+    }
+
+    // runs a frame with the queued input
+    pub fn frame(&mut self) {
+        let input = RawInput {
+            screen_rect: Some(Rect::from_min_size(Pos2::ZERO, Vec2::new(1200.0, 900.0))),
+            events: mem::take(&mut self.events),
+            ..Default::default()
+        };
+
+        let app = &mut self.app;
+        let output = self.ctx.run(input, |ctx| app.show(ctx));
+        self.nodes = output
+            .platform_output
+            .accesskit_update
+            .map(|update| update.nodes)
+            .unwrap_or_default();
+    }
+
+    // waits for the DB writes and cache updates the last frames started, then shows the result
+    pub fn settle(&mut self) {
+        let started = Instant::now();
+
+        tokio::task::block_in_place(|| loop {
+            wait_for_pending();
+
+            if Handle::current().metrics().num_alive_tasks() <= self.idle_tasks {
+                break;
+            }
+            if started.elapsed() > SETTLE_TIMEOUT {
+                panic!("Background tasks didn't finish");
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        });
+
+        // the first frame lays out windows that were just opened
+        self.frame();
+        self.frame();
+    }
+
+    pub fn show_page(&mut self, page: AppPage) {
+        self.click(&page.to_string());
+    }
+
+    // clicks the only enabled widget with the name, e.g. a button's text or the name given with
+    // accessible_name
+    pub fn click(&mut self, name: &str) {
+        let found: Vec<&(NodeId, Node)> = self
+            .nodes
+            .iter()
+            .filter(|(_, node)| node.name() == Some(name) && !node.is_disabled())
+            .collect();
+
+        let target = match found.as_slice() {
+            [(id, _)] => *id,
+            [] => panic!("No enabled widget \"{}\", found {:?}", name, self.names()),
+            _ => panic!("{} widgets \"{}\"", found.len(), name),
+        };
+
+        self.events
+            .push(Event::AccessKitActionRequest(ActionRequest {
+                action: accesskit::Action::Default,
+                target,
+                data: None,
+            }));
+        self.frame();
+        self.settle();
+    }
+
+    pub fn press(&mut self, key: Key, modifiers: Modifiers) {
+        self.events.push(Event::Key {
+            key,
+            physical_key: None,
+            pressed: true,
+            repeat: false,
+            modifiers,
+        });
+        self.frame();
+        self.settle();
+    }
+
+    // names of the widgets of the last frame
+    pub fn names(&self) -> Vec<&str> {
+        self.nodes
+            .iter()
+            .filter_map(|(_, node)| node.name())
+            .collect()
+    }
+
+    pub fn shows(&self, name: &str) -> bool {
+        self.names().contains(&name)
+    }
+
+    // sources in the library file, not the cache
+    pub fn sources(&self) -> Vec<Source> {
+        let pool = self.app.pool.clone();
+
+        tokio::task::block_in_place(|| {
+            Handle::current()
+                .block_on(async { get_all_sources(&pool).await.expect("Error loading sources") })
+        })
+    }
+}
+
+impl Drop for Harness {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_dir_all(&self.dir) {
+            eprintln!("Error removing {}: {}", self.dir.display(), e);
+        }
+    }
+}
+
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+    use crate::database::insert_source;
+    use crate::formats;
+    use crate::ui::{all_citations, import_dialog};
+
+    fn source(title: &str, url: &str) -> Source {
+        Source {
+            title: title.to_string(),
+            url: url.to_string(),
+            published_date: NaiveDate::from_ymd_opt(2021, 4, 2).unwrap(),
+            viewed_date: NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(),
+            ..Source::default()
+        }
+    }
+
+    fn titles(sources: &[Source]) -> Vec<&str> {
+        sources.iter().map(|source| source.title.as_str()).collect()
+    }
+
+    // saves a source from the start page and opens the list
+    async fn with_source(title: &str) -> Harness {
+        let mut harness = Harness::new().await;
+        harness.show_page(AppPage::Start);
+        harness.app.source_input.title = title.to_string();
+        harness.app.source_input.url = "https://example.com/rust".to_string();
+        harness.click("Save");
+        harness.show_page(AppPage::List);
+        harness
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn save_source() {
+        let harness = with_source("Rust in Action").await;
+
+        let sources = harness.sources();
+        assert_eq!(titles(&sources), ["Rust in Action"]);
+        assert_eq!(sources[0].url, "https://example.com/rust");
+        assert!(!sources[0].uuid.is_empty());
+        assert!(harness.shows("Edit \"Rust in Action\""));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn edit_source() {
+        let mut harness = with_source("Rust in Action").await;
+
+        harness.click("Edit \"Rust in Action\"");
+        assert!(harness.app.edit_modal.open);
+        harness.app.edit_modal.source.title = "Programming Rust".to_string();
+        harness.app.edit_modal.source.notes_written = true;
+        harness.click("Save");
+
+        assert!(!harness.app.edit_modal.open);
+        let sources = harness.sources();
+        assert_eq!(titles(&sources), ["Programming Rust"]);
+        assert!(sources[0].notes_written);
+        assert!(harness.shows("Edit \"Programming Rust\""));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn delete_and_undo() {
+        let mut harness = with_source("Rust in Action").await;
+        let id = harness.sources()[0].id;
+
+        harness.click("Delete \"Rust in Action\"");
+        assert!(harness.sources().is_empty());
+        assert!(!harness.shows("Edit \"Rust in Action\""));
+
+        harness.press(Key::Z, Modifiers::COMMAND);
+        let sources = harness.sources();
+        assert_eq!(titles(&sources), ["Rust in Action"]);
+        assert_eq!(sources[0].id, id);
+    }
+
+    // the library refuses the statement from now on, e.g. "UPDATE" or "DELETE"
+    async fn fail_writes(harness: &Harness, statement: &str) {
+        let trigger = format!(
+            "CREATE TRIGGER fail_{0} BEFORE {0} ON sources BEGIN SELECT RAISE(ABORT, 'disk full'); END",
+            statement
+        );
+        sqlx::query(&trigger)
+            .execute(&*harness.app.pool)
+            .await
+            .unwrap();
+    }
+
+    fn shows_error(harness: &Harness, error: &str) -> bool {
+        harness.names().iter().any(|name| name.starts_with(error))
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn failed_edit_is_rolled_back() {
+        let mut harness = with_source("Rust in Action").await;
+        fail_writes(&harness, "UPDATE").await;
+
+        harness.click("Edit \"Rust in Action\"");
+        harness.app.edit_modal.source.title = "Programming Rust".to_string();
+        harness.click("Save");
+
+        assert_eq!(titles(&harness.sources()), ["Rust in Action"]);
+        assert!(harness.shows("Edit \"Rust in Action\""));
+        assert!(!harness.shows("Edit \"Programming Rust\""));
+        assert!(
+            shows_error(&harness, "Error saving \"Programming Rust\""),
+            "{:?}",
+            harness.names()
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn failed_delete_is_rolled_back() {
+        let mut harness = with_source("Rust in Action").await;
+        fail_writes(&harness, "DELETE").await;
+
+        harness.click("Delete \"Rust in Action\"");
+
+        assert_eq!(titles(&harness.sources()), ["Rust in Action"]);
+        assert!(harness.shows("Edit \"Rust in Action\""));
+        assert!(
+            shows_error(&harness, "Error deleting source"),
+            "{:?}",
+            harness.names()
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn import_skips_duplicates() {
+        let mut harness = with_source("Rust in Action").await;
+
+        let path = harness.path("import.json");
+        let content = formats::by_id("saveit-json")
+            .unwrap()
+            .export(&[
+                source("Rust in Action", "https://example.com/rust"),
+                source("Zero To Production", "https://example.com/zero"),
+            ])
+            .unwrap();
+        fs::write(&path, content).unwrap();
+
+        import_dialog::open_file(&path, &mut harness.app);
+        harness.settle();
+        assert!(harness.shows("Import 1 sources"), "{:?}", harness.names());
+        harness.click("Import 1 sources");
+
+        assert!(!harness.app.import_dialog.open);
+        let mut sources = harness.sources();
+        sources.sort_by(|a, b| a.title.cmp(&b.title));
+        assert_eq!(titles(&sources), ["Rust in Action", "Zero To Production"]);
+    }
+
+    // cargo test --features ui-tests list_frame_time -- --ignored --nocapture
+    #[tokio::test(flavor = "multi_thread")]
+    #[ignore = "measures the frame time of a large library"]
+    async fn list_frame_time() {
+        const SOURCES: usize = 5000;
+        const FRAMES: u32 = 20;
+
+        let mut harness = Harness::new().await;
+        let pool = harness.app.pool.clone();
+        let mut transaction = pool.begin().await.unwrap();
+        for index in 0..SOURCES {
+            let source = source(
+                &format!("Source {}", index),
+                &format!("https://example.com/{}", index),
+            );
+            insert_source(&source, &mut *transaction).await.unwrap();
+        }
+        transaction.commit().await.unwrap();
+        harness.app.load_source_cache().await;
+
+        harness.show_page(AppPage::List);
+        assert_eq!(harness.app.sources_cache.read().unwrap().len(), SOURCES);
+
+        let started = Instant::now();
+        for _ in 0..FRAMES {
+            harness.frame();
+        }
+        println!(
+            "{:?} per frame with {} sources",
+            started.elapsed() / FRAMES,
+            SOURCES
+        );
+    }
+
+    // cargo test --features ui-tests copy_all_time -- --ignored --nocapture
+    #[tokio::test(flavor = "multi_thread")]
+    #[ignore = "measures copying a large library"]
+    async fn copy_all_time() {
+        const SOURCES: usize = 5000;
+
+        let harness = Harness::new().await;
+        let sources: Vec<Source> = (0..SOURCES)
+            .map(|index| Source {
+                id: index as i64,
+                ..source(
+                    &format!("Source {}", index),
+                    &format!("https://example.com/{}", index),
+                )
+            })
+            .collect();
+
+        let started = Instant::now();
+        let text = all_citations(&sources, &harness.app);
+        assert_eq!(text.lines().count(), SOURCES);
+        println!("{:?} to copy {} sources", started.elapsed(), SOURCES);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn pages_render() {
+        let mut harness = with_source("Rust in Action").await;
+
+        for page in [
+            AppPage::Timeline,
+            AppPage::Graph,
+            AppPage::Settings,
+            AppPage::Help,
+            AppPage::List,
+        ] {
+            harness.show_page(page);
+            assert_eq!(harness.app.curr_page, page);
+        }
+    }
+}
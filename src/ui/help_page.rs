@@ -0,0 +1,12 @@
+use egui::{ScrollArea, Ui};
+use egui_commonmark::CommonMarkViewer;
+
+use crate::ui::Application;
+
+const HELP_TEXT: &str = include_str!("../../assets/help.md");
+
+pub fn render(app: &mut Application, ui: &mut Ui) {
+    ScrollArea::vertical().auto_shrink(false).show(ui, |ui| {
+        CommonMarkViewer::new("HelpPage").show(ui, &mut app.markdown_cache, HELP_TEXT);
+    });
+}
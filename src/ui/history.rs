@@ -0,0 +1,118 @@
+use std::sync::{Arc, RwLock};
+
+use chrono::{Local, NaiveDateTime, TimeZone, Utc};
+use egui::{Grid, ScrollArea, Ui};
+use sqlx::SqlitePool;
+use tracing::*;
+
+use crate::database::get_source_history;
+use crate::history::Change;
+use crate::source::Source;
+use crate::ui::commands::{self, UpdateSource};
+use crate::ui::{accessible_name, truncated, Application};
+
+// changes of the source of the edit modal
+#[derive(Default)]
+pub struct History {
+    changes: Arc<RwLock<Vec<Change>>>,
+    loaded: Option<(i64, Option<NaiveDateTime>)>, // source id and change time the changes are of
+}
+
+// history tab of the edit modal, reloaded whenever the source changes
+pub fn render(app: &mut Application, ui: &mut Ui) {
+    let id = app.edit_modal.source.id;
+    let current = app.sources_cache.read().unwrap().get(id);
+    let Some(current) = current else {
+        ui.label("The source isn't in the library");
+        return;
+    };
+
+    let history = &mut app.edit_modal.history;
+    if history.loaded != Some((id, current.updated_at)) {
+        history.loaded = Some((id, current.updated_at));
+        handle_load(id, history.changes.clone(), app.pool.clone());
+    }
+
+    let changes = history.changes.read().unwrap().clone();
+    let changes: Vec<Change> = changes
+        .into_iter()
+        .filter(|change| change.source_id == id)
+        .collect();
+    if changes.is_empty() {
+        ui.label("No changes yet");
+        return;
+    }
+
+    let mut reverted = None;
+
+    ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+        Grid::new("SourceHistory")
+            .num_columns(5)
+            .striped(true)
+            .show(ui, |ui| {
+                ui.strong("Changed");
+                ui.strong("Field");
+                ui.strong("Before");
+                ui.strong("After");
+                ui.label("");
+                ui.end_row();
+
+                for change in &changes {
+                    let changed_at = Utc.from_utc_datetime(&change.changed_at);
+                    ui.label(
+                        changed_at
+                            .with_timezone(&Local)
+                            .format("%Y-%m-%d %H:%M")
+                            .to_string(),
+                    );
+                    ui.label(change.field_name());
+                    ui.label(truncated(&change.old_text(), 40))
+                        .on_hover_text(change.old_text());
+                    ui.label(truncated(&change.new_text(), 40))
+                        .on_hover_text(change.new_text());
+
+                    let button = ui.add_enabled(!app.read_only, egui::Button::new("Revert"));
+                    let button = accessible_name(
+                        button,
+                        format!(
+                            "Revert {} to \"{}\"",
+                            change.field_name(),
+                            truncated(&change.old_text(), 40)
+                        ),
+                    );
+                    if button.clicked() {
+                        reverted = Some(change.clone());
+                    }
+                    ui.end_row();
+                }
+            });
+    });
+
+    if let Some(change) = reverted {
+        trace!("Revert change {} of source {} clicked", change.id, id);
+        revert(&change, current, app, ui);
+    }
+}
+
+// saves the old value of the field, can be undone like an edit
+fn revert(change: &Change, current: Source, app: &mut Application, ui: &Ui) {
+    let mut after = current.clone();
+    if !change.revert(&mut after) {
+        warn!("Can't revert {:?}", change);
+        return;
+    }
+
+    // unsaved edits of other fields in the modal are kept
+    change.revert(&mut app.edit_modal.source);
+    commands::run(UpdateSource::new(current, after), app, ui.ctx());
+}
+
+// async history load
+fn handle_load(id: i64, changes: Arc<RwLock<Vec<Change>>>, pool: Arc<SqlitePool>) {
+    tokio::task::spawn(async move {
+        match get_source_history(id, &pool).await {
+            Ok(history) => *changes.write().unwrap() = history,
+            Err(e) => error!("Error loading history of source {}: {}", id, e),
+        }
+    });
+}
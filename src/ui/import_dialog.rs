@@ -0,0 +1,433 @@
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use egui::{CollapsingHeader, ComboBox, Context, ProgressBar, ScrollArea, Ui};
+use tracing::*;
+
+use crate::crash::record_operation;
+use crate::formats::{self, Format};
+use crate::import::{DuplicateIndex, ImportIds, ImportItem, ImportProgress};
+use crate::source::Source;
+use crate::ui::file_dialog::{self, FilePurpose, FileRequest};
+use crate::ui::handlers::{handle_import_sources, handle_stream_import};
+use crate::ui::{escape_pressed, Application};
+
+// larger files of formats that can be streamed are imported without a preview
+const PREVIEW_LIMIT: u64 = 16 * 1024 * 1024;
+
+// what the folders of imported bookmarks become, for read-later exports the folder is the
+// list an article was in (e.g. "Unread" or "Archive")
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FolderMapping {
+    Tags,       // every folder becomes a tag
+    Collection, // the innermost folder becomes the collection
+    Ignore,
+}
+
+impl FolderMapping {
+    const ALL: [FolderMapping; 3] = [
+        FolderMapping::Tags,
+        FolderMapping::Collection,
+        FolderMapping::Ignore,
+    ];
+}
+
+impl Display for FolderMapping {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FolderMapping::Tags => write!(f, "Tags"),
+            FolderMapping::Collection => write!(f, "Collection"),
+            FolderMapping::Ignore => write!(f, "Ignore"),
+        }
+    }
+}
+
+struct ImportRow {
+    item: ImportItem,
+    duplicate: bool, // already in the library or earlier in the file
+    selected: bool,
+}
+
+// file too large for a preview, imported while it is read
+struct StreamImport {
+    path: PathBuf,
+    size: u64,
+    progress: Option<Arc<ImportProgress>>, // set once the import started
+}
+
+// preview of an import file, duplicates aren't imported unless selected
+pub struct ImportDialog {
+    pub open: bool,
+    format: Option<&'static dyn Format>,
+    rows: Vec<ImportRow>,
+    errors: Vec<String>, // entries of the file that couldn't be read
+    folder_mapping: FolderMapping,
+    ids: ImportIds,
+    stream: Option<StreamImport>,
+}
+
+impl Default for ImportDialog {
+    fn default() -> Self {
+        Self {
+            open: false,
+            format: None,
+            rows: vec![],
+            errors: vec![],
+            folder_mapping: FolderMapping::Tags,
+            ids: ImportIds::New,
+            stream: None,
+        }
+    }
+}
+
+impl ImportDialog {
+    fn source(&self, row: &ImportRow) -> Source {
+        let mut source = row.item.source.clone();
+
+        match self.folder_mapping {
+            FolderMapping::Tags => source.add_tags(&row.item.folders.join(",")),
+            FolderMapping::Collection => {
+                if let Some(folder) = row.item.folders.last() {
+                    source.collection = folder.clone();
+                }
+            }
+            FolderMapping::Ignore => {}
+        }
+
+        source
+    }
+}
+
+// asks for a file to import
+pub fn pick_file(app: &mut Application, ctx: &Context) {
+    record_operation("Import sources");
+    let extensions = formats::import_extensions();
+    let mut request = FileRequest::open(FilePurpose::ImportSources, "Select File")
+        .add_filter("Supported files", &extensions);
+    for format in formats::importers() {
+        request = request.add_filter(format.name(), format.extensions());
+    }
+    file_dialog::show(request, app, ctx);
+}
+
+// reads the picked file and opens the preview
+pub fn open_file(path: &Path, app: &mut Application) {
+    // large files of formats that can be streamed skip the preview
+    let size = fs::metadata(path)
+        .map(|metadata| metadata.len())
+        .unwrap_or(0);
+    if size > PREVIEW_LIMIT {
+        match formats::detect_prefix(path) {
+            Ok(Some(format)) if format.can_stream() => {
+                app.file_error = None;
+                let dialog = &mut app.import_dialog;
+                dialog.format = Some(format);
+                dialog.rows.clear();
+                dialog.errors.clear();
+                dialog.stream = Some(StreamImport {
+                    path: path.to_path_buf(),
+                    size,
+                    progress: None,
+                });
+                dialog.open = true;
+                return;
+            }
+            Ok(_) => {}
+            Err(e) => {
+                error!("Error reading import file: {}", e);
+                app.file_error = Some(format!("Error reading import file: {}", e));
+                return;
+            }
+        }
+    }
+
+    match formats::read_file(path) {
+        Ok((format, report)) => {
+            let mut known = DuplicateIndex::new(&app.sources_cache.read().unwrap().to_vec());
+            let rows = report
+                .items
+                .into_iter()
+                .map(|item| {
+                    let duplicate = known.contains(&item.source);
+                    known.insert(&item.source);
+
+                    ImportRow {
+                        item,
+                        duplicate,
+                        selected: !duplicate,
+                    }
+                })
+                .collect();
+
+            app.file_error = None;
+            let dialog = &mut app.import_dialog;
+            dialog.format = Some(format);
+            dialog.rows = rows;
+            dialog.errors = report.errors;
+            dialog.stream = None;
+            dialog.open = true;
+        }
+        Err(e) => {
+            error!("Error reading import file: {}", e);
+            app.file_error = Some(format!("Error reading import file: {}", e));
+        }
+    }
+}
+
+pub fn render(app: &mut Application, ctx: &Context) {
+    if !app.import_dialog.open {
+        return;
+    }
+
+    let mut open = app.import_dialog.open;
+    let mut imported = false;
+
+    let title = match app.import_dialog.format {
+        Some(format) => format!("Import {}", format.name()),
+        None => "Import".to_string(),
+    };
+    egui::Window::new(title)
+        .collapsible(false)
+        .open(&mut open)
+        .show(ctx, |ui| {
+            if app.import_dialog.stream.is_some() {
+                render_stream(app, ui, ctx);
+                return;
+            }
+
+            let mut used_ids: HashSet<i64> = app
+                .sources_cache
+                .read()
+                .unwrap()
+                .iter()
+                .map(|source| source.id())
+                .collect();
+
+            let dialog = &mut app.import_dialog;
+            let selected = dialog.rows.iter().filter(|row| row.selected).count();
+            // selected sources whose id is in the library or earlier in the file
+            let conflicts = dialog
+                .rows
+                .iter()
+                .filter(|row| row.selected && row.item.source.id > 0)
+                .filter(|row| !used_ids.insert(row.item.source.id))
+                .count();
+            let has_ids = dialog.rows.iter().any(|row| row.item.source.id > 0);
+            let duplicates = dialog.rows.iter().filter(|row| row.duplicate).count();
+
+            ui.label(format!(
+                "{} entries, {} selected, {} already in the library",
+                dialog.rows.len(),
+                selected,
+                duplicates
+            ));
+            render_errors(&dialog.errors, ui);
+
+            ui.horizontal(|ui| {
+                ComboBox::from_label("Folders as")
+                    .selected_text(dialog.folder_mapping.to_string())
+                    .show_ui(ui, |ui| {
+                        for mapping in FolderMapping::ALL {
+                            ui.selectable_value(
+                                &mut dialog.folder_mapping,
+                                mapping,
+                                mapping.to_string(),
+                            );
+                        }
+                    });
+
+                if ui.button("Select new").clicked() {
+                    for row in &mut dialog.rows {
+                        row.selected = !row.duplicate;
+                    }
+                }
+                if ui.button("Select all").clicked() {
+                    for row in &mut dialog.rows {
+                        row.selected = true;
+                    }
+                }
+                if ui.button("Select none").clicked() {
+                    for row in &mut dialog.rows {
+                        row.selected = false;
+                    }
+                }
+            });
+
+            if has_ids {
+                render_ids(&mut dialog.ids, ui);
+                match dialog.ids {
+                    ImportIds::Keep if conflicts > 0 => {
+                        ui.colored_label(
+                            ui.visuals().error_fg_color,
+                            format!("The IDs of {} sources are already used", conflicts),
+                        );
+                    }
+                    ImportIds::Remap if conflicts > 0 => {
+                        ui.label(format!(
+                            "{} sources get new IDs, their IDs are already used",
+                            conflicts
+                        ));
+                    }
+                    _ => {}
+                }
+            }
+
+            ui.add_space(5.0);
+
+            // preview with the folder mapping applied
+            let previews: Vec<Source> = dialog.rows.iter().map(|row| dialog.source(row)).collect();
+            ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                for (row, source) in dialog.rows.iter_mut().zip(&previews) {
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut row.selected, &source.title);
+                        if row.duplicate {
+                            ui.weak("(duplicate)");
+                        }
+                    });
+                    ui.indent(&source.url, |ui| {
+                        ui.weak(&source.url);
+                        let mut details = vec![format!("Viewed {}", source.viewed_date)];
+                        if !source.tags.is_empty() {
+                            details.push(format!("Tags: {}", source.tags));
+                        }
+                        if !source.collection.is_empty() {
+                            details.push(format!("Collection: {}", source.collection));
+                        }
+                        ui.weak(details.join(" · "));
+                    });
+                }
+            });
+
+            ui.add_space(5.0);
+
+            let import_button = egui::Button::new(format!("Import {} sources", selected));
+            let blocked = dialog.ids == ImportIds::Keep && conflicts > 0;
+            if ui
+                .add_enabled(selected > 0 && !blocked, import_button)
+                .clicked()
+            {
+                trace!("Import clicked");
+
+                let sources = dialog
+                    .rows
+                    .iter()
+                    .zip(previews)
+                    .filter(|(row, _)| row.selected)
+                    .map(|(_, source)| source)
+                    .collect();
+                handle_import_sources(sources, dialog.ids, app);
+                imported = true;
+            }
+        });
+
+    if imported || escape_pressed(ctx) {
+        open = false;
+    }
+    app.import_dialog.open = open;
+    if !open {
+        // a running streaming import continues in the background
+        app.import_dialog.rows.clear();
+        app.import_dialog.errors.clear();
+        app.import_dialog.stream = None;
+    }
+}
+
+fn render_stream(app: &mut Application, ui: &mut Ui, ctx: &Context) {
+    let Some(stream) = &mut app.import_dialog.stream else {
+        return;
+    };
+
+    ui.label(format!(
+        "{} ({:.1} MB) is too large for a preview.",
+        stream.path.display(),
+        stream.size as f64 / 1_000_000.0
+    ));
+    ui.label(
+        "Sources are imported while the file is read, sources already in the library are skipped.",
+    );
+
+    ui.add_space(5.0);
+
+    match &stream.progress {
+        None => {
+            render_ids(&mut app.import_dialog.ids, ui);
+            if app.import_dialog.ids == ImportIds::Keep {
+                ui.label("The import stops at the first source whose ID is already used.");
+            }
+
+            if ui.button("Import").clicked() {
+                trace!("Import clicked");
+
+                let progress = Arc::new(ImportProgress::default());
+                stream.progress = Some(progress.clone());
+                let path = stream.path.clone();
+                let format = app.import_dialog.format.expect("Format of the import file");
+                handle_stream_import(path, format, app.import_dialog.ids, progress, app);
+            }
+        }
+        Some(progress) => {
+            let imported = progress.imported.load(Ordering::Relaxed);
+            let duplicates = progress.duplicates.load(Ordering::Relaxed);
+            let done = progress.done.load(Ordering::Relaxed);
+
+            ui.add(ProgressBar::new(progress.fraction()).text(format!(
+                "{} imported, {} duplicates skipped",
+                imported, duplicates
+            )));
+            render_errors(&progress.skipped.read().unwrap(), ui);
+
+            if let Some(error) = &*progress.error.read().unwrap() {
+                ui.colored_label(
+                    ui.visuals().error_fg_color,
+                    format!("Error importing: {}", error),
+                );
+            } else if done {
+                ui.label("Done");
+            } else {
+                ctx.request_repaint_after(Duration::from_millis(100));
+            }
+        }
+    }
+}
+
+// what happens to the ids of the file
+fn render_ids(ids: &mut ImportIds, ui: &mut Ui) {
+    ComboBox::from_label("IDs")
+        .selected_text(ids.to_string())
+        .show_ui(ui, |ui| {
+            for option in ImportIds::ALL {
+                ui.selectable_value(ids, option, option.to_string());
+            }
+        })
+        .response
+        .on_hover_text(
+            "Keeping the IDs keeps citation numbers valid, e.g. after moving to another computer",
+        );
+}
+
+// entries that were skipped because they couldn't be read
+fn render_errors(errors: &[String], ui: &mut Ui) {
+    if errors.is_empty() {
+        return;
+    }
+
+    CollapsingHeader::new(format!(
+        "{} entries couldn't be read and are skipped",
+        errors.len()
+    ))
+    .show(ui, |ui| {
+        ScrollArea::vertical()
+            .id_source("import errors")
+            .max_height(150.0)
+            .show(ui, |ui| {
+                for error in errors {
+                    ui.colored_label(ui.visuals().warn_fg_color, error);
+                }
+            });
+    });
+}
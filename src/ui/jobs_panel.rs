@@ -0,0 +1,124 @@
+use std::time::Duration;
+
+use egui::{Context, Grid, RichText, ScrollArea, Ui};
+use tracing::*;
+
+use crate::scheduler::{self, Job, JobState, MAX_RUNNING};
+use crate::ui::{escape_pressed, truncated, Application};
+
+// running, queued and failed network jobs of all features
+pub fn render(app: &mut Application, ctx: &Context) {
+    if !app.jobs_panel_open {
+        return;
+    }
+
+    let mut open = true;
+    let jobs = scheduler::jobs();
+
+    egui::Window::new("Network jobs")
+        .collapsible(false)
+        .open(&mut open)
+        .show(ctx, |ui| {
+            ui.label(format!(
+                "At most {} requests run at the same time, timeouts and server errors are retried.",
+                MAX_RUNNING
+            ));
+
+            ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                render_jobs(ui, "Running", &jobs, |state| *state == JobState::Running);
+                render_jobs(ui, "Queued", &jobs, |state| {
+                    matches!(state, JobState::Queued | JobState::Retrying(_))
+                });
+                render_jobs(ui, "Failed", &jobs, |state| {
+                    matches!(state, JobState::Failed(_))
+                });
+            });
+
+            let failed = jobs
+                .iter()
+                .any(|job| matches!(job.state, JobState::Failed(_)));
+            if ui
+                .add_enabled(failed, egui::Button::new("Clear failed"))
+                .clicked()
+            {
+                trace!("Clear failed jobs clicked");
+                scheduler::clear_failed();
+            }
+
+            // the saved queue of the library, see queue.rs
+            let queue = app.queue.status.read().unwrap();
+            if !queue.jobs.is_empty() {
+                ui.separator();
+                ui.strong(format!("Waiting for the connection ({})", queue.jobs.len()));
+                for job in &queue.jobs {
+                    ui.label(format!(
+                        "{} for source {} ({} attempts)",
+                        job.kind, job.source_id, job.attempts
+                    ));
+                }
+            }
+        });
+
+    // the jobs change without any input
+    if jobs
+        .iter()
+        .any(|job| !matches!(job.state, JobState::Failed(_)))
+    {
+        ctx.request_repaint_after(Duration::from_millis(500));
+    }
+
+    if !open || escape_pressed(ctx) {
+        app.jobs_panel_open = false;
+    }
+}
+
+fn render_jobs(ui: &mut Ui, heading: &str, jobs: &[Job], state: impl Fn(&JobState) -> bool) {
+    let jobs: Vec<&Job> = jobs.iter().filter(|job| state(&job.state)).collect();
+    ui.strong(format!("{} ({})", heading, jobs.len()));
+    if jobs.is_empty() {
+        return;
+    }
+
+    Grid::new(heading)
+        .num_columns(3)
+        .striped(true)
+        .show(ui, |ui| {
+            for job in jobs {
+                ui.label(truncated(&job.name, 60))
+                    .on_hover_text(job.name.as_str());
+                ui.label(job.state.to_string());
+                ui.label(format!("{} s", job.since.elapsed().as_secs()));
+                ui.end_row();
+            }
+        });
+    ui.add_space(5.0);
+}
+
+// number of running and failed jobs next to the About button, opens the panel
+pub fn render_status(app: &mut Application, ui: &mut Ui) {
+    let jobs = scheduler::jobs();
+    let failed = jobs
+        .iter()
+        .filter(|job| matches!(job.state, JobState::Failed(_)))
+        .count();
+    let active = jobs.len() - failed;
+
+    let text = match (active, failed) {
+        (0, 0) => return,
+        (active, 0) => format!("{} network jobs", active),
+        (0, failed) => format!("{} failed", failed),
+        (active, failed) => format!("{} network jobs, {} failed", active, failed),
+    };
+    let label = egui::Label::new(RichText::new(text).weak()).sense(egui::Sense::click());
+    if ui
+        .add(label)
+        .on_hover_text("Show the network jobs")
+        .clicked()
+    {
+        app.jobs_panel_open = true;
+    }
+
+    if active > 0 {
+        ui.ctx().request_repaint_after(Duration::from_secs(1));
+    }
+}
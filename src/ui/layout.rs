@@ -0,0 +1,51 @@
+use egui::{Grid, Ui};
+
+use crate::ui::TEXT_INPUT_WIDTH;
+
+// below this width forms put their labels above the inputs
+const NARROW_WIDTH: f32 = 600.0;
+const LABEL_COLUMN_WIDTH: f32 = 130.0;
+const MIN_INPUT_WIDTH: f32 = 120.0;
+
+// TEXT_INPUT_WIDTH or less if the rest of the row is narrower
+pub fn input_width(ui: &Ui) -> f32 {
+    TEXT_INPUT_WIDTH
+        .min(ui.available_width())
+        .max(MIN_INPUT_WIDTH)
+}
+
+// two column form of labels and inputs that stacks them in narrow windows
+pub struct FormLayout {
+    narrow: bool,
+    pub input_width: f32,
+}
+
+impl FormLayout {
+    // width the form can take up
+    pub fn new(width: f32) -> Self {
+        let narrow = width < NARROW_WIDTH;
+        let input_width = match narrow {
+            true => width,
+            false => width - LABEL_COLUMN_WIDTH,
+        };
+
+        Self {
+            narrow,
+            input_width: input_width.clamp(MIN_INPUT_WIDTH, TEXT_INPUT_WIDTH),
+        }
+    }
+
+    pub fn grid(&self, id: &str) -> Grid {
+        Grid::new(id).num_columns(match self.narrow {
+            true => 1,
+            false => 2,
+        })
+    }
+
+    // called between a label and its input, puts the input in the next row if the window is narrow
+    pub fn after_label(&self, ui: &mut Ui) {
+        if self.narrow {
+            ui.end_row();
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use egui::{Button, Context, ScrollArea};
+use sqlx::SqlitePool;
+use tracing::*;
+
+use crate::database::{get_all_links, get_all_quotes, get_all_sources};
+use crate::integrity::{self, Problem, Repair};
+use crate::tasks::spawn_tracked;
+use crate::ui::{escape_pressed, Application};
+
+#[derive(Debug, Clone, Default)]
+enum CheckStatus {
+    #[default]
+    NotChecked,
+    Checking,
+    Checked {
+        problems: Vec<Problem>,
+        quarantined: i64, // rows in the quarantine table
+    },
+    Failed(String),
+}
+
+// dialog of the "Check library" tool
+#[derive(Default)]
+pub struct LibraryCheck {
+    pub open: bool,
+    status: Arc<RwLock<CheckStatus>>,
+}
+
+// opens the dialog and checks the library right away
+pub fn start(app: &mut Application) {
+    app.library_check.open = true;
+    handle_check(app.library_check.status.clone(), app.pool.clone());
+}
+
+pub fn render(app: &mut Application, ctx: &Context) {
+    if !app.library_check.open {
+        return;
+    }
+
+    let mut open = true;
+    let status = app.library_check.status.read().unwrap().clone();
+    let mut repair = None;
+
+    egui::Window::new("Check library")
+        .collapsible(false)
+        .open(&mut open)
+        .show(ctx, |ui| match &status {
+            CheckStatus::NotChecked => {}
+            CheckStatus::Checking => {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label("Checking…");
+                });
+                ctx.request_repaint_after(Duration::from_millis(100));
+            }
+            CheckStatus::Failed(error) => {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+            }
+            CheckStatus::Checked {
+                problems,
+                quarantined,
+            } => {
+                if problems.is_empty() {
+                    ui.label("No problems found");
+                } else {
+                    ui.label(format!("{} problems found:", problems.len()));
+                }
+
+                ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                    for problem in problems {
+                        ui.horizontal_wrapped(|ui| {
+                            let can_repair = !app.read_only;
+                            if problem.can_fix()
+                                && ui.add_enabled(can_repair, Button::new("Fix")).clicked()
+                            {
+                                repair = Some((vec![problem.clone()], Repair::Fix));
+                            }
+                            if problem.can_quarantine()
+                                && ui
+                                    .add_enabled(can_repair, Button::new("Quarantine"))
+                                    .clicked()
+                            {
+                                repair = Some((vec![problem.clone()], Repair::Quarantine));
+                            }
+                            ui.label(problem.to_string());
+                        });
+                    }
+                });
+
+                if problems.iter().any(|problem| matches!(problem, Problem::Corruption(_))) {
+                    ui.label("The database file is damaged, restore a backup or an export to keep your sources safe.");
+                }
+                if *quarantined > 0 {
+                    ui.label(format!(
+                        "{} rows are in the quarantine table of the database",
+                        quarantined
+                    ));
+                }
+
+                ui.add_space(5.0);
+
+                ui.horizontal(|ui| {
+                    let fixable: Vec<Problem> = problems
+                        .iter()
+                        .filter(|problem| problem.can_fix())
+                        .cloned()
+                        .collect();
+                    let fix_all = Button::new("Fix all");
+                    if ui
+                        .add_enabled(!app.read_only && !fixable.is_empty(), fix_all)
+                        .on_hover_text("Repairs dates and sync ids, deletes what belongs to deleted sources")
+                        .clicked()
+                    {
+                        repair = Some((fixable, Repair::Fix));
+                    }
+
+                    if ui.button("Check again").clicked() {
+                        trace!("Check library again clicked");
+                        handle_check(app.library_check.status.clone(), app.pool.clone());
+                    }
+                });
+            }
+        });
+
+    if let Some((problems, repair)) = repair {
+        trace!("Repair {} problems clicked", problems.len());
+        handle_repair(problems, repair, app);
+    }
+
+    if !open || escape_pressed(ctx) {
+        app.library_check.open = false;
+    }
+}
+
+// async check
+fn handle_check(status: Arc<RwLock<CheckStatus>>, pool: Arc<SqlitePool>) {
+    *status.write().unwrap() = CheckStatus::Checking;
+
+    tokio::task::spawn(async move {
+        let result = match integrity::check(&pool).await {
+            Ok(problems) => CheckStatus::Checked {
+                problems,
+                quarantined: integrity::quarantined_count(&pool).await.unwrap_or(0),
+            },
+            Err(e) => {
+                error!("Error checking library: {}", e);
+                CheckStatus::Failed(format!("Error checking library: {}", e))
+            }
+        };
+        *status.write().unwrap() = result;
+    });
+}
+
+// async repair, checks again afterwards
+fn handle_repair(problems: Vec<Problem>, repair: Repair, app: &Application) {
+    let status = app.library_check.status.clone();
+    let source_cache = app.sources_cache.clone();
+    let links_cache = app.links_cache.clone();
+    let quotes_cache = app.quotes_cache.clone();
+    let pool = app.pool.clone();
+    *status.write().unwrap() = CheckStatus::Checking;
+
+    spawn_tracked(async move {
+        for problem in &problems {
+            if let Err(e) = integrity::repair(problem, repair, &pool).await {
+                error!("Error repairing library: {}", e);
+                *status.write().unwrap() =
+                    CheckStatus::Failed(format!("Error repairing \"{}\": {}", problem, e));
+                return;
+            }
+        }
+
+        // sources with invalid dates couldn't be loaded before
+        if let Ok(sources) = get_all_sources(&pool).await {
+            *source_cache.write().unwrap() = sources.into();
+        }
+        if let Ok(links) = get_all_links(&pool).await {
+            *links_cache.write().unwrap() = links;
+        }
+        if let Ok(quotes) = get_all_quotes(&pool).await {
+            *quotes_cache.write().unwrap() = quotes;
+        }
+
+        handle_check(status, pool);
+    });
+}
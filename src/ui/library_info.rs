@@ -0,0 +1,185 @@
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Local};
+use egui::{Button, Grid, ProgressBar, Ui};
+use sqlx::SqlitePool;
+use tracing::*;
+
+use crate::crash::record_operation;
+use crate::database::{compact_database, get_library_stats, LibraryStats};
+use crate::tasks::spawn_tracked;
+use crate::ui::Application;
+
+// the counts change while SaveIt is running, so they are loaded again after a while
+const REFRESH_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Default)]
+enum CompactStatus {
+    #[default]
+    Idle,
+    Compacting,
+    Done(u64), // bytes reclaimed
+    Failed(String),
+}
+
+// size of the library on the settings page
+#[derive(Default)]
+pub struct LibraryInfo {
+    stats: Arc<RwLock<Option<LibraryStats>>>,
+    loaded_at: Option<Instant>,
+    compact: Arc<RwLock<CompactStatus>>,
+}
+
+pub fn render(app: &mut Application, ui: &mut Ui) {
+    let info = &mut app.library_info;
+    if info
+        .loaded_at
+        .is_none_or(|loaded_at| loaded_at.elapsed() > REFRESH_INTERVAL)
+    {
+        info.loaded_at = Some(Instant::now());
+        handle_load(info.stats.clone(), app.pool.clone());
+    }
+
+    ui.label("Library:");
+    ui.indent("LibraryInfo", |ui| {
+        match &*app.library_info.stats.read().unwrap() {
+            Some(stats) => {
+                ui.label(format!("File size: {}", file_size(stats.file_size)));
+                ui.label(format!(
+                    "{} sources, {} relations, {} quotes, {} changes in the history",
+                    stats.sources, stats.links, stats.quotes, stats.changes
+                ));
+                render_notes_progress(stats, ui);
+            }
+            None => {
+                ui.spinner();
+            }
+        }
+        ui.label(format!("Last automatic export: {}", last_export(app)));
+    });
+
+    let status = app.library_info.compact.read().unwrap().clone();
+    ui.horizontal_wrapped(|ui| {
+        let compacting = matches!(status, CompactStatus::Compacting);
+        let button = Button::new("Compact database");
+        if ui
+            .add_enabled(!app.read_only && !compacting, button)
+            .on_hover_text(
+                "Frees the space of deleted sources, can take a while for large libraries",
+            )
+            .clicked()
+        {
+            trace!("Compact database clicked");
+            handle_compact(app);
+        }
+
+        match status {
+            CompactStatus::Idle => {}
+            CompactStatus::Compacting => {
+                ui.spinner();
+                ui.ctx().request_repaint_after(Duration::from_millis(100));
+            }
+            CompactStatus::Done(reclaimed) => {
+                ui.label(format!("{} reclaimed", file_size(reclaimed)));
+            }
+            CompactStatus::Failed(error) => {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+            }
+        }
+    });
+}
+
+// sources with notes written in the whole library and per collection, sources without a
+// collection are only counted in the whole library
+fn render_notes_progress(stats: &LibraryStats, ui: &mut Ui) {
+    if stats.sources == 0 {
+        return;
+    }
+
+    let written: i64 = stats.notes.iter().map(|(_, written, _)| written).sum();
+    let collections = stats
+        .notes
+        .iter()
+        .filter(|(collection, _, _)| !collection.is_empty())
+        .map(|(collection, written, total)| (collection.as_str(), *written, *total));
+
+    ui.label("Notes written:");
+    Grid::new("NotesProgress").num_columns(2).show(ui, |ui| {
+        for (name, written, total) in [("All sources", written, stats.sources)]
+            .into_iter()
+            .chain(collections)
+        {
+            ui.label(name);
+            let progress = ProgressBar::new(written as f32 / total.max(1) as f32)
+                .text(format!("{}/{} sources", written, total))
+                .desired_width(200.0);
+            ui.add(progress);
+            ui.end_row();
+        }
+    });
+}
+
+// e.g. "1.4 MB"
+fn file_size(bytes: u64) -> String {
+    match bytes {
+        0..=999 => format!("{} bytes", bytes),
+        1_000..=999_999 => format!("{:.1} kB", bytes as f64 / 1_000.0),
+        _ => format!("{:.1} MB", bytes as f64 / 1_000_000.0),
+    }
+}
+
+// the automatic export is the backup of the library, the time of the file also covers exports of
+// earlier runs
+fn last_export(app: &Application) -> String {
+    let config = app.config.read().unwrap();
+    if !config.auto_export || config.auto_export_path.is_empty() {
+        return "turned off".to_string();
+    }
+
+    match Path::new(&config.auto_export_path)
+        .metadata()
+        .and_then(|metadata| metadata.modified())
+    {
+        Ok(modified) => DateTime::<Local>::from(modified)
+            .format("%Y-%m-%d %H:%M")
+            .to_string(),
+        Err(_) => "never".to_string(),
+    }
+}
+
+// async stats load
+fn handle_load(stats: Arc<RwLock<Option<LibraryStats>>>, pool: Arc<SqlitePool>) {
+    tokio::task::spawn(async move {
+        match get_library_stats(&pool).await {
+            Ok(loaded) => *stats.write().unwrap() = Some(loaded),
+            Err(e) => error!("Error loading library stats: {}", e),
+        }
+    });
+}
+
+// async VACUUM, reloads the stats afterwards
+fn handle_compact(app: &mut Application) {
+    if app.read_only {
+        return;
+    }
+    record_operation("Compact database");
+    let status = app.library_info.compact.clone();
+    let stats = app.library_info.stats.clone();
+    let pool = app.pool.clone();
+    *status.write().unwrap() = CompactStatus::Compacting;
+
+    spawn_tracked(async move {
+        let result = match compact_database(&pool).await {
+            Ok(reclaimed) => CompactStatus::Done(reclaimed),
+            Err(e) => {
+                error!("Error compacting database: {}", e);
+                CompactStatus::Failed(format!("Error compacting database: {}", e))
+            }
+        };
+        *status.write().unwrap() = result;
+
+        handle_load(stats, pool);
+    });
+}
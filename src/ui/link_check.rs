@@ -0,0 +1,189 @@
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use chrono::{DateTime, Local};
+use egui::{Button, Context, Grid, ProgressBar, ScrollArea};
+use tracing::*;
+
+use crate::link_check::{self, CheckProgress, LinkResult};
+use crate::ui::file_dialog::{self, FilePurpose, FileRequest};
+use crate::ui::{escape_pressed, truncated, Application};
+
+#[derive(Debug, Clone, Default)]
+enum CheckStatus {
+    #[default]
+    NotChecked,
+    Checking,
+    Checked(Vec<LinkResult>, DateTime<Local>),
+    Failed(String),
+}
+
+// dialog of the "Check links" tool, the results can be saved as a report
+#[derive(Default)]
+pub struct LinkCheck {
+    pub open: bool,
+    status: Arc<RwLock<CheckStatus>>,
+    progress: Arc<CheckProgress>,
+    error: Option<String>, // saving the report failed
+}
+
+// opens the dialog and checks the links right away
+pub fn start(app: &mut Application) {
+    app.link_check.open = true;
+    if !matches!(
+        *app.link_check.status.read().unwrap(),
+        CheckStatus::Checking
+    ) {
+        handle_check(app);
+    }
+}
+
+pub fn render(app: &mut Application, ctx: &Context) {
+    if !app.link_check.open {
+        return;
+    }
+
+    let mut open = true;
+    let status = app.link_check.status.read().unwrap().clone();
+    let mut check_again = false;
+    let mut save = false;
+
+    egui::Window::new("Check links")
+        .collapsible(false)
+        .open(&mut open)
+        .show(ctx, |ui| match &status {
+            CheckStatus::NotChecked => {}
+            CheckStatus::Checking => {
+                let progress = &app.link_check.progress;
+                let checked = progress.checked.load(Ordering::Relaxed);
+                let total = progress.total.load(Ordering::Relaxed).max(1);
+                ui.add(
+                    ProgressBar::new(checked as f32 / total as f32)
+                        .text(format!("Checked {} of {} links", checked, total)),
+                );
+                ctx.request_repaint_after(Duration::from_millis(100));
+            }
+            CheckStatus::Failed(error) => {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+                check_again = ui.button("Check again").clicked();
+            }
+            CheckStatus::Checked(results, _) => {
+                let problems: Vec<&LinkResult> = results
+                    .iter()
+                    .filter(|result| result.is_broken() || result.redirect.is_some())
+                    .collect();
+                let broken = problems.iter().filter(|result| result.is_broken()).count();
+                ui.label(format!(
+                    "{} links checked, {} broken, {} redirected",
+                    results.len(),
+                    broken,
+                    problems.len() - broken
+                ));
+
+                ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                    Grid::new("LinkCheckGrid").striped(true).show(ui, |ui| {
+                        for result in problems {
+                            match result.is_broken() {
+                                true => ui.colored_label(
+                                    ui.visuals().error_fg_color,
+                                    result.status_text(),
+                                ),
+                                false => ui.label(result.status_text()),
+                            };
+                            ui.label(truncated(&result.title, 40))
+                                .on_hover_text(&result.url);
+                            match (&result.redirect, &result.archived) {
+                                (Some(redirect), _) if !result.is_broken() => {
+                                    ui.hyperlink_to(truncated(redirect, 40), redirect);
+                                }
+                                (_, Some(archived)) => {
+                                    ui.hyperlink_to("Archived copy", archived);
+                                }
+                                _ => {
+                                    ui.label("Not archived");
+                                }
+                            }
+                            ui.end_row();
+                        }
+                    });
+                });
+
+                ui.add_space(5.0);
+
+                ui.horizontal(|ui| {
+                    save = ui
+                        .add_enabled(!results.is_empty(), Button::new("Save report…"))
+                        .on_hover_text("Status, redirect target and archived copy of every link as HTML or CSV")
+                        .clicked();
+                    check_again = ui.button("Check again").clicked();
+                });
+
+                if let Some(error) = &app.link_check.error {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+            }
+        });
+
+    if check_again {
+        trace!("Check links again clicked");
+        handle_check(app);
+    }
+    if save {
+        trace!("Save link report clicked");
+        let request = FileRequest::save(FilePurpose::LinkReport, "Select file", "link-report.html")
+            .add_filter("HTML", &["html", "htm"])
+            .add_filter("CSV", &["csv"]);
+        file_dialog::show(request, app, ctx);
+    }
+
+    if !open || escape_pressed(ctx) {
+        app.link_check.open = false;
+    }
+}
+
+// async check of the cached sources
+fn handle_check(app: &mut Application) {
+    let status = app.link_check.status.clone();
+    let progress = app.link_check.progress.clone();
+    let sources = app.sources_cache.read().unwrap().to_vec();
+    app.link_check.error = None;
+    *status.write().unwrap() = CheckStatus::Checking;
+
+    tokio::task::spawn(async move {
+        let result = match link_check::check_all(&sources, progress).await {
+            Ok(results) => CheckStatus::Checked(results, Local::now()),
+            Err(e) => {
+                error!("Error checking links: {}", e);
+                CheckStatus::Failed(format!("Error checking links: {}", e))
+            }
+        };
+        *status.write().unwrap() = result;
+    });
+}
+
+// writes the report as CSV for a .csv file, as HTML otherwise
+pub fn save(path: &Path, app: &mut Application) {
+    let CheckStatus::Checked(results, checked_at) = app.link_check.status.read().unwrap().clone()
+    else {
+        return;
+    };
+    let csv = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("csv"));
+    let content = match csv {
+        true => link_check::to_csv(&results).map_err(|e| e.to_string()),
+        false => Ok(link_check::to_html(&results, checked_at)),
+    };
+
+    let result = content.and_then(|content| fs::write(path, content).map_err(|e| e.to_string()));
+    app.link_check.error = match result {
+        Ok(()) => None,
+        Err(e) => {
+            error!("Error saving link report: {}", e);
+            Some(format!("Error saving {}: {}", path.display(), e))
+        }
+    };
+}
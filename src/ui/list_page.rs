@@ -1,179 +1,632 @@
-use chrono::{Datelike, NaiveDate};
+use chrono::Datelike;
 use egui::scroll_area::ScrollBarVisibility;
 use egui::text;
 use egui::text::LayoutJob;
 use egui::TextFormat;
-use egui::{CentralPanel, Context, Grid, TextEdit, Ui};
+use egui::{
+    CentralPanel, CollapsingHeader, ComboBox, Context, TextEdit, Ui, WidgetInfo, WidgetType,
+};
 use egui_extras::DatePickerButton;
-use native_dialog::FileDialog;
-use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
-use std::fs::File;
-use std::io::Write;
-use tokio::task;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::time::Duration;
 use tracing::*;
 
-use crate::database::{get_all_sources, handle_delete_source, handle_update_source, insert_source};
-use crate::source::Source;
-use crate::ui::{set_all_clipboard, set_clipboard, Application, TEXT_INPUT_WIDTH};
-
-#[derive(Serialize, Deserialize)]
-struct Entry {
-    id: i64,
-    title: String,
-    url: String,
-    author: String,
-    published_date: i32,
-    viewed_date: i32,
-    published_date_unknown: bool,
-    comment: String,
-}
-
-impl From<Source> for Entry {
-    fn from(value: Source) -> Self {
-        Self {
-            id: value.id,
-            title: value.title,
-            url: value.url,
-            author: value.author,
-            published_date: value.published_date.num_days_from_ce(),
-            viewed_date: value.viewed_date.num_days_from_ce(),
-            published_date_unknown: value.published_date_unknown,
-            comment: value.comment,
-        }
-    }
-}
-
-#[allow(clippy::from_over_into)]
-impl Into<Source> for Entry {
-    fn into(self) -> Source {
-        Source {
-            id: self.id,
-            title: self.title,
-            url: self.url,
-            author: self.author,
-            published_date: NaiveDate::from_num_days_from_ce_opt(self.published_date).unwrap(),
-            viewed_date: NaiveDate::from_num_days_from_ce_opt(self.viewed_date).unwrap(),
-            published_date_unknown: self.published_date_unknown,
-            comment: self.comment,
-        }
-    }
-}
+use crate::bundle::{read_bundle, write_bundle, Manifest};
+use crate::collation;
+use crate::config::RecentExport;
+use crate::crash::record_operation;
+use crate::formats::{self, Format};
+use crate::hooks::{self, HookEvent};
+use crate::locale;
+use crate::metadata::{resolve_canonical_url, LookupStatus, Metadata};
+use crate::quote::Quote;
+use crate::scheduler;
+use crate::search::{matches_filters, quick_filters, Filter, Matcher, SearchMode, Searchable};
+use crate::source::{Source, SourceType};
+use crate::source_link::{Relation, SourceLink};
+use crate::source_store::domain;
+use crate::ui::actions::Action;
+use crate::ui::commands::{self, DeleteSource, UpdateSource};
+use crate::ui::file_dialog::{self, FilePurpose, FileRequest};
+use crate::ui::handlers::{
+    handle_copy_shared_source, handle_delete_link, handle_delete_quote, handle_import_bundle,
+    handle_insert_link, handle_save_quote, handle_set_pinned,
+};
+use crate::ui::layout::{self, FormLayout};
+use crate::ui::{
+    accessible_name, bibliography, bulk_edit, copy_citation, documents, escape_pressed,
+    find_replace, history, import_dialog, language_combo, prepare_copy, reading_plan,
+    reference_list, refetch, set_clipboard, set_separate_clipboard, snapshot, truncated, AppPage,
+    Application, EditTab, GroupBy, SortBy, TEXT_INPUT_WIDTH,
+};
+use crate::url_analysis;
 
 pub fn render(app: &mut Application, ui: &mut Ui, ctx: &Context) {
-    ui.horizontal(|ui| {
+    ui.horizontal_wrapped(|ui| {
         // Copy all button
         if ui.button("Copy all").clicked() {
-            set_all_clipboard(&app.sources_cache.read().unwrap(), app);
+            commands::run(Action::CopyAll, app, ctx);
         }
 
         // Search bar
+        let search_label = ui.label("Search:");
         let input_search = TextEdit::singleline(&mut app.search_query)
-            .hint_text("Search")
-            .desired_width(TEXT_INPUT_WIDTH);
-        ui.add(input_search);
+            .hint_text("Title, author, tags…")
+            .desired_width(layout::input_width(ui));
+        ui.add(input_search).labelled_by(search_label.id);
 
         // Clear button
         if ui.button("Clear").clicked() {
             app.search_query.clear();
         }
 
+        // a second click goes back to plain text search
+        for (mode, name, hint) in [
+            (
+                SearchMode::Regex,
+                "Regex",
+                "Search with a regular expression",
+            ),
+            (
+                SearchMode::Fuzzy,
+                "Fuzzy",
+                "Words with a few typos match as well",
+            ),
+        ] {
+            let toggle = ui.selectable_label(app.search_mode == mode, name);
+            if toggle.on_hover_text(hint).clicked() {
+                app.search_mode = match app.search_mode == mode {
+                    true => SearchMode::Text,
+                    false => mode,
+                };
+            }
+        }
+
+        let everywhere = ui
+            .selectable_label(app.search_everywhere, "Everywhere")
+            .on_hover_text("Also search the other libraries of the settings");
+        if everywhere.clicked() {
+            app.search_everywhere = !app.search_everywhere;
+            app.update_library_cache();
+        }
+
+        ComboBox::from_label("Group by")
+            .selected_text(app.group_by.to_string())
+            .show_ui(ui, |ui| {
+                for group_by in GroupBy::ALL {
+                    ui.selectable_value(&mut app.group_by, group_by, group_by.to_string());
+                }
+            });
+
+        ComboBox::from_label("Sort by")
+            .selected_text(app.sort_by.to_string())
+            .show_ui(ui, |ui| {
+                for sort_by in SortBy::ALL {
+                    ui.selectable_value(&mut app.sort_by, sort_by, sort_by.to_string());
+                }
+            })
+            .response
+            .on_hover_text(format!(
+                "Titles and authors are compared with the collation of the library: {}",
+                collation::name(&app.collation.read().unwrap())
+            ));
+
+        documents::render_filter(app, ui);
+
+        if ui.button("Documents").clicked() {
+            commands::run(Action::Documents, app, ctx);
+        }
+
         if ui.button("Import").clicked() {
-            let path = FileDialog::new()
-                .set_location("~")
-                .set_title("Select File")
-                .add_filter("Json", &["json"])
-                .show_open_single_file()
-                .unwrap();
-
-            let path = match path {
-                None => return,
-                Some(path) => path,
-            };
-            let content = fs::read_to_string(path).expect("Error reading file");
-            let entries =
-                serde_json::from_str::<Vec<Entry>>(&content).expect("Error deserializing Json");
-
-            let sources = {
-                let mut out = Vec::with_capacity(entries.len());
-                for entry in entries {
-                    out.push(entry.into());
+            commands::run(Action::Import, app, ctx);
+        }
+
+        ui.menu_button("Export", |ui| {
+            for format in formats::exporters() {
+                if ui.button(format.name()).clicked() {
+                    ui.close_menu();
+                    commands::run(Action::Export(format), app, ctx);
                 }
-                out
-            };
+            }
+
+            ui.separator();
+            if ui.button("Numbered references…").clicked() {
+                ui.close_menu();
+                commands::run(Action::ReferenceList, app, ctx);
+            }
+            if ui.button("Alphabetical bibliography…").clicked() {
+                ui.close_menu();
+                commands::run(Action::Bibliography, app, ctx);
+            }
+            if ui.button("Reading plan…").clicked() {
+                ui.close_menu();
+                commands::run(Action::ReadingPlan, app, ctx);
+            }
+
+            render_recent_exports(app, ui);
+        });
+    });
+
+    // an invalid regex matches nothing
+    let matcher = Matcher::new(&app.search_query, app.search_mode);
+    if let Err(error) = &matcher {
+        ui.colored_label(ui.visuals().error_fg_color, error);
+    }
+    let matcher = matcher.ok();
+
+    render_filter_chips(app, ui);
+
+    // selection
+    ui.horizontal_wrapped(|ui| {
+        if ui.button("Select all").clicked() {
+            let visible: Vec<i64> = app
+                .sources_cache
+                .read()
+                .unwrap()
+                .iter()
+                .filter(|source| {
+                    matcher
+                        .as_ref()
+                        .is_some_and(|matcher| matcher.matches(source))
+                })
+                .map(|source| source.id())
+                .collect();
+            app.selected.extend(visible);
+        }
+
+        if ui
+            .add_enabled(
+                !app.selected.is_empty(),
+                egui::Button::new("Clear selection"),
+            )
+            .clicked()
+        {
+            app.selected.clear();
+        }
 
-            let pool = app.pool.clone();
-            let source_cache = app.sources_cache.clone();
+        let bulk_edit_button = egui::Button::new(format!("Bulk edit ({})", app.selected.len()));
+        if ui
+            .add_enabled(!app.selected.is_empty(), bulk_edit_button)
+            .clicked()
+        {
+            trace!("Bulk edit clicked");
+            app.bulk_edit.open = true;
+        }
+
+        let refetch_button =
+            egui::Button::new(format!("Re-fetch metadata ({})", app.selected.len()));
+        if ui
+            .add_enabled(!app.selected.is_empty(), refetch_button)
+            .clicked()
+        {
+            trace!("Re-fetch metadata clicked");
+            app.refetch.open = true;
+        }
+
+        let clips_left = app.clips_left.load(Ordering::Relaxed);
+        let clips_button = egui::Button::new(match clips_left {
+            0 => format!("Copy as separate clips ({})", app.selected.len()),
+            left => format!("Copying clips… ({} left)", left),
+        });
+        if ui
+            .add_enabled(!app.selected.is_empty() && clips_left == 0, clips_button)
+            .on_hover_text("Copy every citation on its own, to paste them one at a time from the clipboard history")
+            .clicked()
+        {
+            trace!("Copy as separate clips clicked");
+            copy_separate_clips(app);
+        }
+        if clips_left > 0 {
+            ctx.request_repaint_after(Duration::from_millis(200));
+        }
+
+        if ui.button("Find & replace").clicked() {
+            commands::run(Action::FindReplace, app, ctx);
+        }
+
+        render_random_pick(app, ui, matcher.as_ref());
+
+        if ui
+            .add_enabled(!app.selected.is_empty(), egui::Button::new("Share bundle"))
+            .clicked()
+        {
+            record_operation("Share bundle");
+            let request = FileRequest::save(FilePurpose::ShareBundle, "Select file", "bundle.zip")
+                .add_filter("SaveIt bundle", &["zip"]);
+            file_dialog::show(request, app, ctx);
+        }
+
+        if ui.button("Import bundle").clicked() {
+            commands::run(Action::ImportBundle, app, ctx);
+        }
+    });
+
+    if let Some(error) = &app.file_error {
+        ui.colored_label(ui.visuals().error_fg_color, error);
+    }
+
+    if let Some(error) = &*app.shared_error.read().unwrap() {
+        ui.colored_label(ui.visuals().error_fg_color, error);
+    }
+
+    for error in app.library_errors.read().unwrap().iter() {
+        ui.colored_label(ui.visuals().error_fg_color, error);
+    }
+
+    // drop selected ids of deleted sources
+    {
+        let sources = app.sources_cache.read().unwrap();
+        app.selected.retain(|id| sources.contains(*id));
+    }
+
+    ui.add_space(10.0);
+
+    render_sources(app, ui, matcher.as_ref());
+    render_edit_modal(app, ctx);
+    render_page_copy(app, ctx);
+    bulk_edit::render(app, ctx);
+    find_replace::render(app, ctx);
+    refetch::render(app, ctx);
+    import_dialog::render(app, ctx);
+    reference_list::render(app, ctx);
+    bibliography::render(app, ctx, bibliography_sources(app, matcher.as_ref()));
+    reading_plan::render(app, ctx, unread_sources(app, matcher.as_ref()));
+    documents::render(app, ctx);
+}
+
+// the selected sources in the order of the list
+fn copy_separate_clips(app: &Application) {
+    let mut sources: Vec<Source> = app
+        .sources_cache
+        .read()
+        .unwrap()
+        .iter()
+        .filter(|source| app.selected.contains(&source.id()))
+        .map(|source| source.to_source())
+        .collect();
+    collation::sort_sources(&mut sources, app.sort_by, &app.collation.read().unwrap());
+
+    set_separate_clipboard(&sources, app);
+}
+
+// writes all sources to a file of the format
+pub fn export_sources(format: &dyn Format, path: &Path, app: &mut Application) {
+    let sources = app.sources_cache.read().unwrap().to_vec();
+    let result = format
+        .export(&sources)
+        .and_then(|content| Ok(fs::write(path, content)?));
+
+    match result {
+        Ok(()) => {
+            app.file_error = None;
+
+            let mut config = app.config.write().unwrap();
+            config.add_recent_export(RecentExport {
+                path: path.to_string_lossy().to_string(),
+                format: format.id().to_string(),
+            });
+            config.save();
+
+            hooks::run(
+                HookEvent::ExportFinished,
+                hooks::export_payload(path, format, &sources),
+                &config,
+            );
+        }
+        Err(e) => {
+            error!("Error exporting sources: {}", e);
+            app.file_error = Some(format!("Error exporting sources: {}", e));
+        }
+    }
+}
+
+// files exported before, written again without asking for the file
+fn render_recent_exports(app: &mut Application, ui: &mut Ui) {
+    let recent_exports = app.config.read().unwrap().recent_exports.clone();
+    if recent_exports.is_empty() {
+        return;
+    }
 
-            task::spawn(async move {
-                let mut handles = vec![];
+    ui.separator();
+    ui.label("Recent exports");
 
-                for source in sources {
-                    let pool = pool.clone();
+    for recent in recent_exports {
+        // formats can be removed in newer versions
+        let format = match formats::by_id(&recent.format) {
+            Some(format) if format.can_export() => format,
+            _ => continue,
+        };
 
-                    handles.push(task::spawn(async move {
-                        insert_source(&source, &pool)
-                            .await
-                            .expect("Error saving source");
-                    }));
+        let path = PathBuf::from(&recent.path);
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| recent.path.clone());
+
+        let button = ui
+            .button(format!("{} ({})", file_name, format.name()))
+            .on_hover_text(&recent.path);
+        if button.clicked() {
+            ui.close_menu();
+            record_operation(format!("Export sources as {}", format.id()));
+            export_sources(format, &path, app);
+        }
+    }
+}
+
+// writes the selected sources with their quotes and links to a zip file
+pub fn share_bundle(path: &Path, app: &mut Application) {
+    let sources = app
+        .sources_cache
+        .read()
+        .unwrap()
+        .iter()
+        .filter(|source| app.selected.contains(&source.id()))
+        .map(|source| source.to_source())
+        .collect();
+    let manifest = Manifest::new(
+        sources,
+        &app.quotes_cache.read().unwrap(),
+        &app.links_cache.read().unwrap(),
+    );
+
+    app.file_error = write_bundle(&manifest, path).err().map(|e| {
+        error!("Error writing bundle: {}", e);
+        format!("Error writing bundle: {}", e)
+    });
+}
+
+pub fn import_bundle(path: &Path, app: &mut Application) {
+    match read_bundle(path) {
+        Ok(manifest) => {
+            app.file_error = None;
+            handle_import_bundle(manifest, app);
+        }
+        Err(e) => {
+            error!("Error reading bundle: {}", e);
+            app.file_error = Some(format!("Error reading bundle: {}", e));
+        }
+    }
+}
+
+// popup asking for the page/paragraph before copying
+fn render_page_copy(app: &mut Application, ctx: &Context) {
+    let source = match &app.page_copy.source {
+        None => return,
+        Some(source) => source.clone(),
+    };
+
+    let mut open = true;
+    let mut copied = false;
+
+    egui::Window::new("Copy with page")
+        .collapsible(false)
+        .resizable(false)
+        .open(&mut open)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                let pages_label = ui.label("Page:");
+                let input_pages = TextEdit::singleline(&mut app.page_copy.pages)
+                    .hint_text("e.g. 4 or 12-15")
+                    .desired_width(TEXT_INPUT_WIDTH / 3.0);
+                let response = ui.add(input_pages).labelled_by(pages_label.id);
+                response.request_focus();
+
+                let enter = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                if ui.button("Copy").clicked() || enter {
+                    trace!("Copy with page clicked");
+                    let source = prepare_copy(&source, app);
+                    let text = source.format_with_pages(
+                        &app.settings.format_standard,
+                        &app.config.read().unwrap(),
+                        Some(&app.page_copy.pages),
+                    );
+                    copy_citation(text, source.library_id().as_slice(), app);
+                    copied = true;
                 }
 
-                for handle in handles {
-                    handle.await.expect("Error saving source");
+                if ui.button("Copy in-text").clicked() {
+                    trace!("Copy in-text with page clicked");
+                    let text = source.format_in_text(
+                        &app.settings.format_standard,
+                        &app.config.read().unwrap(),
+                        Some(&app.page_copy.pages),
+                    );
+                    copy_citation(text, source.library_id().as_slice(), app);
+                    copied = true;
                 }
-                *source_cache.write().unwrap() = get_all_sources(&pool).await.unwrap();
             });
+        });
+
+    if !open || copied || escape_pressed(ctx) {
+        app.page_copy.source = None;
+    }
+}
+
+// chips for the most used tags, the types and statuses, a click turns the filter on or off
+fn render_filter_chips(app: &mut Application, ui: &mut Ui) {
+    let store = app.sources_cache.read().unwrap();
+    let shared = app.shared_cache.read().unwrap();
+    let views: Vec<_> = store.iter().collect();
+    let sources: Vec<&dyn Searchable> = views
+        .iter()
+        .map(|view| view as &dyn Searchable)
+        .chain(shared.iter().map(|source| source as &dyn Searchable))
+        .collect();
+    let mut chips = quick_filters(&sources);
+
+    // active filters stay visible even if they aren't among the most used anymore
+    for filter in &app.filters {
+        if !chips.iter().any(|(chip, _)| chip == filter) {
+            let count = sources
+                .iter()
+                .filter(|source| filter.matches(**source))
+                .count();
+            chips.push((filter.clone(), count));
         }
+    }
+    if chips.is_empty() {
+        return;
+    }
 
-        if ui.button("Export").clicked() {
-            let path = FileDialog::new()
-                .set_location("~")
-                .set_title("Select file")
-                .set_filename("export.json")
-                .add_filter("Json", &["json"])
-                .show_save_single_file()
-                .unwrap();
-
-            let path = match path {
-                None => return,
-                Some(path) => path,
-            };
-            let mut file = match File::create(path) {
-                Ok(f) => f,
-                Err(_) => return,
-            };
-
-            let sources = app
-                .sources_cache
-                .read()
-                .expect("Error reading source cache");
-            let sources = {
-                let mut out = Vec::with_capacity(sources.len());
-                for source in &*sources {
-                    out.push(Entry::from(source.to_owned()))
-                }
-                out
-            };
-            let json =
-                serde_json::to_string_pretty(&sources).expect("Error converting sources to json");
+    let mut toggled: Option<Filter> = None;
+    ui.horizontal_wrapped(|ui| {
+        for (filter, count) in &chips {
+            let active = app.filters.contains(filter);
+            let chip = ui.selectable_label(active, format!("{} ({})", filter, count));
+            if chip.clicked() {
+                toggled = Some(filter.clone());
+            }
+        }
 
-            file.write_all(json.as_bytes())
-                .expect("Error writing to file");
+        if !app.filters.is_empty() && ui.button("Clear filters").clicked() {
+            trace!("Clear filters clicked");
+            app.filters.clear();
         }
     });
 
-    ui.add_space(10.0);
+    if let Some(filter) = toggled {
+        trace!("Filter {:?} clicked", filter);
+        match app.filters.iter().position(|active| *active == filter) {
+            Some(index) => {
+                app.filters.remove(index);
+            }
+            None => app.filters.push(filter),
+        }
+    }
+}
+
+// pinned sources above the list, they stay visible while the list scrolls and don't depend on
+// the search or the grouping
+fn render_pinned(app: &mut Application, ui: &mut Ui, matcher: Option<&Matcher>) {
+    let mut pinned: Vec<Source> = app
+        .sources_cache
+        .read()
+        .unwrap()
+        .iter()
+        .filter(|source| source.pinned())
+        .map(|source| source.to_source())
+        .collect();
+    if pinned.is_empty() {
+        return;
+    }
+    collation::sort_sources(&mut pinned, app.sort_by, &app.collation.read().unwrap());
+
+    let max_height = ui.available_height() / 3.0;
+    CollapsingHeader::new(format!("Pinned ({})", pinned.len()))
+        .id_source("Pinned")
+        .default_open(true)
+        .show(ui, |ui| {
+            egui::ScrollArea::vertical()
+                .id_source("PinnedSources")
+                .max_height(max_height)
+                .auto_shrink([false, true])
+                .show(ui, |ui| {
+                    for source in &pinned {
+                        render_source(app, ui, source, matcher);
+                    }
+                });
+        });
+    ui.separator();
+}
+
+// own sources first, then the shared library, the other libraries only while searching
+// everywhere, without the pinned sources
+fn listed_sources(app: &Application, matcher: Option<&Matcher>) -> Vec<Source> {
+    let searching = matcher.is_some_and(|matcher| !matches!(matcher, Matcher::All));
+    let shared = app.shared_cache.read().unwrap();
+    let others = app.library_cache.read().unwrap();
+    let others = match app.search_everywhere && searching {
+        true => others.as_slice(),
+        false => &[],
+    };
+
+    // only the matching sources are copied
+    let mut sources: Vec<Source> = app
+        .sources_cache
+        .read()
+        .unwrap()
+        .iter()
+        .filter(|source| !source.pinned() && is_listed(source, app, matcher))
+        .map(|source| source.to_source())
+        .chain(
+            shared
+                .iter()
+                .chain(others)
+                .filter(|source| is_listed(*source, app, matcher))
+                .cloned(),
+        )
+        .collect();
+    collation::sort_sources(&mut sources, app.sort_by, &app.collation.read().unwrap());
+
+    sources
+}
+
+// matches the search, the filter chips and the document filter
+fn is_listed(source: &impl Searchable, app: &Application, matcher: Option<&Matcher>) -> bool {
+    matcher.is_some_and(|matcher| matcher.matches(source))
+        && matches_filters(&app.filters, source)
+        && documents::filter_matches(source, app)
+}
+
+// listed sources with the pinned ones that match the search and the filters
+fn bibliography_sources(app: &Application, matcher: Option<&Matcher>) -> Vec<Source> {
+    let pinned: Vec<Source> = app
+        .sources_cache
+        .read()
+        .unwrap()
+        .iter()
+        .filter(|source| source.pinned() && is_listed(source, app, matcher))
+        .map(|source| source.to_source())
+        .collect();
 
-    render_sources(app, ui, ctx);
+    pinned
+        .into_iter()
+        .chain(listed_sources(app, matcher))
+        .collect()
 }
 
-fn render_sources(app: &mut Application, ui: &mut Ui, ctx: &Context) {
+// listed sources of the own library without notes
+fn unread_sources(app: &Application, matcher: Option<&Matcher>) -> Vec<Source> {
+    listed_sources(app, matcher)
+        .into_iter()
+        .filter(|source| source.library_id().is_some() && !source.notes_written)
+        .collect()
+}
+
+// opens a random listed source without notes, for working through the backlog
+fn render_random_pick(app: &mut Application, ui: &mut Ui, matcher: Option<&Matcher>) {
+    let unread = unread_sources(app, matcher);
+
+    let button = egui::Button::new("Read something");
+    if ui
+        .add_enabled(!unread.is_empty(), button)
+        .on_hover_text(
+            "Opens a random source without notes that matches the search and the filters",
+        )
+        .on_disabled_hover_text("Every listed source has its notes written")
+        .clicked()
+    {
+        let source = &unread[fastrand::usize(..unread.len())];
+        trace!("Read something picked source {}", source.id);
+        app.open_edit_modal(source);
+    }
+}
+
+fn render_sources(app: &mut Application, ui: &mut Ui, matcher: Option<&Matcher>) {
+    render_pinned(app, ui, matcher);
+
     egui::ScrollArea::vertical()
         .auto_shrink(false)
         .drag_to_scroll(true)
         .scroll_bar_visibility(ScrollBarVisibility::AlwaysVisible)
         .show(ui, |ui| {
-            if app.sources_cache.clone().read().unwrap().is_empty() {
+            if app.sources_cache.read().unwrap().is_empty()
+                && app.shared_cache.read().unwrap().is_empty()
+            {
                 CentralPanel::default().show_inside(ui, |ui| {
                     ui.vertical_centered(|ui| {
                         ui.heading("Empty");
@@ -182,176 +635,833 @@ fn render_sources(app: &mut Application, ui: &mut Ui, ctx: &Context) {
                 return;
             }
 
-            #[allow(clippy::unnecessary_to_owned)]
-            for source in app.sources_cache.clone().read().unwrap().to_vec() {
-                if !app.search_query.is_empty() && !source.contains(&app.search_query) {
-                    continue;
+            let sources = listed_sources(app, matcher);
+
+            if app.group_by == GroupBy::None {
+                for source in &sources {
+                    render_source(app, ui, source, matcher);
                 }
+                return;
+            }
 
-                // source preview
-                ui.vertical(|ui| {
-                    let id = format!("Index: {}", &source.id);
-                    crate::text_label_wrapped!(&id, ui);
+            let collation = app.collation.read().unwrap().clone();
+            for (group, sources) in group_sources(&sources, app.group_by, &collation) {
+                CollapsingHeader::new(format!("{} ({})", group, sources.len()))
+                    .id_source(format!("Group{:?}{}", app.group_by, group))
+                    .show(ui, |ui| {
+                        // sources can be in multiple tag groups
+                        ui.push_id(&group, |ui| {
+                            for source in &sources {
+                                render_source(app, ui, source, matcher);
+                            }
+                        });
+                    });
+            }
+        });
+}
+
+fn render_source(app: &mut Application, ui: &mut Ui, source: &Source, matcher: Option<&Matcher>) {
+    // source preview
+    ui.vertical(|ui| {
+        match source.shared {
+            true => {
+                ui.label(format!("{} (read-only)", source.library));
+            }
+            false => {
+                let mut selected = app.selected.contains(&source.id);
+                let checkbox = ui.checkbox(&mut selected, "Selected");
+                checkbox.widget_info(|| {
+                    WidgetInfo::selected(
+                        WidgetType::Checkbox,
+                        selected,
+                        format!("Select \"{}\"", truncated(&source.title, 60)),
+                    )
+                });
+                if checkbox.changed() {
+                    match selected {
+                        true => app.selected.insert(source.id),
+                        false => app.selected.remove(&source.id),
+                    };
+                }
+            }
+        }
 
-                    let title = format!("Title: {}", &source.title);
-                    crate::text_label_wrapped!(&title, ui);
+        let id = format!("Index: {}", &source.id);
+        crate::text_label_wrapped!(&id, ui);
 
-                    let url = format!("URL: {}", &source.url);
-                    crate::text_label_wrapped!(&url, ui);
+        highlighted_label("Title", &source.title, matcher, ui);
+        highlighted_label("URL", &source.url, matcher, ui);
+        if !source.canonical_url.is_empty() {
+            highlighted_label("Canonical URL", &source.canonical_url, matcher, ui);
+        }
+        highlighted_label("Author", &source.author, matcher, ui);
 
-                    let author = format!("Author: {}", &source.author);
-                    crate::text_label_wrapped!(&author, ui);
+        if source.source_type != SourceType::Webpage {
+            let source_type = format!("Type: {}", source.source_type);
+            crate::text_label_wrapped!(&source_type, ui);
+        }
 
-                    let published_date = format!(
-                        "Date published: {}",
-                        &source.published_date.format("%d. %m. %Y")
-                    );
-                    if source.published_date_unknown {
-                        crate::text_label_wrapped!("Date published: Unknown", ui);
-                    } else {
-                        crate::text_label_wrapped!(&published_date, ui);
-                    }
+        if !source.language.is_empty() {
+            let language = format!("Language: {}", locale::name(&source.language));
+            crate::text_label_wrapped!(&language, ui);
+        }
 
-                    let viewed_date =
-                        format!("Date viewed: {}", &source.viewed_date.format("%d. %m. %Y"));
-                    crate::text_label_wrapped!(&viewed_date, ui);
-                });
+        if !source.doi.is_empty() {
+            highlighted_label("DOI", &source.doi, matcher, ui);
+        }
+
+        if !source.tags.is_empty() {
+            highlighted_label("Tags", &source.tag_list().join(", "), matcher, ui);
+        }
+
+        if !source.collection.is_empty() {
+            highlighted_label("Collection", &source.collection, matcher, ui);
+        }
+
+        let published_date = format!(
+            "Date published: {}",
+            &source.published_date.format("%d. %m. %Y")
+        );
+        if source.published_date_unknown {
+            crate::text_label_wrapped!("Date published: Unknown", ui);
+        } else {
+            crate::text_label_wrapped!(&published_date, ui);
+        }
+
+        let viewed_date = format!("Date viewed: {}", &source.viewed_date.format("%d. %m. %Y"));
+        crate::text_label_wrapped!(&viewed_date, ui);
+
+        if source.notes_written {
+            crate::text_label_wrapped!("Notes written", ui);
+        }
+
+        if let Some(similarity) = page_changed(app, source) {
+            let changed = format!(
+                "Page changed since the snapshot ({:.0} % similar)",
+                similarity * 100.0
+            );
+            ui.colored_label(ui.visuals().warn_fg_color, changed);
+        }
+
+        if let Some(last_cited) = source.last_cited {
+            let last_cited = format!("Last cited: {}", last_cited.format("%d. %m. %Y %H:%M"));
+            crate::text_label_wrapped!(&last_cited, ui);
+        }
+
+        // linked sources and documents, neither is loaded for the shared library
+        if let Some(id) = source.library_id() {
+            for (_, relation, title) in related_sources(app, id) {
+                let related = format!("{}: {}", relation, title);
+                crate::text_label_wrapped!(&related, ui);
+            }
+
+            let documents = documents::document_names(id, app);
+            if !documents.is_empty() {
+                let cited_in = format!("Cited in: {}", documents.join(", "));
+                crate::text_label_wrapped!(&cited_in, ui);
+            }
+        }
+
+        // formatted citation preview
+        CollapsingHeader::new("Citation")
+            .id_source(format!("CitationPreview{}{}", source.library, source.id)) // needs to be unique per source
+            .show(ui, |ui| {
+                ui.label(source.format(&app.settings.format_standard, &app.config.read().unwrap()));
+            });
+    });
+
+    ui.add_space(5.0);
+
+    if source.shared {
+        render_shared_buttons(app, ui, source);
+        ui.add_space(5.0);
+        ui.separator();
+        ui.add_space(5.0);
+        return;
+    }
 
-                ui.add_space(5.0);
+    // buttons, screen readers name the source as well
+    let title = truncated(&source.title, 60);
+    ui.horizontal_wrapped(|ui| {
+        let copy_button = accessible_name(ui.button("Copy"), format!("Copy \"{}\"", title));
+        let copy_in_text_button = accessible_name(
+            ui.button("Copy in-text"),
+            format!("Copy \"{}\" in-text", title),
+        );
+        let copy_page_button = accessible_name(
+            ui.button("Copy with page"),
+            format!("Copy \"{}\" with page", title),
+        );
+        let pin_button = match source.pinned {
+            true => accessible_name(ui.button("Unpin"), format!("Unpin \"{}\"", title)),
+            false => accessible_name(ui.button("Pin"), format!("Pin \"{}\"", title))
+                .on_hover_text("Lists the source above all others"),
+        };
+        let edit_button = accessible_name(ui.button("Edit"), format!("Edit \"{}\"", title));
+        let duplicate_button =
+            accessible_name(ui.button("Duplicate"), format!("Duplicate \"{}\"", title));
+        let delete_button = accessible_name(ui.button("Delete"), format!("Delete \"{}\"", title));
+
+        // copy one source
+        if copy_button.clicked() {
+            commands::run(Action::CopySource(source.id), app, ui.ctx());
+        }
+
+        // copy the short in-text form
+        if copy_in_text_button.clicked() {
+            trace!("Copy in-text clicked");
+            let text = source.format_in_text(
+                &app.settings.format_standard,
+                &app.config.read().unwrap(),
+                None,
+            );
+            copy_citation(text, source.library_id().as_slice(), app);
+        }
+
+        // asks for a page before copying
+        if copy_page_button.clicked() {
+            trace!("Copy with page clicked");
+            app.page_copy.source = Some(source.clone());
+            app.page_copy.pages.clear();
+        }
+
+        if pin_button.clicked() {
+            trace!("Pin clicked");
+            handle_set_pinned(source.id, !source.pinned, app);
+        }
+
+        // opens edit modal
+        if edit_button.clicked() {
+            trace!("Edit button clicked");
+            app.open_edit_modal(source);
+        }
+
+        // copies the source into the start page form
+        if duplicate_button.clicked() {
+            trace!("Duplicate clicked");
+            app.set_input(source);
+            app.curr_page = AppPage::Start;
+        }
+
+        if delete_button.clicked() {
+            trace!("Delete clicked");
+            commands::run(DeleteSource::new(source.clone(), app), app, ui.ctx());
+        }
+    });
+
+    ui.add_space(5.0);
+    ui.separator();
+    ui.add_space(5.0);
+}
+
+// "Name: value" label like text_label_wrapped with the parts of the value matching the search
+// highlighted, so it's visible why a source matched
+fn highlighted_label(name: &str, value: &str, matcher: Option<&Matcher>, ui: &mut Ui) {
+    let highlight = TextFormat {
+        background: ui.visuals().selection.bg_fill,
+        color: ui.visuals().strong_text_color(),
+        ..Default::default()
+    };
+
+    let mut job = LayoutJob::default();
+    job.append(&format!("{}: ", name), 0.0, TextFormat::default());
+
+    let mut end = 0;
+    for range in matcher
+        .map(|matcher| matcher.find(value))
+        .unwrap_or_default()
+    {
+        job.append(&value[end..range.start], 0.0, TextFormat::default());
+        job.append(&value[range.clone()], 0.0, highlight.clone());
+        end = range.end;
+    }
+    job.append(&value[end..], 0.0, TextFormat::default());
 
-                // buttons
+    job.wrap = text::TextWrapping {
+        max_width: 0.0,
+        max_rows: 1,
+        break_anywhere: true,
+        overflow_character: Some('…'),
+    };
+    ui.label(job);
+}
+
+// buttons of a source from the shared library, which can't be edited
+fn render_shared_buttons(app: &mut Application, ui: &mut Ui, source: &Source) {
+    let title = truncated(&source.title, 60);
+    ui.horizontal_wrapped(|ui| {
+        if accessible_name(ui.button("Copy"), format!("Copy \"{}\"", title)).clicked() {
+            trace!("Copy shared clicked");
+            set_clipboard(source, app);
+        }
+
+        let copy_in_text_button = accessible_name(
+            ui.button("Copy in-text"),
+            format!("Copy \"{}\" in-text", title),
+        );
+        if copy_in_text_button.clicked() {
+            trace!("Copy in-text shared clicked");
+            let text = source.format_in_text(
+                &app.settings.format_standard,
+                &app.config.read().unwrap(),
+                None,
+            );
+            copy_citation(text, &[], app);
+        }
+
+        if ui.button("Copy with page").clicked() {
+            trace!("Copy with page shared clicked");
+            app.page_copy.source = Some(source.clone());
+            app.page_copy.pages.clear();
+        }
+
+        if ui.button("Copy into my library").clicked() {
+            trace!("Copy into my library clicked");
+            handle_copy_shared_source(source, app);
+        }
+    });
+}
+
+// sources bucketed by the group key, sorted by key with the collation of the library
+fn group_sources(
+    sources: &[Source],
+    group_by: GroupBy,
+    collation: &str,
+) -> Vec<(String, Vec<Source>)> {
+    let mut groups: BTreeMap<String, Vec<Source>> = BTreeMap::new();
+
+    for source in sources {
+        let keys = match group_by {
+            GroupBy::None => vec![String::new()],
+            GroupBy::Domain => vec![domain(&source.url).unwrap_or("No domain").to_string()],
+            GroupBy::Author => match source.author.trim() {
+                "" => vec!["No author".to_string()],
+                author => vec![author.to_string()],
+            },
+            GroupBy::Year => match source.published_date_unknown {
+                true => vec!["Unknown".to_string()],
+                false => vec![source.published_date.year().to_string()],
+            },
+            GroupBy::Tag => match source.tag_list().is_empty() {
+                true => vec!["No tags".to_string()],
+                false => source
+                    .tag_list()
+                    .iter()
+                    .map(|tag| tag.to_string())
+                    .collect(),
+            },
+            GroupBy::Collection => match source.collection.trim() {
+                "" => vec!["No collection".to_string()],
+                collection => vec![collection.to_string()],
+            },
+        };
+
+        for key in keys {
+            groups.entry(key).or_default().push(source.clone());
+        }
+    }
+
+    let mut groups: Vec<(String, Vec<Source>)> = groups.into_iter().collect();
+    groups.sort_by(|(a, _), (b, _)| collation::compare(collation, a, b));
+    groups
+}
+
+// edit modal of the source in app.edit_modal
+fn render_edit_modal(app: &mut Application, ctx: &Context) {
+    if !app.edit_modal.open {
+        return;
+    }
+
+    apply_resolved_url(app);
+
+    // needed because the borrow checker is fucking stupid
+    let mut window_open = true;
+
+    // edit modal
+    // the window is sized to its content, so the form is sized to the screen
+    let form = FormLayout::new(ctx.screen_rect().width() - 40.0);
+
+    egui::Window::new("Edit source")
+        .auto_sized()
+        .vscroll(true) // doesn't fit on small screens
+        .resizable(true)
+        .collapsible(false)
+        .open(&mut window_open)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                let tab = &mut app.edit_modal.tab;
+                ui.selectable_value(tab, EditTab::Details, "Details");
+                ui.selectable_value(tab, EditTab::History, "History");
+                ui.selectable_value(tab, EditTab::Snapshot, "Snapshot");
+            });
+            ui.separator();
+
+            match app.edit_modal.tab {
+                EditTab::Details => {}
+                EditTab::History => return history::render(app, ui),
+                EditTab::Snapshot => return snapshot::render(app, ui),
+            }
+
+            form.grid("SourceInput").show(ui, |ui| {
+                // input title
+                let title_label = ui.label("Title:");
+                form.after_label(ui);
+                let input_title = TextEdit::singleline(&mut app.edit_modal.source.title)
+                    .desired_width(form.input_width);
+                let title = ui.add(input_title).labelled_by(title_label.id);
+                if std::mem::take(&mut app.edit_modal.focus) {
+                    title.request_focus();
+                }
+                ui.end_row();
+
+                // input URL
+                let url_label = ui.label("URL:");
+                form.after_label(ui);
+                let input_url = TextEdit::singleline(&mut app.edit_modal.source.url)
+                    .desired_width(form.input_width);
+                // where the old link led says nothing about the new one
+                if ui.add(input_url).labelled_by(url_label.id).changed() {
+                    app.edit_modal.source.canonical_url.clear();
+                }
+                ui.end_row();
+
+                // input canonical URL
+                let canonical_label = ui.label("Canonical URL:");
+                form.after_label(ui);
                 ui.horizontal(|ui| {
-                    let copy_button = ui.button("Copy");
-                    let edit_button = ui.button("Edit");
-                    let delete_button = ui.button("Delete");
-
-                    // copy one source
-                    if copy_button.clicked() {
-                        trace!("Copy clicked");
-                        set_clipboard(&source, app);
-                    }
+                    let input_canonical =
+                        TextEdit::singleline(&mut app.edit_modal.source.canonical_url)
+                            .hint_text("Where the URL leads, cited instead of it")
+                            .desired_width(form.input_width - 100.0);
+                    ui.add(input_canonical).labelled_by(canonical_label.id);
 
-                    // opens edit modal
-                    if edit_button.clicked() {
-                        trace!("Edit button clicked");
-                        app.edit_modal.source = source.clone();
-                        app.edit_modal.open = true;
+                    let resolving = *app.edit_modal.resolve.read().unwrap() == LookupStatus::Fetching;
+                    let web = url_analysis::analyze(&app.edit_modal.source.url).web;
+                    if ui
+                        .add_enabled(!resolving && web, egui::Button::new("Resolve"))
+                        .on_hover_text("Follow the redirects of the URL and read the canonical link of the page")
+                        .clicked()
+                    {
+                        trace!("Resolve canonical URL clicked");
+                        handle_resolve_url(app);
                     }
+                });
+                ui.end_row();
+                render_resolve_status(app, ui);
 
-                    let mut update_cache = false;
-
-                    if app.edit_modal.open && app.edit_modal.source.id == source.id {
-                        // app.edit_source.id == source.id needed because else it would open an edit model x number of sources in the db
-
-                        // needed because the borrow checker is fucking stupid
-                        let mut window_open = true;
-
-                        // edit modal
-                        egui::Window::new("Edit source")
-                            .auto_sized()
-                            .resizable(true)
-                            .collapsible(false)
-                            .open(&mut window_open)
-                            .show(ctx, |ui| {
-                                Grid::new("SourceInput").num_columns(2).show(ui, |ui| {
-                                    // input title
-                                    let title_label = ui.label("Title:");
-                                    let input_title =
-                                        TextEdit::singleline(&mut app.edit_modal.source.title)
-                                            .desired_width(TEXT_INPUT_WIDTH);
-                                    ui.add(input_title).labelled_by(title_label.id);
-                                    ui.end_row();
-
-                                    // input URL
-                                    let url_label = ui.label("URL:");
-                                    let input_url =
-                                        TextEdit::singleline(&mut app.edit_modal.source.url)
-                                            .desired_width(TEXT_INPUT_WIDTH);
-                                    ui.add(input_url).labelled_by(url_label.id);
-                                    ui.end_row();
-
-                                    // input author
-                                    let author_label = ui.label("Author:");
-                                    let input_author =
-                                        TextEdit::singleline(&mut app.edit_modal.source.author)
-                                            .hint_text("Leave empty if unknown")
-                                            .desired_width(TEXT_INPUT_WIDTH);
-                                    ui.add(input_author).labelled_by(author_label.id);
-                                    ui.end_row();
-
-                                    // input published date
-                                    let published_label = ui.label("Date published:");
-                                    ui.horizontal(|ui| {
-                                        ui.add_enabled(
-                                            !app.edit_modal.source.published_date_unknown,
-                                            DatePickerButton::new(
-                                                &mut app.edit_modal.source.published_date,
-                                            )
-                                            .id_source("InputPublishedDate") // needs to be set otherwise the UI would bug with multiple date pickers
-                                            .show_icon(false),
-                                        )
-                                        .labelled_by(published_label.id);
-                                        ui.checkbox(
-                                            &mut app.edit_modal.source.published_date_unknown,
-                                            "Unknown",
-                                        );
-                                    });
-                                    ui.end_row();
-
-                                    // input viewed date
-                                    let viewed_label = ui.label("Date viewed:");
-                                    ui.add(
-                                        DatePickerButton::new(
-                                            &mut app.edit_modal.source.viewed_date,
-                                        )
-                                        .id_source("InputViewedDate") // needs to be set otherwise the UI would bug with multiple date pickers
-                                        .show_icon(false),
-                                    )
-                                    .labelled_by(viewed_label.id);
-                                    ui.end_row();
-
-                                    // input comment
-                                    let comment_label = ui.label("Comment:");
-                                    let input_comment =
-                                        TextEdit::multiline(&mut app.edit_modal.source.comment)
-                                            .desired_width(TEXT_INPUT_WIDTH);
-                                    ui.add(input_comment).labelled_by(comment_label.id);
-                                    ui.end_row();
-                                });
-
-                                ui.add_space(10.0);
-
-                                if ui.button("Save").clicked() {
-                                    trace!("Edit modal save clicked");
-                                    handle_update_source(
-                                        app.edit_modal.source.id,
-                                        &app.edit_modal.source,
-                                        app,
-                                    );
-                                    update_cache = true;
-                                    app.edit_modal.open = false;
-                                }
-                            });
-
-                        if !window_open {
-                            app.edit_modal.open = false;
+                // input author
+                let author_label = ui.label("Author:");
+                form.after_label(ui);
+                let input_author = TextEdit::singleline(&mut app.edit_modal.source.author)
+                    .hint_text("Leave empty if unknown")
+                    .desired_width(form.input_width);
+                ui.add(input_author).labelled_by(author_label.id);
+                ui.end_row();
+
+                // input source type
+                let type_label = ui.label("Type:");
+                form.after_label(ui);
+                ComboBox::from_id_source("EditSourceType")
+                    .selected_text(app.edit_modal.source.source_type.to_string())
+                    .show_ui(ui, |ui| {
+                        for source_type in SourceType::ALL {
+                            ui.selectable_value(
+                                &mut app.edit_modal.source.source_type,
+                                source_type,
+                                source_type.to_string(),
+                            );
                         }
-                    }
+                    })
+                    .response
+                    .labelled_by(type_label.id);
+                ui.end_row();
 
-                    if delete_button.clicked() {
-                        trace!("Delete clicked");
-                        handle_delete_source(source.id, app);
-                        update_cache = true;
-                    }
+                // input language
+                let language_label = ui.label("Language:");
+                form.after_label(ui);
+                language_combo("EditLanguage", &mut app.edit_modal.source.language, ui)
+                    .labelled_by(language_label.id);
+                ui.end_row();
 
-                    if update_cache {
-                        app.update_source_cache();
-                    }
+                // input DOI
+                let doi_label = ui.label("DOI:");
+                form.after_label(ui);
+                let input_doi = TextEdit::singleline(&mut app.edit_modal.source.doi)
+                    .desired_width(form.input_width);
+                ui.add(input_doi).labelled_by(doi_label.id);
+                ui.end_row();
+
+                // input tags
+                let tags_label = ui.label("Tags:");
+                form.after_label(ui);
+                let input_tags = TextEdit::singleline(&mut app.edit_modal.source.tags)
+                    .hint_text("Separated by commas")
+                    .desired_width(form.input_width);
+                ui.add(input_tags).labelled_by(tags_label.id);
+                ui.end_row();
+
+                // input collection
+                let collection_label = ui.label("Collection:");
+                form.after_label(ui);
+                let input_collection = TextEdit::singleline(&mut app.edit_modal.source.collection)
+                    .desired_width(form.input_width);
+                ui.add(input_collection).labelled_by(collection_label.id);
+                ui.end_row();
+
+                // input published date
+                let published_label = ui.label("Date published:");
+                form.after_label(ui);
+                ui.horizontal(|ui| {
+                    ui.add_enabled(
+                        !app.edit_modal.source.published_date_unknown,
+                        DatePickerButton::new(&mut app.edit_modal.source.published_date)
+                            .id_source("InputPublishedDate") // needs to be set otherwise the UI would bug with multiple date pickers
+                            .show_icon(false),
+                    )
+                    .labelled_by(published_label.id);
+                    ui.checkbox(&mut app.edit_modal.source.published_date_unknown, "Unknown");
+                });
+                ui.end_row();
+
+                // input viewed date
+                let viewed_label = ui.label("Date viewed:");
+                form.after_label(ui);
+                ui.add(
+                    DatePickerButton::new(&mut app.edit_modal.source.viewed_date)
+                        .id_source("InputViewedDate") // needs to be set otherwise the UI would bug with multiple date pickers
+                        .show_icon(false),
+                )
+                .labelled_by(viewed_label.id);
+                ui.end_row();
+
+                // input comment
+                let comment_label = ui.label("Comment:");
+                form.after_label(ui);
+                let input_comment = TextEdit::multiline(&mut app.edit_modal.source.comment)
+                    .desired_width(form.input_width);
+                ui.add(input_comment).labelled_by(comment_label.id);
+                ui.end_row();
+
+                // annotation progress
+                ui.label("Notes:");
+                form.after_label(ui);
+                ui.horizontal_wrapped(|ui| {
+                    let source = &mut app.edit_modal.source;
+                    ui.checkbox(&mut source.notes_written, "Notes written");
+                    let words = source.comment.split_whitespace().count();
+                    ui.weak(format!("{} words in the comment", words));
                 });
+                ui.end_row();
+            });
+
+            ui.add_space(10.0);
+
+            render_links_editor(app, ui);
+
+            ui.add_space(10.0);
+
+            render_quotes_editor(app, ui);
+
+            ui.add_space(10.0);
+
+            documents::render_cited_in(app, ui);
+
+            ui.add_space(10.0);
+
+            if ui.button("Save").clicked() {
+                trace!("Edit modal save clicked");
+                let after = app.edit_modal.source.clone();
+                let before = app
+                    .sources_cache
+                    .read()
+                    .unwrap()
+                    .get(after.id)
+                    .unwrap_or_else(|| after.clone());
+                commands::run(UpdateSource::new(before, after), app, ctx);
+                app.edit_modal.open = false;
+            }
+        });
+
+    if !window_open || escape_pressed(ctx) {
+        app.edit_modal.open = false;
+    }
+}
+
+// async resolution of the URL of the edited source
+fn handle_resolve_url(app: &Application) {
+    let url = app.edit_modal.source.url.trim().to_string();
+    let status = app.edit_modal.resolve.clone();
+    *status.write().unwrap() = LookupStatus::Fetching;
+
+    tokio::task::spawn(async move {
+        let name = format!("Resolve canonical URL of {}", url);
+        let result = match scheduler::run(name, || resolve_canonical_url(&url)).await {
+            Ok(canonical) => {
+                let metadata = Metadata {
+                    url: canonical,
+                    ..Default::default()
+                };
+                LookupStatus::Fetched(metadata, "the link".to_string())
+            }
+            Err(e) => {
+                warn!("Error resolving {}: {}", url, e);
+                LookupStatus::Failed(format!("Error resolving URL: {}", e))
+            }
+        };
+        *status.write().unwrap() = result;
+    });
+}
+
+fn apply_resolved_url(app: &mut Application) {
+    let mut status = app.edit_modal.resolve.write().unwrap();
+    if let LookupStatus::Fetched(metadata, service) = &*status {
+        // no canonical URL means the URL itself is canonical
+        app.edit_modal.source.canonical_url = metadata.url.clone().unwrap_or_default();
+        *status = LookupStatus::Applied(service.clone());
+    }
+}
+
+// row below the canonical URL while it's resolved or if that failed
+fn render_resolve_status(app: &Application, ui: &mut Ui) {
+    match &*app.edit_modal.resolve.read().unwrap() {
+        LookupStatus::Fetching => {
+            ui.label("");
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label("Resolving…");
+            });
+            ui.ctx().request_repaint_after(Duration::from_millis(100));
+            ui.end_row();
+        }
+        LookupStatus::Applied(_) if app.edit_modal.source.canonical_url.is_empty() => {
+            ui.label("");
+            ui.weak("The URL doesn't lead elsewhere");
+            ui.end_row();
+        }
+        LookupStatus::Failed(error) => {
+            ui.label("");
+            ui.colored_label(ui.visuals().error_fg_color, error);
+            ui.end_row();
+        }
+        _ => {}
+    }
+}
+
+// similarity of the page at the last re-check if it changed significantly since the snapshot
+fn page_changed(app: &Application, source: &Source) -> Option<f64> {
+    let id = source.library_id()?;
+    app.snapshots_cache
+        .read()
+        .unwrap()
+        .iter()
+        .find(|snapshot| snapshot.source_id == id && snapshot.changed())
+        .and_then(|snapshot| snapshot.similarity)
+}
+
+// links of a source in both directions as (link id, relation label, "[id] title" of the other source)
+fn related_sources(app: &Application, id: i64) -> Vec<(i64, String, String)> {
+    let sources = app.sources_cache.read().unwrap();
+    let title = |id: i64| match sources.view(id) {
+        Some(source) => format!("[{}] {}", id, source.title()),
+        None => format!("[{}]", id),
+    };
+
+    let mut out = vec![];
+    for link in app.links_cache.read().unwrap().iter() {
+        if link.source_id == id {
+            out.push((link.id, link.relation.to_string(), title(link.target_id)));
+        } else if link.target_id == id {
+            out.push((
+                link.id,
+                link.relation.inverse_label().to_string(),
+                title(link.source_id),
+            ));
+        }
+    }
 
-                ui.add_space(5.0);
-                ui.separator();
-                ui.add_space(5.0);
+    out
+}
+
+// related sources section of the edit modal
+fn render_links_editor(app: &mut Application, ui: &mut Ui) {
+    let id = app.edit_modal.source.id;
+
+    ui.label("Related sources:");
+
+    for (link_id, relation, title) in related_sources(app, id) {
+        ui.horizontal(|ui| {
+            let remove_button = accessible_name(
+                ui.small_button("Remove"),
+                format!("Remove relation {} \"{}\"", relation, title),
+            );
+            if remove_button.clicked() {
+                trace!("Remove link clicked");
+                handle_delete_link(link_id, app);
             }
+            let related = format!("{}: {}", relation, title);
+            crate::text_label_wrapped!(&related, ui);
         });
+    }
+
+    ui.horizontal_wrapped(|ui| {
+        let relation_label = ui.label("Relation:");
+        ComboBox::from_id_source("LinkRelation")
+            .selected_text(app.edit_modal.link_relation.to_string())
+            .show_ui(ui, |ui| {
+                for relation in Relation::ALL {
+                    ui.selectable_value(
+                        &mut app.edit_modal.link_relation,
+                        relation,
+                        relation.to_string(),
+                    );
+                }
+            })
+            .response
+            .labelled_by(relation_label.id);
+
+        // "[id] title" of the other sources
+        let targets: Vec<(i64, String)> = app
+            .sources_cache
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|source| source.id() != id)
+            .map(|source| (source.id(), format!("[{}] {}", source.id(), source.title())))
+            .collect();
+        let selected = targets
+            .iter()
+            .find(|(target, _)| Some(*target) == app.edit_modal.link_target)
+            .map(|(_, label)| label.clone())
+            .unwrap_or("Select source".to_string());
+
+        let target_label = ui.label("Source:");
+        ComboBox::from_id_source("LinkTarget")
+            .selected_text(selected)
+            .width(TEXT_INPUT_WIDTH / 2.0)
+            .show_ui(ui, |ui| {
+                for (target, label) in &targets {
+                    ui.selectable_value(&mut app.edit_modal.link_target, Some(*target), label);
+                }
+            })
+            .response
+            .labelled_by(target_label.id);
+
+        let add_button = ui.add_enabled(
+            app.edit_modal.link_target.is_some(),
+            egui::Button::new("Add"),
+        );
+        if add_button.clicked() {
+            trace!("Add link clicked");
+
+            if let Some(target_id) = app.edit_modal.link_target.take() {
+                let link = SourceLink {
+                    id: -1,
+                    source_id: id,
+                    target_id,
+                    relation: app.edit_modal.link_relation,
+                };
+                handle_insert_link(link, app);
+            }
+        }
+    });
+}
+
+// quotes section of the edit modal
+fn render_quotes_editor(app: &mut Application, ui: &mut Ui) {
+    let source = app.edit_modal.source.clone();
+
+    ui.label("Quotes:");
+
+    let quotes: Vec<Quote> = app
+        .quotes_cache
+        .read()
+        .unwrap()
+        .iter()
+        .filter(|quote| quote.source_id == source.id)
+        .cloned()
+        .collect();
+
+    for quote in quotes {
+        ui.horizontal(|ui| {
+            let quote_name = format!("quote \"{}\"", truncated(&quote.text, 40));
+            if accessible_name(ui.small_button("Copy"), format!("Copy {}", quote_name)).clicked() {
+                trace!("Copy quote clicked");
+                let source = prepare_copy(&source, app);
+                let text = quote.format(
+                    &source,
+                    &app.settings.format_standard,
+                    &app.config.read().unwrap(),
+                );
+                copy_citation(text, source.library_id().as_slice(), app);
+            }
+            if accessible_name(ui.small_button("Edit"), format!("Edit {}", quote_name)).clicked() {
+                trace!("Edit quote clicked");
+                app.edit_modal.quote = quote.clone();
+            }
+            let delete_button =
+                accessible_name(ui.small_button("Delete"), format!("Delete {}", quote_name));
+            if delete_button.clicked() {
+                trace!("Delete quote clicked");
+                handle_delete_quote(quote.id, app);
+            }
+
+            let mut preview = format!("\"{}\"", quote.text);
+            if !quote.page.is_empty() {
+                preview.push_str(format!(" (S. {})", quote.page).as_str());
+            }
+            if !quote.note.is_empty() {
+                preview.push_str(format!(" - {}", quote.note).as_str());
+            }
+            crate::text_label_wrapped!(&preview, ui);
+        });
+    }
+
+    let form = FormLayout::new(ui.ctx().screen_rect().width() - 40.0);
+    form.grid("QuoteInput").show(ui, |ui| {
+        // input quote text
+        let text_label = ui.label("Quote:");
+        form.after_label(ui);
+        let input_text = TextEdit::multiline(&mut app.edit_modal.quote.text)
+            .desired_rows(2)
+            .desired_width(form.input_width);
+        ui.add(input_text).labelled_by(text_label.id);
+        ui.end_row();
+
+        // input page
+        let page_label = ui.label("Page:");
+        form.after_label(ui);
+        let input_page = TextEdit::singleline(&mut app.edit_modal.quote.page)
+            .desired_width(form.input_width / 4.0);
+        ui.add(input_page).labelled_by(page_label.id);
+        ui.end_row();
+
+        // input note
+        let note_label = ui.label("Note:");
+        form.after_label(ui);
+        let input_note =
+            TextEdit::singleline(&mut app.edit_modal.quote.note).desired_width(form.input_width);
+        ui.add(input_note).labelled_by(note_label.id);
+        ui.end_row();
+    });
+
+    ui.horizontal(|ui| {
+        let editing = app.edit_modal.quote.id != -1;
+        let save_text = if editing { "Save quote" } else { "Add quote" };
+
+        let save_button = ui.add_enabled(
+            !app.edit_modal.quote.text.is_empty(),
+            egui::Button::new(save_text),
+        );
+        if save_button.clicked() {
+            trace!("Save quote clicked");
+
+            let mut quote = std::mem::take(&mut app.edit_modal.quote);
+            quote.source_id = source.id;
+            handle_save_quote(quote, app);
+        }
+
+        if editing && ui.button("Cancel").clicked() {
+            app.edit_modal.quote = Quote::default();
+        }
+    });
 }
@@ -0,0 +1,189 @@
+use std::fs;
+use std::path::Path;
+
+use chrono::{Local, NaiveTime};
+use egui::{Button, Checkbox, Context, DragValue, Grid};
+use egui_extras::DatePickerButton;
+use tracing::*;
+
+use crate::reading_plan::{self, Pace};
+use crate::source::Source;
+use crate::ui::file_dialog::{self, FilePurpose, FileRequest};
+use crate::ui::{escape_pressed, truncated, Application};
+
+// sessions listed in the preview
+const PREVIEW_SESSIONS: usize = 5;
+
+// dialog of "Reading plan", calendar events for working through the sources without notes
+pub struct ReadingPlanDialog {
+    pub open: bool,
+    pace: Pace,
+    hour: u32,
+    minute: u32,
+    remind: bool,
+    reminder: u32,         // minutes before a session
+    sources: Vec<Source>,  // listed sources without notes when the dialog was last shown
+    error: Option<String>, // last save error
+}
+
+impl Default for ReadingPlanDialog {
+    fn default() -> Self {
+        Self {
+            open: false,
+            pace: Pace {
+                start: Local::now().date_naive(),
+                time: NaiveTime::MIN,
+                minutes: 30,
+                per_session: 1,
+                every_days: 1,
+                reminder: None,
+            },
+            hour: 18,
+            minute: 0,
+            remind: true,
+            reminder: 10,
+            sources: vec![],
+            error: None,
+        }
+    }
+}
+
+impl ReadingPlanDialog {
+    fn pace(&self) -> Pace {
+        Pace {
+            time: NaiveTime::from_hms_opt(self.hour, self.minute, 0).unwrap_or(NaiveTime::MIN),
+            reminder: self.remind.then_some(self.reminder),
+            ..self.pace.clone()
+        }
+    }
+}
+
+// the list page passes the sources it lists that have no notes yet
+pub fn render(app: &mut Application, ctx: &Context, unread: Vec<Source>) {
+    if !app.reading_plan.open {
+        return;
+    }
+
+    let mut open = true;
+    let mut save = false;
+    let dialog = &mut app.reading_plan;
+    dialog.sources = unread;
+
+    egui::Window::new("Reading plan")
+        .collapsible(false)
+        .open(&mut open)
+        .show(ctx, |ui| {
+            ui.label("Schedules reading sessions for the listed sources without notes, in the order of the list:");
+
+            Grid::new("ReadingPlanGrid").num_columns(2).show(ui, |ui| {
+                ui.label("First session:");
+                ui.add(DatePickerButton::new(&mut dialog.pace.start).id_source("ReadingPlanStart"));
+                ui.end_row();
+
+                ui.label("Time:");
+                ui.horizontal(|ui| {
+                    ui.add(DragValue::new(&mut dialog.hour).clamp_range(0..=23));
+                    ui.label(":");
+                    ui.add(DragValue::new(&mut dialog.minute).clamp_range(0..=59));
+                    ui.label("for");
+                    ui.add(
+                        DragValue::new(&mut dialog.pace.minutes)
+                            .clamp_range(5..=480)
+                            .suffix(" min"),
+                    );
+                });
+                ui.end_row();
+
+                ui.label("Pace:");
+                ui.horizontal(|ui| {
+                    ui.add(DragValue::new(&mut dialog.pace.per_session).clamp_range(1..=50));
+                    ui.label("sources every");
+                    ui.add(DragValue::new(&mut dialog.pace.every_days).clamp_range(1..=30));
+                    ui.label("days");
+                });
+                ui.end_row();
+
+                ui.label("Reminder:");
+                ui.horizontal(|ui| {
+                    ui.add(Checkbox::without_text(&mut dialog.remind));
+                    ui.add_enabled(
+                        dialog.remind,
+                        DragValue::new(&mut dialog.reminder)
+                            .clamp_range(0..=1440)
+                            .suffix(" min"),
+                    );
+                    ui.label("before");
+                });
+                ui.end_row();
+            });
+
+            ui.separator();
+
+            let pace = dialog.pace();
+            let sessions = reading_plan::sessions(&dialog.sources, &pace);
+            match (sessions.first(), sessions.last()) {
+                (Some(first), Some(last)) => {
+                    ui.label(format!(
+                        "{} sources in {} sessions, from {} to {}",
+                        dialog.sources.len(),
+                        sessions.len(),
+                        first.start.format("%d.%m.%Y"),
+                        last.start.format("%d.%m.%Y")
+                    ));
+                }
+                _ => {
+                    ui.label("Every listed source has its notes written.");
+                }
+            }
+            for session in sessions.iter().take(PREVIEW_SESSIONS) {
+                let titles: Vec<String> = session
+                    .sources
+                    .iter()
+                    .map(|source| truncated(&source.title, 40))
+                    .collect();
+                ui.label(format!(
+                    "{}: {}",
+                    session.start.format("%d.%m.%Y %H:%M"),
+                    titles.join(", ")
+                ));
+            }
+            if sessions.len() > PREVIEW_SESSIONS {
+                ui.label("…");
+            }
+
+            let any = !sessions.is_empty();
+            if ui.add_enabled(any, Button::new("Save as file")).clicked() {
+                trace!("Save reading plan clicked");
+                save = true;
+            }
+
+            if let Some(error) = &dialog.error {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+            }
+        });
+
+    if save {
+        let request =
+            FileRequest::save(FilePurpose::ReadingPlan, "Select file", "reading-plan.ics")
+                .add_filter("iCalendar", &["ics"]);
+        file_dialog::show(request, app, ctx);
+    }
+
+    if !open || escape_pressed(ctx) {
+        app.reading_plan.open = false;
+    }
+}
+
+// writes the sessions as calendar events to the file
+pub fn save(path: &Path, app: &mut Application) {
+    let dialog = &mut app.reading_plan;
+    let ics = reading_plan::to_ics(&dialog.sources, &dialog.pace());
+
+    dialog.error = match fs::write(path, ics) {
+        Ok(()) => None,
+        Err(e) => {
+            error!("Error saving reading plan: {}", e);
+            Some(format!("Error saving {}: {}", path.display(), e))
+        }
+    };
+}
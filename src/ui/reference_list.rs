@@ -0,0 +1,167 @@
+use std::fs;
+use std::path::Path;
+
+use egui::{Button, Context, ScrollArea, TextEdit};
+use tracing::*;
+
+use crate::references::{self, ReferenceList};
+use crate::source::Source;
+use crate::ui::file_dialog::{self, FilePurpose, FileRequest};
+use crate::ui::{copy_citation, escape_pressed, prepare_copy, Application, TEXT_INPUT_WIDTH};
+
+// dialog of "Numbered references", the sources cited in a pasted manuscript
+#[derive(Default)]
+pub struct ReferenceListDialog {
+    pub open: bool,
+    manuscript: String,
+    error: Option<String>, // last save error
+}
+
+pub fn render(app: &mut Application, ctx: &Context) {
+    if !app.reference_list.open {
+        return;
+    }
+
+    let mut open = true;
+    let list = resolve(app);
+    let text = numbered(&list, app);
+
+    egui::Window::new("Numbered references")
+        .collapsible(false)
+        .open(&mut open)
+        .show(ctx, |ui| {
+            ui.label("Paste your manuscript or a list of IDs, the cited sources are numbered in the order they are first cited:");
+            ScrollArea::vertical()
+                .id_source("Manuscript")
+                .max_height(200.0)
+                .show(ui, |ui| {
+                    let input = TextEdit::multiline(&mut app.reference_list.manuscript)
+                        .hint_text("As shown in [@saveit12] and https://doi.org/10.1000/182 …")
+                        .desired_width(TEXT_INPUT_WIDTH * 1.5)
+                        .desired_rows(6);
+                    ui.add(input);
+                });
+
+            ui.separator();
+
+            ui.label(format!("{} sources cited", list.sources.len()));
+            ScrollArea::vertical()
+                .id_source("References")
+                .max_height(200.0)
+                .show(ui, |ui| {
+                    ui.label(&text);
+                });
+
+            if !list.unknown.is_empty() {
+                ui.colored_label(
+                    ui.visuals().error_fg_color,
+                    format!("Not in the library: {}", list.unknown.join(", ")),
+                );
+            }
+
+            ui.horizontal(|ui| {
+                let any = !list.sources.is_empty();
+                if ui.add_enabled(any, Button::new("Copy")).clicked() {
+                    trace!("Copy numbered references clicked");
+                    let ids: Vec<i64> = list.sources.iter().filter_map(Source::library_id).collect();
+                    copy_citation(text.clone(), &ids, app);
+                }
+
+                if ui.add_enabled(any, Button::new("Save as file")).clicked() {
+                    trace!("Save numbered references clicked");
+                    let request = FileRequest::save(
+                        FilePurpose::ReferenceList,
+                        "Select file",
+                        "references.txt",
+                    )
+                    .add_filter("Text", &["txt"]);
+                    file_dialog::show(request, app, ctx);
+                }
+
+                let mapping = ui
+                    .add_enabled(any, Button::new("Save mapping"))
+                    .on_hover_text("Save a table of the numbers and their references, for numbering footnotes by hand");
+                if mapping.clicked() {
+                    trace!("Save reference mapping clicked");
+                    let request = FileRequest::save(
+                        FilePurpose::ReferenceMapping,
+                        "Select file",
+                        "references.csv",
+                    )
+                    .add_filter("CSV", &["csv"])
+                    .add_filter("Markdown", &["md"]);
+                    file_dialog::show(request, app, ctx);
+                }
+            });
+
+            if let Some(error) = &app.reference_list.error {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+            }
+        });
+
+    if !open || escape_pressed(ctx) {
+        app.reference_list.open = false;
+    }
+}
+
+// writes the numbered references of the pasted manuscript to the file
+pub fn save(path: &Path, app: &mut Application) {
+    let text = numbered(&resolve(app), app);
+
+    app.reference_list.error = match fs::write(path, text) {
+        Ok(()) => None,
+        Err(e) => {
+            error!("Error saving numbered references: {}", e);
+            Some(format!("Error saving {}: {}", path.display(), e))
+        }
+    };
+}
+
+// writes the numbers and references as CSV, or as a Markdown table for .md files
+pub fn save_mapping(path: &Path, app: &mut Application) {
+    let list = prepared(&resolve(app), app);
+    let standard = &app.settings.format_standard;
+    let config = app.config.read().unwrap().clone();
+
+    let markdown = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("md"));
+    let content = match markdown {
+        true => Ok(list.mapping_markdown(standard, &config)),
+        false => list
+            .mapping_csv(standard, &config)
+            .map_err(|e| e.to_string()),
+    };
+
+    let result = content.and_then(|content| fs::write(path, content).map_err(|e| e.to_string()));
+    app.reference_list.error = match result {
+        Ok(()) => None,
+        Err(e) => {
+            error!("Error saving reference mapping: {}", e);
+            Some(format!("Error saving {}: {}", path.display(), e))
+        }
+    };
+}
+
+fn resolve(app: &Application) -> ReferenceList {
+    references::resolve(
+        &app.reference_list.manuscript,
+        &app.sources_cache.read().unwrap().to_vec(),
+    )
+}
+
+// the viewed date is updated like for any other copy
+fn prepared(list: &ReferenceList, app: &Application) -> ReferenceList {
+    ReferenceList {
+        sources: list
+            .sources
+            .iter()
+            .map(|source| prepare_copy(source, app))
+            .collect(),
+        unknown: vec![],
+    }
+}
+
+fn numbered(list: &ReferenceList, app: &Application) -> String {
+    prepared(list, app).numbered(&app.settings.format_standard, &app.config.read().unwrap())
+}
@@ -0,0 +1,382 @@
+use std::error::Error;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+
+use egui::{Context, Grid, ProgressBar, ScrollArea};
+use tracing::*;
+
+use crate::crash::record_operation;
+use crate::metadata::{fetch_page, fetch_video, Metadata};
+use crate::scheduler;
+use crate::source::Source;
+use crate::ui::handlers::handle_bulk_edit;
+use crate::ui::{escape_pressed, truncated, Application};
+use crate::url_analysis;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RefetchField {
+    Title,
+    Author,
+    PublishedDate,
+    Doi,
+    Type,
+}
+
+impl RefetchField {
+    const ALL: [RefetchField; 5] = [
+        RefetchField::Title,
+        RefetchField::Author,
+        RefetchField::PublishedDate,
+        RefetchField::Doi,
+        RefetchField::Type,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            RefetchField::Title => "Title",
+            RefetchField::Author => "Author",
+            RefetchField::PublishedDate => "Date published",
+            RefetchField::Doi => "DOI",
+            RefetchField::Type => "Type",
+        }
+    }
+
+    fn text(&self, source: &Source) -> String {
+        match self {
+            RefetchField::Title => source.title.clone(),
+            RefetchField::Author => source.author.clone(),
+            RefetchField::PublishedDate if source.published_date_unknown => "Unknown".to_string(),
+            RefetchField::PublishedDate => source.published_date.format("%d. %m. %Y").to_string(),
+            RefetchField::Doi => source.doi.clone(),
+            RefetchField::Type => source.source_type.to_string(),
+        }
+    }
+
+    fn copy(&self, from: &Source, to: &mut Source) {
+        match self {
+            RefetchField::Title => to.title = from.title.clone(),
+            RefetchField::Author => to.author = from.author.clone(),
+            RefetchField::PublishedDate => {
+                to.published_date = from.published_date;
+                to.published_date_unknown = from.published_date_unknown;
+            }
+            RefetchField::Doi => to.doi = from.doi.clone(),
+            RefetchField::Type => to.source_type = from.source_type,
+        }
+    }
+}
+
+// fields of a source the page has different values for
+#[derive(Debug, Clone)]
+struct Proposal {
+    title: String,   // current title
+    fetched: Source, // source with all fetched values
+    fields: Vec<FieldChange>,
+}
+
+#[derive(Debug, Clone)]
+struct FieldChange {
+    field: RefetchField,
+    current: String,
+    fetched: String,
+    accepted: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+enum RefetchStatus {
+    #[default]
+    Idle,
+    Fetching,
+    Fetched(Vec<Proposal>, Vec<String>), // proposals and the pages that couldn't be fetched
+}
+
+// dialog of "Re-fetch metadata", nothing is written before the changes are reviewed
+#[derive(Default)]
+pub struct Refetch {
+    pub open: bool,
+    status: Arc<RwLock<RefetchStatus>>,
+    progress: Arc<AtomicUsize>, // pages fetched so far
+    total: usize,
+    proposals: Vec<Proposal>,
+    errors: Vec<String>,
+}
+
+pub fn render(app: &mut Application, ctx: &Context) {
+    if !app.refetch.open {
+        return;
+    }
+
+    // the finished fetch is reviewed in the dialog
+    let fetched = std::mem::take(&mut *app.refetch.status.write().unwrap());
+    match fetched {
+        RefetchStatus::Fetched(proposals, errors) => {
+            app.refetch.proposals = proposals;
+            app.refetch.errors = errors;
+        }
+        status => *app.refetch.status.write().unwrap() = status,
+    }
+    let fetching = matches!(*app.refetch.status.read().unwrap(), RefetchStatus::Fetching);
+
+    let mut open = app.refetch.open;
+    let selected = app.selected.len();
+
+    egui::Window::new(format!("Re-fetch metadata ({} sources)", selected))
+        .collapsible(false)
+        .open(&mut open)
+        .show(ctx, |ui| {
+            ui.label("Looks up the pages of the selected sources again, nothing is changed before you accept it.");
+
+            if ui
+                .add_enabled(selected > 0 && !fetching, egui::Button::new("Fetch"))
+                .clicked()
+            {
+                trace!("Fetch metadata clicked");
+                handle_fetch(app);
+            }
+
+            if fetching {
+                let done = app
+                    .refetch
+                    .progress
+                    .load(Ordering::Relaxed)
+                    .min(app.refetch.total);
+                let progress = done as f32 / app.refetch.total.max(1) as f32;
+                ui.add(ProgressBar::new(progress).text(format!(
+                    "{} / {} pages fetched",
+                    done, app.refetch.total
+                )));
+                ctx.request_repaint();
+                return;
+            }
+
+            if !app.refetch.errors.is_empty() {
+                ui.collapsing(
+                    format!("{} pages couldn't be fetched", app.refetch.errors.len()),
+                    |ui| {
+                        for error in &app.refetch.errors {
+                            ui.colored_label(ui.visuals().error_fg_color, error);
+                        }
+                    },
+                );
+            }
+
+            render_review(app, ui);
+        });
+
+    if open && escape_pressed(ctx) {
+        open = false;
+    }
+
+    // the review of a closed dialog is outdated when it's reopened
+    if !open {
+        app.refetch.proposals.clear();
+        app.refetch.errors.clear();
+    }
+    app.refetch.open = open;
+}
+
+// current and fetched value of every changed field with a checkbox to accept it
+fn render_review(app: &mut Application, ui: &mut egui::Ui) {
+    let changes: usize = app
+        .refetch
+        .proposals
+        .iter()
+        .map(|proposal| proposal.fields.len())
+        .sum();
+    if changes == 0 {
+        return;
+    }
+
+    ui.separator();
+    ui.horizontal(|ui| {
+        ui.label(format!(
+            "{} changes in {} sources",
+            changes,
+            app.refetch.proposals.len()
+        ));
+        for (text, accepted) in [("Accept all", true), ("Reject all", false)] {
+            if ui.button(text).clicked() {
+                for proposal in &mut app.refetch.proposals {
+                    for change in &mut proposal.fields {
+                        change.accepted = accepted;
+                    }
+                }
+            }
+        }
+    });
+
+    ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+        Grid::new("RefetchReview")
+            .num_columns(5)
+            .striped(true)
+            .show(ui, |ui| {
+                ui.strong("Source");
+                ui.strong("Field");
+                ui.strong("Current");
+                ui.strong("Fetched");
+                ui.label("");
+                ui.end_row();
+
+                for proposal in &mut app.refetch.proposals {
+                    for (index, change) in proposal.fields.iter_mut().enumerate() {
+                        match index {
+                            0 => ui.label(format!(
+                                "[{}] {}",
+                                proposal.fetched.id,
+                                truncated(&proposal.title, 30)
+                            )),
+                            _ => ui.label(""),
+                        };
+                        ui.label(change.field.label());
+                        ui.label(truncated(&change.current, 40))
+                            .on_hover_text(change.current.as_str());
+                        ui.label(truncated(&change.fetched, 40))
+                            .on_hover_text(change.fetched.as_str());
+                        ui.checkbox(&mut change.accepted, "Accept");
+                        ui.end_row();
+                    }
+                }
+            });
+    });
+
+    let accepted = app
+        .refetch
+        .proposals
+        .iter()
+        .flat_map(|proposal| &proposal.fields)
+        .filter(|change| change.accepted)
+        .count();
+    let apply = egui::Button::new(format!("Apply {} changes", accepted));
+    if ui
+        .add_enabled(accepted > 0 && !app.read_only, apply)
+        .clicked()
+    {
+        trace!("Apply re-fetched metadata clicked");
+        apply_accepted(app);
+    }
+}
+
+// accepted fields are copied into the sources as they are now, they may have been edited since
+fn apply_accepted(app: &mut Application) {
+    let proposals = std::mem::take(&mut app.refetch.proposals);
+    let sources: Vec<Source> = {
+        let cache = app.sources_cache.read().unwrap();
+        proposals
+            .iter()
+            .filter_map(|proposal| {
+                let mut source = cache.get(proposal.fetched.id)?;
+                let mut changed = false;
+                for change in &proposal.fields {
+                    if change.accepted {
+                        change.field.copy(&proposal.fetched, &mut source);
+                        changed = true;
+                    }
+                }
+                changed.then_some(source)
+            })
+            .collect()
+    };
+
+    handle_bulk_edit(sources, Arc::new(AtomicUsize::new(0)), app);
+}
+
+// async lookup of the selected sources with a web address, one after another
+fn handle_fetch(app: &mut Application) {
+    let sources: Vec<Source> = app
+        .sources_cache
+        .read()
+        .unwrap()
+        .iter()
+        .filter(|source| app.selected.contains(&source.id()))
+        .filter(|source| url_analysis::analyze(source.url()).web)
+        .map(|source| source.to_source())
+        .collect();
+    record_operation(format!("Re-fetch metadata of {} sources", sources.len()));
+
+    let metadata_only = app.config.read().unwrap().metadata_only;
+    let status = app.refetch.status.clone();
+    let progress = app.refetch.progress.clone();
+    app.refetch.total = sources.len();
+    app.refetch.proposals.clear();
+    app.refetch.errors.clear();
+    progress.store(0, Ordering::Relaxed);
+    *status.write().unwrap() = RefetchStatus::Fetching;
+
+    tokio::task::spawn(async move {
+        let mut proposals = vec![];
+        let mut errors = vec![];
+
+        for source in sources {
+            match fetch(&source, metadata_only).await {
+                Ok(metadata) => proposals.extend(propose(&source, metadata)),
+                Err(e) => {
+                    warn!("Error re-fetching {}: {}", source.url, e);
+                    errors.push(format!("[{}] {}: {}", source.id, source.url, e));
+                }
+            }
+            progress.fetch_add(1, Ordering::Relaxed);
+        }
+
+        *status.write().unwrap() = RefetchStatus::Fetched(proposals, errors);
+    });
+}
+
+// the same lookup as for a URL on the start page
+async fn fetch(
+    source: &Source,
+    metadata_only: bool,
+) -> Result<Metadata, Box<dyn Error + Send + Sync>> {
+    let url = source.url.trim();
+    let name = format!("Fetch metadata of {}", url);
+    match url_analysis::analyze(url).video {
+        Some(host) => scheduler::run(name, || fetch_video(url, host)).await,
+        None => scheduler::run(name, || fetch_page(url, metadata_only)).await,
+    }
+}
+
+// None if the page has nothing new, empty values don't replace anything
+fn propose(source: &Source, metadata: Metadata) -> Option<Proposal> {
+    let mut fetched = source.clone();
+    let filled = |value: Option<String>| {
+        value
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+
+    if let Some(title) = filled(metadata.title) {
+        fetched.title = title;
+    }
+    if let Some(author) = filled(metadata.author) {
+        fetched.author = author;
+    }
+    if let Some(published_date) = metadata.published_date {
+        fetched.published_date = published_date;
+        fetched.published_date_unknown = false;
+    }
+    if let Some(doi) = filled(metadata.doi) {
+        fetched.doi = doi;
+    }
+    if let Some(source_type) = metadata.source_type {
+        fetched.source_type = source_type;
+    }
+
+    let fields: Vec<FieldChange> = RefetchField::ALL
+        .into_iter()
+        .map(|field| FieldChange {
+            field,
+            current: field.text(source),
+            fetched: field.text(&fetched),
+            accepted: true,
+        })
+        .filter(|change| change.current != change.fetched)
+        .collect();
+
+    match fields.is_empty() {
+        true => None,
+        false => Some(Proposal {
+            title: source.title.clone(),
+            fetched,
+            fields,
+        }),
+    }
+}
@@ -1,10 +1,37 @@
-use egui::{ComboBox, TextEdit, Ui};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+use chrono::{Local, NaiveDate};
+use egui::{CollapsingHeader, ComboBox, Context, DragValue, Grid, ScrollArea, TextEdit, Ui};
 use tracing::*;
 
+use crate::collation;
 use crate::config::{Config, FormatStandard};
-use crate::ui::{Application, TEXT_INPUT_WIDTH};
+use crate::crash::record_operation;
+use crate::formats;
+use crate::http::{self, HttpSettings};
+use crate::plugins;
+use crate::source::{Source, SourceType};
+use crate::template::Template;
+use crate::ui::actions::Action;
+use crate::ui::commands;
+use crate::ui::file_dialog::{self, FilePurpose, FileRequest};
+use crate::ui::handlers::handle_set_collation;
+use crate::ui::layout;
+use crate::ui::library_info;
+use crate::ui::{Application, MAX_UI_SCALE, MIN_UI_SCALE};
 
+// scrolls on small screens and with a large UI scale
 pub fn render(app: &mut Application, ui: &mut Ui) {
+    ScrollArea::vertical()
+        .auto_shrink(false)
+        .show(ui, |ui| render_settings(app, ui));
+
+    render_hook_confirmation(app, ui.ctx());
+}
+
+fn render_settings(app: &mut Application, ui: &mut Ui) {
     // select source formatting standard
     ComboBox::from_label("Select source format")
         .selected_text(format!("{:?}", app.settings.format_standard))
@@ -19,12 +46,37 @@ pub fn render(app: &mut Application, ui: &mut Ui) {
                 FormatStandard::Custom,
                 "Custom",
             );
+            ui.selectable_value(
+                &mut app.settings.format_standard,
+                FormatStandard::Plugin,
+                "Plugin",
+            );
         });
 
+    if app.settings.format_standard == FormatStandard::Plugin {
+        let formatters = plugins::formatters();
+        let selected = formatters
+            .iter()
+            .find(|plugin| plugin.id == app.settings.plugin_format)
+            .map_or("None".to_string(), |plugin| plugin.name.clone());
+
+        ComboBox::from_label("Format plugin")
+            .selected_text(selected)
+            .show_ui(ui, |ui| {
+                for plugin in &formatters {
+                    ui.selectable_value(
+                        &mut app.settings.plugin_format,
+                        plugin.id.clone(),
+                        &plugin.name,
+                    );
+                }
+            });
+    }
+
     ui.horizontal(|ui| {
         let custom_label = ui.label("Custom format:");
-        let input_custom_format =
-            TextEdit::singleline(&mut app.settings.custom_format).desired_width(TEXT_INPUT_WIDTH);
+        let input_custom_format = TextEdit::singleline(&mut app.settings.custom_format)
+            .desired_width(layout::input_width(ui));
 
         let enabled = matches!(app.settings.format_standard, FormatStandard::Custom);
 
@@ -32,6 +84,232 @@ pub fn render(app: &mut Application, ui: &mut Ui) {
             .labelled_by(custom_label.id);
     });
 
+    ui.horizontal(|ui| {
+        let in_text_label = ui.label("Custom in-text format:");
+        let input_in_text_format = TextEdit::singleline(&mut app.settings.custom_in_text_format)
+            .desired_width(layout::input_width(ui));
+
+        let enabled = matches!(app.settings.format_standard, FormatStandard::Custom);
+
+        ui.add_enabled(enabled, input_in_text_format)
+            .labelled_by(in_text_label.id);
+    });
+
+    // custom format errors from the last save attempt
+    for error in &app.settings.format_errors {
+        ui.colored_label(ui.visuals().error_fg_color, error);
+    }
+
+    ui.add_space(5.0);
+
+    // live preview of the selected format
+    ui.horizontal_wrapped(|ui| {
+        ui.label("Preview:");
+
+        let sample = sample_source();
+        let preview = match app.settings.format_standard {
+            FormatStandard::Custom => sample.format_custom(&app.settings.custom_format, Some("4")),
+            FormatStandard::Plugin => {
+                plugins::format(&app.settings.plugin_format, &sample, Some("4"))
+            }
+            standard => sample.format_with_pages(&standard, &app.config.read().unwrap(), Some("4")),
+        };
+        ui.label(preview);
+    });
+
+    ui.horizontal_wrapped(|ui| {
+        ui.label("In-text preview:");
+
+        let sample = sample_source();
+        let preview = match app.settings.format_standard {
+            FormatStandard::Custom => {
+                sample.format_custom(&app.settings.custom_in_text_format, Some("4"))
+            }
+            FormatStandard::Plugin => {
+                plugins::format_in_text(&app.settings.plugin_format, &sample, Some("4"))
+                    .unwrap_or_else(|| {
+                        let config = app.config.read().unwrap();
+                        sample.format_in_text(&FormatStandard::Default, &config, Some("4"))
+                    })
+            }
+            standard => sample.format_in_text(&standard, &app.config.read().unwrap(), Some("4")),
+        };
+        ui.label(preview);
+    });
+
+    ui.add_space(5.0);
+
+    render_collection_formats(app, ui);
+
+    ui.add_space(5.0);
+
+    ui.checkbox(
+        &mut app.settings.update_viewed_on_copy,
+        "Set date viewed to today when copying a source",
+    );
+
+    ui.add_space(5.0);
+
+    ui.horizontal(|ui| {
+        let scale_label = ui.label("UI scale:");
+        ui.add(
+            DragValue::new(&mut app.settings.ui_scale)
+                .clamp_range(MIN_UI_SCALE..=MAX_UI_SCALE)
+                .speed(0.01)
+                .custom_formatter(|scale, _| format!("{:.0} %", scale * 100.0))
+                .custom_parser(|text| {
+                    let percent = text.trim().trim_end_matches('%').trim();
+                    percent.parse::<f64>().ok().map(|percent| percent / 100.0)
+                }),
+        )
+        .labelled_by(scale_label.id)
+        .on_hover_text("Ctrl + Plus and Ctrl + Minus zoom as well, Ctrl + 0 resets the scale");
+    });
+
+    ui.add_space(5.0);
+
+    // read-only library shown alongside the own one
+    ui.horizontal(|ui| {
+        let shared_label = ui.label("Shared library:");
+        let input_shared_library = TextEdit::singleline(&mut app.settings.shared_library)
+            .hint_text("None")
+            .desired_width(layout::input_width(ui));
+        ui.add(input_shared_library).labelled_by(shared_label.id);
+
+        if ui.button("Browse").clicked() {
+            trace!("Browse shared library clicked");
+            let request = FileRequest::open(FilePurpose::SharedLibrary, "Select library")
+                .add_filter("SaveIt library", &["db"]);
+            file_dialog::show(request, app, ui.ctx());
+        }
+
+        if ui.button("Remove").clicked() {
+            app.settings.shared_library.clear();
+        }
+    });
+
+    ui.add_space(5.0);
+
+    // read-only libraries only searched with "Everywhere" on the list page
+    ui.horizontal(|ui| {
+        let libraries_label = ui.label("Other libraries:");
+        let input_other_libraries = TextEdit::multiline(&mut app.settings.other_libraries)
+            .hint_text("One library file per line")
+            .desired_rows(2)
+            .desired_width(layout::input_width(ui));
+        ui.add(input_other_libraries)
+            .labelled_by(libraries_label.id)
+            .on_hover_text("Searched together with your library when \"Everywhere\" is on");
+
+        if ui.button("Add").clicked() {
+            trace!("Add other library clicked");
+            let request = FileRequest::open(FilePurpose::OtherLibrary, "Select library")
+                .add_filter("SaveIt library", &["db"]);
+            file_dialog::show(request, app, ui.ctx());
+        }
+    });
+
+    ui.add_space(5.0);
+
+    // stored in the library, applied without saving the settings
+    let mut collation = app.collation.read().unwrap().clone();
+    ui.add_enabled_ui(!app.read_only, |ui| {
+        ComboBox::from_label("Sort order of this library")
+            .selected_text(collation::name(&collation))
+            .show_ui(ui, |ui| {
+                for (locale, name) in collation::COLLATIONS {
+                    ui.selectable_value(&mut collation, locale.to_string(), name);
+                }
+            })
+            .response
+            .on_hover_text(
+                "How titles and authors are sorted on the list page, e.g. \"Ärzte\" next to \"Arzte\"",
+            );
+    });
+    if collation != *app.collation.read().unwrap() {
+        handle_set_collation(&collation, app);
+    }
+
+    ui.add_space(5.0);
+
+    // LAN sync
+    ui.checkbox(
+        &mut app.settings.lan_sync,
+        "Sync with SaveIt on other devices in the local network",
+    );
+    ui.horizontal(|ui| {
+        let key_label = ui.label("Sync key:");
+        let input_sync_key = TextEdit::singleline(&mut app.settings.sync_key)
+            .password(true)
+            .hint_text("Same on all devices")
+            .desired_width(layout::input_width(ui));
+        ui.add_enabled(app.settings.lan_sync, input_sync_key)
+            .labelled_by(key_label.id);
+    });
+    render_sync_status(app, ui);
+
+    ui.add_space(5.0);
+
+    #[cfg(feature = "server")]
+    render_local_server(app, ui);
+
+    ui.add_space(5.0);
+
+    // network access of lookups, updates and LAN sync, the local server isn't affected
+    ui.checkbox(
+        &mut app.settings.offline_mode,
+        "Offline mode (no lookups, update checks or LAN sync)",
+    );
+    ui.add_enabled_ui(!app.settings.offline_mode, |ui| {
+        ui.checkbox(
+            &mut app.settings.metadata_only,
+            "Only download the page head when looking up URLs (for slow or metered connections)",
+        );
+        ui.checkbox(
+            &mut app.settings.resolve_urls,
+            "Store where links lead when saving sources (e.g. for shortened links like bit.ly)",
+        );
+        ui.horizontal(|ui| {
+            let timeout_label = ui.label("Timeout:");
+            ui.add(
+                DragValue::new(&mut app.settings.http_timeout)
+                    .clamp_range(1..=120)
+                    .suffix(" s"),
+            )
+            .labelled_by(timeout_label.id);
+        });
+        ui.horizontal(|ui| {
+            let proxy_label = ui.label("Proxy:");
+            let input_proxy = TextEdit::singleline(&mut app.settings.http_proxy)
+                .hint_text("e.g. http://proxy.example.com:8080")
+                .desired_width(layout::input_width(ui));
+            ui.add(input_proxy).labelled_by(proxy_label.id);
+        });
+        ui.horizontal(|ui| {
+            let user_agent_label = ui.label("User agent:");
+            let input_user_agent = TextEdit::singleline(&mut app.settings.http_user_agent)
+                .hint_text(format!("SaveIt/{}", env!("CARGO_PKG_VERSION")))
+                .desired_width(layout::input_width(ui));
+            ui.add(input_user_agent).labelled_by(user_agent_label.id);
+        });
+    });
+
+    ui.add_space(5.0);
+
+    render_auto_export(app, ui);
+
+    ui.add_space(5.0);
+
+    render_inbox(app, ui);
+
+    ui.add_space(5.0);
+
+    render_plugins(ui);
+
+    ui.add_space(5.0);
+
+    render_hooks(app, ui);
+
     ui.add_space(5.0);
     ui.separator();
     ui.add_space(5.0);
@@ -39,14 +317,720 @@ pub fn render(app: &mut Application, ui: &mut Ui) {
     // Save button
     if ui.button("Save").clicked() {
         trace!("Save clicked");
-        let mut config = Config::get_config();
+
+        // don't save a custom format that would produce broken citations, the format of a
+        // collection counts as well
+        let uses = |standard: FormatStandard| {
+            app.settings.format_standard == standard
+                || app
+                    .settings
+                    .collection_formats
+                    .values()
+                    .any(|other| *other == standard)
+        };
+        let mut format_errors = vec![];
+        if uses(FormatStandard::Custom) {
+            format_errors.extend(
+                [
+                    ("Custom format", &app.settings.custom_format),
+                    ("Custom in-text format", &app.settings.custom_in_text_format),
+                ]
+                .into_iter()
+                .filter_map(|(name, format)| {
+                    Template::parse(format)
+                        .err()
+                        .map(|e| format!("{}: {}", name, e))
+                }),
+            );
+        }
+        if uses(FormatStandard::Plugin) && app.settings.plugin_format.is_empty() {
+            format_errors.push("Select the plugin that formats the sources".to_string());
+        }
+        app.settings.format_errors = format_errors;
+        if !app.settings.format_errors.is_empty() {
+            warn!("Not saving invalid custom format");
+            return;
+        }
+
+        let mut config = app.config.write().unwrap();
 
         // Source formatting standard
         config.format_standard = app.settings.format_standard;
 
         // Custom format
         config.custom_format = app.settings.custom_format.clone();
+        config.custom_in_text_format = app.settings.custom_in_text_format.clone();
+        config.plugin_format = app.settings.plugin_format.clone();
+        config.collection_formats = app.settings.collection_formats.clone();
+
+        config.update_viewed_on_copy = app.settings.update_viewed_on_copy;
+
+        config.shared_library = app.settings.shared_library.trim().to_string();
+        config.other_libraries = library_paths(&app.settings.other_libraries);
+
+        let sync_changed = config.lan_sync != app.settings.lan_sync
+            || config.sync_key != app.settings.sync_key
+            || config.offline_mode != app.settings.offline_mode;
+        config.lan_sync = app.settings.lan_sync;
+        config.sync_key = app.settings.sync_key.clone();
+
+        let server_changed = config.local_server != app.settings.local_server
+            || config.local_server_port != app.settings.local_server_port;
+        config.local_server = app.settings.local_server;
+        config.local_server_port = app.settings.local_server_port;
+        config.assistant_api = app.settings.assistant_api;
+
+        config.metadata_only = app.settings.metadata_only;
+        config.resolve_urls = app.settings.resolve_urls;
+        config.http_timeout = app.settings.http_timeout;
+        config.http_proxy = app.settings.http_proxy.trim().to_string();
+        config.http_user_agent = app.settings.http_user_agent.trim().to_string();
+        config.offline_mode = app.settings.offline_mode;
+        http::configure(&HttpSettings::from_config(&config));
+        app.queue.wake();
+
+        let auto_export_changed = config.auto_export != app.settings.auto_export
+            || config.auto_export_path != app.settings.auto_export_path.trim()
+            || config.auto_export_format != app.settings.auto_export_format
+            || config.auto_export_interval != app.settings.auto_export_interval;
+        config.auto_export = app.settings.auto_export;
+        config.auto_export_path = app.settings.auto_export_path.trim().to_string();
+        config.auto_export_format = app.settings.auto_export_format.clone();
+        config.auto_export_interval = app.settings.auto_export_interval;
+        config.auto_export_on_exit = app.settings.auto_export_on_exit;
+
+        let inbox_changed =
+            config.inbox != app.settings.inbox || config.inbox_dir != app.settings.inbox_dir.trim();
+        config.inbox = app.settings.inbox;
+        config.inbox_dir = app.settings.inbox_dir.trim().to_string();
+
+        config.hook_source_saved = app.settings.hook_source_saved.trim().to_string();
+        config.hook_citation_copied = app.settings.hook_citation_copied.trim().to_string();
+        config.hook_export_finished = app.settings.hook_export_finished.trim().to_string();
+
+        // applied by the next frame
+        config.ui_scale = app.settings.ui_scale;
 
         config.save();
+        drop(config);
+
+        app.update_shared_cache();
+        app.update_library_cache();
+        if sync_changed {
+            app.restart_lan_sync();
+        }
+        if server_changed {
+            app.restart_local_server();
+        }
+        if auto_export_changed {
+            app.restart_auto_export();
+        }
+        if inbox_changed {
+            app.restart_inbox();
+        }
+    }
+
+    ui.add_space(5.0);
+    ui.separator();
+    ui.add_space(5.0);
+
+    library_info::render(app, ui);
+
+    ui.add_space(5.0);
+
+    ui.horizontal(|ui| {
+        if ui
+            .button("Check library")
+            .on_hover_text("Looks for damaged data and offers to repair it")
+            .clicked()
+        {
+            commands::run(Action::CheckLibrary, app, ui.ctx());
+        }
+        if ui
+            .button("Check links")
+            .on_hover_text("Looks for broken and redirected links and their archived copies")
+            .clicked()
+        {
+            commands::run(Action::CheckLinks, app, ui.ctx());
+        }
+    });
+
+    ui.add_space(5.0);
+
+    ui.horizontal(|ui| {
+        if ui.button("Export settings").clicked() {
+            trace!("Export settings clicked");
+            record_operation("Export settings");
+            let request = FileRequest::save(
+                FilePurpose::ExportSettings,
+                "Select file",
+                "saveit-settings.toml",
+            )
+            .add_filter("TOML", &["toml"]);
+            file_dialog::show(request, app, ui.ctx());
+        }
+
+        if ui.button("Import settings").clicked() {
+            trace!("Import settings clicked");
+            record_operation("Import settings");
+            let request = FileRequest::open(FilePurpose::ImportSettings, "Select File")
+                .add_filter("TOML", &["toml"]);
+            file_dialog::show(request, app, ui.ctx());
+        }
+
+        ui.checkbox(
+            &mut app.settings.export_hooks,
+            "Export the commands run on events",
+        )
+        .on_hover_text("The sync key is never exported");
+    });
+
+    if let Some(error) = &app.settings.transfer_error {
+        ui.colored_label(ui.visuals().error_fg_color, error);
+    }
+}
+
+pub fn export_settings(path: &Path, app: &mut Application) {
+    app.settings.transfer_error = app
+        .config
+        .read()
+        .unwrap()
+        .export(path, app.settings.export_hooks)
+        .err()
+        .map(|e| format!("Error exporting settings: {}", e));
+}
+
+// commands run on events, the event is passed as JSON on stdin
+fn render_hooks(app: &mut Application, ui: &mut Ui) {
+    ui.label("Commands to run (the event is passed as JSON on stdin):");
+
+    for (name, command) in [
+        ("Source saved:", &mut app.settings.hook_source_saved),
+        ("Citation copied:", &mut app.settings.hook_citation_copied),
+        ("Export finished:", &mut app.settings.hook_export_finished),
+    ] {
+        ui.horizontal(|ui| {
+            let label = ui.label(name);
+            let input = TextEdit::singleline(command)
+                .hint_text("None")
+                .code_editor()
+                .desired_width(layout::input_width(ui));
+            ui.add(input).labelled_by(label.id);
+        });
+    }
+}
+
+// scripts in the plugins folder with what they add and their errors
+fn render_plugins(ui: &mut Ui) {
+    ui.label("Plugins:");
+
+    let plugins = plugins::list();
+    if plugins.is_empty() {
+        ui.label("No plugins installed");
     }
+    for plugin in &plugins {
+        let mut provides = vec![];
+        if plugin.formats {
+            provides.push("citation format");
+        }
+        if plugin.fetches {
+            provides.push("metadata");
+        }
+
+        ui.horizontal_wrapped(|ui| {
+            ui.label(&plugin.name)
+                .on_hover_text(plugin.path.display().to_string());
+            match &plugin.error {
+                Some(error) => ui.colored_label(ui.visuals().error_fg_color, error),
+                None if provides.is_empty() => ui.weak("adds nothing"),
+                None => ui.weak(provides.join(", ")),
+            };
+        });
+    }
+
+    ui.horizontal(|ui| {
+        if ui.button("Open plugins folder").clicked() {
+            trace!("Open plugins folder clicked");
+            let dir = plugins::plugins_dir();
+            if let Err(e) = fs::create_dir_all(&dir).and_then(|_| open::that(&dir)) {
+                error!("Error opening plugins folder: {}", e);
+            }
+        }
+
+        if ui.button("Reload plugins").clicked() {
+            trace!("Reload plugins clicked");
+            record_operation("Reload plugins");
+            plugins::reload();
+        }
+    });
+}
+
+// always current library file, e.g. for a LaTeX build
+fn render_auto_export(app: &mut Application, ui: &mut Ui) {
+    ui.checkbox(
+        &mut app.settings.auto_export,
+        "Export the library to a file automatically",
+    );
+    ui.add_enabled_ui(app.settings.auto_export, |ui| {
+        let format = formats::by_id(&app.settings.auto_export_format);
+
+        ui.horizontal(|ui| {
+            let file_label = ui.label("File:");
+            let input_file = TextEdit::singleline(&mut app.settings.auto_export_path)
+                .hint_text("None")
+                .desired_width(layout::input_width(ui));
+            ui.add(input_file).labelled_by(file_label.id);
+
+            if ui.button("Browse").clicked() {
+                trace!("Browse automatic export clicked");
+                let mut request = FileRequest::save(
+                    FilePurpose::AutoExportFile,
+                    "Select file",
+                    match format {
+                        Some(format) => format!("library.{}", format.extensions()[0]),
+                        None => "library".to_string(),
+                    },
+                );
+                if let Some(format) = format {
+                    request = request.add_filter(format.name(), format.extensions());
+                }
+                file_dialog::show(request, app, ui.ctx());
+            }
+        });
+
+        ui.horizontal(|ui| {
+            let format_label = ui.label("Format:");
+            ComboBox::from_id_source("auto_export_format")
+                .selected_text(format.map(|format| format.name()).unwrap_or("Unknown"))
+                .show_ui(ui, |ui| {
+                    for format in formats::exporters() {
+                        ui.selectable_value(
+                            &mut app.settings.auto_export_format,
+                            format.id().to_string(),
+                            format.name(),
+                        );
+                    }
+                })
+                .response
+                .labelled_by(format_label.id);
+        });
+
+        ui.horizontal(|ui| {
+            let interval_label = ui.label("Every:");
+            ui.add(
+                DragValue::new(&mut app.settings.auto_export_interval)
+                    .clamp_range(0..=168)
+                    .suffix(" h"),
+            )
+            .labelled_by(interval_label.id)
+            .on_hover_text("0 to only export on exit");
+        });
+
+        ui.checkbox(&mut app.settings.auto_export_on_exit, "Export on exit");
+    });
+
+    if let Some(error) = &app.settings.auto_export_error {
+        ui.colored_label(ui.visuals().error_fg_color, error);
+    } else if let Some(auto_export) = &app.auto_export {
+        let status = auto_export.status.read().unwrap();
+        match (&status.error, status.last_export) {
+            (Some(error), _) => {
+                ui.colored_label(
+                    ui.visuals().error_fg_color,
+                    format!("Error exporting library: {}", error),
+                );
+            }
+            (None, Some(last_export)) => {
+                ui.label(format!(
+                    "Last export {}",
+                    last_export.format("%d. %m. %Y %H:%M")
+                ));
+            }
+            (None, None) => {}
+        }
+    }
+}
+
+// folder other tools (e.g. a browser extension or a script) save files to
+// collections cited in another format than the rest, e.g. a paper for a journal
+fn render_collection_formats(app: &mut Application, ui: &mut Ui) {
+    // collections that lost their sources keep their format until it is reset
+    let collections: BTreeSet<String> = app
+        .sources_cache
+        .read()
+        .unwrap()
+        .iter()
+        .map(|source| source.collection().trim().to_string())
+        .filter(|collection| !collection.is_empty())
+        .chain(app.settings.collection_formats.keys().cloned())
+        .collect();
+    let mut collections: Vec<String> = collections.into_iter().collect();
+    let collation = app.collation.read().unwrap().clone();
+    collections.sort_by(|a, b| collation::compare(&collation, a, b));
+
+    CollapsingHeader::new(format!(
+        "Formats of collections ({})",
+        app.settings.collection_formats.len()
+    ))
+    .id_source("CollectionFormats")
+    .show(ui, |ui| {
+        if collections.is_empty() {
+            ui.label("None of your sources is in a collection.");
+            return;
+        }
+
+        ui.label("Sources of these collections are copied in their own format:");
+        Grid::new("CollectionFormatsGrid")
+            .num_columns(2)
+            .show(ui, |ui| {
+                for collection in &collections {
+                    let collection_label = ui.label(collection);
+
+                    let mut standard = app.settings.collection_formats.get(collection).copied();
+                    ComboBox::from_id_source(("CollectionFormat", collection))
+                        .selected_text(match standard {
+                            Some(standard) => format!("{:?}", standard),
+                            None => "Selected format".to_string(),
+                        })
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut standard, None, "Selected format");
+                            for option in [
+                                FormatStandard::Default,
+                                FormatStandard::Custom,
+                                FormatStandard::Plugin,
+                            ] {
+                                ui.selectable_value(
+                                    &mut standard,
+                                    Some(option),
+                                    format!("{:?}", option),
+                                );
+                            }
+                        })
+                        .response
+                        .labelled_by(collection_label.id);
+
+                    match standard {
+                        Some(standard) => {
+                            app.settings
+                                .collection_formats
+                                .insert(collection.clone(), standard);
+                        }
+                        None => {
+                            app.settings.collection_formats.remove(collection);
+                        }
+                    }
+                    ui.end_row();
+                }
+            });
+    });
+}
+
+fn render_inbox(app: &mut Application, ui: &mut Ui) {
+    ui.checkbox(
+        &mut app.settings.inbox,
+        "Import files dropped into a folder automatically",
+    )
+    .on_hover_text("JSON, BibTeX and RIS files, imported files are moved to \"imported\"");
+    ui.add_enabled_ui(app.settings.inbox, |ui| {
+        ui.horizontal(|ui| {
+            let folder_label = ui.label("Folder:");
+            let input_folder = TextEdit::singleline(&mut app.settings.inbox_dir)
+                .hint_text("None")
+                .desired_width(layout::input_width(ui));
+            ui.add(input_folder).labelled_by(folder_label.id);
+
+            if ui.button("Browse").clicked() {
+                trace!("Browse inbox clicked");
+                let request = FileRequest::folder(FilePurpose::InboxFolder, "Select folder");
+                file_dialog::show(request, app, ui.ctx());
+            }
+        });
+    });
+
+    if let Some(error) = &app.settings.inbox_error {
+        ui.colored_label(ui.visuals().error_fg_color, error);
+    } else if let Some(inbox) = &app.inbox {
+        let status = inbox.status.read().unwrap();
+        match (&status.error, &status.last_import) {
+            (Some(error), _) => {
+                ui.colored_label(
+                    ui.visuals().error_fg_color,
+                    format!("Error importing {}", error),
+                );
+            }
+            (None, Some((time, file, imported))) => {
+                ui.label(format!(
+                    "Imported {} sources from {} at {}",
+                    imported,
+                    file,
+                    time.format("%d. %m. %Y %H:%M")
+                ));
+            }
+            (None, None) => {}
+        }
+    }
+}
+
+// the local server for external tools, not available in builds without it
+#[cfg(feature = "server")]
+fn render_local_server(app: &mut Application, ui: &mut Ui) {
+    ui.checkbox(
+        &mut app.settings.local_server,
+        "Serve the library on localhost for external tools",
+    );
+    ui.horizontal(|ui| {
+        let port_label = ui.label("Port:");
+        ui.add_enabled(
+            app.settings.local_server,
+            DragValue::new(&mut app.settings.local_server_port).clamp_range(1024..=65535),
+        )
+        .labelled_by(port_label.id);
+    });
+    ui.add_enabled(
+        app.settings.local_server,
+        egui::Checkbox::new(
+            &mut app.settings.assistant_api,
+            "Allow writing assistants to search and format sources (JSON-RPC)",
+        ),
+    );
+    if let Some(error) = &app.settings.server_error {
+        ui.colored_label(ui.visuals().error_fg_color, error);
+    } else if app.local_server.is_some() {
+        ui.label(format!(
+            "Listening on http://127.0.0.1:{}/sources",
+            app.config.read().unwrap().local_server_port
+        ));
+    }
+}
+
+// devices found by LAN sync and their last sync
+fn render_sync_status(app: &Application, ui: &mut Ui) {
+    if let Some(error) = &app.settings.sync_error {
+        ui.colored_label(ui.visuals().error_fg_color, error);
+    }
+
+    if app.settings.lan_sync && app.settings.sync_key.is_empty() {
+        ui.colored_label(
+            ui.visuals().warn_fg_color,
+            "Enter a sync key to start syncing",
+        );
+    }
+
+    if app.settings.lan_sync && app.settings.offline_mode {
+        ui.colored_label(
+            ui.visuals().warn_fg_color,
+            "LAN sync is paused in offline mode",
+        );
+    }
+
+    let lan_sync = match &app.lan_sync {
+        None => return,
+        Some(lan_sync) => lan_sync,
+    };
+
+    let peers = lan_sync.peers.read().unwrap();
+    if peers.is_empty() {
+        ui.label("Searching for other devices…");
+        return;
+    }
+
+    for (id, peer) in peers.iter() {
+        let address = match peer.address {
+            Some(address) => address.to_string(),
+            None => "offline".to_string(),
+        };
+        let status = match (&peer.error, peer.last_sync) {
+            (Some(error), _) => format!("error: {}", error),
+            (None, Some(last_sync)) => {
+                format!("last sync {}", last_sync.format("%d. %m. %Y %H:%M"))
+            }
+            (None, None) => "not synced yet".to_string(),
+        };
+        ui.label(format!(
+            "Device {} ({}): {}",
+            &id[..8.min(id.len())],
+            address,
+            status
+        ));
+    }
+}
+
+pub fn import_settings(path: &Path, app: &mut Application) {
+    match Config::import(path) {
+        Ok(mut config) => {
+            // the sync key and the device id stay the ones of this device, a settings file without
+            // a key keeps the current one
+            {
+                let current = app.config.read().unwrap();
+                config.sync_device_id = current.sync_device_id.clone();
+                if config.sync_key.is_empty() {
+                    config.sync_key = current.sync_key.clone();
+                }
+            }
+
+            // the commands of a shared settings file only run after they were confirmed
+            let imported = [
+                std::mem::take(&mut config.hook_source_saved),
+                std::mem::take(&mut config.hook_citation_copied),
+                std::mem::take(&mut config.hook_export_finished),
+            ];
+            {
+                let current = app.config.read().unwrap();
+                config.hook_source_saved = current.hook_source_saved.clone();
+                config.hook_citation_copied = current.hook_citation_copied.clone();
+                config.hook_export_finished = current.hook_export_finished.clone();
+            }
+            let current = [
+                &config.hook_source_saved,
+                &config.hook_citation_copied,
+                &config.hook_export_finished,
+            ];
+            let changed = imported.iter().zip(current).any(|(a, b)| a != b);
+            app.settings.imported_hooks = match changed && imported.iter().any(|c| !c.is_empty()) {
+                true => Some(imported),
+                false => None,
+            };
+
+            app.settings.format_standard = config.format_standard;
+            app.settings.custom_format = config.custom_format.clone();
+            app.settings.custom_in_text_format = config.custom_in_text_format.clone();
+            app.settings.plugin_format = config.plugin_format.clone();
+            app.settings.collection_formats = config.collection_formats.clone();
+            app.settings.update_viewed_on_copy = config.update_viewed_on_copy;
+            app.settings.shared_library = config.shared_library.clone();
+            app.settings.other_libraries = config.other_libraries.join("\n");
+            app.settings.lan_sync = config.lan_sync;
+            app.settings.sync_key = config.sync_key.clone();
+            app.settings.local_server = config.local_server;
+            app.settings.local_server_port = config.local_server_port;
+            app.settings.assistant_api = config.assistant_api;
+            app.settings.metadata_only = config.metadata_only;
+            app.settings.resolve_urls = config.resolve_urls;
+            app.settings.http_timeout = config.http_timeout;
+            app.settings.http_proxy = config.http_proxy.clone();
+            app.settings.http_user_agent = config.http_user_agent.clone();
+            app.settings.offline_mode = config.offline_mode;
+            app.settings.auto_export = config.auto_export;
+            app.settings.auto_export_path = config.auto_export_path.clone();
+            app.settings.auto_export_format = config.auto_export_format.clone();
+            app.settings.auto_export_interval = config.auto_export_interval;
+            app.settings.auto_export_on_exit = config.auto_export_on_exit;
+            app.settings.inbox = config.inbox;
+            app.settings.inbox_dir = config.inbox_dir.clone();
+            app.settings.hook_source_saved = config.hook_source_saved.clone();
+            app.settings.hook_citation_copied = config.hook_citation_copied.clone();
+            app.settings.hook_export_finished = config.hook_export_finished.clone();
+            app.settings.ui_scale = config.ui_scale;
+            app.settings.format_errors.clear();
+            app.settings.transfer_error = None;
+
+            config.save();
+            http::configure(&HttpSettings::from_config(&config));
+            app.queue.wake();
+            *app.config.write().unwrap() = config;
+            app.update_shared_cache();
+            app.update_library_cache();
+            app.restart_lan_sync();
+            app.restart_local_server();
+            app.restart_auto_export();
+            app.restart_inbox();
+        }
+        Err(e) => {
+            error!("Error importing settings: {}", e);
+            app.settings.transfer_error = Some(format!("Error importing settings: {}", e));
+        }
+    }
+}
+
+// the imported commands are shown before they replace the current ones, a settings file of
+// someone else could run anything
+fn render_hook_confirmation(app: &mut Application, ctx: &Context) {
+    let hooks = match &app.settings.imported_hooks {
+        None => return,
+        Some(hooks) => hooks.clone(),
+    };
+
+    let mut close = false;
+
+    egui::Window::new("Imported commands")
+        .collapsible(false)
+        .resizable(false)
+        .show(ctx, |ui| {
+            ui.label("The imported settings run these commands on events. Only enable them if you trust whoever made the file.");
+            ui.add_space(5.0);
+
+            for (name, command) in ["Source saved:", "Citation copied:", "Export finished:"]
+                .into_iter()
+                .zip(&hooks)
+            {
+                ui.horizontal(|ui| {
+                    ui.label(name);
+                    match command.is_empty() {
+                        true => ui.weak("None"),
+                        false => ui.code(command),
+                    };
+                });
+            }
+            ui.add_space(5.0);
+
+            ui.horizontal(|ui| {
+                if ui.button("Enable commands").clicked() {
+                    trace!("Imported commands enabled");
+                    let [source_saved, citation_copied, export_finished] = hooks.clone();
+                    app.settings.hook_source_saved = source_saved;
+                    app.settings.hook_citation_copied = citation_copied;
+                    app.settings.hook_export_finished = export_finished;
+
+                    let mut config = app.config.write().unwrap();
+                    config.hook_source_saved = app.settings.hook_source_saved.clone();
+                    config.hook_citation_copied = app.settings.hook_citation_copied.clone();
+                    config.hook_export_finished = app.settings.hook_export_finished.clone();
+                    config.save();
+                    close = true;
+                }
+                if ui.button("Keep current commands").clicked() {
+                    close = true;
+                }
+            });
+        });
+
+    if close {
+        app.settings.imported_hooks = None;
+    }
+}
+
+// example source used for the format preview
+fn sample_source() -> Source {
+    Source {
+        id: 1,
+        title: "The Rust Programming Language".to_string(),
+        url: "https://doc.rust-lang.org/book/".to_string(),
+        author: "Steve Klabnik, Carol Nichols".to_string(),
+        published_date: NaiveDate::from_ymd_opt(2018, 5, 15).unwrap(),
+        viewed_date: Local::now().date_naive(),
+        published_date_unknown: false,
+        comment: String::new(),
+        last_cited: None,
+        tags: "programming, rust".to_string(),
+        collection: String::new(),
+        doi: String::new(),
+        source_type: SourceType::Webpage,
+        uuid: String::new(),
+        updated_at: None,
+        pinned: false,
+        notes_written: false,
+        canonical_url: String::new(),
+        language: String::new(),
+        shared: false,
+        library: String::new(),
+    }
+}
+
+// one path per line, empty lines are dropped
+fn library_paths(text: &str) -> Vec<String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect()
 }
@@ -0,0 +1,279 @@
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use chrono::{Local, NaiveDateTime, TimeZone, Utc};
+use egui::{Button, ScrollArea, Ui};
+use sqlx::SqlitePool;
+use tracing::*;
+
+use crate::database::{get_snapshot, save_snapshot, set_snapshot_check};
+use crate::snapshot::{self, Recheck, Snapshot};
+use crate::ui::Application;
+use crate::url_analysis;
+
+// paragraphs shown per side of a re-check, pages can have thousands
+const MAX_PARAGRAPHS: usize = 50;
+
+// snapshot of the page of the source of the edit modal
+#[derive(Default)]
+pub struct SnapshotTab {
+    snapshot: Arc<RwLock<Option<Snapshot>>>,
+    loaded: Option<i64>, // source id the snapshot is of
+    status: Arc<RwLock<SnapshotStatus>>,
+}
+
+#[derive(Debug, Clone, Default)]
+enum SnapshotStatus {
+    #[default]
+    Idle,
+    Fetching,
+    Rechecked(Recheck),
+    Failed(String),
+}
+
+// snapshot tab of the edit modal
+pub fn render(app: &mut Application, ui: &mut Ui) {
+    let source = &app.edit_modal.source;
+    let Some(id) = source.library_id() else {
+        ui.label("The source isn't in the library");
+        return;
+    };
+    if !url_analysis::analyze(&source.url).web {
+        ui.label("Snapshots are only saved of web pages");
+        return;
+    }
+    let url = source.url.trim().to_string();
+
+    let tab = &mut app.edit_modal.snapshot;
+    if tab.loaded != Some(id) {
+        tab.loaded = Some(id);
+        *tab.status.write().unwrap() = SnapshotStatus::Idle;
+        handle_load(id, tab.snapshot.clone(), app.pool.clone());
+    }
+
+    let snapshot = tab.snapshot.read().unwrap().clone();
+    let status = tab.status.read().unwrap().clone();
+    let fetching = matches!(status, SnapshotStatus::Fetching);
+
+    match &snapshot {
+        None => {
+            ui.label("No snapshot of the page yet. Save one when citing it to notice later if the page changed.");
+        }
+        Some(snapshot) => {
+            ui.label(format!(
+                "Snapshot of {} from {}, {} paragraphs",
+                snapshot.url,
+                local_time(&snapshot.fetched_at),
+                snapshot.text.lines().count()
+            ));
+            if let (Some(similarity), Some(checked_at)) = (snapshot.similarity, snapshot.checked_at)
+            {
+                let text = format!(
+                    "Last re-check on {}: {:.0} % similar",
+                    local_time(&checked_at),
+                    similarity * 100.0
+                );
+                match snapshot.changed() {
+                    true => ui.colored_label(
+                        ui.visuals().warn_fg_color,
+                        format!(
+                            "{}, the page changed significantly since the snapshot",
+                            text
+                        ),
+                    ),
+                    false => ui.label(text),
+                };
+            }
+        }
+    }
+
+    ui.horizontal(|ui| {
+        let enabled = !app.read_only && !fetching;
+        if let Some(snapshot) = &snapshot {
+            let recheck = ui
+                .add_enabled(enabled, Button::new("Re-check"))
+                .on_hover_text("Download the page again and compare it with the snapshot");
+            if recheck.clicked() {
+                trace!("Re-check snapshot of source {} clicked", id);
+                handle_recheck(snapshot.clone(), app);
+            }
+        }
+
+        let save_text = match snapshot {
+            Some(_) => "Save new snapshot",
+            None => "Save snapshot",
+        };
+        let save = ui
+            .add_enabled(enabled, Button::new(save_text))
+            .on_hover_text("Save the text of the page as it is now, e.g. after citing it again");
+        if save.clicked() {
+            trace!("Save snapshot of source {} clicked", id);
+            handle_save(id, url, app);
+        }
+    });
+
+    match status {
+        SnapshotStatus::Idle => {}
+        SnapshotStatus::Fetching => {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label("Downloading the page…");
+            });
+            ui.ctx().request_repaint_after(Duration::from_millis(100));
+        }
+        SnapshotStatus::Rechecked(recheck) => render_recheck(&recheck, ui),
+        SnapshotStatus::Failed(error) => {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+    }
+}
+
+// paragraphs that were removed from or added to the page
+fn render_recheck(recheck: &Recheck, ui: &mut Ui) {
+    ui.separator();
+    if recheck.removed.is_empty() && recheck.added.is_empty() {
+        ui.label("The text of the page didn't change");
+        return;
+    }
+
+    ScrollArea::vertical()
+        .id_source("SnapshotDiff")
+        .max_height(300.0)
+        .show(ui, |ui| {
+            for (heading, paragraphs, color) in [
+                ("Removed", &recheck.removed, ui.visuals().error_fg_color),
+                ("Added", &recheck.added, ui.visuals().warn_fg_color),
+            ] {
+                if paragraphs.is_empty() {
+                    continue;
+                }
+                ui.strong(format!("{} ({})", heading, paragraphs.len()));
+                for paragraph in paragraphs.iter().take(MAX_PARAGRAPHS) {
+                    ui.colored_label(color, paragraph);
+                }
+                if paragraphs.len() > MAX_PARAGRAPHS {
+                    ui.weak(format!("and {} more", paragraphs.len() - MAX_PARAGRAPHS));
+                }
+            }
+        });
+}
+
+fn local_time(time: &NaiveDateTime) -> String {
+    Utc.from_utc_datetime(time)
+        .with_timezone(&Local)
+        .format("%d. %m. %Y %H:%M")
+        .to_string()
+}
+
+// async snapshot load
+fn handle_load(id: i64, snapshot: Arc<RwLock<Option<Snapshot>>>, pool: Arc<SqlitePool>) {
+    *snapshot.write().unwrap() = None;
+
+    tokio::task::spawn(async move {
+        match get_snapshot(id, &pool).await {
+            Ok(loaded) => *snapshot.write().unwrap() = loaded,
+            Err(e) => error!("Error loading snapshot of source {}: {}", id, e),
+        }
+    });
+}
+
+// async download of the page, replaces the snapshot of the source
+fn handle_save(id: i64, url: String, app: &Application) {
+    let snapshot = app.edit_modal.snapshot.snapshot.clone();
+    let status = app.edit_modal.snapshot.status.clone();
+    let snapshots = app.snapshots_cache.clone();
+    let pool = app.pool.clone();
+    *status.write().unwrap() = SnapshotStatus::Fetching;
+
+    tokio::task::spawn(async move {
+        let text = match snapshot::fetch_text(&url).await {
+            Ok(text) => text,
+            Err(e) => {
+                warn!("Error fetching snapshot of {}: {}", url, e);
+                *status.write().unwrap() =
+                    SnapshotStatus::Failed(format!("Error downloading the page: {}", e));
+                return;
+            }
+        };
+
+        let saved = Snapshot {
+            source_id: id,
+            url,
+            text,
+            fetched_at: Utc::now().naive_utc(),
+            similarity: None,
+            checked_at: None,
+        };
+        if let Err(e) = save_snapshot(&saved, &pool).await {
+            error!("Error saving snapshot of source {}: {}", id, e);
+            *status.write().unwrap() =
+                SnapshotStatus::Failed(format!("Error saving the snapshot: {}", e));
+            return;
+        }
+
+        update_cache(&saved, &snapshots);
+        *snapshot.write().unwrap() = Some(saved);
+        *status.write().unwrap() = SnapshotStatus::Idle;
+    });
+}
+
+// async download of the page, compared with the snapshot
+fn handle_recheck(saved: Snapshot, app: &Application) {
+    let snapshot = app.edit_modal.snapshot.snapshot.clone();
+    let status = app.edit_modal.snapshot.status.clone();
+    let snapshots = app.snapshots_cache.clone();
+    let pool = app.pool.clone();
+    *status.write().unwrap() = SnapshotStatus::Fetching;
+
+    tokio::task::spawn(async move {
+        let result = match recheck(saved, &pool).await {
+            Ok((checked, recheck)) => {
+                update_cache(&checked, &snapshots);
+                *snapshot.write().unwrap() = Some(checked);
+                SnapshotStatus::Rechecked(recheck)
+            }
+            Err(e) => {
+                warn!("Error re-checking snapshot: {}", e);
+                SnapshotStatus::Failed(format!("Error re-checking the page: {}", e))
+            }
+        };
+        *status.write().unwrap() = result;
+    });
+}
+
+// compares the page with the snapshot and saves the similarity
+pub async fn recheck(
+    mut saved: Snapshot,
+    pool: &SqlitePool,
+) -> Result<(Snapshot, Recheck), Box<dyn std::error::Error + Send + Sync>> {
+    let text = snapshot::fetch_text(&saved.url).await?;
+    let recheck = snapshot::compare(&saved.text, &text);
+    debug!(
+        "Page of source {} is {:.2} similar to its snapshot",
+        saved.source_id, recheck.similarity
+    );
+
+    let checked_at = Utc::now().naive_utc();
+    set_snapshot_check(saved.source_id, recheck.similarity, checked_at, pool).await?;
+    saved.similarity = Some(recheck.similarity);
+    saved.checked_at = Some(checked_at);
+
+    Ok((saved, recheck))
+}
+
+// the list shows the change without loading all snapshots again, it has them without text
+pub fn update_cache(snapshot: &Snapshot, snapshots: &RwLock<Vec<Snapshot>>) {
+    let entry = Snapshot {
+        text: String::new(),
+        ..snapshot.clone()
+    };
+
+    let mut snapshots = snapshots.write().unwrap();
+    match snapshots
+        .iter_mut()
+        .find(|other| other.source_id == snapshot.source_id)
+    {
+        Some(other) => *other = entry,
+        None => snapshots.push(entry),
+    }
+}
@@ -1,36 +1,201 @@
-use egui::{Grid, TextEdit, Ui};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use egui::{Button, CollapsingHeader, ComboBox, TextEdit, Ui};
 use egui_extras::DatePickerButton;
 use tracing::*;
 
-use crate::database::handle_source_save;
-use crate::ui::{Application, TEXT_INPUT_WIDTH};
+use crate::image;
+use crate::metadata::{
+    fetch_crossref, fetch_identifier, fetch_page, fetch_video, LookupStatus, Metadata,
+};
+use crate::paste::{self, Pasted};
+use crate::pdf;
+use crate::scheduler;
+use crate::source::{Source, SourceType};
+use crate::ui::commands::{self, SaveSource};
+use crate::ui::file_dialog::{self, FilePurpose, FileRequest};
+use crate::ui::layout::{self, FormLayout};
+use crate::ui::{language_combo, Application, SourceInput};
+use crate::url_analysis::{self, Identifier};
+
+// pasted text and the fields read from it, they are only filled in after the user checked them
+#[derive(Default)]
+pub struct SmartPaste {
+    text: String,
+    preview: Option<Metadata>,
+    reveal: bool, // opens the section on the next frame, e.g. after pasting text outside of it
+}
 
 pub fn render(app: &mut Application, ui: &mut Ui) {
-    Grid::new("SourceInput").num_columns(2).show(ui, |ui| {
+    apply_lookup(app);
+    paste_shortcut(app, ui);
+    drop_file(app, ui);
+
+    // new source from an identifier
+    ui.horizontal_wrapped(|ui| {
+        let lookup_label = ui.label("Look up:");
+        let input_identifier = TextEdit::singleline(&mut app.identifier_input)
+            .hint_text("arXiv ID, PMID or DOI")
+            .desired_width(layout::input_width(ui) - 100.0);
+        let response = ui.add(input_identifier).labelled_by(lookup_label.id);
+
+        let identifier = url_analysis::parse_identifier(&app.identifier_input);
+        let fetching = *app.lookup.read().unwrap() == LookupStatus::Fetching;
+        let hover_text = match &identifier {
+            Some(identifier) => format!("Fill in a new source from {}", identifier),
+            None => "e.g. arXiv:2101.00001, PMID 31452104 or 10.1000/182".to_string(),
+        };
+        let enter = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+        let clicked = ui
+            .add_enabled(!fetching && identifier.is_some(), Button::new("Look up"))
+            .on_hover_text(hover_text)
+            .clicked();
+
+        if let Some(identifier) = identifier.filter(|_| !fetching && (clicked || enter)) {
+            trace!("Look up identifier clicked");
+            handle_identifier_lookup(app, identifier);
+        }
+
+        if ui
+            .add_enabled(!fetching, Button::new("From file…"))
+            .on_hover_text(
+                "Fill in title, author, date and DOI from a PDF file or an image source with its \
+                capture date from an image file, or drop the file here",
+            )
+            .clicked()
+        {
+            trace!("From file clicked");
+            let mut extensions = vec!["pdf"];
+            extensions.extend(image::IMAGE_EXTENSIONS);
+            let request = FileRequest::open(FilePurpose::SourceFile, "Select file")
+                .add_filter("PDF and images", &extensions)
+                .add_filter("PDF", &["pdf"])
+                .add_filter("Images", &image::IMAGE_EXTENSIONS);
+            file_dialog::show(request, app, ui.ctx());
+        }
+    });
+
+    render_smart_paste(app, ui);
+
+    ui.add_space(5.0);
+
+    let form = FormLayout::new(ui.available_width());
+    form.grid("SourceInput").show(ui, |ui| {
         // input title
         let title_label = ui.label("Title:");
+        form.after_label(ui);
         let input_title =
-            TextEdit::singleline(&mut app.source_input.title).desired_width(TEXT_INPUT_WIDTH);
+            TextEdit::singleline(&mut app.source_input.title).desired_width(form.input_width);
         ui.add(input_title).labelled_by(title_label.id);
         ui.end_row();
 
         // input URL
         let url_label = ui.label("URL:");
-        let input_url =
-            TextEdit::singleline(&mut app.source_input.url).desired_width(TEXT_INPUT_WIDTH);
-        ui.add(input_url).labelled_by(url_label.id);
+        form.after_label(ui);
+        ui.horizontal(|ui| {
+            let input_url = TextEdit::singleline(&mut app.source_input.url)
+                .desired_width(form.input_width - 100.0);
+            if ui.add(input_url).labelled_by(url_label.id).changed() {
+                analyze_url(app);
+            }
+
+            let analysis = url_analysis::analyze(&app.source_input.url);
+            let fetching = *app.lookup.read().unwrap() == LookupStatus::Fetching;
+            let hover_text = match analysis.video {
+                Some(host) => format!("Fill in title, channel and upload date from {}", host),
+                None => "Fill in title, author and date from the page".to_string(),
+            };
+            if ui
+                .add_enabled(!fetching && analysis.web, Button::new("Look up"))
+                .on_hover_text(hover_text)
+                .clicked()
+            {
+                trace!("Look up URL clicked");
+                handle_url_lookup(app);
+            }
+        });
+        ui.end_row();
+
+        // input source type
+        let type_label = ui.label("Type:");
+        form.after_label(ui);
+        ComboBox::from_id_source("InputSourceType")
+            .selected_text(app.source_input.source_type.to_string())
+            .show_ui(ui, |ui| {
+                for source_type in SourceType::ALL {
+                    ui.selectable_value(
+                        &mut app.source_input.source_type,
+                        source_type,
+                        source_type.to_string(),
+                    );
+                }
+            })
+            .response
+            .labelled_by(type_label.id);
+        ui.end_row();
+
+        // input language
+        let language_label = ui.label("Language:");
+        form.after_label(ui);
+        language_combo("InputLanguage", &mut app.source_input.language, ui)
+            .on_hover_text("Terms like \"Stand\" or \"Accessed\" of the built-in formats")
+            .labelled_by(language_label.id);
+        ui.end_row();
+
+        // input DOI
+        let doi_label = ui.label("DOI:");
+        form.after_label(ui);
+        ui.horizontal(|ui| {
+            let input_doi = TextEdit::singleline(&mut app.source_input.doi)
+                .hint_text("e.g. 10.1000/182")
+                .desired_width(form.input_width - 100.0);
+            ui.add(input_doi).labelled_by(doi_label.id);
+
+            let fetching = *app.lookup.read().unwrap() == LookupStatus::Fetching;
+            if ui
+                .add_enabled(
+                    !fetching && !app.source_input.doi.trim().is_empty(),
+                    Button::new("Look up"),
+                )
+                .on_hover_text("Fill in title, author and date from Crossref")
+                .clicked()
+            {
+                trace!("Look up DOI clicked");
+                handle_doi_lookup(app);
+            }
+        });
         ui.end_row();
 
         // input author
         let author_label = ui.label("Author:");
+        form.after_label(ui);
         let input_author = TextEdit::singleline(&mut app.source_input.author)
             .hint_text("Leave empty if unknown")
-            .desired_width(TEXT_INPUT_WIDTH);
+            .desired_width(form.input_width);
         ui.add(input_author).labelled_by(author_label.id);
         ui.end_row();
 
+        // input tags
+        let tags_label = ui.label("Tags:");
+        form.after_label(ui);
+        let input_tags = TextEdit::singleline(&mut app.source_input.tags)
+            .hint_text("Separated by commas")
+            .desired_width(form.input_width);
+        ui.add(input_tags).labelled_by(tags_label.id);
+        ui.end_row();
+
+        // input collection
+        let collection_label = ui.label("Collection:");
+        form.after_label(ui);
+        let input_collection =
+            TextEdit::singleline(&mut app.source_input.collection).desired_width(form.input_width);
+        ui.add(input_collection).labelled_by(collection_label.id);
+        ui.end_row();
+
         // input published date
         let published_label = ui.label("Date published:");
+        form.after_label(ui);
         ui.horizontal(|ui| {
             ui.add_enabled(
                 !app.source_input.published_date_unknown,
@@ -45,6 +210,7 @@ pub fn render(app: &mut Application, ui: &mut Ui) {
 
         // input viewed date
         let viewed_label = ui.label("Date viewed:");
+        form.after_label(ui);
         ui.add(
             DatePickerButton::new(&mut app.source_input.viewed_date)
                 .id_source("InputViewedDate") // needs to be set otherwise the UI would bug with multiple date pickers
@@ -55,12 +221,21 @@ pub fn render(app: &mut Application, ui: &mut Ui) {
 
         // input comment
         let comment_label = ui.label("Comment:");
+        form.after_label(ui);
         let input_comment =
-            TextEdit::multiline(&mut app.source_input.comment).desired_width(TEXT_INPUT_WIDTH);
+            TextEdit::multiline(&mut app.source_input.comment).desired_width(form.input_width);
         ui.add(input_comment).labelled_by(comment_label.id);
         ui.end_row();
     });
 
+    if url_analysis::analyze(&app.source_input.url)
+        .wikipedia
+        .is_some()
+    {
+        ui.label("Saving links to the current revision of the article and uses its date as date published");
+    }
+    render_lookup_status(app, ui);
+
     ui.add_space(5.0);
     ui.separator();
     ui.add_space(5.0);
@@ -69,7 +244,7 @@ pub fn render(app: &mut Application, ui: &mut Ui) {
         // save input source to DB
         if ui.button("Save").clicked() {
             trace!("Save clicked");
-            handle_source_save(app);
+            commands::run(SaveSource::new(app), app, ui.ctx());
         }
 
         // clear input
@@ -79,3 +254,362 @@ pub fn render(app: &mut Application, ui: &mut Ui) {
         }
     });
 }
+
+// new source from a block of text, e.g. a forwarded newsletter
+fn render_smart_paste(app: &mut Application, ui: &mut Ui) {
+    let reveal = std::mem::take(&mut app.smart_paste.reveal);
+    CollapsingHeader::new("Smart paste")
+        .open(reveal.then_some(true))
+        .show(ui, |ui| {
+        let input_text = TextEdit::multiline(&mut app.smart_paste.text)
+            .hint_text("Newsletter snippet, forwarded mail or mailto: link")
+            .desired_rows(3)
+            .desired_width(layout::input_width(ui));
+        let response = ui.add(input_text);
+        if response.changed() {
+            app.smart_paste.preview = None;
+        }
+
+        if ui
+            .add_enabled(
+                !app.smart_paste.text.trim().is_empty(),
+                Button::new("Read fields"),
+            )
+            .on_hover_text("Fill in a BibTeX entry, RIS record, DOI or link, or find the title, URL, author and date in other text")
+            .clicked()
+        {
+            trace!("Read fields clicked");
+            let text = app.smart_paste.text.clone();
+            handle_paste(app, &text);
+        }
+
+        let Some(preview) = app.smart_paste.preview.clone() else {
+            return;
+        };
+        if preview == Metadata::default() {
+            ui.label("Nothing found in the text");
+            return;
+        }
+
+        let form = FormLayout::new(ui.available_width());
+        form.grid("SmartPastePreview").show(ui, |ui| {
+            let mut row = |name: &str, value: Option<String>| {
+                if let Some(value) = value {
+                    ui.label(name);
+                    form.after_label(ui);
+                    ui.label(value);
+                    ui.end_row();
+                }
+            };
+            row("Title:", preview.title.clone());
+            row("URL:", preview.url.clone());
+            row("Type:", preview.source_type.map(|source_type| source_type.to_string()));
+            row("DOI:", preview.doi.clone());
+            row("Author:", preview.author.clone());
+            row(
+                "Date published:",
+                preview
+                    .published_date
+                    .map(|date| date.format("%d. %m. %Y").to_string()),
+            );
+        });
+
+        ui.horizontal(|ui| {
+            if ui
+                .button("Use")
+                .on_hover_text("Fill in these fields, the other fields are kept")
+                .clicked()
+            {
+                trace!("Use smart paste clicked");
+                fill_input(&mut app.source_input, preview);
+                app.smart_paste = SmartPaste::default();
+            }
+            if ui.button("Discard").clicked() {
+                trace!("Discard smart paste clicked");
+                app.smart_paste.preview = None;
+            }
+        });
+    });
+}
+
+// Ctrl + V on the start page outside of a text field
+fn paste_shortcut(app: &mut Application, ui: &Ui) {
+    if ui.ctx().memory(|memory| memory.focused().is_some()) {
+        return;
+    }
+
+    let pasted = ui.input(|input| {
+        input.events.iter().find_map(|event| match event {
+            egui::Event::Paste(text) if !text.trim().is_empty() => Some(text.clone()),
+            _ => None,
+        })
+    });
+    if let Some(text) = pasted {
+        trace!("Pasted into the start page");
+        handle_paste(app, &text);
+    }
+}
+
+// fills in what an importer can read right away and looks up identifiers and links, other text
+// is shown in the smart paste section first
+fn handle_paste(app: &mut Application, text: &str) {
+    match paste::detect(text) {
+        Pasted::Source(source, format, count) => {
+            fill_input_from_source(&mut app.source_input, *source);
+            let service = match count {
+                1 => format.name().to_string(),
+                _ => format!("the first of {} {} entries", count, format.name()),
+            };
+            *app.lookup.write().unwrap() = LookupStatus::Applied(service);
+            app.smart_paste = SmartPaste::default();
+        }
+        Pasted::Identifier(identifier) => {
+            app.smart_paste = SmartPaste::default();
+            handle_identifier_lookup(app, identifier);
+        }
+        Pasted::Url(url) => {
+            app.smart_paste = SmartPaste::default();
+            app.source_input.url = url;
+            analyze_url(app);
+            handle_url_lookup(app);
+        }
+        Pasted::Text(metadata) => {
+            app.smart_paste = SmartPaste {
+                text: text.to_string(),
+                preview: Some(metadata),
+                reveal: true,
+            };
+        }
+    }
+}
+
+// a PDF or image dropped onto the window while the start page is open
+fn drop_file(app: &mut Application, ui: &Ui) {
+    let dropped = ui.input(|input| {
+        input
+            .raw
+            .dropped_files
+            .iter()
+            .filter_map(|file| file.path.clone())
+            .find(|path| is_pdf(path) || image::is_image(path))
+    });
+    if let Some(path) = dropped {
+        trace!("File dropped onto the start page");
+        open_file(path, app);
+    }
+}
+
+fn is_pdf(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("pdf"))
+}
+
+// reads the metadata of the PDF or image in the background like a lookup, found fields replace
+// typed ones
+pub fn open_file(path: PathBuf, app: &Application) {
+    let status = app.lookup.clone();
+    *status.write().unwrap() = LookupStatus::Fetching;
+
+    tokio::task::spawn_blocking(move || {
+        // an image always becomes an image source, the file is its URL
+        if !is_pdf(&path) {
+            let metadata = image::read_metadata(&path);
+            *status.write().unwrap() = LookupStatus::Fetched(metadata, "the image".to_string());
+            return;
+        }
+
+        let result = match pdf::read_metadata(&path) {
+            Ok(metadata) => LookupStatus::Fetched(metadata, "the PDF".to_string()),
+            Err(e) => {
+                warn!("Error reading PDF {}: {}", path.display(), e);
+                LookupStatus::Failed(format!("Error reading PDF: {}", e))
+            }
+        };
+        *status.write().unwrap() = result;
+    });
+}
+
+// fills in what the pasted URL reveals, e.g. the DOI of a doi.org link
+fn analyze_url(app: &mut Application) {
+    let analysis = url_analysis::analyze(&app.source_input.url);
+
+    if let Some(doi) = analysis.doi {
+        if app.source_input.doi.is_empty() {
+            debug!("Found DOI {} in URL", doi);
+            app.source_input.doi = doi;
+        }
+    }
+    if analysis.video.is_some() {
+        app.source_input.source_type = SourceType::Video;
+    }
+}
+
+// async arXiv, PubMed or Crossref lookup replacing the input with the found source
+fn handle_identifier_lookup(app: &mut Application, identifier: Identifier) {
+    app.clear_input();
+
+    let status = app.lookup.clone();
+    *status.write().unwrap() = LookupStatus::Fetching;
+
+    tokio::task::spawn(async move {
+        let service = match identifier {
+            Identifier::Doi(_) => "Crossref",
+            Identifier::ArXiv(_) => "arXiv",
+            Identifier::PubMed(_) => "PubMed",
+        };
+        let name = format!("Look up {}", identifier);
+        let result = match scheduler::run(name, || fetch_identifier(&identifier)).await {
+            Ok(metadata) => LookupStatus::Fetched(metadata, service.to_string()),
+            Err(e) => {
+                warn!("Error looking up {}: {}", identifier, e);
+                LookupStatus::Failed(format!("Error looking up {}: {}", identifier, e))
+            }
+        };
+        *status.write().unwrap() = result;
+    });
+}
+
+// async lookup of the URL, via oEmbed for videos and the meta tags for other pages
+fn handle_url_lookup(app: &Application) {
+    let url = app.source_input.url.trim().to_string();
+    let video = url_analysis::analyze(&url).video;
+    let metadata_only = app.config.read().unwrap().metadata_only;
+    let status = app.lookup.clone();
+    *status.write().unwrap() = LookupStatus::Fetching;
+
+    tokio::task::spawn(async move {
+        let name = format!("Fetch metadata of {}", url);
+        let (result, service) = match video {
+            Some(host) => (
+                scheduler::run(name, || fetch_video(&url, host)).await,
+                host.to_string(),
+            ),
+            None => (
+                scheduler::run(name, || fetch_page(&url, metadata_only)).await,
+                "the page".to_string(),
+            ),
+        };
+        let result = match result {
+            Ok(metadata) => LookupStatus::Fetched(metadata, service),
+            Err(e) => {
+                warn!("Error looking up {}: {}", url, e);
+                LookupStatus::Failed(format!("Error looking up URL: {}", e))
+            }
+        };
+        *status.write().unwrap() = result;
+    });
+}
+
+// async Crossref lookup of the DOI input
+fn handle_doi_lookup(app: &Application) {
+    let doi = app.source_input.doi.trim().to_string();
+    let status = app.lookup.clone();
+    *status.write().unwrap() = LookupStatus::Fetching;
+
+    tokio::task::spawn(async move {
+        let name = format!("Look up DOI {}", doi);
+        let result = match scheduler::run(name, || fetch_crossref(&doi)).await {
+            Ok(metadata) => LookupStatus::Fetched(metadata, "Crossref".to_string()),
+            Err(e) => {
+                warn!("Error looking up DOI {}: {}", doi, e);
+                LookupStatus::Failed(format!("Error looking up DOI: {}", e))
+            }
+        };
+        *status.write().unwrap() = result;
+    });
+}
+
+// writes a finished lookup into the input, fetched values replace typed ones
+fn apply_lookup(app: &mut Application) {
+    let metadata = {
+        let mut status = app.lookup.write().unwrap();
+        match &*status {
+            LookupStatus::Fetched(metadata, service) => {
+                let metadata = metadata.clone();
+                *status = LookupStatus::Applied(service.clone());
+                metadata
+            }
+            _ => return,
+        }
+    };
+
+    // a typed URL is kept, e.g. the page of a video instead of its embed link, but the file of
+    // an image is the source itself
+    let keep_url =
+        !app.source_input.url.is_empty() && metadata.source_type != Some(SourceType::Image);
+    let metadata = Metadata {
+        url: metadata.url.filter(|_| !keep_url),
+        ..metadata
+    };
+    fill_input(&mut app.source_input, metadata);
+}
+
+// replaces the fields of the input that were found
+fn fill_input(input: &mut SourceInput, metadata: Metadata) {
+    let Metadata {
+        title,
+        author,
+        published_date,
+        url,
+        doi,
+        source_type,
+    } = metadata;
+
+    if let Some(title) = title {
+        input.title = title;
+    }
+    if let Some(author) = author {
+        input.author = author;
+    }
+    if let Some(published_date) = published_date {
+        input.published_date = published_date;
+        input.published_date_unknown = false;
+    }
+    if let Some(url) = url {
+        input.url = url;
+    }
+    if let Some(doi) = doi {
+        input.doi = doi;
+    }
+    if let Some(source_type) = source_type {
+        input.source_type = source_type;
+    }
+}
+
+// replaces the whole input except for the date viewed, e.g. with a pasted BibTeX entry
+fn fill_input_from_source(input: &mut SourceInput, source: Source) {
+    input.title = source.title;
+    input.url = source.url;
+    input.author = source.author;
+    input.published_date = source.published_date;
+    input.published_date_unknown = source.published_date_unknown;
+    input.comment = source.comment;
+    input.tags = source.tags;
+    input.collection = source.collection;
+    input.doi = source.doi;
+    input.source_type = source.source_type;
+    input.language = source.language;
+}
+
+fn render_lookup_status(app: &Application, ui: &mut Ui) {
+    match &*app.lookup.read().unwrap() {
+        LookupStatus::Idle | LookupStatus::Fetched(..) => {}
+        LookupStatus::Fetching => {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label("Looking up…");
+            });
+            // the result arrives without any input
+            ui.ctx().request_repaint_after(Duration::from_millis(100));
+        }
+        LookupStatus::Applied(service) => {
+            ui.label(format!(
+                "Filled in from {}, please check the fields",
+                service
+            ));
+        }
+        LookupStatus::Failed(error) => {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+    }
+}
@@ -0,0 +1,203 @@
+use chrono::{Datelike, NaiveDate};
+use egui::{
+    Align2, CentralPanel, Color32, FontId, Id, Pos2, Rect, ScrollArea, Sense, Slider, Stroke, Ui,
+    Vec2,
+};
+use tracing::*;
+
+use crate::source::Source;
+use crate::ui::{accessible_name, truncated, AppPage, Application};
+
+const MARGIN: f32 = 20.0;
+const AXIS_HEIGHT: f32 = 30.0;
+const ROW_HEIGHT: f32 = 22.0;
+const MARKER_RADIUS: f32 = 4.0;
+const MIN_TICK_SPACING: f32 = 60.0; // pixels between year labels
+const MAX_LABEL_CHARS: usize = 30;
+const MIN_ZOOM: f32 = 10.0; // pixels per year
+const MAX_ZOOM: f32 = 5000.0;
+
+pub fn render(app: &mut Application, ui: &mut Ui) {
+    let mut sources: Vec<Source> = app
+        .sources_cache
+        .read()
+        .unwrap()
+        .iter()
+        .filter(|source| !source.published_date_unknown())
+        .map(|source| source.to_source())
+        .collect();
+    sources.sort_by_key(|source| source.published_date);
+
+    let unknown = app.sources_cache.read().unwrap().len() - sources.len();
+
+    ui.horizontal(|ui| {
+        ui.add(
+            Slider::new(&mut app.timeline_zoom, MIN_ZOOM..=MAX_ZOOM)
+                .logarithmic(true)
+                .suffix(" px/year")
+                .text("Zoom"),
+        );
+
+        if unknown > 0 {
+            ui.label(format!(
+                "{} sources without a published date are not shown",
+                unknown
+            ));
+        }
+    });
+
+    ui.add_space(10.0);
+
+    let (first, last) = match (sources.first(), sources.last()) {
+        (Some(first), Some(last)) => (first.published_date, last.published_date),
+        _ => {
+            CentralPanel::default().show_inside(ui, |ui| {
+                ui.vertical_centered(|ui| {
+                    ui.heading("Empty");
+                });
+            });
+            return;
+        }
+    };
+
+    // axis covers whole years
+    let start = NaiveDate::from_ymd_opt(first.year(), 1, 1).unwrap();
+    let end = NaiveDate::from_ymd_opt(last.year() + 1, 1, 1).unwrap();
+
+    ScrollArea::both().auto_shrink(false).show(ui, |ui| {
+        let px_per_day = app.timeline_zoom / 365.25;
+        let x_of = |date: NaiveDate| MARGIN + (date - start).num_days() as f32 * px_per_day;
+
+        // place labels in rows so they don't overlap
+        let font = FontId::proportional(14.0);
+        let mut row_ends: Vec<f32> = vec![];
+        let mut placed = Vec::with_capacity(sources.len());
+        for source in &sources {
+            let label = truncated(&source.title, MAX_LABEL_CHARS);
+            let width = ui.fonts(|fonts| {
+                fonts
+                    .layout_no_wrap(label.clone(), font.clone(), Color32::WHITE)
+                    .size()
+                    .x
+            });
+            let x = x_of(source.published_date);
+
+            let row = match row_ends.iter().position(|end| *end < x - MARKER_RADIUS) {
+                Some(row) => row,
+                None => {
+                    row_ends.push(0.0);
+                    row_ends.len() - 1
+                }
+            };
+            row_ends[row] = x + MARKER_RADIUS * 2.0 + width + 10.0;
+
+            placed.push((source, label, x, row));
+        }
+
+        let width = x_of(end) + MARGIN;
+        let height = AXIS_HEIGHT + row_ends.len() as f32 * ROW_HEIGHT + MARGIN;
+        let (response, painter) = ui.allocate_painter(Vec2::new(width, height), Sense::hover());
+        let origin = response.rect.min;
+        let visuals = ui.visuals().clone();
+        let stroke = Stroke::new(1.0, visuals.text_color());
+
+        // axis with year ticks
+        let step = [1, 2, 5, 10, 25, 50, 100, 250, 500]
+            .into_iter()
+            .find(|step| *step as f32 * app.timeline_zoom >= MIN_TICK_SPACING)
+            .unwrap_or(1000);
+        painter.line_segment(
+            [
+                origin + Vec2::new(MARGIN, AXIS_HEIGHT),
+                origin + Vec2::new(x_of(end), AXIS_HEIGHT),
+            ],
+            stroke,
+        );
+        for year in start.year()..=end.year() {
+            let x = x_of(NaiveDate::from_ymd_opt(year, 1, 1).unwrap());
+
+            if year % step == 0 {
+                painter.line_segment(
+                    [
+                        origin + Vec2::new(x, AXIS_HEIGHT - 6.0),
+                        origin + Vec2::new(x, height),
+                    ],
+                    Stroke::new(1.0, visuals.widgets.noninteractive.bg_stroke.color),
+                );
+                painter.text(
+                    origin + Vec2::new(x, AXIS_HEIGHT - 8.0),
+                    Align2::CENTER_BOTTOM,
+                    year.to_string(),
+                    font.clone(),
+                    visuals.text_color(),
+                );
+            }
+
+            // month ticks when zoomed in far enough
+            if app.timeline_zoom >= 12.0 * MIN_TICK_SPACING / 2.0 && year < end.year() {
+                for month in 2..=12 {
+                    let x = x_of(NaiveDate::from_ymd_opt(year, month, 1).unwrap());
+                    painter.line_segment(
+                        [
+                            origin + Vec2::new(x, AXIS_HEIGHT - 3.0),
+                            origin + Vec2::new(x, AXIS_HEIGHT + 3.0),
+                        ],
+                        stroke,
+                    );
+                }
+            }
+        }
+
+        // sources
+        for (source, label, x, row) in placed {
+            let center = origin + Vec2::new(x, AXIS_HEIGHT + (row as f32 + 0.5) * ROW_HEIGHT);
+            let text_pos = Pos2::new(center.x + MARKER_RADIUS * 2.0, center.y);
+            let text_rect = painter.text(
+                text_pos,
+                Align2::LEFT_CENTER,
+                label,
+                font.clone(),
+                visuals.text_color(),
+            );
+
+            let rect = Rect::from_min_max(
+                Pos2::new(center.x - MARKER_RADIUS, text_rect.min.y),
+                text_rect.max,
+            );
+            let marker = ui.interact(
+                rect,
+                Id::new("TimelineSource").with(source.id),
+                Sense::click(),
+            );
+            let marker = accessible_name(marker, format!("Edit \"{}\"", source.title))
+                .on_hover_text(format!(
+                    "{}\n{}\n{}",
+                    source.title,
+                    source.author,
+                    source.published_date.format("%d. %m. %Y")
+                ));
+
+            // markers can be reached with Tab as well
+            let color = match marker.hovered() || marker.has_focus() {
+                true => visuals.selection.bg_fill,
+                false => visuals.hyperlink_color,
+            };
+            painter.circle_filled(center, MARKER_RADIUS, color);
+
+            // opens the source on the list page
+            if marker.clicked() {
+                trace!("Timeline source {} clicked", source.id);
+                app.open_edit_modal(source);
+                app.curr_page = AppPage::List;
+            }
+        }
+
+        // ctrl + scroll zooms
+        if ui.rect_contains_pointer(response.rect) {
+            let zoom = ui.input(|i| i.zoom_delta());
+            if zoom != 1.0 {
+                app.timeline_zoom = (app.timeline_zoom * zoom).clamp(MIN_ZOOM, MAX_ZOOM);
+            }
+        }
+    });
+}
@@ -0,0 +1,52 @@
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use egui::{Align2, Context, RichText};
+
+use crate::ui::Application;
+
+const SHOWN_FOR: Duration = Duration::from_secs(8);
+
+// errors of background writes, shown in the corner until they time out or are clicked away
+pub type Toasts = Arc<RwLock<Vec<Toast>>>;
+
+pub struct Toast {
+    text: String,
+    since: Instant,
+}
+
+pub fn push(toasts: &Toasts, text: impl Into<String>) {
+    toasts.write().unwrap().push(Toast {
+        text: text.into(),
+        since: Instant::now(),
+    });
+}
+
+pub fn render(app: &Application, ctx: &Context) {
+    let mut toasts = app.toasts.write().unwrap();
+    toasts.retain(|toast| toast.since.elapsed() < SHOWN_FOR);
+    if toasts.is_empty() {
+        return;
+    }
+
+    let mut dismissed = None;
+    egui::Area::new("Toasts".into())
+        .anchor(Align2::LEFT_BOTTOM, [10.0, -10.0])
+        .order(egui::Order::Foreground)
+        .show(ctx, |ui| {
+            for (index, toast) in toasts.iter().enumerate() {
+                let frame = egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.colored_label(ui.visuals().error_fg_color, &toast.text);
+                    ui.label(RichText::new("Click to dismiss").weak().small());
+                });
+                if frame.response.interact(egui::Sense::click()).clicked() {
+                    dismissed = Some(index);
+                }
+            }
+        });
+
+    if let Some(index) = dismissed {
+        toasts.remove(index);
+    }
+    ctx.request_repaint_after(Duration::from_millis(500));
+}
@@ -0,0 +1,392 @@
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tracing::*;
+
+use crate::http;
+
+const RELEASES_URL: &str = "https://api.github.com/repos/TgZ39/SaveIt/releases/latest";
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Release {
+    pub tag_name: String,
+    pub html_url: String,
+    #[serde(default)]
+    pub assets: Vec<Asset>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Asset {
+    pub name: String,
+    pub browser_download_url: String,
+}
+
+// binary of a release and the file with its SHA256
+#[derive(Debug, Clone, PartialEq)]
+pub struct Download {
+    pub url: String,
+    pub checksum_url: String,
+}
+
+impl Release {
+    // release binary for the current platform and architecture, releases without its checksum
+    // can't be installed
+    pub fn platform_download(&self) -> Option<Download> {
+        let name = asset_name(std::env::consts::OS, std::env::consts::ARCH);
+        let checksum = format!("{}.sha256", name);
+        let url = |name: &str| {
+            self.assets
+                .iter()
+                .find(|asset| asset.name == name)
+                .map(|asset| asset.browser_download_url.clone())
+        };
+
+        Some(Download {
+            url: url(&name)?,
+            checksum_url: url(&checksum)?,
+        })
+    }
+}
+
+// e.g. "SaveIt-linux-x86_64", "SaveIt-macos-aarch64" or "SaveIt-windows-x86_64.exe"
+fn asset_name(os: &str, arch: &str) -> String {
+    let suffix = match os {
+        "windows" => ".exe",
+        _ => "",
+    };
+    format!("SaveIt-{}-{}{}", os, arch, suffix)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum UpdateStatus {
+    NotChecked,
+    Checking,
+    UpToDate,
+    Available {
+        version: String,
+        url: String,
+        download: Option<Download>, // binary for this platform, if the release has one
+    },
+    Failed(String),
+    Installing,
+    Installed(String),
+    InstallFailed(String),
+    Restored,
+    RestoreFailed(String),
+}
+
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(600);
+
+pub async fn fetch_latest_release() -> Result<Release, Box<dyn Error + Send + Sync>> {
+    debug!("Fetching latest release from {}", RELEASES_URL);
+
+    let release = http::client()?
+        .get(RELEASES_URL)
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<Release>()
+        .await?;
+
+    Ok(release)
+}
+
+pub async fn check_for_update() -> UpdateStatus {
+    match fetch_latest_release().await {
+        Ok(release) => {
+            if is_newer(&release.tag_name, env!("CARGO_PKG_VERSION")) {
+                info!("Update available: {}", &release.tag_name);
+                UpdateStatus::Available {
+                    download: release.platform_download(),
+                    version: release.tag_name,
+                    url: release.html_url,
+                }
+            } else {
+                UpdateStatus::UpToDate
+            }
+        }
+        Err(e) => {
+            error!("Error checking for updates: {}", e);
+            UpdateStatus::Failed(e.to_string())
+        }
+    }
+}
+
+// parses versions like "v1.2.3" or "1.2.3"
+fn parse_version(version: &str) -> Option<(u64, u64, u64)> {
+    let mut parts = version.trim_start_matches('v').split('.');
+
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next()?.parse().ok()?;
+    let patch = parts.next().unwrap_or("0").parse().ok()?;
+
+    Some((major, minor, patch))
+}
+
+pub fn is_newer(latest: &str, current: &str) -> bool {
+    match (parse_version(latest), parse_version(current)) {
+        (Some(latest), Some(current)) => latest > current,
+        _ => false,
+    }
+}
+
+// the replaced binary, kept until the next update so it can be restored
+fn backup_path(exe: &Path) -> PathBuf {
+    exe.with_extension("old")
+}
+
+// written by the update and removed once the new version started, "started" is added by the
+// first start, so a second start finding it knows the first one failed
+fn pending_path(exe: &Path) -> PathBuf {
+    exe.with_extension("pending")
+}
+
+// downloads the new binary, checks it against its published checksum and replaces the running
+// executable with it
+pub async fn install_update(version: String, download: Download) -> UpdateStatus {
+    info!("Installing update {} from {}", &version, &download.url);
+
+    let bytes = match download_verified(&download).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            error!("Error downloading update: {}", e);
+            return UpdateStatus::InstallFailed(e.to_string());
+        }
+    };
+
+    let installed = version.clone();
+    match tokio::task::spawn_blocking(move || replace_executable(&bytes, &installed)).await {
+        Ok(Ok(_)) => UpdateStatus::Installed(version),
+        Ok(Err(e)) => {
+            error!("Error installing update: {}", e);
+            UpdateStatus::InstallFailed(e.to_string())
+        }
+        Err(e) => UpdateStatus::InstallFailed(e.to_string()),
+    }
+}
+
+async fn download_verified(download: &Download) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+    let checksum = String::from_utf8(download_bytes(&download.checksum_url).await?)?;
+    let expected = parse_checksum(&checksum).ok_or("The checksum file of the update is invalid")?;
+    let bytes = download_bytes(&download.url).await?;
+
+    match sha256(&bytes) == expected {
+        true => Ok(bytes),
+        false => Err("The download doesn't match its checksum".into()),
+    }
+}
+
+async fn download_bytes(url: &str) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+    debug!("Downloading {}", url);
+
+    let bytes = http::client()?
+        .get(url)
+        .timeout(DOWNLOAD_TIMEOUT)
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+
+    Ok(bytes.to_vec())
+}
+
+// "<hex>" or the "<hex>  <file name>" of sha256sum
+fn parse_checksum(text: &str) -> Option<String> {
+    let checksum = text.split_whitespace().next()?.to_ascii_lowercase();
+    match checksum.len() == 64 && checksum.chars().all(|c| c.is_ascii_hexdigit()) {
+        true => Some(checksum),
+        false => None,
+    }
+}
+
+fn sha256(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+fn replace_executable(bytes: &[u8], version: &str) -> io::Result<()> {
+    let exe = std::env::current_exe()?;
+    let new = exe.with_extension("new");
+    let backup = backup_path(&exe);
+
+    debug!("Writing new binary to {:?}", &new);
+    fs::write(&new, bytes)?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&new, fs::Permissions::from_mode(0o755))?;
+    }
+
+    // the running binary can be renamed but not overwritten on every platform
+    let _ = fs::remove_file(&backup);
+    if let Err(e) = fs::rename(&exe, &backup) {
+        let _ = fs::remove_file(&new);
+        return Err(e);
+    }
+
+    if let Err(e) = fs::rename(&new, &exe) {
+        warn!("Error replacing binary, rolling back: {}", e);
+
+        fs::rename(&backup, &exe)?;
+        let _ = fs::remove_file(&new);
+        return Err(e);
+    }
+
+    fs::write(pending_path(&exe), version)
+}
+
+// true if there is a previous version to go back to
+pub fn has_backup() -> bool {
+    std::env::current_exe().is_ok_and(|exe| backup_path(&exe).exists())
+}
+
+// puts the previous version back in place, it's used from the next start on
+pub fn restore_backup() -> io::Result<()> {
+    let exe = std::env::current_exe()?;
+    let backup = backup_path(&exe);
+    let failed = exe.with_extension("failed");
+    info!("Restoring previous version from {:?}", &backup);
+
+    if !backup.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "There is no previous version",
+        ));
+    }
+
+    let _ = fs::remove_file(&failed);
+    fs::rename(&exe, &failed)?;
+    if let Err(e) = fs::rename(&backup, &exe) {
+        fs::rename(&failed, &exe)?;
+        return Err(e);
+    }
+
+    // the running binary can't be removed on Windows, it's removed by the next restore then
+    let _ = fs::remove_file(&failed);
+    let _ = fs::remove_file(pending_path(&exe));
+    Ok(())
+}
+
+// called first on start, restores and restarts the previous version if the first start after
+// an update never got to the window
+pub fn check_update_start() {
+    let exe = match std::env::current_exe() {
+        Ok(exe) => exe,
+        Err(_) => return,
+    };
+    let pending = pending_path(&exe);
+    let marker = match fs::read_to_string(&pending) {
+        Ok(marker) => marker,
+        Err(_) => return,
+    };
+
+    if !marker.ends_with("started") {
+        debug!("First start after updating to {}", marker.trim());
+        if let Err(e) = fs::write(&pending, format!("{}\nstarted", marker.trim())) {
+            warn!("Error marking update as started: {}", e);
+        }
+        return;
+    }
+
+    error!("The last start after the update failed, restoring the previous version");
+    if let Err(e) = restore_backup() {
+        error!("Error restoring previous version: {}", e);
+        let _ = fs::remove_file(&pending);
+        return;
+    }
+
+    match std::process::Command::new(&exe)
+        .args(std::env::args_os().skip(1))
+        .spawn()
+    {
+        Ok(_) => std::process::exit(0),
+        Err(e) => error!("Error starting previous version: {}", e),
+    }
+}
+
+// the window and the library opened, so the update works
+pub fn confirm_update_start() {
+    let exe = match std::env::current_exe() {
+        Ok(exe) => exe,
+        Err(_) => return,
+    };
+    let pending = pending_path(&exe);
+
+    if pending.exists() {
+        debug!("Update started successfully");
+        if let Err(e) = fs::remove_file(&pending) {
+            warn!("Error removing update marker: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn release(names: &[&str]) -> Release {
+        Release {
+            tag_name: "v9.9.9".to_string(),
+            html_url: String::new(),
+            assets: names
+                .iter()
+                .map(|name| Asset {
+                    name: name.to_string(),
+                    browser_download_url: format!("https://example.com/{}", name),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn names_assets_by_platform() {
+        assert_eq!(asset_name("linux", "x86_64"), "SaveIt-linux-x86_64");
+        assert_eq!(asset_name("macos", "aarch64"), "SaveIt-macos-aarch64");
+        assert_eq!(asset_name("windows", "x86_64"), "SaveIt-windows-x86_64.exe");
+    }
+
+    #[test]
+    fn picks_exact_asset_with_checksum() {
+        let name = asset_name(std::env::consts::OS, std::env::consts::ARCH);
+        let checksum = format!("{}.sha256", name);
+
+        let other_files = release(&[
+            "SaveIt-linux.tar.gz",
+            "SaveIt-windows.zip",
+            "SaveIt-linux-riscv64",
+            "SaveIt-linux-x86_64.sha256",
+        ]);
+        assert_eq!(other_files.platform_download(), None);
+
+        let without_checksum = release(&[&name]);
+        assert_eq!(without_checksum.platform_download(), None);
+
+        let download = release(&["SaveIt.tar.gz", &checksum, &name])
+            .platform_download()
+            .unwrap();
+        assert_eq!(download.url, format!("https://example.com/{}", name));
+        assert_eq!(
+            download.checksum_url,
+            format!("https://example.com/{}", checksum)
+        );
+    }
+
+    #[test]
+    fn reads_checksums() {
+        let hash = sha256(b"SaveIt");
+        assert_eq!(parse_checksum(&hash), Some(hash.clone()));
+        assert_eq!(
+            parse_checksum(&format!("{}  SaveIt-linux-x86_64\n", hash.to_uppercase())),
+            Some(hash.clone())
+        );
+        assert_eq!(parse_checksum("not a checksum"), None);
+        assert_eq!(parse_checksum(&hash[..40]), None);
+        assert_eq!(parse_checksum(""), None);
+    }
+}